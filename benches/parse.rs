@@ -0,0 +1,93 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Measures the parsing of the table directory, `head`, `maxp`, `loca` and
+//! `glyf`, and of the `CFF ` table and its INDEX structures.
+//!
+//! Run with `cargo bench --bench parse`. The benchmark has no harness, so it
+//! also runs on stable Rust.
+
+extern crate font_parse;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use font_parse::tables::cff::{write_index, Cff, Index};
+use font_parse::tables::head::Head;
+use font_parse::tables::maxp::Maxp;
+use font_parse::{Font, OpentypeTableAccess, ParseTable};
+
+const ROUNDS: usize = 15;
+
+/// Prints the median time of one call of `f`.
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    let mut times: Vec<Duration> = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                f();
+            }
+            start.elapsed() / iterations
+        })
+        .collect();
+    times.sort();
+    println!("{:<24} {:>10.2?}", name, times[ROUNDS / 2]);
+}
+
+fn main() {
+    let truetype = &include_bytes!("../tests/font_files/Inconsolata-Regular.ttf")[..];
+    let cff = &include_bytes!("../tests/font_files/LinBiolinum_R.otf")[..];
+
+    for &(name, data) in &[("directory (ttf)", truetype), ("directory (otf)", cff)] {
+        bench(name, 10_000, || {
+            black_box(Font::from_bytes(black_box(data), 0).unwrap());
+        });
+    }
+
+    let font = Font::from_bytes(truetype, 0).unwrap();
+    bench("head", 100_000, || {
+        black_box(font.parse_table::<Head>().unwrap().get().unwrap());
+    });
+    bench("maxp", 100_000, || {
+        black_box(font.parse_table::<Maxp>().unwrap().get().unwrap());
+    });
+    bench("loca", 1_000, || {
+        black_box(font.glyphs().unwrap());
+    });
+    let mut glyphs = font.glyphs().unwrap();
+    let num_glyphs = glyphs.num_glyphs();
+    bench("glyf (all glyphs)", 20, || {
+        for index in 0..num_glyphs {
+            black_box(glyphs.index(index).unwrap());
+        }
+    });
+
+    let font = Font::from_bytes(cff, 0).unwrap();
+    bench("CFF table", 1_000, || {
+        black_box(font.parse_table::<Cff>().unwrap().get().unwrap());
+    });
+    // INDEX structures with one, two and three byte offsets
+    for &(name, item_len) in &[
+        ("CFF INDEX (1 byte)", 0),
+        ("CFF INDEX (2 bytes)", 20),
+        ("CFF INDEX (3 bytes)", 40),
+    ] {
+        let items: Vec<Vec<u8>> = (0..2000).map(|i| vec![i as u8; item_len]).collect();
+        let mut index = vec![];
+        write_index(&items, &mut index).unwrap();
+        bench(name, 10_000, || {
+            black_box(Index::parse_from(black_box(&index)).unwrap());
+        });
+    }
+}
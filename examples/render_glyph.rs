@@ -13,88 +13,45 @@
 //    limitations under the License.
 
 extern crate font_parse;
-#[macro_use]
-extern crate log;
 extern crate simple_logger;
-extern crate svg;
 
-use font_parse::{Font, Glyph, OpentypeTableAccess, PathInstruction, QuadraticPath};
-
-use svg::node::element::path::Data;
-use svg::node::element::Path;
-use svg::Document;
+use font_parse::{Font, OpentypeTableAccess};
 
 use std::env;
 
+/// Renders a single glyph into `image.svg`.
+///
+/// Usage: `render_glyph <font file> <glyph id>`
 fn main() {
-    simple_logger::init_with_level(log::Level::Trace).unwrap();
+    simple_logger::init_with_level(log::Level::Info).unwrap();
 
-    let glyph_id: u32 = env::args()
-        .nth(1)
-        .expect("Expected 1 argument")
+    let mut args = env::args().skip(1);
+    let font_path = args.next().expect("Expected font file as first argument");
+    let glyph_id: u32 = args
+        .next()
+        .expect("Expected glyph id as second argument")
         .parse()
-        .expect("Expected number as first argument");
+        .expect("Expected number as second argument");
 
-    // let data = include_bytes!("../tests/font_files/LinBiolinum_R.otf");
-    let data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
-    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let data = std::fs::read(font_path).expect("Could not read font file.");
+    let font = Font::from_bytes(&data, 0).expect("Could not read font.");
 
     let mut glyph_accessor = font.glyphs().unwrap();
-
-    // path data
-    let mut data = Data::new();
-    match glyph_accessor.index(glyph_id) {
-        Ok(Some(Glyph::Cff(mut glyph))) => {
-            for instr in glyph.contour_iter() {
-                match instr {
-                    PathInstruction::MoveTo(x, y) => {
-                        data = data.move_by((f32::from(x), f32::from(y)));
-                    }
-                    PathInstruction::LineTo(x, y) => {
-                        data = data.line_by((f32::from(x), f32::from(y)));
-                    }
-                    PathInstruction::CurveTo(c1x, c1y, c2x, c2y, x, y) => {
-                        let c1x = f32::from(c1x);
-                        let c1y = f32::from(c1y);
-                        let c2x = f32::from(c2x) + c1x;
-                        let c2y = f32::from(c2y) + c1y;
-                        let x = f32::from(x) + c2x;
-                        let y = f32::from(y) + c2y;
-                        data = data.cubic_curve_by((c1x, c1y, c2x, c2y, x, y));
-                    }
-                    PathInstruction::Close => {
-                        data = data.close();
-                    }
-                }
-            }
-        }
-        Ok(Some(Glyph::Ttf(glyph))) => {
-            for path in glyph.contour_iter() {
-                info!("{:?}", path);
-                match path {
-                    QuadraticPath::MoveTo(x, y) => data = data.move_to((x, y)),
-                    QuadraticPath::LineTo(x, y) => data = data.line_to((x, y)),
-                    QuadraticPath::CurveTo(cx, cy, x, y) => {
-                        data = data.quadratic_curve_to((cx, cy, x, y))
-                    }
-                    QuadraticPath::Close => data = data.close(),
-                }
-            }
-        }
+    let outline = match glyph_accessor.outline(glyph_id) {
+        Ok(Some(outline)) => outline,
         Ok(None) => panic!("Glyph not found"),
         Err(err) => panic!("{:?}", err),
     };
 
-    let path = Path::new()
-        .set("fill", "black")
-        .set("fill-rule", "nonzero")
-        .set("stroke-width", 0)
-        .set("transform", "scale(1,-1)")
-        .set("d", data);
-
-    let document = Document::new()
-        .set("viewBox", (0, -1800, 2000, 2000))
-        .add(path);
-
-    svg::save("image.svg", &document).unwrap();
+    let (min, max) = outline.control_bounds().expect("Glyph has no outline");
+    let document = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}"><path fill="black" d="{}"/></svg>"#,
+        min.x,
+        -max.y,
+        max.x - min.x,
+        max.y - min.y,
+        outline.to_svg_path()
+    );
+
+    std::fs::write("image.svg", document).unwrap();
 }
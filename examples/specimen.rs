@@ -0,0 +1,42 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+extern crate font_parse;
+
+use font_parse::svg::{specimen_sheet, SpecimenOptions};
+use font_parse::Font;
+
+use std::env;
+
+/// Renders all glyphs of a font (or the given range of glyph ids) into
+/// `specimen.svg`.
+///
+/// Usage: `specimen <font file> [<first glyph id> <last glyph id>]`
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let font_path = args.first().expect("Expected font file as first argument");
+
+    let mut options = SpecimenOptions::default();
+    if args.len() >= 3 {
+        let first: u32 = args[1].parse().expect("Expected number as second argument");
+        let last: u32 = args[2].parse().expect("Expected number as third argument");
+        options.glyphs = Some(first..last + 1);
+    }
+
+    let data = std::fs::read(font_path).expect("Could not read font file.");
+    let font = Font::from_bytes(&data, 0).expect("Could not read font.");
+
+    let document = specimen_sheet(&font, &options).unwrap();
+    std::fs::write("specimen.svg", document).unwrap();
+}
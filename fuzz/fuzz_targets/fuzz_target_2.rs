@@ -3,30 +3,16 @@
 extern crate libfuzzer_sys;
 extern crate font_parse;
 
-use font_parse::{Glyph, OpentypeTableAccess, Tag};
-
-struct FuzzFont<'a> {
-    data: &'a [u8],
-}
-
-impl<'a> OpentypeTableAccess for FuzzFont<'a> {
-    fn table_data(&self, tag: Tag) -> Option<&[u8]> {
-        if tag == Tag::new('C', 'F', 'F', ' ') {
-            Some(self.data)
-        } else {
-            None
-        }
-    }
-
-    fn all_tables(&self) -> Vec<Tag> {
-        vec![Tag::new('C', 'F', 'F', ' ')]
-    }
-}
+use font_parse::cff_font::CffFont;
+use font_parse::{Glyph, OpentypeTableAccess};
 
 fuzz_target!(|data: &[u8]| {
     // fuzzed code goes here
-    let font = FuzzFont { data };
-    let mut glyph_accessor = match font.glyphs() {
+    let font = match CffFont::from_bytes(data) {
+        Ok(font) => font,
+        Err(_) => return,
+    };
+    let mut glyph_accessor = match (&font).glyphs() {
         Ok(x) => x,
         Err(_) => return,
     };
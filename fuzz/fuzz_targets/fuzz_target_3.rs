@@ -0,0 +1,32 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate font_parse;
+
+use font_parse::{Font, Glyph, OpentypeTableAccess, TtfGlyph};
+
+fuzz_target!(|data: &[u8]| {
+    // fuzzed code goes here
+    let font = match Font::from_bytes(data, 0) {
+        Ok(font) => font,
+        Err(_) => return,
+    };
+    let _ = font_parse::validate::validate(&font);
+    let mut glyph_accessor = match font.glyphs() {
+        Ok(x) => x,
+        Err(_) => return,
+    };
+
+    for i in 0..glyph_accessor.num_glyphs() {
+        if let Ok(Some(Glyph::Ttf(glyph))) = glyph_accessor.index(i) {
+            if let Ok(contours) = glyph.contour_iter() {
+                let _: Vec<_> = contours.collect();
+            }
+            if let TtfGlyph::Simple(glyph) = glyph {
+                let _ = glyph.contours();
+                let _ = glyph.flags();
+            }
+        }
+        let _ = glyph_accessor.outline(i);
+    }
+});
@@ -0,0 +1,30 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate font_parse;
+
+use font_parse::tables::glyf::{
+    parse_composite_glyph, parse_header, parse_simple_glyph, remap_components,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // fuzzed code goes here
+    let header = match parse_header(data) {
+        Ok((_, header)) => header,
+        Err(_) => return,
+    };
+    if header.number_of_contours >= 0 {
+        if let Ok((_, glyph)) = parse_simple_glyph(data) {
+            let _: Vec<_> = glyph.point_iter().collect();
+            let _ = glyph.contours();
+            let _ = glyph.flags();
+            let _ = glyph.end_points();
+        }
+    } else {
+        if let Ok((_, glyph)) = parse_composite_glyph(data) {
+            let _: Vec<_> = glyph.components().collect();
+            let _ = glyph.instructions();
+        }
+        let _ = remap_components(&mut data.to_vec(), |index| Ok(index));
+    }
+});
@@ -0,0 +1,289 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! The Adobe Glyph List, which maps the glyph names of fonts without a
+//! `cmap` table to Unicode.
+//!
+//! [`to_unicode`](fn.to_unicode.html) implements the mapping of the Adobe
+//! Glyph List Specification, including `uniXXXX` and `uXXXXX` names,
+//! ligatures with components joined by underscores and suffixes after a
+//! period. [`glyph_name`](fn.glyph_name.html) names characters so that they
+//! are mapped back.
+//!
+//! Examples
+//! --------
+//!
+//! ```
+//! use font_parse::agl::{self, GlyphList};
+//!
+//! let unicode = agl::to_unicode("f_uni0069.alt", GlyphList::Agl);
+//! assert_eq!(unicode.unwrap(), "fi");
+//! assert_eq!(agl::glyph_name('\u{E9}'), "eacute");
+//! ```
+
+mod glyph_list;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use self::glyph_list::{GLYPH_LIST, ZAPF_DINGBATS_LIST};
+use crate::cff::STANDARD_STRINGS;
+use crate::GlyphIndex;
+
+/// The glyph list which is searched for the components of glyph names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphList {
+    /// The Adobe Glyph List.
+    Agl,
+    /// The ITC Zapf Dingbats Glyph List followed by the Adobe Glyph List,
+    /// which is used for the Zapf Dingbats font.
+    ZapfDingbats,
+}
+
+impl GlyphList {
+    /// Returns the glyph list for the font with the given PostScript name.
+    pub fn for_font(font_name: &str) -> Self {
+        match font_name {
+            "ZapfDingbats" | "ITCZapfDingbats" => GlyphList::ZapfDingbats,
+            _ => GlyphList::Agl,
+        }
+    }
+}
+
+fn search(list: &'static [(&str, &str)], name: &str) -> Option<&'static str> {
+    list.binary_search_by(|&(entry, _)| entry.as_bytes().cmp(name.as_bytes()))
+        .ok()
+        .map(|index| list[index].1)
+}
+
+/// Returns the Unicode characters of a glyph name listed in the Adobe Glyph
+/// List.
+///
+/// Most names map to a single character, but some, such as `dalethatafpatah`,
+/// map to a sequence of characters. Use [`to_unicode`](fn.to_unicode.html)
+/// for names which are not listed.
+///
+/// Examples
+/// --------
+///
+/// ```
+/// use font_parse::agl;
+///
+/// assert_eq!(agl::lookup("Aacute"), Some("\u{C1}"));
+/// assert_eq!(agl::lookup("not-a-glyph"), None);
+/// ```
+pub fn lookup(name: &str) -> Option<&'static str> {
+    search(&GLYPH_LIST, name)
+}
+
+/// Parses uppercase hexadecimal digits into a character.
+///
+/// Surrogates and values above U+10FFFF are rejected.
+fn parse_hex_char(digits: &str) -> Option<char> {
+    if digits.is_empty()
+        || !digits
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'A'..=b'F'))
+    {
+        return None;
+    }
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(std::char::from_u32)
+}
+
+/// Maps a single component of a glyph name.
+fn component_to_unicode(component: &str, list: GlyphList) -> Option<Cow<'static, str>> {
+    let listed = match list {
+        GlyphList::Agl => lookup(component),
+        GlyphList::ZapfDingbats => {
+            search(&ZAPF_DINGBATS_LIST, component).or_else(|| lookup(component))
+        }
+    };
+    if let Some(unicode) = listed {
+        return Some(Cow::Borrowed(unicode));
+    }
+
+    // a sequence of four digit values
+    if let Some(digits) = component.strip_prefix("uni") {
+        if digits.is_ascii() && !digits.is_empty() && digits.len().is_multiple_of(4) {
+            return (0..digits.len())
+                .step_by(4)
+                .map(|start| parse_hex_char(&digits[start..start + 4]))
+                .collect::<Option<String>>()
+                .map(Cow::Owned);
+        }
+    }
+    // a single value with four to six digits
+    if let Some(digits) = component.strip_prefix('u') {
+        if (4..=6).contains(&digits.len()) {
+            return parse_hex_char(digits).map(|c| Cow::Owned(c.to_string()));
+        }
+    }
+    None
+}
+
+/// Maps a glyph name to Unicode as described in the Adobe Glyph List
+/// Specification.
+///
+/// Everything after the first period is ignored, and the rest is split into
+/// components at underscores. Every component is looked up in the glyph list
+/// or parsed as `uniXXXX` with one or more four digit values or as `uXXXX`
+/// to `uXXXXXX`, where the digits are uppercase hexadecimal. Components which
+/// can not be mapped are dropped.
+///
+/// Returns `None` if no component could be mapped.
+pub fn to_unicode(name: &str, list: GlyphList) -> Option<String> {
+    let name = name.split('.').next().unwrap_or("");
+    let unicode: String = name
+        .split('_')
+        .filter_map(|component| component_to_unicode(component, list))
+        .collect();
+    if unicode.is_empty() {
+        None
+    } else {
+        Some(unicode)
+    }
+}
+
+/// Returns a glyph name which maps back to the character.
+///
+/// Characters which are named by one of the standard strings of CFF fonts,
+/// such as most Latin characters, use this name. All other characters are
+/// named `uniXXXX` or, outside of the Basic Multilingual Plane, `uXXXXX`.
+pub fn glyph_name(c: char) -> Cow<'static, str> {
+    let mut buffer = [0; 4];
+    let unicode: &str = c.encode_utf8(&mut buffer);
+    match STANDARD_STRINGS
+        .iter()
+        .find(|&&name| lookup(name) == Some(unicode))
+    {
+        Some(&name) => Cow::Borrowed(name),
+        None if (c as u32) <= 0xFFFF => Cow::Owned(format!("uni{:04X}", c as u32)),
+        None => Cow::Owned(format!("u{:X}", c as u32)),
+    }
+}
+
+/// Returns a glyph name which maps back to a sequence of characters, such as
+/// the characters of a ligature.
+///
+/// The names of the characters are joined by underscores, for example
+/// `f_f_i`.
+pub fn sequence_glyph_name(string: &str) -> String {
+    string.chars().map(glyph_name).collect::<Vec<_>>().join("_")
+}
+
+/// Maps the Unicode values of glyph names to the glyphs for a `cmap` table.
+///
+/// The names are given in the order of the glyphs. Names which map to a
+/// sequence of characters are skipped, and the first glyph is used for names
+/// with the same value.
+pub fn cmap_mappings<I, S>(names: I, list: GlyphList) -> BTreeMap<u32, GlyphIndex>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut mappings = BTreeMap::new();
+    for (index, name) in names.into_iter().enumerate() {
+        let unicode = match to_unicode(name.as_ref(), list) {
+            Some(unicode) => unicode,
+            None => continue,
+        };
+        let mut chars = unicode.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            mappings.entry(c as u32).or_insert(index as GlyphIndex);
+        }
+    }
+    mappings
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert!(GLYPH_LIST.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(ZAPF_DINGBATS_LIST
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(lookup("A"), Some("A"));
+        assert_eq!(lookup("zukatakana"), Some("\u{30BA}"));
+        assert_eq!(lookup("dalethatafpatah"), Some("\u{5D3}\u{5B2}"));
+        assert_eq!(lookup("Euro"), Some("\u{20AC}"));
+        assert_eq!(lookup(".notdef"), None);
+        assert_eq!(lookup(""), None);
+    }
+
+    #[test]
+    fn test_to_unicode() {
+        let agl = |name| to_unicode(name, GlyphList::Agl);
+        // the examples of the specification
+        assert_eq!(agl("Lcommaaccent").unwrap(), "\u{13B}");
+        assert_eq!(agl("uni20AC0308").unwrap(), "\u{20AC}\u{308}");
+        assert_eq!(agl("u1040C").unwrap(), "\u{1040C}");
+        assert_eq!(agl("uniD801DC0C"), None);
+        assert_eq!(agl("uni20ac"), None);
+        assert_eq!(
+            agl("Lcommaaccent_uni20AC0308_u1040C.alternate").unwrap(),
+            "\u{13B}\u{20AC}\u{308}\u{1040C}"
+        );
+        assert_eq!(agl("uni013B").unwrap(), "\u{13B}");
+        assert_eq!(agl("u013B").unwrap(), "\u{13B}");
+        assert_eq!(agl("foo"), None);
+        assert_eq!(agl(".notdef"), None);
+        assert_eq!(agl("a.sc").unwrap(), "a");
+        assert_eq!(agl("f_f_i").unwrap(), "ffi");
+        assert_eq!(agl("f_foo_i").unwrap(), "fi");
+        assert_eq!(agl("u110000"), None);
+        assert_eq!(agl("u1234567"), None);
+        assert_eq!(agl("uni"), None);
+        assert_eq!(agl("uni00410"), None);
+
+        assert_eq!(agl("a1"), None);
+        let zapf_dingbats = GlyphList::for_font("ZapfDingbats");
+        assert_eq!(to_unicode("a1", zapf_dingbats).unwrap(), "\u{2701}");
+        assert_eq!(to_unicode("A", zapf_dingbats).unwrap(), "A");
+    }
+
+    #[test]
+    fn test_glyph_name() {
+        assert_eq!(glyph_name('A'), "A");
+        assert_eq!(glyph_name(' '), "space");
+        assert_eq!(glyph_name('\u{E9}'), "eacute");
+        assert_eq!(glyph_name('\u{416}'), "uni0416");
+        assert_eq!(glyph_name('\u{1040C}'), "u1040C");
+        assert_eq!(sequence_glyph_name("ffi"), "f_f_i");
+        assert_eq!(sequence_glyph_name(""), "");
+
+        for c in (0..0x3000)
+            .chain(0x1F600..0x1F610)
+            .filter_map(std::char::from_u32)
+        {
+            assert_eq!(
+                to_unicode(&glyph_name(c), GlyphList::Agl),
+                Some(c.to_string()),
+                "{:x}",
+                c as u32
+            );
+        }
+    }
+
+    #[test]
+    fn test_cmap_mappings() {
+        let names = [".notdef", "A", "f_i", "uni0041", "space.alt", "B"];
+        let mappings: Vec<_> = cmap_mappings(names, GlyphList::Agl).into_iter().collect();
+        assert_eq!(mappings, vec![(0x20, 4), (0x41, 1), (0x42, 5)]);
+    }
+}
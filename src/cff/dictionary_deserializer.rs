@@ -46,6 +46,8 @@ impl Operator {
             Operator::Short(13) => "UniqueID",
             Operator::Short(5) => "FontBBox",
             Operator::Long(8) => "StrokeWidth",
+            Operator::Short(15) => "charset",
+            Operator::Short(16) => "Encoding",
             Operator::Short(17) => "CharStrings",
             Operator::Short(18) => "Private",
             Operator::Short(19) => "Subrs",
@@ -141,7 +143,7 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'a mut DictionaryDeserializer<'b, '
 
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
-        string bytes byte_buf option unit unit_struct newtype_struct
+        bytes byte_buf option unit unit_struct newtype_struct
         tuple_struct map struct enum ignored_any
     }
 
@@ -180,6 +182,13 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'a mut DictionaryDeserializer<'b, '
         }
     }
 
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
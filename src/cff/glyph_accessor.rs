@@ -15,7 +15,8 @@
 use crate::cff;
 use crate::error::ParserError;
 use crate::tables::cff::Cff as CffTable;
-use crate::{OpentypeTableAccess, ParseTable};
+use crate::tables::SfntTable;
+use crate::{OpentypeTableAccess, Tag};
 
 use nom::{be_i16, be_i32, Err, IResult};
 
@@ -66,6 +67,16 @@ impl Fixed16_16 {
         self.0 as i16
     }
 
+    /// Creates a fixed-point number from its raw 16.16 representation.
+    pub fn from_bits(bits: i32) -> Fixed16_16 {
+        Fixed16_16(bits)
+    }
+
+    /// Returns the raw 16.16 representation.
+    pub fn to_bits(self) -> i32 {
+        self.0
+    }
+
     /// Checked addition. Computes `self + rhs`, returning `None` if overflow occurred.
     pub fn checked_add(self, rhs: Fixed16_16) -> Option<Fixed16_16> {
         self.0.checked_add(rhs.0).map(Fixed16_16)
@@ -99,6 +110,15 @@ impl<'font> Glyph<'font> {
     pub fn contour_iter(&mut self) -> &mut CffCharstringParser<'font> {
         &mut self.parser
     }
+
+    /// Returns the advance width stored in the charstring.
+    ///
+    /// The width is read together with the first path instructions, so this
+    /// returns `None` until the contours have been iterated and for glyphs
+    /// which use `defaultWidthX`.
+    pub fn width(&self) -> Option<Fixed16_16> {
+        self.parser.width
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -108,15 +128,23 @@ pub struct GlyphAccessor<'table_data> {
 }
 
 impl<'table_data> GlyphAccessor<'table_data> {
-    pub fn new(
-        font: &impl OpentypeTableAccess<'table_data>,
-    ) -> Result<Self, ParserError> {
-        todo!();
-        // let cff = font.parse_table()?;
-        // Ok(GlyphAccessor {
-        //     cff,
-        //     parser_stack: Default::default(),
-        // })
+    pub fn new<F>(font: &F) -> Result<Self, ParserError>
+    where
+        F: OpentypeTableAccess<'table_data>,
+        F::TableData: Into<&'table_data [u8]>,
+    {
+        let data = font
+            .table_data(Tag(*CffTable::TAG))
+            .ok_or_else(|| ParserError::expected_table(Tag(*CffTable::TAG)))?;
+        let cff = CffTable::from_data(data.into(), ())?;
+        Ok(GlyphAccessor::from_table(cff))
+    }
+
+    pub(crate) fn from_table(cff: CffTable<'table_data>) -> Self {
+        GlyphAccessor {
+            cff,
+            parser_stack: Default::default(),
+        }
     }
 
     pub fn num_glyphs(&self) -> u32 {
@@ -304,7 +332,7 @@ impl<'a> CffCharstringParser<'a> {
     fn parse_stems(&mut self) -> Option<()> {
         let has_width_arg = self.stack.len() % 2 != 0;
         if has_width_arg && self.width.is_none() {
-            self.width = Some(self.stack.pop_back()? + self.nominal_width_x);
+            self.width = Some(self.stack.pop_front()? + self.nominal_width_x);
         }
 
         self.nstems += self.stack.len() >> 1;
@@ -624,6 +652,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::erasing_op, clippy::identity_op, clippy::neg_multiply)]
     fn test_cff_number_decoding() {
         let data = &[32, 246, 247, 10, 248, 10, 251, 10, 252, 10];
         let mut stack = VecDeque::new();
@@ -656,6 +685,22 @@ mod test {
         assert_eq!(stack.len(), 0);
     }
 
+    #[test]
+    fn test_cff_charstring_stem_width() {
+        // the width comes before the stem hints y dy and is followed by rmoveto
+        let data = &[50 + 139, 10 + 139, 20 + 139, 1, 30 + 139, 40 + 139, 21];
+        let mut stack = VecDeque::new();
+        {
+            let mut parser = CffCharstringParser::new(0, data, &mut stack, None, None, 100);
+            let moveto = parser.next().unwrap();
+            assert_eq!(moveto, PathInstruction::MoveTo(30.into(), 40.into()));
+            assert_eq!(parser.width, Some(150.into()));
+            assert_eq!(parser.nstems, 1);
+        }
+
+        assert_eq!(stack.len(), 0);
+    }
+
     #[test]
     fn test_cff_charstring_hmoveto() {
         let data = &[10 + 139, 22];
@@ -12,31 +12,28 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use crate::error::ParserError;
+use crate::error::{structure, ParserError};
+use crate::reader::Reader;
 
-use nom::{be_u16, be_u24, be_u32, be_u8};
+use std::io::{self, Write};
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct Index<'data> {
     /// The offsets into the data that index the elements
     pub offsets: Vec<usize>,
-    pub data: &'data [u8]
+    pub data: &'data [u8],
 }
 
 impl<'data> Index<'data> {
     pub fn empty() -> Self {
         Index {
             offsets: vec![],
-            data: &[]
+            data: &[],
         }
     }
 
     pub fn parse_from(data: &'data [u8]) -> Result<Self, ParserError> {
-        parse_index(data)
-            .map(|(_, index)| Index {
-                ..index
-            })
-            .map_err(ParserError::from)
+        Ok(parse_index(data)?.1)
     }
 
     pub fn get(&self, index: usize) -> Option<&'data [u8]> {
@@ -49,58 +46,139 @@ impl<'data> Index<'data> {
     pub fn len(&self) -> usize {
         self.offsets.len().saturating_sub(1)
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the index, choosing the offset size anew.
+    pub fn write_to<W: Write>(&self, sink: &mut W) -> io::Result<()> {
+        let items: Vec<&[u8]> = (0..self.len()).filter_map(|i| self.get(i)).collect();
+        write_index(&items, sink)
+    }
+}
+
+/// Returns the smallest offset size that can represent `max_offset`.
+pub(crate) fn offset_size(max_offset: usize) -> u8 {
+    match max_offset {
+        0..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xff_ffff => 3,
+        _ => 4,
+    }
+}
+
+/// Writes `items` as INDEX using the smallest offset size that fits the data.
+pub fn write_index<W: Write, T: AsRef<[u8]>>(items: &[T], sink: &mut W) -> io::Result<()> {
+    if items.len() > u16::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too many items for a cff index.",
+        ));
+    }
+    sink.write_all(&(items.len() as u16).to_be_bytes())?;
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let data_len: usize = items.iter().map(|item| item.as_ref().len()).sum();
+    if data_len >= u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too much data for a cff index.",
+        ));
+    }
+    let off_size = offset_size(data_len + 1);
+    sink.write_all(&[off_size])?;
+
+    // offsets are 1-based
+    let mut offset = 1;
+    write_offset(offset, off_size, sink)?;
+    for item in items {
+        offset += item.as_ref().len();
+        write_offset(offset, off_size, sink)?;
+    }
+    for item in items {
+        sink.write_all(item.as_ref())?;
+    }
+    Ok(())
 }
 
-named_args!(parse_offset(off_size: u8)<&[u8], usize>,
-    switch!(
-        value!(off_size), //< offset size
-        1 => map!(be_u8, |x| x as usize) |
-        2 => map!(be_u16, |x| x as usize) |
-        3 => map!(be_u24, |x| x as usize) |
-        4 => map!(be_u32, |x| x as usize)
-    )
-);
-
-named_args!(parse_offset_list(num_offsets: usize)<&[u8], Vec<usize>>,
-    map!(
-        cond!(
-            num_offsets > 0,
-            do_parse!(
-                off_size: be_u8 >>
-                offsets: count!(apply!(parse_offset, off_size), num_offsets + 1) >>
-                (offsets)
-            )
-        ),
-        |vec| vec.unwrap_or_default()
-    )
-);
-
-named!(pub(crate) parse_index<&[u8], Index>,
-    do_parse!(
-        num_offsets: map!(be_u16, |x| x as usize) >>
-        offsets: apply!(parse_offset_list, num_offsets) >>
-        data: take!(offsets.last().map(|&offset| offset.saturating_sub(1)).unwrap_or(0)) >>
-        (Index { offsets, data })
-    )
-);
+/// Returns the number of bytes `write_index` produces for `items`.
+pub(crate) fn index_len<T: AsRef<[u8]>>(items: &[T]) -> usize {
+    if items.is_empty() {
+        return 2;
+    }
+    let data_len: usize = items.iter().map(|item| item.as_ref().len()).sum();
+    3 + (items.len() + 1) * offset_size(data_len + 1) as usize + data_len
+}
+
+fn write_offset<W: Write>(offset: usize, off_size: u8, sink: &mut W) -> io::Result<()> {
+    sink.write_all(&(offset as u32).to_be_bytes()[4 - off_size as usize..])
+}
+
+/// Reads an INDEX including the data of its elements.
+pub(crate) fn read_index<'data>(reader: &mut Reader<'data>) -> Result<Index<'data>, ParserError> {
+    reader.structure(structure::INDEX);
+    let count: u16 = reader.read()?;
+    if count == 0 {
+        return Ok(Index::empty());
+    }
+    let off_size: u8 = reader.read()?;
+    if !(1..=4).contains(&off_size) {
+        return Err(reader.malformed(format!("Invalid offset size {}.", off_size)));
+    }
+    // read all offsets at once instead of checking the bounds for each one
+    let off_size = usize::from(off_size);
+    let offset_data = reader.read_bytes((usize::from(count) + 1) * off_size)?;
+    let offsets: Vec<usize> = match off_size {
+        1 => offset_data
+            .iter()
+            .map(|&offset| usize::from(offset))
+            .collect(),
+        2 => offset_data
+            .chunks_exact(2)
+            .map(|offset| usize::from(u16::from_be_bytes([offset[0], offset[1]])))
+            .collect(),
+        3 => offset_data
+            .chunks_exact(3)
+            .map(|offset| u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize)
+            .collect(),
+        _ => offset_data
+            .chunks_exact(4)
+            .map(|offset| u32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]) as usize)
+            .collect(),
+    };
+    // offsets are 1-based
+    let data_len = offsets.last().map_or(0, |&offset| offset.saturating_sub(1));
+    let data = reader.read_bytes(data_len)?;
+    Ok(Index { offsets, data })
+}
+
+/// Parses an INDEX, returning the data following it.
+pub(crate) fn parse_index(input: &[u8]) -> Result<(&[u8], Index<'_>), ParserError> {
+    let mut reader = Reader::new(input);
+    let index = read_index(&mut reader)?;
+    Ok((reader.remaining(), index))
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::error::ErrorKind;
 
     #[test]
-    fn test_parse_offset() {
-        let data = [0x12];
-        assert_eq!(0x12, parse_offset(&data, 1).unwrap().1);
-
-        let data = [0x12, 0x34];
-        assert_eq!(0x1234, parse_offset(&data, 2).unwrap().1);
-
-        let data = [0x12, 0x34, 0x56];
-        assert_eq!(0x123456, parse_offset(&data, 3).unwrap().1);
-
-        let data = [0x12, 0x34, 0x56, 0x78];
-        assert_eq!(0x12345678, parse_offset(&data, 4).unwrap().1);
+    fn test_read_offsets() {
+        // one element in every offset size, the last offset is 0x03 to 0x010203
+        for &(off_size, end) in &[(1, 0x03), (2, 0x0203), (3, 0x01_0203), (4, 0x01_0203)] {
+            let mut data = vec![0x00, 0x01, off_size as u8];
+            data.extend_from_slice(&1u32.to_be_bytes()[4 - off_size..]);
+            data.extend_from_slice(&(end as u32).to_be_bytes()[4 - off_size..]);
+            data.resize(data.len() + end - 1, 0);
+            let index = parse_index(&data).unwrap().1;
+            assert_eq!(index.offsets, vec![1, end]);
+            assert_eq!(index.get(0).unwrap().len(), end - 1);
+        }
     }
 
     #[test]
@@ -122,5 +200,35 @@ mod test {
             },
             parse_index(&data).unwrap().1
         );
+
+        // the data of the elements is cut off
+        let err = Index::parse_from(&data[..6]).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnexpectedEndOfData));
+        assert_eq!(err.structure(), Some("INDEX"));
+        // offsets have one to four bytes
+        assert!(Index::parse_from(&[0x00, 0x01, 0x05]).is_err());
+    }
+
+    #[test]
+    fn test_write_index() {
+        let mut out = vec![];
+        write_index::<_, &[u8]>(&[], &mut out).unwrap();
+        assert_eq!(out, [0x00, 0x00]);
+
+        let mut out = vec![];
+        write_index(&[&[0x0a][..], &[0x0b]], &mut out).unwrap();
+        assert_eq!(out, [0x00, 0x02, 0x01, 0x01, 0x02, 0x03, 0x0a, 0x0b]);
+
+        // offsets beyond 255 need two bytes
+        let items = vec![vec![0u8; 300], vec![1u8; 2]];
+        let mut out = vec![];
+        write_index(&items, &mut out).unwrap();
+        assert_eq!(
+            &out[..9],
+            &[0x00, 0x02, 0x02, 0x00, 0x01, 0x01, 0x2d, 0x01, 0x2f]
+        );
+        let index = Index::parse_from(&out).unwrap();
+        assert_eq!(index.get(0), Some(&items[0][..]));
+        assert_eq!(index.get(1), Some(&items[1][..]));
     }
 }
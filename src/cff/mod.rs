@@ -18,9 +18,12 @@ mod glyph_accessor;
 mod standard_strings;
 mod dictionary_deserializer;
 mod index;
+pub(crate) mod subset;
+mod writer;
 
 pub use self::glyph_accessor::*;
 pub use self::standard_strings::*;
 pub(crate) use self::dictionary_deserializer::DictionaryDeserializer;
-pub use self::index::Index;
-pub(crate) use self::index::parse_index;
\ No newline at end of file
+pub use self::index::{write_index, Index};
+pub(crate) use self::index::{index_len, offset_size, parse_index, read_index};
+pub use self::writer::*;
\ No newline at end of file
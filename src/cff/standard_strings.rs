@@ -1,11 +1,11 @@
 //    Copyright 2018 Manuel Reinhardt
-// 
+//
 //    Licensed under the Apache License, Version 2.0 (the "License");
 //    you may not use this file except in compliance with the License.
 //    You may obtain a copy of the License at
-// 
+//
 //        http://www.apache.org/licenses/LICENSE-2.0
-// 
+//
 //    Unless required by applicable law or agreed to in writing, software
 //    distributed under the License is distributed on an "AS IS" BASIS,
 //    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
@@ -404,4 +404,22 @@ pub const STANDARD_STRINGS: [&'static str; 391] = [
     "Regular",
     "Roman",
     "Semibold",
-];
\ No newline at end of file
+];
+/// The character codes of the standard encoding for the string ids 96 to 149.
+const STANDARD_ENCODING_UPPER: [u8; 54] = [
+    161, 162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175, 177, 178, 179, 180,
+    182, 183, 184, 185, 186, 187, 188, 189, 191, 193, 194, 195, 196, 197, 198, 199, 200, 202, 203,
+    205, 206, 207, 208, 225, 227, 232, 233, 234, 235, 241, 245, 248, 249, 250, 251,
+];
+
+/// Returns the string id of the glyph name that the standard encoding assigns
+/// to `code`, or 0 (`.notdef`) for unassigned codes.
+pub fn standard_encoding(code: u8) -> u16 {
+    match code {
+        32..=126 => u16::from(code) - 31,
+        _ => STANDARD_ENCODING_UPPER
+            .iter()
+            .position(|&c| c == code)
+            .map_or(0, |index| index as u16 + 96),
+    }
+}
@@ -0,0 +1,363 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{encode_charset, parse_index, CffParts, Index};
+use crate::error::ParserError;
+use crate::tables::cff::Cff;
+use crate::GlyphIndex;
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::ops::Range;
+
+const MAX_SUBROUTINE_DEPTH: usize = 10;
+
+const CALLSUBR: u8 = 10;
+const RETURN: u8 = 11;
+const ENDCHAR: u8 = 14;
+const CALLGSUBR: u8 = 29;
+
+fn subroutine_bias(num_subrs: usize) -> i32 {
+    if num_subrs < 1240 {
+        107
+    } else if num_subrs < 33900 {
+        1131
+    } else {
+        32768
+    }
+}
+
+fn unexpected_end() -> ParserError {
+    ParserError::from_string("Unexpected end of charstring.".to_string())
+}
+
+/// The subroutines called by a set of charstrings.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SubrUsage {
+    pub(crate) global_subrs: BTreeSet<usize>,
+    pub(crate) local_subrs: BTreeSet<usize>,
+}
+
+/// Follows the subroutine calls of a charstring without evaluating its path.
+struct CharstringScanner<'a, 'u> {
+    cff: &'a Cff<'a>,
+    usage: &'u mut SubrUsage,
+    stack: Vec<i32>,
+    num_stems: usize,
+    seac: Option<(u8, u8)>,
+}
+
+impl<'a, 'u> CharstringScanner<'a, 'u> {
+    fn operand(code: &[u8], pos: usize, len: usize) -> Result<&[u8], ParserError> {
+        code.get(pos..pos + len).ok_or_else(unexpected_end)
+    }
+
+    /// Scans `code` and returns `true` once `endchar` is reached.
+    fn scan(&mut self, code: &'a [u8], depth: usize) -> Result<bool, ParserError> {
+        if depth > MAX_SUBROUTINE_DEPTH {
+            return Err(ParserError::from_string(
+                "Subroutines are nested too deeply.".to_string(),
+            ));
+        }
+        let mut pos = 0;
+        while pos < code.len() {
+            let byte = code[pos];
+            pos += 1;
+            match byte {
+                28 => {
+                    let bytes = Self::operand(code, pos, 2)?;
+                    self.stack
+                        .push(i32::from(i16::from_be_bytes([bytes[0], bytes[1]])));
+                    pos += 2;
+                }
+                32..=246 => self.stack.push(i32::from(byte) - 139),
+                247..=250 => {
+                    let w = Self::operand(code, pos, 1)?[0];
+                    self.stack
+                        .push((i32::from(byte) - 247) * 256 + i32::from(w) + 108);
+                    pos += 1;
+                }
+                251..=254 => {
+                    let w = Self::operand(code, pos, 1)?[0];
+                    self.stack
+                        .push(-(i32::from(byte) - 251) * 256 - i32::from(w) - 108);
+                    pos += 1;
+                }
+                255 => {
+                    let bytes = Self::operand(code, pos, 4)?;
+                    let fixed = i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    self.stack.push(fixed >> 16);
+                    pos += 4;
+                }
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => {
+                    self.num_stems += self.stack.len() / 2;
+                    self.stack.clear();
+                }
+                // hintmask, cntrmask
+                19 | 20 => {
+                    self.num_stems += self.stack.len() / 2;
+                    self.stack.clear();
+                    pos += self.num_stems.div_ceil(8);
+                }
+                CALLSUBR | CALLGSUBR => {
+                    let (subrs, used) = if byte == CALLSUBR {
+                        (&self.cff.local_subrs, &mut self.usage.local_subrs)
+                    } else {
+                        (&self.cff.global_subrs, &mut self.usage.global_subrs)
+                    };
+                    let number = self.stack.pop().ok_or_else(|| {
+                        ParserError::from_string("Missing subroutine number.".to_string())
+                    })?;
+                    let index = number + subroutine_bias(subrs.len());
+                    let subr = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| subrs.get(index))
+                        .ok_or_else(|| {
+                            ParserError::from_string(format!(
+                                "Call of nonexistent subroutine {}.",
+                                index
+                            ))
+                        })?;
+                    used.insert(index as usize);
+                    if self.scan(subr, depth + 1)? {
+                        return Ok(true);
+                    }
+                }
+                RETURN => return Ok(false),
+                ENDCHAR => {
+                    // the deprecated seac form of endchar builds an accented
+                    // character from two glyphs in the standard encoding
+                    if self.stack.len() >= 4 {
+                        let n = self.stack.len();
+                        self.seac = Some((self.stack[n - 2] as u8, self.stack[n - 1] as u8));
+                    }
+                    return Ok(true);
+                }
+                // escaped operators; arithmetic operators are not evaluated
+                12 => {
+                    pos += 1;
+                    self.stack.clear();
+                }
+                _ => self.stack.clear(),
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Records the subroutines called by a glyph in `usage`.
+///
+/// Returns the standard encoding codes of the base and accent character if
+/// the glyph is an accented character built with seac.
+pub(crate) fn scan_charstring(
+    cff: &Cff<'_>,
+    glyph: u32,
+    usage: &mut SubrUsage,
+) -> Result<Option<(u8, u8)>, ParserError> {
+    let charstring = cff
+        .charstring(glyph)
+        .ok_or_else(|| ParserError::from_string(format!("Glyph {} does not exist.", glyph)))?;
+    let mut scanner = CharstringScanner {
+        cff,
+        usage,
+        stack: Vec::new(),
+        num_stems: 0,
+        seac: None,
+    };
+    scanner
+        .scan(charstring, 0)
+        .map_err(|err| ParserError::glyph_parse(glyph, err))?;
+    Ok(scanner.seac)
+}
+
+/// Returns the byte ranges of all entries of a DICT together with their
+/// operator. Two-byte operators are returned as `0x0c00 | second byte`.
+fn dict_entries(data: &[u8]) -> Result<Vec<(u16, Range<usize>)>, ParserError> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    while pos < data.len() {
+        let byte = data[pos];
+        pos += match byte {
+            12 => 2,
+            0..=21 => 1,
+            28 => 3,
+            29 => 5,
+            30 => {
+                let len = data[pos + 1..]
+                    .iter()
+                    .position(|&b| b & 0x0f == 0x0f || b & 0xf0 == 0xf0)
+                    .ok_or_else(|| {
+                        ParserError::from_string("Unterminated real number.".to_string())
+                    })?;
+                len + 2
+            }
+            32..=246 => 1,
+            247..=254 => 2,
+            _ => {
+                return Err(ParserError::from_string(format!(
+                    "Invalid byte in cff dict: {}",
+                    byte
+                )))
+            }
+        };
+        if pos > data.len() {
+            return Err(ParserError::from_string(
+                "Unexpected end of cff dict.".to_string(),
+            ));
+        }
+        match byte {
+            12 => entries.push((0x0c00 | u16::from(data[pos - 1]), start..pos)),
+            0..=21 => entries.push((u16::from(byte), start..pos)),
+            _ => continue,
+        }
+        start = pos;
+    }
+    Ok(entries)
+}
+
+/// Returns a copy of the DICT without the entries for the given operators.
+fn filter_dict(data: &[u8], removed: &[u16]) -> Result<Vec<u8>, ParserError> {
+    let mut filtered = Vec::with_capacity(data.len());
+    for (operator, range) in dict_entries(data)? {
+        if !removed.contains(&operator) {
+            filtered.extend_from_slice(&data[range]);
+        }
+    }
+    Ok(filtered)
+}
+
+/// Returns the subroutines with all unused ones replaced by a bare `return`.
+///
+/// Unused subroutines at the end are removed as long as this does not change
+/// the bias, so that the numbers of the remaining ones stay valid.
+fn prune_subrs(subrs: &Index<'_>, used: &BTreeSet<usize>) -> Vec<Vec<u8>> {
+    let len = used.iter().next_back().map_or(0, |&last| last + 1);
+    let len = match subroutine_bias(subrs.len()) {
+        107 => len,
+        1131 => len.max(1240),
+        _ => len.max(33900),
+    };
+    (0..len)
+        .map(|index| match subrs.get(index) {
+            Some(subr) if used.contains(&index) => subr.to_vec(),
+            _ => vec![RETURN],
+        })
+        .collect()
+}
+
+/// Writes a CFF table that contains a subset of the glyphs of `data`.
+///
+/// `glyphs` holds the original index of every glyph of the new table, or
+/// `None` for glyphs that are left empty. Charstrings are copied unchanged and
+/// subroutines that are no longer called are pruned.
+pub(crate) fn subset_cff(
+    data: &[u8],
+    cff: &Cff<'_>,
+    glyphs: &[Option<GlyphIndex>],
+) -> Result<Vec<u8>, ParserError> {
+    let after_header = data
+        .get(usize::from(cff.header.header_size)..)
+        .ok_or_else(|| ParserError::from_string("Truncated cff header.".to_string()))?;
+    let (after_names, _) = parse_index(after_header)?;
+    let name_index = &after_header[..after_header.len() - after_names.len()];
+    let (after_top_dict, top_dict_index) = parse_index(after_names)?;
+    let top_dict = top_dict_index
+        .get(0)
+        .ok_or_else(|| ParserError::from_string("Expected top dict index.".to_string()))?;
+    let (after_strings, _) = parse_index(after_top_dict)?;
+    let string_index = &after_top_dict[..after_top_dict.len() - after_strings.len()];
+
+    let entries = dict_entries(top_dict)?;
+    // ROS marks CID-keyed fonts, which have several private dicts
+    if entries.iter().any(|&(operator, _)| operator == 0x0c1e) {
+        return Err(ParserError::from_string(
+            "CID-keyed cff fonts can not be subset.".to_string(),
+        ));
+    }
+    // charset, Encoding, CharStrings and Private are written anew
+    let top_dict = filter_dict(top_dict, &[15, 16, 17, 18])?;
+    let (private_len, private_offset) = cff.top_dict_data.private;
+    let private_dict = private_offset
+        .checked_add(private_len)
+        .and_then(|private_end| data.get(private_offset..private_end))
+        .ok_or_else(|| ParserError::from_string("no private dict".to_string()))?;
+    let private_dict = filter_dict(private_dict, &[19])?;
+
+    let mut usage = SubrUsage::default();
+    let mut sids = Vec::with_capacity(glyphs.len());
+    let mut charstrings = Vec::with_capacity(glyphs.len());
+    for (new_index, &glyph) in glyphs.iter().enumerate() {
+        let old_index = glyph.map_or(new_index as u32, u32::from);
+        if new_index > 0 {
+            let sid = cff.charset.get(old_index as usize).ok_or_else(|| {
+                ParserError::from_string(format!("Glyph {} has no name.", old_index))
+            })?;
+            sids.push(*sid);
+        }
+        match glyph {
+            Some(glyph) => {
+                scan_charstring(cff, u32::from(glyph), &mut usage)?;
+                charstrings.push(cff.charstring(u32::from(glyph)).unwrap_or(&[]).to_vec());
+            }
+            None => charstrings.push(vec![ENDCHAR]),
+        }
+    }
+
+    let global_subrs = prune_subrs(&cff.global_subrs, &usage.global_subrs);
+    let local_subrs = prune_subrs(&cff.local_subrs, &usage.local_subrs);
+    let charset = encode_charset(&sids);
+
+    let mut out = Vec::new();
+    CffParts {
+        name_index,
+        top_dict: &top_dict,
+        string_index,
+        global_subrs: &global_subrs,
+        charset: &charset,
+        charstrings: &charstrings,
+        private_dict: &private_dict,
+        local_subrs: &local_subrs,
+    }
+    .write_to(&mut out)
+    .map_err(ParserError::from_err)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dict_entries() {
+        // FontBBox with four operands followed by a real number for Private
+        let data = [
+            0x8b, 0x8c, 0xf7, 0x00, 0x1c, 0x12, 0x34, 0x05, 0x1e, 0xe2, 0xa2, 0x5f, 0x0c, 0x02,
+        ];
+        assert_eq!(
+            dict_entries(&data).unwrap(),
+            vec![(5, 0..8), (0x0c02, 8..14)]
+        );
+        assert_eq!(filter_dict(&data, &[5]).unwrap(), &data[8..]);
+    }
+
+    #[test]
+    fn test_prune_subrs() {
+        let data = [0x00, 0x03, 0x01, 0x01, 0x02, 0x03, 0x04, 0x0a, 0x0b, 0x0c];
+        let subrs = Index::parse_from(&data).unwrap();
+        let used = [1].iter().cloned().collect();
+        assert_eq!(prune_subrs(&subrs, &used), vec![vec![RETURN], vec![0x0b]]);
+    }
+}
@@ -0,0 +1,899 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{index_len, offset_size, write_index, Fixed16_16, PathInstruction, STANDARD_STRINGS};
+use crate::error::ParserError;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::Range;
+
+// Type 2 charstring operators
+const HSTEM: u8 = 1;
+const VSTEM: u8 = 3;
+const VMOVETO: u8 = 4;
+const RLINETO: u8 = 5;
+const HLINETO: u8 = 6;
+const VLINETO: u8 = 7;
+const RRCURVETO: u8 = 8;
+const CALLSUBR: u8 = 10;
+const RETURN: u8 = 11;
+const ESCAPE: u8 = 12;
+const ENDCHAR: u8 = 14;
+const HSTEMHM: u8 = 18;
+const HINTMASK: u8 = 19;
+const CNTRMASK: u8 = 20;
+const RMOVETO: u8 = 21;
+const HMOVETO: u8 = 22;
+const VSTEMHM: u8 = 23;
+const VVCURVETO: u8 = 26;
+const HHCURVETO: u8 = 27;
+const SHORTINT: u8 = 28;
+const CALLGSUBR: u8 = 29;
+const VHCURVETO: u8 = 30;
+const HVCURVETO: u8 = 31;
+
+/// The maximum number of arguments on the Type 2 charstring argument stack.
+const MAX_ARGS: usize = 48;
+
+/// The maximum number of commands that are moved into a single subroutine.
+const MAX_SUBR_COMMANDS: usize = 16;
+
+/// The maximum number of subroutines in the global and the local subroutine
+/// index combined.
+const MAX_SUBRS: usize = 2 * 65535;
+
+/// Appends an integer in the shortest encoding shared by DICTs and charstrings.
+fn push_short_integer(out: &mut Vec<u8>, value: i16) {
+    let value = i32::from(value);
+    match value {
+        -107..=107 => out.push((value + 139) as u8),
+        108..=1131 => {
+            let value = value - 108;
+            out.extend_from_slice(&[(value >> 8) as u8 + 247, value as u8]);
+        }
+        -1131..=-108 => {
+            let value = -value - 108;
+            out.extend_from_slice(&[(value >> 8) as u8 + 251, value as u8]);
+        }
+        _ => {
+            out.push(SHORTINT);
+            out.extend_from_slice(&(value as i16).to_be_bytes());
+        }
+    }
+}
+
+/// Appends a charstring operand.
+fn push_number(out: &mut Vec<u8>, value: Fixed16_16) {
+    if value.frac() == 0 {
+        push_short_integer(out, value.int());
+    } else {
+        out.push(255);
+        out.extend_from_slice(&value.to_bits().to_be_bytes());
+    }
+}
+
+/// Serializes the operands and operators of a CFF DICT.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DictWriter {
+    data: Vec<u8>,
+}
+
+impl DictWriter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends an integer operand in its shortest encoding.
+    pub fn integer(&mut self, value: i32) -> &mut Self {
+        if value >= i32::from(i16::MIN) && value <= i32::from(i16::MAX) {
+            push_short_integer(&mut self.data, value as i16);
+            self
+        } else {
+            self.long_integer(value)
+        }
+    }
+
+    /// Appends an integer operand that always occupies five bytes.
+    ///
+    /// This is used for offsets that are only known once the size of the
+    /// DICT itself is fixed.
+    pub fn long_integer(&mut self, value: i32) -> &mut Self {
+        self.data.push(29);
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Appends a real number operand in nibble encoding.
+    ///
+    /// Values that are not finite are written as zero.
+    pub fn real(&mut self, value: f64) -> &mut Self {
+        let value = if value.is_finite() { value } else { 0.0 };
+        let plain = format!("{}", value);
+        let scientific = format!("{:e}", value);
+        let string = if scientific.len() < plain.len() {
+            scientific
+        } else {
+            plain
+        };
+
+        let mut nibbles = Vec::with_capacity(string.len() + 2);
+        let mut chars = string.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '0'..='9' => nibbles.push(c as u8 - b'0'),
+                '.' => nibbles.push(0xa),
+                'e' if chars.peek() == Some(&'-') => {
+                    chars.next();
+                    nibbles.push(0xc);
+                }
+                'e' => nibbles.push(0xb),
+                '-' => nibbles.push(0xe),
+                _ => {}
+            }
+        }
+        // the leading zero of numbers like 0.5 can be omitted
+        match nibbles[..] {
+            [0, 0xa, ..] => {
+                nibbles.remove(0);
+            }
+            [0xe, 0, 0xa, ..] => {
+                nibbles.remove(1);
+            }
+            _ => {}
+        }
+        nibbles.push(0xf);
+        if nibbles.len() % 2 != 0 {
+            nibbles.push(0xf);
+        }
+
+        self.data.push(30);
+        self.data
+            .extend(nibbles.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+        self
+    }
+
+    /// Appends a one-byte operator.
+    pub fn operator(&mut self, operator: u8) -> &mut Self {
+        self.data.push(operator);
+        self
+    }
+
+    /// Appends a two-byte operator, i.e. one prefixed by the escape byte 12.
+    pub fn escaped_operator(&mut self, operator: u8) -> &mut Self {
+        self.data.extend_from_slice(&[ESCAPE, operator]);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Assigns string ids to the strings of a CFF font.
+///
+/// Standard strings are referenced by their predefined id, all other strings
+/// are stored once in the string index.
+#[derive(Debug, Default, Clone)]
+pub struct StringTable {
+    strings: Vec<String>,
+    ids: HashMap<String, u16>,
+}
+
+impl StringTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the string id of `string`, adding it to the string index if
+    /// it is neither a standard string nor contained already.
+    pub fn sid(&mut self, string: &str) -> u16 {
+        if let Some(sid) = STANDARD_STRINGS.iter().position(|&s| s == string) {
+            return sid as u16;
+        }
+        if let Some(&sid) = self.ids.get(string) {
+            return sid;
+        }
+        let sid = (STANDARD_STRINGS.len() + self.strings.len()) as u16;
+        self.strings.push(string.to_string());
+        self.ids.insert(string.to_string(), sid);
+        sid
+    }
+
+    /// The strings that are not standard strings in the order of their ids.
+    pub fn strings(&self) -> &[String] {
+        &self.strings
+    }
+
+    /// Writes the string index.
+    pub fn write_to<W: Write>(&self, sink: &mut W) -> io::Result<()> {
+        write_index(&self.strings, sink)
+    }
+}
+
+struct CharstringEncoder {
+    out: Vec<u8>,
+    width: Option<Fixed16_16>,
+}
+
+impl CharstringEncoder {
+    /// The maximal number of arguments of the next command, which leaves
+    /// room for a pending width on the argument stack.
+    fn max_args(&self) -> usize {
+        if self.width.is_some() {
+            MAX_ARGS - 1
+        } else {
+            MAX_ARGS
+        }
+    }
+
+    fn command(&mut self, args: &[Fixed16_16], operator: u8) {
+        // the width is the first operand of the first stack-clearing operator
+        if let Some(width) = self.width.take() {
+            push_number(&mut self.out, width);
+        }
+        for &arg in args {
+            push_number(&mut self.out, arg);
+        }
+        self.out.push(operator);
+    }
+
+    fn move_to(&mut self, dx: Fixed16_16, dy: Fixed16_16) {
+        let zero = Fixed16_16::default();
+        if dy == zero {
+            self.command(&[dx], HMOVETO);
+        } else if dx == zero {
+            self.command(&[dy], VMOVETO);
+        } else {
+            self.command(&[dx, dy], RMOVETO);
+        }
+    }
+
+    /// Encodes a sequence of lines at the start of `path` and returns the
+    /// number of instructions that were consumed.
+    fn lines(&mut self, path: &[PathInstruction]) -> usize {
+        let zero = Fixed16_16::default();
+        let lines: Vec<(Fixed16_16, Fixed16_16)> = path
+            .iter()
+            .map_while(|instr| match *instr {
+                PathInstruction::LineTo(dx, dy) => Some((dx, dy)),
+                _ => None,
+            })
+            .collect();
+
+        let (dx, dy) = lines[0];
+        let max_args = self.max_args();
+        let mut args = Vec::new();
+        if dx == zero || dy == zero {
+            // alternating horizontal and vertical lines
+            let horizontal = dy == zero;
+            for (n, &(dx, dy)) in lines.iter().enumerate().take(max_args) {
+                if horizontal == (n % 2 == 0) && dy == zero {
+                    args.push(dx);
+                } else if horizontal != (n % 2 == 0) && dx == zero {
+                    args.push(dy);
+                } else {
+                    break;
+                }
+            }
+            self.command(&args, if horizontal { HLINETO } else { VLINETO });
+            args.len()
+        } else {
+            for &(dx, dy) in lines.iter().take(max_args / 2) {
+                if dx == zero || dy == zero {
+                    break;
+                }
+                args.extend_from_slice(&[dx, dy]);
+            }
+            self.command(&args, RLINETO);
+            args.len() / 2
+        }
+    }
+
+    /// Encodes a sequence of curves at the start of `path` and returns the
+    /// number of instructions that were consumed.
+    fn curves(&mut self, path: &[PathInstruction]) -> usize {
+        let zero = Fixed16_16::default();
+        let curves: Vec<[Fixed16_16; 6]> = path
+            .iter()
+            .map_while(|instr| match *instr {
+                PathInstruction::CurveTo(dx1, dy1, dx2, dy2, dx, dy) => {
+                    Some([dx1, dy1, dx2, dy2, dx, dy])
+                }
+                _ => None,
+            })
+            .collect();
+
+        let max_args = self.max_args();
+        let mut args = Vec::new();
+        let [dx1, dy1, _, _, dx, dy] = curves[0];
+        if dy1 == zero && dy == zero {
+            for c in curves.iter().take(max_args / 4) {
+                if c[1] != zero || c[5] != zero {
+                    break;
+                }
+                args.extend_from_slice(&[c[0], c[2], c[3], c[4]]);
+            }
+            self.command(&args, HHCURVETO);
+            args.len() / 4
+        } else if dx1 == zero && dx == zero {
+            for c in curves.iter().take(max_args / 4) {
+                if c[0] != zero || c[4] != zero {
+                    break;
+                }
+                args.extend_from_slice(&[c[1], c[2], c[3], c[5]]);
+            }
+            self.command(&args, VVCURVETO);
+            args.len() / 4
+        } else if dy1 == zero || dx1 == zero {
+            self.alternating_curves(&curves, dy1 == zero)
+        } else {
+            for c in curves.iter().take(max_args / 6) {
+                if c[0] == zero || c[1] == zero {
+                    break;
+                }
+                args.extend_from_slice(c);
+            }
+            self.command(&args, RRCURVETO);
+            args.len() / 6
+        }
+    }
+
+    /// Encodes curves whose tangents alternate between horizontal and
+    /// vertical using hvcurveto or vhcurveto.
+    fn alternating_curves(&mut self, curves: &[[Fixed16_16; 6]], horizontal_start: bool) -> usize {
+        let zero = Fixed16_16::default();
+        let mut args = Vec::new();
+        let mut count = 0;
+        // one argument is reserved for the end point of the last curve
+        for (n, c) in curves.iter().enumerate().take((self.max_args() - 1) / 4) {
+            let horizontal = horizontal_start == (n % 2 == 0);
+            let (starts_aligned, ends_aligned) = if horizontal {
+                (c[1] == zero, c[4] == zero)
+            } else {
+                (c[0] == zero, c[5] == zero)
+            };
+            if !starts_aligned {
+                break;
+            }
+            count += 1;
+            if horizontal {
+                args.extend_from_slice(&[c[0], c[2], c[3], c[5]]);
+            } else {
+                args.extend_from_slice(&[c[1], c[2], c[3], c[4]]);
+            }
+            if !ends_aligned {
+                // only the last curve may end in an arbitrary direction
+                args.push(if horizontal { c[4] } else { c[5] });
+                break;
+            }
+        }
+        self.command(
+            &args,
+            if horizontal_start {
+                HVCURVETO
+            } else {
+                VHCURVETO
+            },
+        );
+        count
+    }
+}
+
+/// Encodes a path as Type 2 charstring.
+///
+/// `width` is the difference between the advance width of the glyph and
+/// `nominalWidthX`, or `None` if the glyph has the default width. The path
+/// does not contain hints and `Close` instructions are implied by the
+/// charstring format.
+pub fn encode_charstring(path: &[PathInstruction], width: Option<Fixed16_16>) -> Vec<u8> {
+    let path: Vec<PathInstruction> = path
+        .iter()
+        .cloned()
+        .filter(|&instr| instr != PathInstruction::Close)
+        .collect();
+    let mut encoder = CharstringEncoder {
+        out: Vec::new(),
+        width,
+    };
+
+    let zero = Fixed16_16::default();
+    match path.first() {
+        None | Some(PathInstruction::MoveTo(..)) => {}
+        // every contour has to start with a moveto
+        Some(_) => encoder.move_to(zero, zero),
+    }
+
+    let mut i = 0;
+    while i < path.len() {
+        i += match path[i] {
+            PathInstruction::MoveTo(dx, dy) => {
+                encoder.move_to(dx, dy);
+                1
+            }
+            PathInstruction::LineTo(..) => encoder.lines(&path[i..]),
+            PathInstruction::CurveTo(..) => encoder.curves(&path[i..]),
+            PathInstruction::Close => unreachable!(),
+        };
+    }
+    encoder.command(&[], ENDCHAR);
+    encoder.out
+}
+
+/// Charstrings whose repeated command sequences were moved into subroutines.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Subroutinized {
+    pub charstrings: Vec<Vec<u8>>,
+    pub global_subrs: Vec<Vec<u8>>,
+    pub local_subrs: Vec<Vec<u8>>,
+}
+
+fn subroutine_bias(num_subrs: usize) -> i32 {
+    if num_subrs < 1240 {
+        107
+    } else if num_subrs < 33900 {
+        1131
+    } else {
+        32768
+    }
+}
+
+/// Returns the number of bytes saved by moving a sequence of `len` bytes that
+/// occurs `count` times into a subroutine.
+fn estimated_savings(len: usize, count: usize) -> isize {
+    // a call usually takes two bytes, the subroutine itself needs a return
+    // operator and an offset in the subroutine index
+    count as isize * (len as isize - 2) - (len as isize + 3)
+}
+
+/// Splits a charstring into the byte ranges of its commands, i.e. operators
+/// together with their operands, up to but excluding `endchar`.
+fn split_commands(charstring: &[u8]) -> Result<Vec<Range<usize>>, ParserError> {
+    let mut commands = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    let mut num_args = 0;
+    let mut num_stems: usize = 0;
+    while pos < charstring.len() {
+        match charstring[pos] {
+            SHORTINT => pos += 3,
+            32..=246 => pos += 1,
+            247..=254 => pos += 2,
+            255 => pos += 5,
+            CALLSUBR | CALLGSUBR | RETURN => {
+                return Err(ParserError::from_string(
+                    "Charstrings that call subroutines cannot be subroutinized.".to_string(),
+                ));
+            }
+            ENDCHAR => return Ok(commands),
+            operator => {
+                pos += 1;
+                match operator {
+                    HSTEM | VSTEM | HSTEMHM | VSTEMHM => num_stems += num_args / 2,
+                    HINTMASK | CNTRMASK => {
+                        num_stems += num_args / 2;
+                        pos += num_stems.div_ceil(8);
+                    }
+                    ESCAPE => pos += 1,
+                    _ => {}
+                }
+                commands.push(start..pos);
+                start = pos;
+                num_args = 0;
+                continue;
+            }
+        }
+        num_args += 1;
+    }
+    if pos > charstring.len() || start != pos {
+        return Err(ParserError::from_string(
+            "Unexpected end of charstring.".to_string(),
+        ));
+    }
+    Ok(commands)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Occurrence {
+    glyph: usize,
+    commands: (usize, usize),
+}
+
+/// Moves command sequences that occur repeatedly in the given charstrings
+/// into subroutines.
+///
+/// Subroutines are distributed between the global and the local subroutine
+/// index, so that the most frequently called ones can be referenced with the
+/// shortest numbers. The charstrings must not contain subroutine calls.
+pub fn subroutinize<T: AsRef<[u8]>>(charstrings: &[T]) -> Result<Subroutinized, ParserError> {
+    let commands = charstrings
+        .iter()
+        .map(|charstring| split_commands(charstring.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut candidates: HashMap<&[u8], Vec<Occurrence>> = HashMap::new();
+    for (glyph, (charstring, commands)) in charstrings.iter().zip(&commands).enumerate() {
+        let charstring = charstring.as_ref();
+        // the first command may contain the advance width of the glyph
+        for start in 1..commands.len() {
+            for end in start + 1..=(start + MAX_SUBR_COMMANDS).min(commands.len()) {
+                let bytes = &charstring[commands[start].start..commands[end - 1].end];
+                candidates.entry(bytes).or_default().push(Occurrence {
+                    glyph,
+                    commands: (start, end),
+                });
+            }
+        }
+    }
+    let mut candidates: Vec<(&[u8], Vec<Occurrence>)> = candidates
+        .into_iter()
+        .filter(|(bytes, occurrences)| estimated_savings(bytes.len(), occurrences.len()) > 0)
+        .collect();
+    candidates.sort_by_key(|(bytes, occurrences)| {
+        (
+            Reverse(estimated_savings(bytes.len(), occurrences.len())),
+            *bytes,
+        )
+    });
+
+    // greedily pick the candidates that save the most bytes, skipping
+    // occurrences that overlap with already extracted sequences
+    let mut extracted: Vec<Vec<bool>> = commands.iter().map(|c| vec![false; c.len()]).collect();
+    let mut calls: Vec<Vec<(usize, usize, usize)>> = vec![Vec::new(); charstrings.len()];
+    let mut subrs: Vec<(&[u8], usize)> = Vec::new();
+    for (bytes, occurrences) in candidates {
+        if subrs.len() == MAX_SUBRS {
+            break;
+        }
+        let mut accepted = Vec::new();
+        for occurrence in occurrences {
+            let (start, end) = occurrence.commands;
+            let flags = &mut extracted[occurrence.glyph][start..end];
+            if flags.iter().all(|&flag| !flag) {
+                flags.iter_mut().for_each(|flag| *flag = true);
+                accepted.push(occurrence);
+            }
+        }
+        if estimated_savings(bytes.len(), accepted.len()) <= 0 {
+            for occurrence in accepted {
+                let (start, end) = occurrence.commands;
+                let flags = &mut extracted[occurrence.glyph][start..end];
+                flags.iter_mut().for_each(|flag| *flag = false);
+            }
+            continue;
+        }
+        for occurrence in &accepted {
+            let (start, end) = occurrence.commands;
+            calls[occurrence.glyph].push((start, end, subrs.len()));
+        }
+        subrs.push((bytes, accepted.len()));
+    }
+
+    // split the subroutines evenly between both indexes and give the most
+    // frequently used ones the numbers with the shortest encoding
+    let num_local = subrs.len().div_ceil(2);
+    let num_global = subrs.len() / 2;
+    let number_cost = |number: i32| match number.abs() {
+        0..=107 => 1,
+        108..=1131 => 2,
+        _ => 3,
+    };
+    let mut slots: Vec<(bool, usize)> = (0..num_local)
+        .map(|i| (false, i))
+        .chain((0..num_global).map(|i| (true, i)))
+        .collect();
+    slots.sort_by_key(|&(global, i)| {
+        let bias = subroutine_bias(if global { num_global } else { num_local });
+        number_cost(i as i32 - bias)
+    });
+    let mut by_usage: Vec<usize> = (0..subrs.len()).collect();
+    by_usage.sort_by_key(|&subr| Reverse(subrs[subr].1));
+
+    let mut slot_of_subr = vec![(false, 0); subrs.len()];
+    let mut result = Subroutinized {
+        charstrings: Vec::with_capacity(charstrings.len()),
+        global_subrs: vec![Vec::new(); num_global],
+        local_subrs: vec![Vec::new(); num_local],
+    };
+    for (&subr, &(global, i)) in by_usage.iter().zip(&slots) {
+        slot_of_subr[subr] = (global, i);
+        let index = if global {
+            &mut result.global_subrs
+        } else {
+            &mut result.local_subrs
+        };
+        index[i].extend_from_slice(subrs[subr].0);
+        index[i].push(RETURN);
+    }
+
+    for ((charstring, commands), mut calls) in charstrings.iter().zip(&commands).zip(calls) {
+        let charstring = charstring.as_ref();
+        calls.sort_unstable();
+        let mut out = Vec::with_capacity(charstring.len());
+        let mut pos = 0;
+        for (start, end, subr) in calls {
+            out.extend_from_slice(&charstring[pos..commands[start].start]);
+            let (global, i) = slot_of_subr[subr];
+            let bias = subroutine_bias(if global { num_global } else { num_local });
+            push_short_integer(&mut out, (i as i32 - bias) as i16);
+            out.push(if global { CALLGSUBR } else { CALLSUBR });
+            pos = commands[end - 1].end;
+        }
+        out.extend_from_slice(&charstring[pos..]);
+        result.charstrings.push(out);
+    }
+    Ok(result)
+}
+
+/// Encodes a charset in format 0 or 2, whichever is shorter.
+///
+/// `sids` holds the string ids of the glyph names of all glyphs except
+/// `.notdef`.
+pub fn encode_charset(sids: &[u16]) -> Vec<u8> {
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for &sid in sids {
+        match ranges.last_mut() {
+            Some((first, n_left))
+                if u32::from(*first) + u32::from(*n_left) + 1 == u32::from(sid) =>
+            {
+                *n_left += 1;
+            }
+            _ => ranges.push((sid, 0)),
+        }
+    }
+
+    let mut charset = Vec::new();
+    if ranges.len() * 4 < sids.len() * 2 {
+        charset.push(2);
+        for (first, n_left) in ranges {
+            charset.extend_from_slice(&first.to_be_bytes());
+            charset.extend_from_slice(&n_left.to_be_bytes());
+        }
+    } else {
+        charset.push(0);
+        for &sid in sids {
+            charset.extend_from_slice(&sid.to_be_bytes());
+        }
+    }
+    charset
+}
+
+/// The parts of a CFF table with a single font, which are laid out and
+/// connected by offsets in `write_to`.
+#[derive(Debug, Clone)]
+pub(crate) struct CffParts<'a> {
+    pub(crate) name_index: &'a [u8],
+    /// The top DICT without the charset, CharStrings and Private operators.
+    pub(crate) top_dict: &'a [u8],
+    pub(crate) string_index: &'a [u8],
+    pub(crate) global_subrs: &'a [Vec<u8>],
+    pub(crate) charset: &'a [u8],
+    pub(crate) charstrings: &'a [Vec<u8>],
+    /// The private DICT without the Subrs operator.
+    pub(crate) private_dict: &'a [u8],
+    pub(crate) local_subrs: &'a [Vec<u8>],
+}
+
+impl<'a> CffParts<'a> {
+    pub(crate) fn write_to<W: Write>(&self, sink: &mut W) -> io::Result<()> {
+        let mut private_dict = self.private_dict.to_vec();
+        if !self.local_subrs.is_empty() {
+            // local subroutines are placed directly after the private dict,
+            // which grows by the five byte offset and the operator
+            let mut subrs = DictWriter::new();
+            subrs
+                .long_integer((private_dict.len() + 6) as i32)
+                .operator(19);
+            private_dict.extend_from_slice(subrs.as_bytes());
+        }
+
+        // the offsets in the top dict are known once the size of everything
+        // in front of the charset is fixed
+        let top_dict_index = |charset_offset: usize, charstrings_offset: usize| {
+            let mut dict = DictWriter::new();
+            dict.long_integer(charset_offset as i32)
+                .operator(15)
+                .long_integer(charstrings_offset as i32)
+                .operator(17)
+                .long_integer(private_dict.len() as i32)
+                .long_integer((charstrings_offset + index_len(self.charstrings)) as i32)
+                .operator(18);
+            let mut top_dict = self.top_dict.to_vec();
+            top_dict.extend_from_slice(dict.as_bytes());
+            let mut index = Vec::new();
+            write_index(&[top_dict], &mut index).map(|_| index)
+        };
+
+        let charset_offset = 4
+            + self.name_index.len()
+            + top_dict_index(0, 0)?.len()
+            + self.string_index.len()
+            + index_len(self.global_subrs);
+        let charstrings_offset = charset_offset + self.charset.len();
+        let total_len = charstrings_offset
+            + index_len(self.charstrings)
+            + private_dict.len()
+            + index_len(self.local_subrs);
+        if total_len > i32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Too much data for a cff table.",
+            ));
+        }
+
+        sink.write_all(&[1, 0, 4, offset_size(total_len)])?;
+        sink.write_all(self.name_index)?;
+        sink.write_all(&top_dict_index(charset_offset, charstrings_offset)?)?;
+        sink.write_all(self.string_index)?;
+        write_index(self.global_subrs, sink)?;
+        sink.write_all(self.charset)?;
+        write_index(self.charstrings, sink)?;
+        sink.write_all(&private_dict)?;
+        if !self.local_subrs.is_empty() {
+            write_index(self.local_subrs, sink)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fixed(values: &[i16]) -> Vec<Fixed16_16> {
+        values.iter().map(|&v| v.into()).collect()
+    }
+
+    #[test]
+    fn test_dict_integer() {
+        let mut dict = DictWriter::new();
+        dict.integer(0)
+            .integer(100)
+            .integer(-1000)
+            .integer(10000)
+            .integer(-100000);
+        assert_eq!(
+            dict.as_bytes(),
+            &[0x8b, 0xef, 0xfe, 0x7c, 0x1c, 0x27, 0x10, 0x1d, 0xff, 0xfe, 0x79, 0x60]
+        );
+    }
+
+    #[test]
+    fn test_dict_real() {
+        let mut dict = DictWriter::new();
+        dict.real(-2.25);
+        assert_eq!(dict.as_bytes(), &[0x1e, 0xe2, 0xa2, 0x5f]);
+
+        let mut dict = DictWriter::new();
+        dict.real(0.140541E-3);
+        assert_eq!(dict.as_bytes(), &[0x1e, 0x1a, 0x40, 0x54, 0x1c, 0x4f]);
+
+        let mut dict = DictWriter::new();
+        dict.real(0.5);
+        assert_eq!(dict.as_bytes(), &[0x1e, 0xa5, 0xff]);
+    }
+
+    #[test]
+    fn test_string_table() {
+        let mut strings = StringTable::new();
+        assert_eq!(strings.sid("space"), 1);
+        assert_eq!(strings.sid("uni0394"), 391);
+        assert_eq!(strings.sid("Semibold"), 390);
+        assert_eq!(strings.sid("uni0395"), 392);
+        assert_eq!(strings.sid("uni0394"), 391);
+        assert_eq!(strings.strings(), &["uni0394", "uni0395"]);
+    }
+
+    #[test]
+    fn test_encode_charstring() {
+        let f = fixed(&[10, 20, 0, 30, -40, 0, 50, 60]);
+        let path = [
+            PathInstruction::MoveTo(f[0], f[1]),
+            PathInstruction::LineTo(f[3], f[2]),
+            PathInstruction::LineTo(f[2], f[4]),
+            PathInstruction::LineTo(f[6], f[7]),
+            PathInstruction::Close,
+        ];
+        assert_eq!(
+            encode_charstring(&path, Some(f[6])),
+            vec![189, 149, 159, RMOVETO, 169, 99, HLINETO, 189, 199, RLINETO, ENDCHAR]
+        );
+
+        let path = [
+            PathInstruction::MoveTo(f[2], f[0]),
+            PathInstruction::CurveTo(f[0], f[2], f[1], f[3], f[2], f[6]),
+            PathInstruction::CurveTo(f[2], f[0], f[1], f[4], f[7], f[2]),
+            PathInstruction::CurveTo(f[0], f[1], f[1], f[3], f[6], f[7]),
+        ];
+        assert_eq!(
+            encode_charstring(&path, None),
+            vec![
+                149, VMOVETO, 149, 159, 169, 189, 149, 159, 99, 199, HVCURVETO, 149, 159, 159, 169,
+                189, 199, RRCURVETO, ENDCHAR
+            ]
+        );
+        assert_eq!(encode_charstring(&[], Some(f[0])), vec![149, ENDCHAR]);
+    }
+
+    #[test]
+    fn test_encode_width_with_many_arguments() {
+        let f = fixed(&[0, 10, 20]);
+        let mut encoder = CharstringEncoder {
+            out: Vec::new(),
+            width: Some(f[2]),
+        };
+        let horizontal = PathInstruction::LineTo(f[1], f[0]);
+        let vertical = PathInstruction::LineTo(f[0], f[1]);
+        let path: Vec<_> = (0..60)
+            .map(|n| if n % 2 == 0 { horizontal } else { vertical })
+            .collect();
+        // the width and the arguments fit on the argument stack
+        assert_eq!(encoder.lines(&path), MAX_ARGS - 1);
+        assert_eq!(encoder.out.len(), MAX_ARGS + 1);
+        assert_eq!(encoder.out.last(), Some(&HLINETO));
+        assert_eq!(encoder.lines(&path), MAX_ARGS);
+
+        let mut encoder = CharstringEncoder {
+            out: Vec::new(),
+            width: Some(f[2]),
+        };
+        let path = vec![PathInstruction::LineTo(f[1], f[2]); 30];
+        assert_eq!(encoder.lines(&path), (MAX_ARGS - 1) / 2);
+        let path = vec![PathInstruction::CurveTo(f[1], f[2], f[1], f[2], f[1], f[2]); 10];
+        let mut encoder = CharstringEncoder {
+            out: Vec::new(),
+            width: Some(f[2]),
+        };
+        assert_eq!(encoder.curves(&path), (MAX_ARGS - 1) / 6);
+    }
+
+    #[test]
+    fn test_subroutinize() {
+        let shared = [149, 159, RLINETO, 169, 179, 189, 199, RLINETO, 100, HLINETO];
+        let mut charstrings = vec![];
+        for i in 0..3 {
+            let mut charstring = vec![139 + i, HMOVETO];
+            charstring.extend_from_slice(&shared);
+            charstring.push(ENDCHAR);
+            charstrings.push(charstring);
+        }
+
+        let result = subroutinize(&charstrings).unwrap();
+        assert_eq!(result.global_subrs.len(), 0);
+        assert_eq!(result.local_subrs.len(), 1);
+        assert_eq!(&result.local_subrs[0][..shared.len()], &shared[..]);
+        assert_eq!(result.local_subrs[0][shared.len()], RETURN);
+        for (i, charstring) in result.charstrings.iter().enumerate() {
+            assert_eq!(
+                charstring,
+                &vec![139 + i as u8, HMOVETO, 32, CALLSUBR, ENDCHAR]
+            );
+        }
+
+        assert!(subroutinize(&[vec![32, CALLSUBR, ENDCHAR]]).is_err());
+    }
+}
@@ -0,0 +1,538 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Using bare CFF font programs, such as the `FontFile3` streams embedded in
+//! PDF files, as fonts.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::agl::{self, GlyphList};
+use crate::cff::GlyphAccessor as CffGlyphAccessor;
+use crate::error::ParserError;
+use crate::outline::Outline;
+use crate::tables::cff::Cff;
+use crate::tables::cmap::{write_cmap, Cmap};
+use crate::tables::head::Head;
+use crate::tables::hhea::Hhea;
+use crate::tables::hmtx::{Hmtx, LongHorMetric};
+use crate::tables::loca::LocFormat;
+use crate::tables::maxp::Maxp;
+use crate::tables::name::{self, Name, NameRecord};
+use crate::tables::os2::Os2;
+use crate::tables::post::Post;
+use crate::tables::SfntTable;
+use crate::{write_font, GlyphIndex, OpentypeTableAccess, Tag};
+
+/// The `OS/2` Unicode range bits of common blocks with their first and last
+/// code points.
+const UNICODE_RANGES: [(usize, u32, u32); 29] = [
+    // Basic Latin
+    (0, 0x0000, 0x007F),
+    // Latin-1 Supplement
+    (1, 0x0080, 0x00FF),
+    // Latin Extended-A
+    (2, 0x0100, 0x017F),
+    // Latin Extended-B
+    (3, 0x0180, 0x024F),
+    // IPA Extensions
+    (4, 0x0250, 0x02AF),
+    // Spacing Modifier Letters
+    (5, 0x02B0, 0x02FF),
+    // Combining Diacritical Marks
+    (6, 0x0300, 0x036F),
+    // Greek and Coptic
+    (7, 0x0370, 0x03FF),
+    // Cyrillic
+    (9, 0x0400, 0x04FF),
+    // Armenian
+    (10, 0x0530, 0x058F),
+    // Hebrew
+    (11, 0x0590, 0x05FF),
+    // Arabic
+    (13, 0x0600, 0x06FF),
+    // Latin Extended Additional
+    (29, 0x1E00, 0x1EFF),
+    // Greek Extended
+    (30, 0x1F00, 0x1FFF),
+    // General Punctuation
+    (31, 0x2000, 0x206F),
+    // Superscripts And Subscripts
+    (32, 0x2070, 0x209F),
+    // Currency Symbols
+    (33, 0x20A0, 0x20CF),
+    // Letterlike Symbols
+    (35, 0x2100, 0x214F),
+    // Number Forms
+    (36, 0x2150, 0x218F),
+    // Arrows
+    (37, 0x2190, 0x21FF),
+    // Mathematical Operators
+    (38, 0x2200, 0x22FF),
+    // Miscellaneous Technical
+    (39, 0x2300, 0x23FF),
+    // Box Drawing
+    (43, 0x2500, 0x257F),
+    // Geometric Shapes
+    (45, 0x25A0, 0x25FF),
+    // Miscellaneous Symbols
+    (46, 0x2600, 0x26FF),
+    // Dingbats
+    (47, 0x2700, 0x27BF),
+    // Private Use Area
+    (60, 0xE000, 0xF8FF),
+    // Alphabetic Presentation Forms
+    (62, 0xFB00, 0xFB4F),
+    // Non-Plane 0
+    (57, 0x10000, 0x10FFFF),
+];
+
+/// The advance width and the bounding box of a glyph.
+#[derive(Debug, Copy, Clone)]
+struct GlyphMetrics {
+    advance_width: u16,
+    bounds: Option<[i16; 4]>,
+}
+
+/// Reads the advance widths and bounding boxes from the charstrings.
+fn glyph_metrics(cff: &Cff<'_>) -> Vec<GlyphMetrics> {
+    let default_width = cff.private_dict_data.default_width_x;
+    let mut accessor = CffGlyphAccessor::from_table(cff.clone());
+    (0..cff.num_glyphs())
+        .map(|index| {
+            let mut glyph = match accessor.index(index) {
+                Some(glyph) => glyph,
+                None => {
+                    return GlyphMetrics {
+                        advance_width: 0,
+                        bounds: None,
+                    }
+                }
+            };
+            let path: Vec<_> = glyph.contour_iter().collect();
+            let width = glyph
+                .width()
+                .map_or(default_width, |width| f32::from(width).round() as i32);
+            let bounds =
+                Outline::from_path_instructions(path)
+                    .control_bounds()
+                    .map(|(min, max)| {
+                        [
+                            min.x.floor() as i16,
+                            min.y.floor() as i16,
+                            max.x.ceil() as i16,
+                            max.y.ceil() as i16,
+                        ]
+                    });
+            GlyphMetrics {
+                advance_width: width.clamp(0, i32::from(u16::MAX)) as u16,
+                bounds,
+            }
+        })
+        .collect()
+}
+
+/// Returns the units per em for the scale of the `FontMatrix`.
+fn units_per_em(font_matrix: &[f32]) -> u16 {
+    match font_matrix.first() {
+        Some(&scale) if scale > 0.0 => (1.0 / scale).round().clamp(16.0, 16384.0) as u16,
+        _ => 1000,
+    }
+}
+
+/// Returns the `OS/2` weight class for the `Weight` string of the Top DICT.
+fn weight_class(weight: &str) -> u16 {
+    let weight: String = weight
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    match weight.as_str() {
+        "thin" | "hairline" => 100,
+        "extralight" | "ultralight" => 200,
+        "light" => 300,
+        "medium" => 500,
+        "semibold" | "demibold" | "demi" => 600,
+        "bold" => 700,
+        "extrabold" | "ultrabold" => 800,
+        "black" | "heavy" => 900,
+        _ => 400,
+    }
+}
+
+/// A bare CFF font program which is not wrapped in an SFNT font file.
+///
+/// The CFF data is available as the `CFF ` table. The `head`, `hhea`, `hmtx`,
+/// `maxp` and `cmap` tables are synthesized from the Top DICT, the
+/// charstrings and the encoding, so glyphs and metrics can be read just like
+/// those of an OpenType font. The `cmap` table maps the character codes of the
+/// CFF encoding, which are the codes used by PDF content streams, and not
+/// Unicode code points.
+///
+/// [`write_otf`](#method.write_otf) converts the font program into an
+/// OpenType font file.
+///
+/// Examples
+/// --------
+///
+/// ```no_run
+/// use font_parse::cff_font::CffFont;
+/// use font_parse::OpentypeTableAccess;
+///
+/// let data = std::fs::read("FontFile3.cff").unwrap();
+/// let font = CffFont::from_bytes(&data).unwrap();
+/// let outline = (&font).glyphs().unwrap().outline(1).unwrap();
+///
+/// let mut otf = vec![];
+/// font.write_otf(&mut otf).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CffFont<'a> {
+    cff: Cff<'a>,
+    data: &'a [u8],
+    metrics: Vec<GlyphMetrics>,
+    head: Head,
+    hhea: Hhea,
+    tables: BTreeMap<Tag, Vec<u8>>,
+}
+
+impl<'a> CffFont<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParserError> {
+        let cff = Cff::from_data(data, ())?;
+        let metrics = glyph_metrics(&cff);
+        let bounds = metrics
+            .iter()
+            .filter_map(|metrics| metrics.bounds)
+            .fold(None, |bbox: Option<[i16; 4]>, bounds| {
+                Some(match bbox {
+                    None => bounds,
+                    Some(bbox) => [
+                        bbox[0].min(bounds[0]),
+                        bbox[1].min(bounds[1]),
+                        bbox[2].max(bounds[2]),
+                        bbox[3].max(bounds[3]),
+                    ],
+                })
+            })
+            .unwrap_or_default();
+        let top_dict = &cff.top_dict_data;
+        let bold = weight_class(&top_dict.weight) >= 700;
+        let italic = top_dict.italic_angle != 0.0;
+        let units_per_em = units_per_em(&top_dict.font_matrix);
+
+        let head = Head {
+            major_version: 1,
+            minor_version: 0,
+            font_revision: 0x0001_0000,
+            check_sum_adjustment: 0,
+            magic_number: 0x5F0F_3CF5,
+            // baseline at y = 0 and left side bearing point at x = 0
+            flags: 0b11,
+            units_per_em,
+            created: 0,
+            modified: 0,
+            x_min: bounds[0],
+            y_min: bounds[1],
+            x_max: bounds[2],
+            y_max: bounds[3],
+            mac_style: u16::from(bold) | u16::from(italic) << 1,
+            lowest_rec_ppem: 3,
+            font_direction_hint: 2,
+            index_to_loc_format: LocFormat::Short,
+            glyph_data_format: 0,
+        };
+
+        let hmtx = Hmtx::from_metrics(
+            &metrics
+                .iter()
+                .map(|metrics| LongHorMetric {
+                    advance_width: metrics.advance_width,
+                    lsb: metrics.bounds.map_or(0, |bounds| bounds[0]),
+                })
+                .collect::<Vec<_>>(),
+        );
+        let with_bounds = || {
+            metrics
+                .iter()
+                .filter_map(|metrics| Some((i32::from(metrics.advance_width), metrics.bounds?)))
+        };
+        let hhea = Hhea {
+            major_version: 1,
+            minor_version: 0,
+            ascender: bounds[3],
+            descender: bounds[1],
+            line_gap: 0,
+            advance_width_max: metrics
+                .iter()
+                .map(|metrics| metrics.advance_width)
+                .max()
+                .unwrap_or(0),
+            min_left_side_bearing: with_bounds()
+                .map(|(_, bounds)| bounds[0])
+                .min()
+                .unwrap_or(0),
+            min_right_side_bearing: with_bounds()
+                .map(|(width, bounds)| (width - i32::from(bounds[2])) as i16)
+                .min()
+                .unwrap_or(0),
+            x_max_extent: with_bounds()
+                .map(|(_, bounds)| bounds[2])
+                .max()
+                .unwrap_or(0),
+            caret_slope_rise: if italic { units_per_em as i16 } else { 1 },
+            // the italic angle is counter-clockwise from the vertical
+            caret_slope_run: (-top_dict.italic_angle.to_radians().tan() * f32::from(units_per_em))
+                .round() as i16,
+            caret_offset: 0,
+            metric_data_format: 0,
+            number_of_h_metrics: hmtx.h_metrics.len() as u16,
+        };
+
+        let mappings: BTreeMap<u32, GlyphIndex> = cff
+            .encoding()
+            .iter()
+            .map(|(&code, &glyph)| (u32::from(code), glyph))
+            .collect();
+
+        let mut tables = BTreeMap::new();
+        let mut out = vec![];
+        head.write_to(&mut out).map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Head::TAG), out);
+        let mut out = vec![];
+        hhea.write_to(&mut out).map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Hhea::TAG), out);
+        let mut out = vec![];
+        hmtx.write_to(&mut out).map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Hmtx::TAG), out);
+        let mut out = vec![];
+        Maxp::version_0_5(cff.num_glyphs() as u16)
+            .write_to(&mut out)
+            .map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Maxp::TAG), out);
+        let mut out = vec![];
+        write_cmap(&mappings, &mut out).map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Cmap::TAG), out);
+
+        Ok(CffFont {
+            cff,
+            data,
+            metrics,
+            head,
+            hhea,
+            tables,
+        })
+    }
+
+    /// Returns the parsed CFF font program.
+    pub fn cff(&self) -> &Cff<'a> {
+        &self.cff
+    }
+
+    /// Writes the font as an OpenType font file with CFF outlines.
+    ///
+    /// In addition to the synthesized tables, an `OS/2` table, a `name` table
+    /// with the strings of the Top DICT and a `post` table without glyph names
+    /// are written. The `cmap` table of the font file maps the Unicode values
+    /// which the Adobe Glyph List Specification assigns to the glyph names
+    /// instead of the codes of the encoding.
+    pub fn write_otf(&self, sink: &mut dyn Write) -> io::Result<()> {
+        let mappings = self.unicode_mappings();
+        let mut tables = self.tables.clone();
+        let mut out = vec![];
+        write_cmap(&mappings, &mut out)?;
+        tables.insert(Tag(*Cmap::TAG), out);
+        let os2 = self.os2(&mappings);
+        let mut out = vec![];
+        os2.write_to(&mut out)?;
+        tables.insert(Tag(*Os2::TAG), out);
+        let mut out = vec![];
+        self.name(&os2).write_to(&mut out)?;
+        tables.insert(Tag(*Name::TAG), out);
+        let mut out = vec![];
+        self.post().write_to(&mut out)?;
+        tables.insert(Tag(*Post::TAG), out);
+        tables.insert(Tag(*Cff::TAG), self.data.to_vec());
+
+        let tags: Vec<Tag> = tables.keys().cloned().collect();
+        write_font(&&tables, Tag(*b"OTTO"), &tags, sink)
+    }
+
+    /// Maps the Unicode values of the glyph names to the glyphs.
+    fn unicode_mappings(&self) -> BTreeMap<u32, GlyphIndex> {
+        let names =
+            (0..self.cff.num_glyphs()).map(|index| self.cff.glyph_name(index).unwrap_or_default());
+        agl::cmap_mappings(names, GlyphList::for_font(&self.cff.name))
+    }
+
+    fn os2(&self, mappings: &BTreeMap<u32, GlyphIndex>) -> Os2 {
+        let top_dict = &self.cff.top_dict_data;
+        let scale = |factor: f32| (f32::from(self.head.units_per_em) * factor).round() as i16;
+        let top = |c: char| {
+            mappings
+                .get(&(c as u32))
+                .and_then(|&glyph| self.metrics.get(usize::from(glyph))?.bounds)
+                .map_or(0, |bounds| bounds[3])
+        };
+        let x_height = top('x');
+
+        // version 3 and later average over all glyphs with a width
+        let widths: Vec<i32> = self
+            .metrics
+            .iter()
+            .map(|metrics| i32::from(metrics.advance_width))
+            .filter(|&width| width != 0)
+            .collect();
+        let x_avg_char_width = if widths.is_empty() {
+            0
+        } else {
+            widths.iter().sum::<i32>() / widths.len() as i32
+        };
+
+        let weight_class = weight_class(&top_dict.weight);
+        let mut fs_selection = 0;
+        if top_dict.italic_angle != 0.0 {
+            fs_selection |= 1;
+        }
+        if weight_class >= 700 {
+            fs_selection |= 1 << 5;
+        }
+        if fs_selection == 0 {
+            // regular
+            fs_selection |= 1 << 6;
+        }
+
+        let mut unicode_range = [0; 4];
+        for &(bit, first, last) in UNICODE_RANGES.iter() {
+            if mappings.range(first..=last).next().is_some() {
+                unicode_range[bit / 32] |= 1 << (bit % 32);
+            }
+        }
+        let bmp_codes = || mappings.keys().map(|&code| code.min(0xFFFF) as u16);
+
+        Os2 {
+            x_avg_char_width: x_avg_char_width as i16,
+            weight_class,
+            subscript_x_size: scale(0.65),
+            subscript_y_size: scale(0.6),
+            subscript_y_offset: scale(0.075),
+            superscript_x_size: scale(0.65),
+            superscript_y_size: scale(0.6),
+            superscript_y_offset: scale(0.35),
+            strikeout_size: top_dict.underline_thickness.round() as i16,
+            strikeout_position: if x_height > 0 {
+                x_height / 2
+            } else {
+                scale(0.25)
+            },
+            unicode_range,
+            // Latin 1
+            code_page_range: [u32::from(unicode_range[0] & 1 != 0), 0],
+            fs_selection,
+            first_char_index: bmp_codes().min().unwrap_or(0),
+            last_char_index: bmp_codes().max().unwrap_or(0),
+            typo_ascender: self.hhea.ascender,
+            typo_descender: self.hhea.descender,
+            typo_line_gap: self.hhea.line_gap,
+            win_ascent: self.head.y_max.max(0) as u16,
+            win_descent: (-i32::from(self.head.y_min)).max(0) as u16,
+            x_height,
+            cap_height: top('H'),
+            ..Os2::default()
+        }
+    }
+
+    fn name(&self, os2: &Os2) -> Name<'static> {
+        let top_dict = &self.cff.top_dict_data;
+        let postscript_name = &self.cff.name;
+        let family_name = if top_dict.family_name.is_empty() {
+            postscript_name
+        } else {
+            &top_dict.family_name
+        };
+        let subfamily_name = match (os2.fs_selection & (1 << 5) != 0, os2.fs_selection & 1 != 0) {
+            (false, false) => "Regular",
+            (true, false) => "Bold",
+            (false, true) => "Italic",
+            (true, true) => "Bold Italic",
+        };
+        let full_name = if !top_dict.full_name.is_empty() {
+            top_dict.full_name.clone()
+        } else if subfamily_name == "Regular" {
+            family_name.clone()
+        } else {
+            format!("{} {}", family_name, subfamily_name)
+        };
+        // the version is a string id, which is 0 if there is no version
+        let version = match top_dict.version {
+            0 => None,
+            sid => self.cff.string(sid as u16),
+        }
+        .map_or_else(|| "1.000".to_string(), |version| version.into_owned());
+        let copyright = if top_dict.copyright.is_empty() {
+            &top_dict.notice
+        } else {
+            &top_dict.copyright
+        };
+
+        let records = [
+            (name::COPYRIGHT, copyright.clone()),
+            (name::FAMILY_NAME, family_name.clone()),
+            (name::SUBFAMILY_NAME, subfamily_name.to_string()),
+            (name::UNIQUE_ID, format!("{};{}", version, postscript_name)),
+            (name::FULL_NAME, full_name),
+            (name::VERSION, format!("Version {}", version)),
+            (name::POSTSCRIPT_NAME, postscript_name.clone()),
+        ]
+        .iter()
+        .filter(|(_, string)| !string.is_empty())
+        .map(|(name_id, string)| NameRecord::windows(*name_id, string))
+        .collect();
+        Name {
+            records,
+            language_tags: vec![],
+        }
+    }
+
+    fn post(&self) -> Post<'static> {
+        let top_dict = &self.cff.top_dict_data;
+        Post {
+            version: 0x0003_0000,
+            italic_angle: (top_dict.italic_angle * 65536.0).round() as i32,
+            // the CFF position is the center of the underline and the `post`
+            // position is its top
+            underline_position: (top_dict.underline_position + top_dict.underline_thickness / 2.0)
+                .round() as i16,
+            underline_thickness: top_dict.underline_thickness.round() as i16,
+            is_fixed_pitch: u32::from(top_dict.is_fixed_pitch()),
+            min_mem_type42: 0,
+            max_mem_type42: 0,
+            min_mem_type1: 0,
+            max_mem_type1: 0,
+            glyph_name_index: vec![],
+            names: vec![],
+        }
+    }
+}
+
+impl<'a, 'w> OpentypeTableAccess<'w> for &'w CffFont<'a> {
+    type TableData = &'w [u8];
+
+    fn table_data(&self, tag: Tag) -> Option<Self::TableData> {
+        if tag == Tag(*Cff::TAG) {
+            Some(self.data)
+        } else {
+            self.tables.get(&tag).map(Vec::as_slice)
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Checksums of tables and of whole fonts.
+//!
+//! Every table record stores the checksum of its table, and the
+//! `checkSumAdjustment` of the `head` table makes the checksum of the whole
+//! font [`FONT_CHECKSUM`](constant.FONT_CHECKSUM.html).
+//! [`Font::verify_checksums`](../struct.Font.html#method.verify_checksums)
+//! compares them with the data of a font.
+
+use crate::{FontRecord, Tag};
+
+/// The checksum of a font including its `checkSumAdjustment`.
+pub const FONT_CHECKSUM: u32 = 0xB1B0_AFBA;
+
+/// The tag of the table holding the checksum adjustment.
+const HEAD: Tag = Tag(*b"head");
+
+/// Returns the sum of the data as big-endian 32 bit integers, where the last
+/// integer is padded with zeros.
+pub fn table_checksum(mut table: &[u8]) -> u32 {
+    let mut sum: u32 = 0;
+    while table.len() >= 4 {
+        let (first, second) = table.split_at(4);
+        let first = [first[0], first[1], first[2], first[3]];
+        sum = sum.wrapping_add(u32::from_be_bytes(first));
+        table = second;
+    }
+    let mut final_bytes = [0; 4];
+    for (index, val) in table.iter().enumerate() {
+        final_bytes[index] = *val;
+    }
+    sum.wrapping_add(u32::from_be_bytes(final_bytes))
+}
+
+/// Returns the checksum of a `head` table, for which the
+/// `checkSumAdjustment` is zero.
+pub fn head_checksum(head: &[u8]) -> u32 {
+    let adjustment = head.get(8..12).map_or(0, |adjustment| {
+        u32::from_be_bytes([adjustment[0], adjustment[1], adjustment[2], adjustment[3]])
+    });
+    table_checksum(head).wrapping_sub(adjustment)
+}
+
+/// The checksum of a table as stored in its table record and as computed
+/// from its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableChecksum {
+    pub tag: Tag,
+    pub expected: u32,
+    /// `None` if the table is out of bounds.
+    pub computed: Option<u32>,
+}
+
+impl TableChecksum {
+    pub fn is_valid(&self) -> bool {
+        self.computed == Some(self.expected)
+    }
+}
+
+/// The result of
+/// [`Font::verify_checksums`](../struct.Font.html#method.verify_checksums).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumReport {
+    /// The checksums of all tables in the order of their tags.
+    pub tables: Vec<TableChecksum>,
+    /// The `checkSumAdjustment` stored in the `head` table and the value it
+    /// should have for the table directory and the table data.
+    ///
+    /// `None` if there is no complete `head` table.
+    pub adjustment: Option<(u32, u32)>,
+}
+
+impl ChecksumReport {
+    /// Returns whether all table checksums and the checksum adjustment are
+    /// correct.
+    pub fn is_valid(&self) -> bool {
+        self.tables.iter().all(TableChecksum::is_valid)
+            && self
+                .adjustment
+                .is_some_and(|(expected, computed)| expected == computed)
+    }
+
+    /// Returns the tables whose checksums are not correct.
+    pub fn invalid_tables(&self) -> impl Iterator<Item = &TableChecksum> {
+        self.tables.iter().filter(|table| !table.is_valid())
+    }
+}
+
+/// Computes the checksums of the font with the table directory at
+/// `directory_offset` in `data`.
+pub(crate) fn verify_checksums(
+    data: &[u8],
+    directory_offset: usize,
+    record: &FontRecord,
+) -> ChecksumReport {
+    let table_data = |tag: Tag| {
+        let record = &record.tables[&tag];
+        let start = record.offset as usize;
+        data.get(start..start.checked_add(record.length as usize)?)
+    };
+    let tables: Vec<TableChecksum> = record
+        .tables
+        .values()
+        .map(|table| TableChecksum {
+            tag: table.tag,
+            expected: table.check_sum,
+            computed: table_data(table.tag).map(|data| {
+                if table.tag == HEAD {
+                    head_checksum(data)
+                } else {
+                    table_checksum(data)
+                }
+            }),
+        })
+        .collect();
+
+    // the checksum of the font is the sum of the checksums of the directory
+    // and of the tables, which are padded with zeros
+    let num_tables = data
+        .get(directory_offset + 4..directory_offset + 6)
+        .map_or(0, |num_tables| {
+            u16::from_be_bytes([num_tables[0], num_tables[1]])
+        });
+    let directory_len = 12 + 16 * usize::from(num_tables);
+    let directory = data.get(directory_offset..directory_offset + directory_len);
+    let head = record
+        .tables
+        .get(&HEAD)
+        .and_then(|_| table_data(HEAD))
+        .and_then(|head| head.get(8..12));
+    let adjustment = match (directory, head) {
+        (Some(directory), Some(stored)) => {
+            let font_checksum = tables
+                .iter()
+                .filter_map(|table| table.computed)
+                .fold(table_checksum(directory), u32::wrapping_add);
+            let stored = u32::from_be_bytes([stored[0], stored[1], stored[2], stored[3]]);
+            Some((stored, FONT_CHECKSUM.wrapping_sub(font_checksum)))
+        }
+        _ => None,
+    };
+
+    ChecksumReport { tables, adjustment }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_table_checksum() {
+        assert_eq!(table_checksum(&[]), 0);
+        assert_eq!(table_checksum(&[0, 0, 0, 1, 0, 0, 0, 2]), 3);
+        assert_eq!(table_checksum(&[0, 0, 0, 1, 2]), 0x0200_0001);
+        assert_eq!(table_checksum(&[0xFF; 8]), 0xFFFF_FFFE);
+
+        let mut head = vec![0; 54];
+        head[3] = 1;
+        head[8..12].copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(head_checksum(&head), 1);
+        assert_eq!(head_checksum(&head[..10]), table_checksum(&head[..10]));
+    }
+}
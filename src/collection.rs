@@ -0,0 +1,208 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Writing font collections (`.ttc` and `.otc` files).
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Write};
+
+use crate::checksum::table_checksum;
+use crate::error::ParserError;
+use crate::font_builder::{padded_len, table_order};
+use crate::{Font, FontRecord, OpentypeTableAccess, TableRecord, Tag};
+
+/// A font to be written into a collection.
+#[derive(Debug)]
+pub struct CollectionFont<'f, F> {
+    /// The source of the tables.
+    pub font: &'f F,
+    /// The sfnt version, `[0, 1, 0, 0]` for TrueType outlines and `OTTO` for
+    /// CFF outlines.
+    pub version_tag: Tag,
+    /// The tables of the font.
+    pub tables: Vec<Tag>,
+}
+
+/// Writes a font collection.
+///
+/// Tables with identical data are stored only once and shared by the fonts,
+/// except for `head` which holds the checksum adjustment of its font. If
+/// `dsig` is given, a version 2.0 header is written which points to the
+/// `DSIG` table appended to the collection, otherwise a version 1.0 header.
+pub fn write_font_collection<'a, F: OpentypeTableAccess<'a>>(
+    fonts: &[CollectionFont<'_, F>],
+    dsig: Option<&[u8]>,
+    sink: &mut dyn Write,
+) -> io::Result<()> {
+    let mut table_data = Vec::with_capacity(fonts.len());
+    for font in fonts {
+        if font.tables.len() > usize::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A font can't contain more than 2^16 - 1 tables.",
+            ));
+        }
+        let mut tags = BTreeSet::new();
+        for tag in &font.tables {
+            if !tags.insert(*tag) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Font contains table {} more than once.", tag),
+                ));
+            }
+        }
+        let mut tables = Vec::with_capacity(font.tables.len());
+        for tag in table_order(font.tables.iter().cloned()) {
+            let data = font.font.table_data(tag).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Font does not contain table {}.", tag),
+                )
+            })?;
+            tables.push((tag, data));
+        }
+        table_data.push(tables);
+    }
+
+    let header_len = 12 + 4 * fonts.len() + if dsig.is_some() { 12 } else { 0 };
+    let mut directory_offsets = Vec::with_capacity(fonts.len());
+    let mut offset = header_len;
+    for font in fonts {
+        directory_offsets.push(offset);
+        offset += 12 + 16 * font.tables.len();
+    }
+
+    // the offsets and data of the shared tables and of the head tables
+    let mut blobs: Vec<(usize, &[u8])> = Vec::new();
+    let mut shared_tables: HashMap<&[u8], usize> = HashMap::new();
+    let mut heads: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut records = Vec::with_capacity(fonts.len());
+    for tables in &table_data {
+        let mut table_records = BTreeMap::new();
+        for (tag, data) in tables {
+            let data: &[u8] = data;
+            let mut table_offset = offset;
+            let check_sum = if *tag == Tag(*b"head") {
+                if data.len() < 12 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "The head table is truncated.",
+                    ));
+                }
+                let mut head = data.to_vec();
+                // set the checksum adjustment to zero
+                head[8..12].copy_from_slice(&[0, 0, 0, 0]);
+                let check_sum = table_checksum(&head);
+                heads.push((offset, head));
+                offset += padded_len(data.len());
+                check_sum
+            } else {
+                if let Some(&shared_offset) = shared_tables.get(data) {
+                    table_offset = shared_offset;
+                } else {
+                    shared_tables.insert(data, offset);
+                    blobs.push((offset, data));
+                    offset += padded_len(data.len());
+                }
+                table_checksum(data)
+            };
+            table_records.insert(
+                *tag,
+                TableRecord {
+                    tag: *tag,
+                    check_sum,
+                    offset: offset_u32(table_offset)?,
+                    length: data.len() as u32,
+                },
+            );
+        }
+        records.push(table_records);
+    }
+    let dsig_offset = offset;
+    offset_u32(dsig_offset + dsig.map_or(0, <[u8]>::len))?;
+
+    // compute the checksum adjustment of every font
+    let mut directories = Vec::with_capacity(fonts.len());
+    let mut head_index = 0;
+    for (font, table_records) in fonts.iter().zip(records) {
+        let record = FontRecord::new(u32::from_be_bytes(font.version_tag.0), table_records);
+        let mut directory = Vec::new();
+        record.write_to(&mut directory)?;
+        let font_checksum = record
+            .tables
+            .values()
+            .fold(table_checksum(&directory), |sum, table| {
+                sum.wrapping_add(table.check_sum)
+            });
+        if record.tables.contains_key(&Tag(*b"head")) {
+            let check_sum_adjustment = 0xB1B0_AFBAu32.wrapping_sub(font_checksum);
+            heads[head_index].1[8..12].copy_from_slice(&check_sum_adjustment.to_be_bytes());
+            head_index += 1;
+        }
+        directories.push(directory);
+    }
+
+    sink.write_all(b"ttcf")?;
+    if dsig.is_some() {
+        sink.write_all(&[0, 2, 0, 0])?;
+    } else {
+        sink.write_all(&[0, 1, 0, 0])?;
+    }
+    sink.write_all(&(fonts.len() as u32).to_be_bytes())?;
+    for &directory_offset in &directory_offsets {
+        sink.write_all(&(directory_offset as u32).to_be_bytes())?;
+    }
+    if let Some(dsig) = dsig {
+        sink.write_all(b"DSIG")?;
+        sink.write_all(&(dsig.len() as u32).to_be_bytes())?;
+        sink.write_all(&(dsig_offset as u32).to_be_bytes())?;
+    }
+    for directory in &directories {
+        sink.write_all(directory)?;
+    }
+
+    let mut tables: Vec<(usize, &[u8])> = blobs;
+    tables.extend(
+        heads
+            .iter()
+            .map(|(offset, data)| (*offset, data.as_slice())),
+    );
+    tables.sort_by_key(|&(offset, _)| offset);
+    for (_, data) in tables {
+        sink.write_all(data)?;
+        sink.write_all(&[0; 4][..padded_len(data.len()) - data.len()])?;
+    }
+    if let Some(dsig) = dsig {
+        sink.write_all(dsig)?;
+    }
+    Ok(())
+}
+
+fn offset_u32(offset: usize) -> io::Result<u32> {
+    if offset > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too much data for a font collection.",
+        ));
+    }
+    Ok(offset as u32)
+}
+
+/// Writes the font with the given index of a collection as a standalone font
+/// file.
+pub fn extract_font(data: &[u8], index: u32, sink: &mut dyn Write) -> Result<(), ParserError> {
+    Font::from_bytes(data, index)?
+        .write_to(sink)
+        .map_err(ParserError::from_err)
+}
@@ -0,0 +1,507 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Conversion between quadratic (TrueType) and cubic (CFF) outlines.
+//!
+//! Quadratic curves are converted to cubic curves exactly by degree
+//! elevation. Cubic curves are approximated by quadratic splines within a
+//! given tolerance using the algorithm of the `cu2qu` library: for increasing
+//! numbers of segments a spline is constructed and accepted once the
+//! farthest distance to the original curve is below the tolerance.
+//!
+//! Splines are returned as a list of points where the first and last point
+//! lie on the curve and all other points are off-curve control points. As in
+//! TrueType, an on-curve point is implied halfway between two consecutive
+//! off-curve points.
+
+use crate::cff::Fixed16_16;
+use crate::outline::Point;
+use crate::{PathInstruction, QuadraticPath};
+
+/// The maximal number of quadratic segments used to approximate a single
+/// cubic curve.
+const MAX_SEGMENTS: usize = 100;
+
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The paths do not consist of the same sequence of drawing commands.
+    #[display(fmt = "Paths are not compatible.")]
+    IncompatiblePaths,
+    /// No quadratic spline with at most `MAX_SEGMENTS` segments is within the
+    /// requested tolerance.
+    #[display(fmt = "Could not approximate curve within the given tolerance.")]
+    ApproximationNotFound,
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Converts a TrueType path into an equivalent path of cubic curves.
+///
+/// The relative coordinates of the returned instructions are computed from
+/// rounded absolute positions, such that no error accumulates along the path.
+pub fn quadratic_to_cubic(path: impl IntoIterator<Item = QuadraticPath>) -> Vec<PathInstruction> {
+    let mut result = vec![];
+    let mut cursor = (Fixed16_16::default(), Fixed16_16::default());
+    let mut emit_relative = |points: &[Point]| {
+        let mut deltas = Vec::with_capacity(points.len() * 2);
+        for p in points {
+            let next = (Fixed16_16::from(p.x), Fixed16_16::from(p.y));
+            deltas.push(next.0 - cursor.0);
+            deltas.push(next.1 - cursor.1);
+            cursor = next;
+        }
+        deltas
+    };
+    let mut last = Point::default();
+    for instr in path {
+        match instr {
+            QuadraticPath::MoveTo(x, y) => {
+                last = Point::new(x as f32, y as f32);
+                let d = emit_relative(&[last]);
+                result.push(PathInstruction::MoveTo(d[0], d[1]));
+            }
+            QuadraticPath::LineTo(x, y) => {
+                last = Point::new(x as f32, y as f32);
+                let d = emit_relative(&[last]);
+                result.push(PathInstruction::LineTo(d[0], d[1]));
+            }
+            QuadraticPath::CurveTo(cx, cy, x, y) => {
+                let control = Point::new(cx as f32, cy as f32);
+                let end = Point::new(x as f32, y as f32);
+                let [_, c1, c2, _] = elevate(last, control, end);
+                last = end;
+                let d = emit_relative(&[c1, c2, end]);
+                result.push(PathInstruction::CurveTo(d[0], d[1], d[2], d[3], d[4], d[5]));
+            }
+            QuadraticPath::Close => result.push(PathInstruction::Close),
+        }
+    }
+    result
+}
+
+/// Returns the cubic curve describing the same curve as the quadratic curve
+/// with the given control points.
+pub fn elevate(p0: Point, p1: Point, p2: Point) -> [Point; 4] {
+    [
+        p0,
+        p0 + (p1 - p0) * (2.0 / 3.0),
+        p2 + (p1 - p2) * (2.0 / 3.0),
+        p2,
+    ]
+}
+
+/// Approximates a cubic path by a quadratic one whose maximal deviation from
+/// the original is `max_err` font units.
+///
+/// Since `QuadraticPath` only supports integer coordinates the on-curve points
+/// implied between two control points are rounded, which may add an error of
+/// up to half a font unit.
+pub fn cubic_to_quadratic(
+    path: impl IntoIterator<Item = PathInstruction>,
+    max_err: f32,
+) -> Result<Vec<QuadraticPath>, ConversionError> {
+    let path: Vec<_> = path.into_iter().collect();
+    let mut result = compatible_cubic_to_quadratic(&[path], max_err)?;
+    Ok(result.remove(0))
+}
+
+/// Approximates several compatible cubic paths (for example the masters of a
+/// variable font) by quadratic paths.
+///
+/// Corresponding curves of all paths are approximated using the same number
+/// of quadratic segments, such that the resulting paths are compatible again.
+/// The tolerance `max_err` is shared by all paths.
+pub fn compatible_cubic_to_quadratic(
+    paths: &[Vec<PathInstruction>],
+    max_err: f32,
+) -> Result<Vec<Vec<QuadraticPath>>, ConversionError> {
+    let paths: Vec<Vec<AbsoluteInstruction>> = paths.iter().map(|path| to_absolute(path)).collect();
+    let first = match paths.first() {
+        Some(first) => first,
+        None => return Ok(vec![]),
+    };
+    let compatible = paths.iter().all(|path| {
+        path.len() == first.len()
+            && path
+                .iter()
+                .zip(first)
+                .all(|(a, b)| std::mem::discriminant(a) == std::mem::discriminant(b))
+    });
+    if !compatible {
+        return Err(ConversionError::IncompatiblePaths);
+    }
+
+    let mut results = vec![vec![]; paths.len()];
+    let max_errors = vec![max_err; paths.len()];
+    for index in 0..first.len() {
+        match first[index] {
+            AbsoluteInstruction::CurveTo(..) => {
+                let curves: Vec<[Point; 4]> = paths
+                    .iter()
+                    .map(|path| match path[index] {
+                        AbsoluteInstruction::CurveTo(p0, p1, p2, p3) => [p0, p1, p2, p3],
+                        _ => unreachable!(),
+                    })
+                    .collect();
+                let splines = curves_to_quadratic(&curves, &max_errors)
+                    .ok_or(ConversionError::ApproximationNotFound)?;
+                for (result, spline) in results.iter_mut().zip(splines) {
+                    push_spline(result, &spline);
+                }
+            }
+            _ => {
+                for (result, path) in results.iter_mut().zip(&paths) {
+                    result.push(match path[index] {
+                        AbsoluteInstruction::MoveTo(p) => {
+                            QuadraticPath::MoveTo(round(p.x), round(p.y))
+                        }
+                        AbsoluteInstruction::LineTo(p) => {
+                            QuadraticPath::LineTo(round(p.x), round(p.y))
+                        }
+                        AbsoluteInstruction::Close => QuadraticPath::Close,
+                        AbsoluteInstruction::CurveTo(..) => unreachable!(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Approximates a single cubic curve by a quadratic spline.
+///
+/// Returns `None` if no spline with at most 100 segments is within
+/// `max_err`.
+pub fn curve_to_quadratic(curve: [Point; 4], max_err: f32) -> Option<Vec<Point>> {
+    (1..=MAX_SEGMENTS).find_map(|n| cubic_approx_spline(curve, n, max_err))
+}
+
+/// Approximates several cubic curves by quadratic splines which all consist
+/// of the same number of segments.
+///
+/// `max_errors` contains the tolerance for each of the curves. Returns `None`
+/// if it does not have one tolerance for every curve.
+pub fn curves_to_quadratic(curves: &[[Point; 4]], max_errors: &[f32]) -> Option<Vec<Vec<Point>>> {
+    if curves.len() != max_errors.len() {
+        return None;
+    }
+    if curves.is_empty() {
+        return Some(vec![]);
+    }
+
+    let mut splines = vec![vec![]; curves.len()];
+    let mut last_i = 0;
+    let mut i = 0;
+    let mut n = 1;
+    loop {
+        match cubic_approx_spline(curves[i], n, max_errors[i]) {
+            None => {
+                if n == MAX_SEGMENTS {
+                    return None;
+                }
+                n += 1;
+                last_i = i;
+            }
+            Some(spline) => {
+                splines[i] = spline;
+                i = (i + 1) % curves.len();
+                if i == last_i {
+                    return Some(splines);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum AbsoluteInstruction {
+    MoveTo(Point),
+    LineTo(Point),
+    CurveTo(Point, Point, Point, Point),
+    Close,
+}
+
+fn to_absolute(path: &[PathInstruction]) -> Vec<AbsoluteInstruction> {
+    let mut cursor = Point::default();
+    path.iter()
+        .map(|instr| match *instr {
+            PathInstruction::MoveTo(dx, dy) => {
+                cursor = cursor + Point::new(dx.into(), dy.into());
+                AbsoluteInstruction::MoveTo(cursor)
+            }
+            PathInstruction::LineTo(dx, dy) => {
+                cursor = cursor + Point::new(dx.into(), dy.into());
+                AbsoluteInstruction::LineTo(cursor)
+            }
+            PathInstruction::CurveTo(dx1, dy1, dx2, dy2, dx, dy) => {
+                let start = cursor;
+                let c1 = start + Point::new(dx1.into(), dy1.into());
+                let c2 = c1 + Point::new(dx2.into(), dy2.into());
+                cursor = c2 + Point::new(dx.into(), dy.into());
+                AbsoluteInstruction::CurveTo(start, c1, c2, cursor)
+            }
+            PathInstruction::Close => AbsoluteInstruction::Close,
+        })
+        .collect()
+}
+
+fn round(value: f32) -> i32 {
+    value.round() as i32
+}
+
+/// Appends a spline to a quadratic path, making implied on-curve points
+/// explicit.
+fn push_spline(path: &mut Vec<QuadraticPath>, spline: &[Point]) {
+    let controls = &spline[1..spline.len() - 1];
+    let end = spline[spline.len() - 1];
+    for (i, control) in controls.iter().enumerate() {
+        let on_curve = match controls.get(i + 1) {
+            Some(next) => (*control + *next) * 0.5,
+            None => end,
+        };
+        path.push(QuadraticPath::CurveTo(
+            round(control.x),
+            round(control.y),
+            round(on_curve.x),
+            round(on_curve.y),
+        ));
+    }
+}
+
+fn cubic_approx_control(t: f32, p0: Point, p1: Point, p2: Point, p3: Point) -> Point {
+    let q1 = p0 + (p1 - p0) * 1.5;
+    let q2 = p3 + (p2 - p3) * 1.5;
+    q1 + (q2 - q1) * t
+}
+
+/// Computes the intersection of the lines `ab` and `cd`.
+fn calc_intersect(a: Point, b: Point, c: Point, d: Point) -> Option<Point> {
+    let ab = b - a;
+    let cd = d - c;
+    // `ab` rotated by 90 degrees
+    let p = Point::new(-ab.y, ab.x);
+    let denominator = p.dot(cd);
+    if denominator == 0.0 {
+        return None;
+    }
+    let h = p.dot(a - c) / denominator;
+    Some(c + cd * h)
+}
+
+/// Checks whether the cubic curve given by the control points stays within
+/// a circle of radius `tolerance` around the origin.
+fn cubic_farthest_fit_inside(p0: Point, p1: Point, p2: Point, p3: Point, tolerance: f32) -> bool {
+    if p2.length() <= tolerance && p1.length() <= tolerance {
+        return true;
+    }
+    let mid = (p0 + (p1 + p2) * 3.0 + p3) * 0.125;
+    if mid.length() > tolerance {
+        return false;
+    }
+    let deriv3 = (p3 + p2 - p1 - p0) * 0.125;
+    cubic_farthest_fit_inside(p0, (p0 + p1) * 0.5, mid - deriv3, mid, tolerance)
+        && cubic_farthest_fit_inside(mid, mid + deriv3, (p2 + p3) * 0.5, p3, tolerance)
+}
+
+fn cubic_approx_quadratic(cubic: [Point; 4], tolerance: f32) -> Option<Vec<Point>> {
+    let [c0, c1, c2, c3] = cubic;
+    let q1 = calc_intersect(c0, c1, c2, c3)?;
+    let e1 = c0 + (q1 - c0) * (2.0 / 3.0);
+    let e2 = c3 + (q1 - c3) * (2.0 / 3.0);
+    if !cubic_farthest_fit_inside(
+        Point::default(),
+        e1 - c1,
+        e2 - c2,
+        Point::default(),
+        tolerance,
+    ) {
+        return None;
+    }
+    Some(vec![c0, q1, c3])
+}
+
+/// Splits a cubic curve into `n` curves of equal parameter length.
+fn split_cubic_into_n(cubic: [Point; 4], n: usize) -> Vec<[Point; 4]> {
+    let [p0, p1, p2, p3] = cubic;
+    // polynomial coefficients
+    let c = (p1 - p0) * 3.0;
+    let b = (p2 - p1) * 3.0 - c;
+    let d = p0;
+    let a = p3 - d - c - b;
+
+    let dt = 1.0 / n as f32;
+    let delta_2 = dt * dt;
+    let delta_3 = dt * delta_2;
+    (0..n)
+        .map(|i| {
+            let t1 = i as f32 * dt;
+            let t1_2 = t1 * t1;
+            let a1 = a * delta_3;
+            let b1 = (a * (3.0 * t1) + b) * delta_2;
+            let c1 = (b * (2.0 * t1) + c + a * (3.0 * t1_2)) * dt;
+            let d1 = a * (t1 * t1_2) + b * t1_2 + c * t1 + d;
+
+            let q1 = c1 * (1.0 / 3.0) + d1;
+            let q2 = (b1 + c1) * (1.0 / 3.0) + q1;
+            [d1, q1, q2, a1 + d1 + c1 + b1]
+        })
+        .collect()
+}
+
+fn cubic_approx_spline(cubic: [Point; 4], n: usize, tolerance: f32) -> Option<Vec<Point>> {
+    if n == 1 {
+        return cubic_approx_quadratic(cubic, tolerance);
+    }
+
+    let cubics = split_cubic_into_n(cubic, n);
+    let mut next_q1 =
+        cubic_approx_control(0.0, cubics[0][0], cubics[0][1], cubics[0][2], cubics[0][3]);
+    let mut q2 = cubic[0];
+    let mut d1 = Point::default();
+    let mut spline = vec![cubic[0], next_q1];
+    for i in 1..=n {
+        let [_, c1, c2, c3] = cubics[i - 1];
+        let q0 = q2;
+        let q1 = next_q1;
+        if i < n {
+            let [n0, n1, n2, n3] = cubics[i];
+            next_q1 = cubic_approx_control(i as f32 / (n - 1) as f32, n0, n1, n2, n3);
+            spline.push(next_q1);
+            q2 = (q1 + next_q1) * 0.5;
+        } else {
+            q2 = c3;
+        }
+
+        let d0 = d1;
+        d1 = q2 - c3;
+        if d1.length() > tolerance
+            || !cubic_farthest_fit_inside(
+                d0,
+                q0 + (q1 - q0) * (2.0 / 3.0) - c1,
+                q2 + (q1 - q2) * (2.0 / 3.0) - c2,
+                d1,
+                tolerance,
+            )
+        {
+            return None;
+        }
+    }
+    spline.push(cubic[3]);
+    Some(spline)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::outline::Segment;
+
+    /// The maximal distance of sampled points of `curve` to the spline.
+    fn spline_error(curve: [Point; 4], spline: &[Point]) -> f32 {
+        let mut segments = vec![];
+        let controls = &spline[1..spline.len() - 1];
+        let mut start = spline[0];
+        for (i, &control) in controls.iter().enumerate() {
+            let end = match controls.get(i + 1) {
+                Some(&next) => (control + next) * 0.5,
+                None => spline[spline.len() - 1],
+            };
+            segments.push(Segment::Quadratic(start, control, end));
+            start = end;
+        }
+        let cubic = Segment::Cubic(curve[0], curve[1], curve[2], curve[3]);
+        (0..=100)
+            .map(|i| {
+                let p = cubic.point(i as f32 / 100.0);
+                segments
+                    .iter()
+                    .flat_map(|s| (0..=200).map(move |j| (s.point(j as f32 / 200.0) - p).length()))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    const CURVE: [Point; 4] = [
+        Point { x: 0.0, y: 0.0 },
+        Point { x: 0.0, y: 300.0 },
+        Point { x: 500.0, y: 600.0 },
+        Point { x: 700.0, y: 0.0 },
+    ];
+
+    #[test]
+    fn test_elevation_is_exact() {
+        let quadratic = Segment::Quadratic(
+            Point::new(0.0, 0.0),
+            Point::new(30.0, 90.0),
+            Point::new(60.0, 0.0),
+        );
+        let [p0, p1, p2, p3] = elevate(quadratic.start(), Point::new(30.0, 90.0), quadratic.end());
+        let cubic = Segment::Cubic(p0, p1, p2, p3);
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!((cubic.point(t) - quadratic.point(t)).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_curve_to_quadratic_tolerance() {
+        for &tolerance in &[10.0, 1.0, 0.1] {
+            let spline = curve_to_quadratic(CURVE, tolerance).unwrap();
+            assert!(spline_error(CURVE, &spline) <= tolerance * 1.05);
+        }
+        let coarse = curve_to_quadratic(CURVE, 10.0).unwrap();
+        let fine = curve_to_quadratic(CURVE, 0.1).unwrap();
+        assert!(coarse.len() < fine.len());
+    }
+
+    #[test]
+    fn test_compatible_curves() {
+        let other = [CURVE[0], CURVE[1], Point::new(300.0, 700.0), CURVE[3]];
+        let splines = curves_to_quadratic(&[CURVE, other], &[1.0, 1.0]).unwrap();
+        assert_eq!(splines[0].len(), splines[1].len());
+        assert!(spline_error(CURVE, &splines[0]) <= 1.05);
+        assert!(spline_error(other, &splines[1]) <= 1.05);
+    }
+
+    #[test]
+    fn test_compatible_curves_missing_tolerance() {
+        assert_eq!(curves_to_quadratic(&[CURVE, CURVE], &[1.0]), None);
+    }
+
+    #[test]
+    fn test_round_trip_path() {
+        let quadratic = vec![
+            QuadraticPath::MoveTo(10, 20),
+            QuadraticPath::LineTo(100, 20),
+            QuadraticPath::CurveTo(150, 70, 100, 120),
+            QuadraticPath::Close,
+        ];
+        let cubic = quadratic_to_cubic(quadratic.clone());
+        assert_eq!(cubic[0], PathInstruction::MoveTo(10.into(), 20.into()));
+        assert_eq!(cubic[1], PathInstruction::LineTo(90.into(), 0.into()));
+
+        // a quadratic curve elevated to a cubic one is converted back exactly
+        let back = cubic_to_quadratic(cubic, 0.5).unwrap();
+        assert_eq!(back, quadratic);
+    }
+
+    #[test]
+    fn test_incompatible_paths() {
+        let a = vec![PathInstruction::MoveTo(0.into(), 0.into())];
+        let b = vec![PathInstruction::LineTo(0.into(), 0.into())];
+        assert_eq!(
+            compatible_cubic_to_quadratic(&[a, b], 1.0),
+            Err(ConversionError::IncompatiblePaths)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Reading Mac OS resource forks such as `.dfont` files, which store fonts
+//! as `sfnt` resources.
+
+use nom::{be_i16, be_u16, be_u24, be_u32, be_u8};
+
+use crate::error::ParserError;
+use crate::{parse_tag, Font, Tag};
+
+/// The resource type of fonts.
+const SFNT_TYPE: Tag = Tag(*b"sfnt");
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResourceHeader {
+    /// The offset of the resource data from the start of the fork.
+    pub data_offset: u32,
+    /// The offset of the resource map from the start of the fork.
+    pub map_offset: u32,
+    pub data_length: u32,
+    pub map_length: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    pub resource_type: Tag,
+    pub id: i16,
+    /// The name of the resource in the Mac OS Roman encoding.
+    pub name: Option<Vec<u8>>,
+    pub attributes: u8,
+    /// The offset of the resource from the start of the resource data.
+    pub data_offset: u32,
+}
+
+named!(parse_resource_header<&[u8], ResourceHeader>,
+    do_parse!(
+        data_offset: be_u32 >>
+        map_offset: be_u32 >>
+        data_length: be_u32 >>
+        map_length: be_u32 >>
+        (ResourceHeader { data_offset, map_offset, data_length, map_length })
+    )
+);
+
+// Parses the offsets of the type list and the name list from the resource
+// map.
+named!(parse_map_offsets<&[u8], (u16, u16)>,
+    do_parse!(
+        // copy of the header, handle to the next map, file reference number
+        // and attributes
+        take!(16 + 4 + 2 + 2) >>
+        type_list_offset: be_u16 >>
+        name_list_offset: be_u16 >>
+        (type_list_offset, name_list_offset)
+    )
+);
+
+// Parses a type list entry into the type, the number of resources and the
+// offset of the reference list from the type list.
+named!(parse_type_entry<&[u8], (Tag, usize, u16)>,
+    do_parse!(
+        resource_type: parse_tag >>
+        num_resources_minus_one: be_u16 >>
+        reference_list_offset: be_u16 >>
+        (resource_type, usize::from(num_resources_minus_one) + 1, reference_list_offset)
+    )
+);
+
+// Parses a reference list entry into the id, the offset of the name, the
+// attributes and the offset of the data.
+named!(parse_reference<&[u8], (i16, u16, u8, u32)>,
+    do_parse!(
+        id: be_i16 >>
+        name_offset: be_u16 >>
+        attributes: be_u8 >>
+        data_offset: be_u24 >>
+        // reserved for the handle
+        be_u32 >>
+        (id, name_offset, attributes, data_offset)
+    )
+);
+
+named!(parse_name<&[u8], &[u8]>, length_data!(be_u8));
+
+/// A Mac OS resource fork.
+///
+/// Fonts are stored as `sfnt` resources, which are available in the order of
+/// the resource map with [`font`](#method.font) just like the fonts of a
+/// collection.
+///
+/// Examples
+/// --------
+///
+/// ```no_run
+/// use font_parse::dfont::ResourceFork;
+///
+/// let data = std::fs::read("Fonts.dfont").unwrap();
+/// let fork = ResourceFork::from_bytes(&data).unwrap();
+/// for index in 0..fork.num_fonts() {
+///     let font = fork.font(index as u32).unwrap();
+///     println!("{:?}", font.record().tables.keys().collect::<Vec<_>>());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResourceFork<'a> {
+    pub header: ResourceHeader,
+    pub resources: Vec<Resource>,
+    data: &'a [u8],
+}
+
+impl<'a> ResourceFork<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParserError> {
+        let (_, header) = parse_resource_header(data)?;
+        let map = data
+            .get(header.map_offset as usize..)
+            .and_then(|map| map.get(..header.map_length as usize))
+            .ok_or_else(|| {
+                ParserError::from_string("The resource map is truncated.".to_string())
+            })?;
+        let (_, (type_list_offset, name_list_offset)) = parse_map_offsets(map)?;
+        let type_list = map.get(usize::from(type_list_offset)..).unwrap_or(&[]);
+        let name_list = map.get(usize::from(name_list_offset)..).unwrap_or(&[]);
+
+        let (rest, num_types_minus_one) = be_u16(type_list)?;
+        // an empty map stores 0xFFFF as the number of types minus one
+        let num_types = num_types_minus_one.wrapping_add(1);
+        let (_, types) = count!(rest, parse_type_entry, usize::from(num_types))?;
+        let mut resources = Vec::new();
+        for (resource_type, num_resources, reference_list_offset) in types {
+            let reference_list = type_list
+                .get(usize::from(reference_list_offset)..)
+                .unwrap_or(&[]);
+            let (_, references) = count!(reference_list, parse_reference, num_resources)?;
+            for (id, name_offset, attributes, data_offset) in references {
+                let name = if name_offset == 0xFFFF {
+                    None
+                } else {
+                    let name = name_list.get(usize::from(name_offset)..).unwrap_or(&[]);
+                    Some(parse_name(name)?.1.to_vec())
+                };
+                resources.push(Resource {
+                    resource_type,
+                    id,
+                    name,
+                    attributes,
+                    data_offset,
+                });
+            }
+        }
+        Ok(ResourceFork {
+            header,
+            resources,
+            data,
+        })
+    }
+
+    /// Returns the data of a resource.
+    pub fn resource_data(&self, resource: &Resource) -> Result<&'a [u8], ParserError> {
+        let offset = u64::from(self.header.data_offset) + u64::from(resource.data_offset);
+        let block = self
+            .data
+            .get(offset as usize..)
+            .ok_or_else(|| ParserError::from_string("Resource data out of bounds.".to_string()))?;
+        let (_, data) = length_data!(block, be_u32)?;
+        Ok(data)
+    }
+
+    /// Returns all resources of the given type.
+    pub fn resources_of_type(&self, resource_type: Tag) -> impl Iterator<Item = &Resource> {
+        self.resources
+            .iter()
+            .filter(move |resource| resource.resource_type == resource_type)
+    }
+
+    /// Returns the number of `sfnt` resources.
+    pub fn num_fonts(&self) -> usize {
+        self.resources_of_type(SFNT_TYPE).count()
+    }
+
+    /// Returns the font stored in the `sfnt` resource with the given index.
+    pub fn font(&self, index: u32) -> Result<Font<'a>, ParserError> {
+        let resource = self
+            .resources_of_type(SFNT_TYPE)
+            .nth(index as usize)
+            .ok_or_else(|| ParserError::font_not_found(index as usize))?;
+        Font::from_bytes(self.resource_data(resource)?, 0)
+    }
+}
@@ -0,0 +1,246 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Validation of the table directory of a font.
+//!
+//! [`Font::from_bytes`](../struct.Font.html#method.from_bytes) loads fonts
+//! leniently and only keeps the last record of every tag. The directory can
+//! be checked on demand with
+//! [`Font::directory_diagnostics`](../struct.Font.html#method.directory_diagnostics),
+//! or when loading with
+//! [`Font::from_bytes_strict`](../struct.Font.html#method.from_bytes_strict).
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::{structure, ParserError};
+use crate::reader::Reader;
+use crate::{read_table_record, TableRecord, Tag};
+
+/// A problem found in a table directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectoryDiagnostic {
+    /// The table extends past the end of the file.
+    OutOfBounds {
+        tag: Tag,
+        offset: u32,
+        length: u32,
+        file_length: usize,
+    },
+    /// The data of two tables overlaps.
+    Overlap(Tag, Tag),
+    /// There is more than one record for the tag.
+    DuplicateTag(Tag),
+    /// The records are not sorted by their tags, so binary search fails.
+    Unsorted { previous: Tag, tag: Tag },
+    /// The table is empty.
+    ZeroLength(Tag),
+    /// The table does not start at a multiple of four bytes.
+    Misaligned { tag: Tag, offset: u32 },
+    /// `searchRange`, `entrySelector` and `rangeShift` do not match the number
+    /// of tables.
+    SearchParameters {
+        expected: (u16, u16, u16),
+        found: (u16, u16, u16),
+    },
+}
+
+impl DirectoryDiagnostic {
+    /// Returns whether the table data can not be used as it is.
+    ///
+    /// All other diagnostics violate the specification, but the tables are
+    /// still readable.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            DirectoryDiagnostic::OutOfBounds { .. }
+                | DirectoryDiagnostic::Overlap(..)
+                | DirectoryDiagnostic::DuplicateTag(_)
+        )
+    }
+}
+
+impl Display for DirectoryDiagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DirectoryDiagnostic::OutOfBounds {
+                tag,
+                offset,
+                length,
+                file_length,
+            } => write!(
+                f,
+                "Table {} at offset {} with length {} exceeds the file length {}.",
+                tag, offset, length, file_length
+            ),
+            DirectoryDiagnostic::Overlap(first, second) => {
+                write!(f, "Tables {} and {} overlap.", first, second)
+            }
+            DirectoryDiagnostic::DuplicateTag(tag) => {
+                write!(f, "Table {} occurs more than once.", tag)
+            }
+            DirectoryDiagnostic::Unsorted { previous, tag } => {
+                write!(f, "Table {} is listed after table {}.", tag, previous)
+            }
+            DirectoryDiagnostic::ZeroLength(tag) => write!(f, "Table {} is empty.", tag),
+            DirectoryDiagnostic::Misaligned { tag, offset } => write!(
+                f,
+                "Table {} at offset {} is not aligned to four bytes.",
+                tag, offset
+            ),
+            DirectoryDiagnostic::SearchParameters { expected, found } => write!(
+                f,
+                "Expected search range, entry selector and range shift {:?}, found {:?}.",
+                expected, found
+            ),
+        }
+    }
+}
+
+/// The error returned for table directories with diagnostics which are
+/// errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDirectory(pub Vec<DirectoryDiagnostic>);
+
+impl Display for InvalidDirectory {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Invalid table directory:")?;
+        for diagnostic in &self.0 {
+            write!(f, " {}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidDirectory {}
+
+/// Returns `searchRange`, `entrySelector` and `rangeShift` for the number of
+/// tables.
+pub fn search_parameters(num_tables: u16) -> (u16, u16, u16) {
+    if num_tables == 0 {
+        return (0, 0, 0);
+    }
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range = (1u32 << entry_selector) * 16;
+    let range_shift = u32::from(num_tables) * 16 - search_range;
+    (search_range as u16, entry_selector, range_shift as u16)
+}
+
+/// The search parameters and the table records in the order of the directory.
+type Directory = ((u16, u16, u16), Vec<TableRecord>);
+
+fn read_directory(reader: &mut Reader) -> Result<Directory, ParserError> {
+    reader.structure(structure::TABLE_DIRECTORY);
+    // sfnt version
+    reader.skip(4)?;
+    let num_tables: u16 = reader.read()?;
+    let found = (reader.read()?, reader.read()?, reader.read()?);
+    let records = (0..num_tables)
+        .map(|_| read_table_record(reader))
+        .collect::<Result<_, _>>()?;
+    Ok((found, records))
+}
+
+/// Checks the table directory which starts at `offset` in `data`.
+///
+/// The records are checked in the order of the directory, including records
+/// with duplicate tags.
+pub fn validate_directory(
+    data: &[u8],
+    offset: usize,
+) -> Result<Vec<DirectoryDiagnostic>, ParserError> {
+    let directory = data.get(offset..).ok_or_else(|| {
+        ParserError::from_string("The table directory is out of bounds.".to_string())
+    })?;
+    let (found, records) =
+        read_directory(&mut Reader::new(directory)).map_err(|err| err.locate(data))?;
+    let mut diagnostics = vec![];
+
+    let expected = search_parameters(records.len() as u16);
+    if found != expected {
+        diagnostics.push(DirectoryDiagnostic::SearchParameters { expected, found });
+    }
+
+    // the number of records and the index of the last record of every tag
+    let mut occurrences: HashMap<Tag, (usize, usize)> = HashMap::new();
+    for (index, record) in records.iter().enumerate() {
+        let (count, last) = occurrences.entry(record.tag).or_insert((0, index));
+        *count += 1;
+        *last = index;
+    }
+
+    for (index, record) in records.iter().enumerate() {
+        let tag = record.tag;
+        let (count, last) = occurrences[&tag];
+        if count > 1 && last == index {
+            diagnostics.push(DirectoryDiagnostic::DuplicateTag(tag));
+        }
+        if let Some(previous) = index.checked_sub(1).map(|previous| records[previous].tag) {
+            if previous > tag {
+                diagnostics.push(DirectoryDiagnostic::Unsorted { previous, tag });
+            }
+        }
+        if u64::from(record.offset) + u64::from(record.length) > data.len() as u64 {
+            diagnostics.push(DirectoryDiagnostic::OutOfBounds {
+                tag,
+                offset: record.offset,
+                length: record.length,
+                file_length: data.len(),
+            });
+        }
+        if record.length == 0 {
+            diagnostics.push(DirectoryDiagnostic::ZeroLength(tag));
+        }
+        if record.offset % 4 != 0 {
+            diagnostics.push(DirectoryDiagnostic::Misaligned {
+                tag,
+                offset: record.offset,
+            });
+        }
+    }
+
+    // compare every table with the one reaching furthest among those before it
+    let mut by_offset: Vec<&TableRecord> =
+        records.iter().filter(|record| record.length != 0).collect();
+    by_offset.sort_by_key(|record| (record.offset, record.tag));
+    let mut furthest: Option<(u64, Tag)> = None;
+    for record in by_offset {
+        let end = u64::from(record.offset) + u64::from(record.length);
+        if let Some((furthest_end, furthest_tag)) = furthest {
+            if u64::from(record.offset) < furthest_end {
+                diagnostics.push(DirectoryDiagnostic::Overlap(furthest_tag, record.tag));
+            }
+            if end <= furthest_end {
+                continue;
+            }
+        }
+        furthest = Some((end, record.tag));
+    }
+
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search_parameters() {
+        assert_eq!(search_parameters(1), (16, 0, 0));
+        assert_eq!(search_parameters(9), (128, 3, 16));
+        assert_eq!(search_parameters(16), (256, 4, 0));
+        assert_eq!(search_parameters(0), (0, 0, 0));
+    }
+}
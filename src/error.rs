@@ -22,15 +22,76 @@ use std::fmt::{self, Display, Formatter};
 
 use crate::Tag;
 
-#[derive(Debug, Display)]
+/// Codes of the `nom::ErrorKind::Custom` errors with which parsers name the
+/// structure they are reading.
+pub(crate) mod structure {
+    pub const END_PTS_OF_CONTOURS: u32 = 0;
+    pub const INSTRUCTIONS: u32 = 1;
+    pub const GLYPH_HEADER: u32 = 2;
+    pub const COMPONENT: u32 = 3;
+    pub const TABLE_DIRECTORY: u32 = 4;
+    pub const TABLE_RECORD: u32 = 5;
+    pub const COLLECTION_HEADER: u32 = 6;
+    pub const CFF_HEADER: u32 = 7;
+    pub const INDEX: u32 = 8;
+    pub const ENCODING_RECORD: u32 = 9;
+    pub const CMAP_SEGMENTS: u32 = 10;
+    pub const SEQUENTIAL_MAP_GROUP: u32 = 11;
+    pub const LONG_HOR_METRIC: u32 = 12;
+    pub const NAME_RECORD: u32 = 13;
+    pub const POINTS: u32 = 14;
+
+    pub(super) const NAMES: [&str; 15] = [
+        "endPtsOfContours",
+        "instructions",
+        "glyph header",
+        "component",
+        "table directory",
+        "table record",
+        "collection header",
+        "CFF header",
+        "INDEX",
+        "encoding record",
+        "cmap segments",
+        "sequential map group",
+        "longHorMetric",
+        "name record",
+        "points",
+    ];
+}
+
+#[derive(Debug)]
 pub struct ParserError {
-    kind: Box<ErrorKind>,
+    inner: Box<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    kind: ErrorKind,
+    /// The table in which the error occurred.
+    table: Option<Tag>,
+    /// The structure that was being parsed.
+    structure: Option<&'static str>,
+    position: Option<Position>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Position {
+    /// The address of the data at which a parser failed, which becomes an
+    /// offset once the table containing the data is known.
+    Address(usize),
+    Offset(usize),
 }
 
 impl ParserError {
     pub fn new(kind: ErrorKind) -> Self {
         ParserError {
-            kind: Box::new(kind),
+            inner: Box::new(Inner {
+                kind,
+                table: None,
+                structure: None,
+                position: None,
+            }),
         }
     }
 
@@ -56,55 +117,161 @@ impl ParserError {
     }
 
     pub fn glyph_parse(index: u32, error: ParserError) -> Self {
-        ParserError::new(ErrorKind::GlyphParse {
+        let table = error.table();
+        let mut error = ParserError::new(ErrorKind::GlyphParse {
             index,
             cause: error,
-        })
+        });
+        error.inner.table = table;
+        error
     }
 
     pub fn kind(&self) -> &ErrorKind {
-        &self.kind
+        &self.inner.kind
+    }
+
+    /// Returns the tag of the table in which the error occurred, if known.
+    pub fn table(&self) -> Option<Tag> {
+        self.inner.table
+    }
+
+    /// Returns the offset from the start of the table at which the error
+    /// occurred, if known. Errors in the table directory have offsets from the
+    /// start of the file.
+    pub fn offset(&self) -> Option<usize> {
+        match self.inner.position {
+            Some(Position::Offset(offset)) => Some(offset),
+            _ => match self.kind() {
+                ErrorKind::GlyphParse { cause, .. } => cause.offset(),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns the name of the field or structure that was being parsed, if
+    /// known.
+    pub fn structure(&self) -> Option<&'static str> {
+        self.inner.structure.or_else(|| match self.kind() {
+            ErrorKind::GlyphParse { cause, .. } => cause.structure(),
+            _ => None,
+        })
+    }
+
+    /// Sets the table in which the error occurred unless it is already known.
+    ///
+    /// If the parser failed within `data`, the offset of the error from its
+    /// start is recorded.
+    pub(crate) fn in_table(mut self, tag: Tag, data: &[u8]) -> Self {
+        if self.inner.table.is_some() {
+            return self;
+        }
+        self.inner.table = Some(tag);
+        self.locate(data)
+    }
+
+    /// Records the offset of the error from the start of `data` if the parser
+    /// failed within it.
+    pub(crate) fn locate(mut self, data: &[u8]) -> Self {
+        if let Some(Position::Address(address)) = self.inner.position {
+            let start = data.as_ptr() as usize;
+            self.inner.position = match address.checked_sub(start) {
+                Some(offset) if offset <= data.len() => Some(Position::Offset(offset)),
+                _ => None,
+            };
+        }
+        self
+    }
+
+    /// Sets the offset at which the error occurred.
+    pub(crate) fn at_offset(mut self, offset: usize) -> Self {
+        self.inner.position = Some(Position::Offset(offset));
+        self
+    }
+
+    /// Records that the error occurred at the start of `data`, which becomes
+    /// an offset once the table or file containing `data` is known.
+    pub(crate) fn at_data(mut self, data: &[u8]) -> Self {
+        self.inner.position = Some(Position::Address(data.as_ptr() as usize));
+        self
+    }
+
+    /// Sets the structure that was being parsed, given by one of the codes in
+    /// `structure`, unless it is already known.
+    pub(crate) fn in_structure(mut self, code: u32) -> Self {
+        if self.inner.structure.is_none() {
+            self.inner.structure = structure::NAMES.get(code as usize).cloned();
+        }
+        self
+    }
+
+    /// Converts the errors of nom's parsers, which are listed from the
+    /// outermost to the innermost parser.
+    fn from_nom_errors(errors: &[(&[u8], nom::ErrorKind)]) -> Self {
+        let (input, kind) = match errors.last() {
+            Some((input, kind)) => (input, kind.clone()),
+            None => return ParserError::new(ErrorKind::UnexpectedEndOfData),
+        };
+        let kind = match kind {
+            nom::ErrorKind::Complete | nom::ErrorKind::Eof => ErrorKind::UnexpectedEndOfData,
+            kind => ErrorKind::Malformed(kind),
+        };
+        let mut error = ParserError::new(kind);
+        error.inner.position = Some(Position::Address(input.as_ptr() as usize));
+        error.inner.structure = errors.iter().rev().find_map(|(_, kind)| match kind {
+            nom::ErrorKind::Custom(code) => structure::NAMES.get(*code as usize).cloned(),
+            _ => None,
+        });
+        error
+    }
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind())?;
+        // glyph errors show the location of their cause
+        if let ErrorKind::GlyphParse { .. } = self.kind() {
+            return Ok(());
+        }
+        let mut context = Vec::new();
+        if let Some(structure) = self.inner.structure {
+            context.push(format!("while parsing {}", structure));
+        }
+        if let Some(tag) = self.inner.table {
+            context.push(format!("in table {}", tag));
+        }
+        if let Some(Position::Offset(offset)) = self.inner.position {
+            context.push(format!("at offset {}", offset));
+        }
+        if !context.is_empty() {
+            write!(f, " ({})", context.join(" "))?;
+        }
+        Ok(())
     }
 }
 
 impl<'a> From<nom::Err<&'a [u8]>> for ParserError {
     fn from(nom_err: nom::Err<&'a [u8]>) -> ParserError {
-        let error_context = match nom_err {
-            nom::Err::Incomplete(_) => return ParserError::new(ErrorKind::UnexpectedEndOfData),
-            nom::Err::Error(context) => context,
-            nom::Err::Failure(context) => context,
-        };
-
-        let v: Vec<(&'a [u8], nom::ErrorKind)> = nom::error_to_list(&error_context);
-        for (_, kind) in &v {
-            match kind {
-                nom::ErrorKind::Custom(0) => return ParserError::from_string(format!("Problem 0")),
-                _ => {}
+        match nom_err {
+            nom::Err::Incomplete(_) => ParserError::new(ErrorKind::UnexpectedEndOfData),
+            nom::Err::Error(context) | nom::Err::Failure(context) => {
+                ParserError::from_nom_errors(&nom::error_to_list(&context))
             }
         }
-
-        ParserError::from_string(format!("{:?}", v))
     }
 }
 
 impl<'a> From<nom::Err<nom::types::CompleteByteSlice<'a>>> for ParserError {
     fn from(nom_err: nom::Err<nom::types::CompleteByteSlice<'a>>) -> ParserError {
-        let error_context = match nom_err {
-            nom::Err::Incomplete(_) => return ParserError::new(ErrorKind::UnexpectedEndOfData),
-            nom::Err::Error(context) => context,
-            nom::Err::Failure(context) => context,
-        };
-
-        let v: Vec<(nom::types::CompleteByteSlice<'a>, nom::ErrorKind)> =
-            nom::error_to_list(&error_context);
-        for (_, kind) in &v {
-            match kind {
-                nom::ErrorKind::Custom(0) => return ParserError::from_string(format!("Problem 0")),
-                _ => {}
+        match nom_err {
+            nom::Err::Incomplete(_) => ParserError::new(ErrorKind::UnexpectedEndOfData),
+            nom::Err::Error(context) | nom::Err::Failure(context) => {
+                let errors: Vec<_> = nom::error_to_list(&context)
+                    .into_iter()
+                    .map(|(input, kind)| (input.0, kind))
+                    .collect();
+                ParserError::from_nom_errors(&errors)
             }
         }
-
-        ParserError::from_string(format!("{:?}", v))
     }
 }
 
@@ -115,11 +282,12 @@ impl From<DeserializerError> for ParserError {
 }
 
 impl Error for ParserError {
-    fn cause(&self) -> Option<&dyn Error> {
-        match *self.kind {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.kind() {
             ErrorKind::TableParse(_, Some(ref cause)) => Some(cause),
             ErrorKind::CffDictionaryDeserialize(ref err) => Some(err),
-            ErrorKind::ForeignError(ref err) => Some(err.as_ref()),
+            // foreign errors are displayed in place of this error
+            ErrorKind::ForeignError(ref err) => err.source(),
             ErrorKind::GlyphParse { ref cause, .. } => Some(cause),
             _ => None,
         }
@@ -128,8 +296,14 @@ impl Error for ParserError {
 
 #[derive(Debug)]
 pub enum ErrorKind {
-    GlyphParse { index: u32, cause: ParserError },
+    GlyphParse {
+        index: u32,
+        cause: ParserError,
+    },
     UnexpectedEndOfData,
+    /// The data does not have the expected format. The kind of the failed
+    /// parser is given.
+    Malformed(nom::ErrorKind),
     FontNotFound(usize),
     TableMissing(Tag),
     TableParse(Tag, Option<ParserError>),
@@ -141,12 +315,23 @@ pub enum ErrorKind {
 impl Display for ErrorKind {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         match self {
-            ErrorKind::Other(s) => f.write_str(&s),
-            ErrorKind::TableParse(tag, _) => write!(f, "{} table could not be parsed.", tag),
             ErrorKind::GlyphParse { index, .. } => {
-                write!(f, "Glyph at index {} could not be parsed.", index)
+                write!(f, "Glyph at index {} could not be parsed", index)
+            }
+            ErrorKind::UnexpectedEndOfData => f.write_str("Unexpected end of data"),
+            ErrorKind::Malformed(kind) => {
+                write!(f, "Malformed data ({} parser failed)", kind.description())
+            }
+            ErrorKind::FontNotFound(index) => {
+                write!(f, "The file does not contain a font with index {}", index)
             }
-            _ => Ok(()),
+            ErrorKind::TableMissing(tag) => write!(f, "Required table {} is missing", tag),
+            ErrorKind::TableParse(tag, _) => write!(f, "{} table could not be parsed", tag),
+            ErrorKind::CffDictionaryDeserialize(_) => {
+                f.write_str("CFF dictionary could not be deserialized")
+            }
+            ErrorKind::Other(s) => f.write_str(s),
+            ErrorKind::ForeignError(err) => write!(f, "{}", err),
         }
     }
 }
@@ -154,7 +339,11 @@ impl Display for ErrorKind {
 #[derive(Debug, Display)]
 pub struct DeserializerError(ParserError);
 
-impl std::error::Error for DeserializerError {}
+impl std::error::Error for DeserializerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
 
 impl de::Error for DeserializerError {
     fn custom<T: Display>(msg: T) -> Self {
@@ -167,3 +356,60 @@ impl<'a> From<nom::Err<&'a [u8]>> for DeserializerError {
         DeserializerError(nom_err.into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let kinds = vec![
+            ErrorKind::GlyphParse {
+                index: 3,
+                cause: ParserError::new(ErrorKind::UnexpectedEndOfData),
+            },
+            ErrorKind::UnexpectedEndOfData,
+            ErrorKind::Malformed(nom::ErrorKind::Tag),
+            ErrorKind::FontNotFound(2),
+            ErrorKind::TableMissing(Tag(*b"head")),
+            ErrorKind::TableParse(Tag(*b"head"), None),
+            ErrorKind::CffDictionaryDeserialize(de::Error::custom("invalid operator")),
+            ErrorKind::Other("other".to_string()),
+            ErrorKind::ForeignError(Box::new(std::fmt::Error)),
+        ];
+        for kind in kinds {
+            assert!(!kind.to_string().is_empty(), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_source() {
+        let cause = ParserError::new(ErrorKind::UnexpectedEndOfData);
+        let err = ParserError::glyph_parse(1, cause);
+        assert_eq!(
+            err.source().map(ToString::to_string),
+            Some("Unexpected end of data".to_string())
+        );
+
+        // foreign errors take the place of the parser error
+        let err = ParserError::from_err(std::fmt::Error);
+        assert_eq!(err.to_string(), std::fmt::Error.to_string());
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn test_nom_error_position() {
+        let data = [0u8, 1, 2];
+        let err: ParserError = nom::be_u32(&data[1..]).unwrap_err().into();
+        assert!(matches!(err.kind(), ErrorKind::UnexpectedEndOfData));
+
+        let err: ParserError = tag!(&data[1..], "ab").unwrap_err().into();
+        let err = err.in_table(Tag(*b"test"), &data);
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::Malformed(nom::ErrorKind::Tag)
+        ));
+        assert_eq!(err.offset(), Some(1));
+        assert_eq!(err.table(), Some(Tag(*b"test")));
+    }
+}
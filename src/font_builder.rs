@@ -0,0 +1,273 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::checksum::table_checksum;
+use crate::error::ParserError;
+use crate::{FontRecord, OpentypeTableAccess, TableRecord, Tag};
+
+/// The recommended order of the tables of fonts with TrueType outlines.
+const TRUETYPE_ORDER: [&[u8; 4]; 20] = [
+    b"head", b"hhea", b"maxp", b"OS/2", b"hmtx", b"LTSH", b"VDMX", b"hdmx", b"cmap", b"fpgm",
+    b"prep", b"cvt ", b"loca", b"glyf", b"kern", b"name", b"post", b"gasp", b"PCLT", b"DSIG",
+];
+
+/// The recommended order of the tables of fonts with CFF outlines.
+const CFF_ORDER: [&[u8; 4]; 8] = [
+    b"head", b"hhea", b"maxp", b"OS/2", b"name", b"cmap", b"post", b"CFF ",
+];
+
+const PADDING: usize = std::mem::size_of::<u32>();
+
+/// Returns the length of a table including the padding to a multiple of four
+/// bytes.
+pub(crate) fn padded_len(len: usize) -> usize {
+    len + (PADDING - len % PADDING) % PADDING
+}
+
+/// Sorts tags in the order recommended by the OpenType specification for the
+/// table data. Tables without a recommended position follow sorted by tag.
+pub(crate) fn table_order<I: IntoIterator<Item = Tag>>(tags: I) -> Vec<Tag> {
+    let mut tags: Vec<Tag> = tags.into_iter().collect();
+    let order: &[&[u8; 4]] = if tags.contains(&Tag(*b"CFF ")) {
+        &CFF_ORDER
+    } else {
+        &TRUETYPE_ORDER
+    };
+    tags.sort_by_key(|tag| {
+        let position = order.iter().position(|&other| other == &tag.0);
+        (position.unwrap_or(order.len()), *tag)
+    });
+    tags
+}
+
+/// Assembles a font file from its tables.
+///
+/// The table data is aligned to four bytes and written in the recommended
+/// order. The checksums of all tables and the `checkSumAdjustment` of the
+/// `head` table are computed when the font is written.
+///
+/// Examples
+/// --------
+///
+/// ```
+/// use font_parse::{Font, FontBuilder, Tag};
+///
+/// let font_data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
+/// let font = Font::from_bytes(font_data, 0).unwrap();
+///
+/// let mut builder = FontBuilder::new(Tag([0, 1, 0, 0]));
+/// builder
+///     .copy_tables(&font, &[Tag(*b"head"), Tag(*b"glyf"), Tag(*b"loca")])
+///     .unwrap();
+/// builder.add_table(Tag(*b"gasp"), vec![0, 1, 0, 1, 0xff, 0xff, 0, 0x0f]);
+///
+/// let mut data = vec![];
+/// builder.write_to(&mut data).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FontBuilder<'a> {
+    version_tag: Tag,
+    tables: BTreeMap<Tag, Cow<'a, [u8]>>,
+}
+
+impl<'a> FontBuilder<'a> {
+    /// Creates a builder for a font with the given sfnt version, which is
+    /// `[0, 1, 0, 0]` for TrueType outlines and `OTTO` for CFF outlines.
+    pub fn new(version_tag: Tag) -> Self {
+        FontBuilder {
+            version_tag,
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a table, replacing a previously added table with the same tag.
+    pub fn add_table<D: Into<Cow<'a, [u8]>>>(&mut self, tag: Tag, data: D) -> &mut Self {
+        self.tables.insert(tag, data.into());
+        self
+    }
+
+    /// Copies tables from another font.
+    pub fn copy_tables<'t, F: OpentypeTableAccess<'t>>(
+        &mut self,
+        font: &F,
+        tags: &[Tag],
+    ) -> Result<&mut Self, ParserError> {
+        for &tag in tags {
+            let data = font
+                .table_data(tag)
+                .ok_or_else(|| ParserError::expected_table(tag))?;
+            self.tables.insert(tag, Cow::Owned(data.to_vec()));
+        }
+        Ok(self)
+    }
+
+    /// Removes a table and returns its data.
+    pub fn remove_table(&mut self, tag: Tag) -> Option<Cow<'a, [u8]>> {
+        self.tables.remove(&tag)
+    }
+
+    pub fn has_table(&self, tag: Tag) -> bool {
+        self.tables.contains_key(&tag)
+    }
+
+    /// Writes the font file.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        if self.tables.len() > usize::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A font can't contain more than 2^16 - 1 tables.",
+            ));
+        }
+        let order = table_order(self.tables.keys().cloned());
+
+        let mut offset = 12 + 16 * self.tables.len();
+        let mut head = None;
+        let mut table_records = BTreeMap::new();
+        for &tag in &order {
+            let data = &self.tables[&tag];
+            let check_sum = if tag == Tag(*b"head") {
+                if data.len() < 12 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "The head table is truncated.",
+                    ));
+                }
+                let mut head_data = data.to_vec();
+                // the checksum adjustment is zero while computing checksums
+                head_data[8..12].copy_from_slice(&[0, 0, 0, 0]);
+                let check_sum = table_checksum(&head_data);
+                head = Some(head_data);
+                check_sum
+            } else {
+                table_checksum(data)
+            };
+            if offset + data.len() > u32::MAX as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Too much data for a font.",
+                ));
+            }
+            table_records.insert(
+                tag,
+                TableRecord {
+                    tag,
+                    check_sum,
+                    offset: offset as u32,
+                    length: data.len() as u32,
+                },
+            );
+            offset += padded_len(data.len());
+        }
+
+        let record = FontRecord::new(u32::from_be_bytes(self.version_tag.0), table_records);
+        let mut directory = Vec::with_capacity(12 + 16 * self.tables.len());
+        record.write_to(&mut directory)?;
+        if let Some(ref mut head) = head {
+            let font_checksum = record
+                .tables
+                .values()
+                .fold(table_checksum(&directory), |sum, table| {
+                    sum.wrapping_add(table.check_sum)
+                });
+            let check_sum_adjustment = 0xB1B0_AFBAu32.wrapping_sub(font_checksum);
+            head[8..12].copy_from_slice(&check_sum_adjustment.to_be_bytes());
+        }
+
+        sink.write_all(&directory)?;
+        for tag in order {
+            let data: &[u8] = match head {
+                Some(ref head) if tag == Tag(*b"head") => head,
+                _ => &self.tables[&tag],
+            };
+            sink.write_all(data)?;
+            sink.write_all(&[0; PADDING][..padded_len(data.len()) - data.len()])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Font;
+
+    #[test]
+    fn test_table_order() {
+        let tags = [b"glyf", b"GSUB", b"head", b"loca", b"cmap", b"BASE"]
+            .iter()
+            .map(|&&tag| Tag(tag));
+        let expected: Vec<Tag> = [b"head", b"cmap", b"loca", b"glyf", b"BASE", b"GSUB"]
+            .iter()
+            .map(|&&tag| Tag(tag))
+            .collect();
+        assert_eq!(table_order(tags), expected);
+
+        let tags = [b"CFF ", b"name", b"head"].iter().map(|&&tag| Tag(tag));
+        let expected: Vec<Tag> = [b"head", b"name", b"CFF "]
+            .iter()
+            .map(|&&tag| Tag(tag))
+            .collect();
+        assert_eq!(table_order(tags), expected);
+    }
+
+    #[test]
+    fn test_font_builder() {
+        let data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
+        let font = Font::from_bytes(data, 0).unwrap();
+        let tags: Vec<Tag> = font.record().tables.keys().cloned().collect();
+
+        let mut builder = FontBuilder::new(Tag([0, 1, 0, 0]));
+        builder.copy_tables(&font, &tags).unwrap();
+        builder.add_table(Tag(*b"zzzz"), &[1, 2, 3][..]);
+        let mut out = vec![];
+        builder.write_to(&mut out).unwrap();
+
+        // the checksum of the whole font is the magic number
+        assert_eq!(out.len() % 4, 0);
+        assert_eq!(table_checksum(&out), 0xB1B0_AFBA);
+
+        let written = Font::from_bytes(&out, 0).unwrap();
+        assert_eq!(written.record().tables.len(), tags.len() + 1);
+        assert_eq!(written.table_data(Tag(*b"zzzz")), Some(&[1, 2, 3][..]));
+        let mut previous_offset = 0;
+        for tag in table_order(written.record().tables.keys().cloned()) {
+            let record = written.record().tables[&tag];
+            assert_eq!(record.offset % 4, 0);
+            assert!(record.offset > previous_offset);
+            previous_offset = record.offset;
+            if tag != Tag(*b"head") && tag != Tag(*b"zzzz") {
+                assert_eq!(written.table_data(tag), font.table_data(tag));
+                assert_eq!(record.check_sum, font.record().tables[&tag].check_sum);
+            }
+        }
+
+        assert!(builder.remove_table(Tag(*b"zzzz")).is_some());
+        assert!(!builder.has_table(Tag(*b"zzzz")));
+        assert!(builder.copy_tables(&font, &[Tag(*b"CFF ")]).is_err());
+        builder.add_table(Tag(*b"head"), vec![0; 8]);
+        assert!(builder.write_to(&mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_empty_font_builder() {
+        let builder = FontBuilder::new(Tag(*b"OTTO"));
+        let mut out = vec![];
+        builder.write_to(&mut out).unwrap();
+        assert_eq!(out, [b'O', b'T', b'T', b'O', 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
@@ -16,6 +16,7 @@ use crate::cff::{Glyph as CffGlyph, GlyphAccessor as CffGlyphAccessor};
 use crate::ttf_glyph_accessor::{Glyph as TtfGlyph, GlyphAccessor as TtfGlyphAccessor};
 
 use crate::error::ParserError;
+use crate::outline::Outline;
 
 #[derive(Debug)]
 pub enum Glyph<'font> {
@@ -23,6 +24,19 @@ pub enum Glyph<'font> {
     Ttf(TtfGlyph<'font>),
 }
 
+impl<'font> Glyph<'font> {
+    /// Returns the outline of the glyph in font units.
+    ///
+    /// Composite TrueType glyphs need access to their components and have to
+    /// be resolved with `GlyphAccessor::outline` instead.
+    pub fn outline(&mut self) -> Result<Outline, ParserError> {
+        match self {
+            Glyph::Cff(glyph) => Ok(Outline::from_path_instructions(glyph.contour_iter())),
+            Glyph::Ttf(glyph) => Ok(Outline::from_quadratic_path(glyph.contour_iter()?)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum _GlyphAccessor<'font> {
     Cff(CffGlyphAccessor<'font>),
@@ -47,4 +61,21 @@ impl<'font> GlyphAccessor<'font> {
         };
         Ok(glyph)
     }
+
+    /// Returns the outline of the glyph with the given index in font units.
+    ///
+    /// Returns `None` if the glyph does not exist or has no outline.
+    pub fn outline(&mut self, index: u32) -> Result<Option<Outline>, ParserError> {
+        match self.0 {
+            _GlyphAccessor::Cff(ref mut accessor) => Ok(accessor
+                .index(index)
+                .map(|mut glyph| Outline::from_path_instructions(glyph.contour_iter()))),
+            _GlyphAccessor::Ttf(ref accessor) => {
+                if index > u32::from(u16::MAX) {
+                    return Ok(None);
+                }
+                accessor.outline(index as u16)
+            }
+        }
+    }
 }
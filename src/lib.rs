@@ -25,24 +25,47 @@ extern crate log;
 #[macro_use]
 extern crate derive_more;
 
-use nom::IResult;
-use nom::{be_u16, be_u32, be_u8};
+use nom::be_u8;
 
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::io::Write;
 
+pub mod agl;
 mod cff;
+pub mod cff_font;
+pub mod checksum;
+pub mod collection;
+pub mod curve_conversion;
+pub mod dfont;
+pub mod directory;
 mod error;
+mod font_builder;
 mod glyph_accessor;
+pub mod outline;
+pub mod owned;
+pub mod path_ops;
+mod reader;
+pub mod sdf;
+pub mod subset;
+pub mod svg;
 pub mod tables;
 pub(crate) mod ttf_glyph_accessor;
+pub mod type1;
+pub mod validate;
+pub mod woff;
+pub mod woff2;
 
-use crate::error::{ErrorKind, ParserError};
+use crate::error::structure;
+pub use crate::error::{ErrorKind, ParserError};
+use crate::reader::{FontData, Offset32, Reader};
 
 use crate::cff::GlyphAccessor as CffGlyphAccessor;
-pub use crate::cff::{Glyph as CffGlyph, PathInstruction};
+pub use crate::cff::{Fixed16_16, Glyph as CffGlyph, PathInstruction};
 use crate::glyph_accessor::_GlyphAccessor;
+use crate::checksum::ChecksumReport;
+use crate::directory::{DirectoryDiagnostic, InvalidDirectory};
+pub use crate::font_builder::FontBuilder;
 pub use crate::glyph_accessor::{Glyph, GlyphAccessor};
 use crate::ttf_glyph_accessor::GlyphAccessor as TtfGlyphAccessor;
 pub use crate::ttf_glyph_accessor::{Glyph as TtfGlyph, QuadraticPath};
@@ -118,12 +141,25 @@ pub struct FontRecord {
 }
 
 impl FontRecord {
+    /// Creates a table directory, computing the fields for binary search.
+    pub fn new(version: u32, tables: BTreeMap<Tag, TableRecord>) -> Self {
+        let (search_range, entry_selector, range_shift) =
+            directory::search_parameters(tables.len() as u16);
+        FontRecord {
+            version,
+            search_range,
+            entry_selector,
+            range_shift,
+            tables,
+        }
+    }
+
     pub fn write_to<W: Write>(&self, mut sink: W) -> std::io::Result<()> {
-        sink.write(&self.version.to_be_bytes())?;
-        sink.write(&(self.tables.len() as u16).to_be_bytes())?;
-        sink.write(&self.search_range.to_be_bytes())?;
-        sink.write(&self.entry_selector.to_be_bytes())?;
-        sink.write(&self.range_shift.to_be_bytes())?;
+        sink.write_all(&self.version.to_be_bytes())?;
+        sink.write_all(&(self.tables.len() as u16).to_be_bytes())?;
+        sink.write_all(&self.search_range.to_be_bytes())?;
+        sink.write_all(&self.entry_selector.to_be_bytes())?;
+        sink.write_all(&self.range_shift.to_be_bytes())?;
         for (_tag, table) in &self.tables {
             table.write_to(&mut sink)?;
         }
@@ -177,6 +213,7 @@ pub trait OpentypeTableAccess<'table_data> {
     fn glyphs(&self) -> Result<GlyphAccessor<'table_data>, ParserError>
     where
         Self: Sized,
+        Self::TableData: Into<&'table_data [u8]>,
     {
         match CffGlyphAccessor::new(self) {
             Err(err) => match err.kind() {
@@ -222,12 +259,13 @@ pub trait ParseTable<'table_data> {
     /// --------
     ///
     /// ```
-    /// use font_parse::{Font, OpentypeTableAccess, tables};
+    /// use font_parse::{Font, ParseTable, tables};
     ///
     /// let font_data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
     /// let font = Font::from_bytes(font_data, 0).unwrap();
     ///
-    /// let loca_table: tables::head::Head = font.parse_table().unwrap();
+    /// let head_table = font.parse_table::<tables::head::Head>().unwrap();
+    /// let head = head_table.get().unwrap();
     /// ```
     ///
     /// Panics
@@ -278,6 +316,15 @@ impl<'table_data, T: OpentypeTableAccess<'table_data>> ParseTable<'table_data> f
     }
 }
 
+/// Tables held in memory, for example to write a font with modified tables.
+impl<'a> OpentypeTableAccess<'a> for &'a BTreeMap<Tag, Vec<u8>> {
+    type TableData = &'a [u8];
+
+    fn table_data(&self, tag: Tag) -> Option<Self::TableData> {
+        self.get(&tag).map(Vec::as_slice)
+    }
+}
+
 #[derive(Debug)]
 pub struct FontKitFont<'a> {
     pub inner: &'a font_kit::font::Font,
@@ -291,107 +338,28 @@ impl<'a> OpentypeTableAccess<'static> for FontKitFont<'a> {
     }
 }
 
-fn int_log_base_2(mut val: u16) -> u16 {
-    let mut r = 0;
-    while {
-        val >>= 1;
-        val > 0
-    } {
-        r += 1
-    }
-    r
-}
-
+/// Writes a font consisting of the given tables of `font`.
+///
+/// This is a shortcut for copying the tables into a
+/// [`FontBuilder`](struct.FontBuilder.html). Fails with `InvalidInput` if
+/// `font` does not contain one of the tables.
 pub fn write_font<'a>(
     font: &impl OpentypeTableAccess<'a>,
     version_tag: Tag,
     tables: &[Tag],
     sink: &mut dyn Write,
 ) -> std::io::Result<()> {
-    use std::convert::TryFrom;
-    const PADDING: u32 = std::mem::size_of::<u32>() as u32;
-
-    let num_tables =
-        u16::try_from(tables.len()).expect("A font can't contain more than 2^16 - 1 tables");
-    // Log2(maximum power of 2 <= numTables)
-    let entry_selector = int_log_base_2(num_tables);
-    // (Maximum power of 2 <= numTables) x 16
-    let search_range = (1u16 << entry_selector) * 16;
-    let range_shift = num_tables * 16 - search_range;
-
-    let first_table_offset = 16 + num_tables * 16;
-
-    let mut offset = first_table_offset as u32;
-    let mut table_records = BTreeMap::new();
-
-    let mut head_data = vec![];
-
-    let mut font_checksum: u32 = 0;
+    let mut builder = FontBuilder::new(version_tag);
     for &tag in tables {
-        let record = if tag == Tag(*b"head") {
-            head_data = font
-                .table_data(tag)
-                .expect("did not find corresponding table")
-                .to_vec();
-            // set the checksum adjustment to zero
-            (&mut head_data[8..12]).swap_with_slice(&mut [0, 0, 0, 0]);
-            TableRecord {
-                tag,
-                offset,
-                length: head_data.len() as u32,
-                check_sum: compute_table_checksum(&head_data),
-            }
-        } else {
-            let table_data = font
-                .table_data(tag)
-                .expect("did not find corresponding table");
-            TableRecord {
-                tag,
-                offset,
-                length: table_data.len() as u32,
-                check_sum: compute_table_checksum(&*table_data),
-            }
-        };
-
-        font_checksum = font_checksum.wrapping_add(record.check_sum);
-        table_records.insert(tag, record);
-        // add offset, including padding
-        offset += record.length + (PADDING - record.length % PADDING) % PADDING;
-    }
-
-    let font_record = FontRecord {
-        version: u32::from_be_bytes(version_tag.0),
-        search_range,
-        entry_selector,
-        range_shift,
-        tables: table_records,
-    };
-    let mut font_record_bytes = Vec::with_capacity(first_table_offset as usize);
-    font_record.write_to(&mut font_record_bytes).unwrap();
-    font_checksum = font_checksum.wrapping_add(compute_table_checksum(&font_record_bytes));
-
-    let check_sum_adjustment =
-        u32::from_be_bytes([0xB1, 0xB0, 0xAF, 0xBa]).wrapping_sub(font_checksum);
-    (&mut head_data[8..12]).swap_with_slice(&mut check_sum_adjustment.to_be_bytes());
-
-    // finally write the font file
-    sink.write_all(&font_record_bytes)?;
-    // padding
-    sink.write_all(&[0; PADDING as usize])?;
-
-    for &tag in tables {
-        if tag == Tag(*b"head") {
-            sink.write_all(&head_data)?;
-        } else {
-            sink.write_all(&*font.table_data(tag).unwrap())?;
-        }
-        // write padding bytes after every table
-        let num_zero_bytes =
-            ((PADDING - font_record.tables[&tag].length % PADDING) % PADDING) as usize;
-        sink.write_all(&[0u8, 0, 0, 0, 0, 0, 0, 0][..num_zero_bytes])?;
+        let data = font.table_data(tag).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Font does not contain table {}.", tag),
+            )
+        })?;
+        builder.add_table(tag, data.to_vec());
     }
-
-    Ok(())
+    builder.write_to(sink)
 }
 
 /// A type which reads a font file from bytes and implements `OpentypeTableAccess`.
@@ -399,8 +367,10 @@ pub fn write_font<'a>(
 /// It currently supports font files based on SFNT tables (TrueType and OpenType).
 #[derive(Debug, Clone)]
 pub struct Font<'a> {
-    record: FontRecord,
+    record: Cow<'a, FontRecord>,
     collection: Option<FontCollection>,
+    /// The offset of the table directory in `data`.
+    directory_offset: usize,
     data: &'a [u8],
 }
 
@@ -408,8 +378,9 @@ impl<'a> Font<'a> {
     /// Create a `Font` from a slice of bytes and an index for selecting a font
     /// from an OpenType font collection.
     pub fn from_bytes(bytes: &'a [u8], index: u32) -> Result<Self, ParserError> {
-        let (_, font_header) = parse_slice(bytes)?;
+        let font_header = parse(bytes)?;
         let mut collection = None;
+        let mut directory_offset = 0;
         let record = match font_header {
             FontFile::Single(record) => record,
             FontFile::Collection(c) => {
@@ -418,17 +389,74 @@ impl<'a> Font<'a> {
                     .get(index as usize)
                     .ok_or_else(|| ParserError::font_not_found(index as usize))?
                     .clone();
+                let (_, _, offsets, _) = read_collection_header(&mut Reader::new(bytes))?;
+                directory_offset = offsets[index as usize].to_usize();
                 collection = Some(c);
                 record
             }
         };
         Ok(Font {
-            record,
+            record: Cow::Owned(record),
             collection,
+            directory_offset,
             data: bytes,
         })
     }
 
+    /// Creates a `Font` like [`from_bytes`](#method.from_bytes), but fails
+    /// if the table directory has diagnostics which are errors.
+    ///
+    /// The error wraps an
+    /// [`InvalidDirectory`](directory/struct.InvalidDirectory.html) with these
+    /// diagnostics.
+    pub fn from_bytes_strict(bytes: &'a [u8], index: u32) -> Result<Self, ParserError> {
+        let font = Font::from_bytes(bytes, index)?;
+        let errors: Vec<_> = font
+            .directory_diagnostics()?
+            .into_iter()
+            .filter(DirectoryDiagnostic::is_error)
+            .collect();
+        if errors.is_empty() {
+            Ok(font)
+        } else {
+            Err(ParserError::from_err(InvalidDirectory(errors)))
+        }
+    }
+
+    /// Compares the checksums of the table records and the
+    /// `checkSumAdjustment` of the `head` table with the data of the font.
+    ///
+    /// Examples
+    /// --------
+    ///
+    /// ```
+    /// use font_parse::Font;
+    ///
+    /// let font_data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
+    /// let font = Font::from_bytes(font_data, 0).unwrap();
+    /// let report = font.verify_checksums();
+    /// for table in report.invalid_tables() {
+    ///     println!("{} is corrupted", table.tag);
+    /// }
+    /// ```
+    pub fn verify_checksums(&self) -> ChecksumReport {
+        checksum::verify_checksums(self.data, self.directory_offset, &self.record)
+    }
+
+    /// Checks the table directory of the font.
+    ///
+    /// Tables which are out of bounds are not returned by
+    /// [`table_data`](trait.OpentypeTableAccess.html#tymethod.table_data), and
+    /// only the last record of duplicate tags is used.
+    pub fn directory_diagnostics(&self) -> Result<Vec<DirectoryDiagnostic>, ParserError> {
+        directory::validate_directory(self.data, self.directory_offset)
+    }
+
+    /// Returns the table directory of the font.
+    pub fn record(&self) -> &FontRecord {
+        &self.record
+    }
+
     // pub fn from_tables(tables: &[Tag], record: FontRecord, provider: &dyn OpentypeTableAccess) -> Self {
     //     let num_tables = tables
     //         .iter()
@@ -443,27 +471,20 @@ impl<'a> Font<'a> {
     //     todo!()
     // }
 
-    pub fn write_to<W: Write>(&self, mut sink: W) -> std::io::Result<()> {
-        let mut offset = 16 + self.record.tables.len() as u32 * 16;
-        let mut record = self.record.clone();
-        for (_tag, table) in &mut record.tables {
-            table.offset = offset;
-            offset += table.length;
-            // alignment
-            offset += (8 - table.length % 8) % 8;
-        }
-        record.write_to(&mut sink)?;
-        // padding
-        sink.write_all(&[0, 0, 0, 0])?;
-
-        for table in record.tables.values() {
-            sink.write_all(self.table_data(table.tag).unwrap())?;
-            // write padding bytes after every table
-            let num_zero_bytes = ((8 - table.length % 8) % 8) as usize;
-            sink.write_all(&[0u8, 0, 0, 0, 0, 0, 0, 0][..num_zero_bytes])?;
+    /// Writes the font as a standalone font file, which extracts it if it
+    /// is part of a collection.
+    pub fn write_to<W: Write>(&self, sink: W) -> std::io::Result<()> {
+        let mut builder = FontBuilder::new(Tag(self.record.version.to_be_bytes()));
+        for &tag in self.record.tables.keys() {
+            let data = self.table_data(tag).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Table {} is out of bounds.", tag),
+                )
+            })?;
+            builder.add_table(tag, data);
         }
-
-        Ok(())
+        builder.write_to(sink)
     }
 }
 
@@ -479,21 +500,6 @@ impl<'a> OpentypeTableAccess<'a> for Font<'a> {
     }
 }
 
-fn compute_table_checksum(mut table: &[u8]) -> u32 {
-    let mut sum: u32 = 0;
-    while table.len() >= 4 {
-        let (first, second) = table.split_at(4);
-        let first = [first[0], first[1], first[2], first[3]];
-        sum = sum.wrapping_add(u32::from_be_bytes(first));
-        table = second;
-    }
-    let mut final_bytes = [0; 4];
-    for (index, val) in table.into_iter().enumerate() {
-        final_bytes[index] = *val;
-    }
-    sum.wrapping_add(u32::from_be_bytes(final_bytes))
-}
-
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct TableRecord {
     pub tag: Tag,
@@ -504,10 +510,10 @@ pub struct TableRecord {
 
 impl TableRecord {
     pub fn write_to<W: Write>(&self, mut sink: W) -> std::io::Result<()> {
-        sink.write(&self.tag.0)?;
-        sink.write(&self.check_sum.to_be_bytes())?;
-        sink.write(&self.offset.to_be_bytes())?;
-        sink.write(&self.length.to_be_bytes())?;
+        sink.write_all(&self.tag.0)?;
+        sink.write_all(&self.check_sum.to_be_bytes())?;
+        sink.write_all(&self.offset.to_be_bytes())?;
+        sink.write_all(&self.length.to_be_bytes())?;
         Ok(())
     }
 }
@@ -526,58 +532,79 @@ fn to_btree_map(vec: Vec<TableRecord>) -> BTreeMap<Tag, TableRecord> {
     vec.into_iter().map(|record| (record.tag, record)).collect()
 }
 
-named!(parse_font<&[u8],FontRecord>,
-    do_parse!(
-        version: be_u32 >>
-        num_tables: be_u16 >>
-        search_range: be_u16 >>
-        entry_selector: be_u16 >>
-        range_shift: be_u16 >>
+fn read_font(reader: &mut Reader) -> Result<FontRecord, ParserError> {
+    reader.structure(structure::TABLE_DIRECTORY);
+    let version = reader.read()?;
+    let num_tables: u16 = reader.read()?;
+    let search_range = reader.read()?;
+    let entry_selector = reader.read()?;
+    let range_shift = reader.read()?;
+    let mut tables = Vec::with_capacity(usize::from(num_tables));
+    for _ in 0..num_tables {
+        tables.push(read_table_record(reader)?);
+    }
+    Ok(FontRecord {
+        version,
+        search_range,
+        entry_selector,
+        range_shift,
         // tables must be sorted for binary search
-        tables: map!(count!(table_record, num_tables as usize), to_btree_map) >>
-        (FontRecord {
-            version,
-            search_range,
-            entry_selector,
-            range_shift,
-            tables
-        })
-    )
-);
+        tables: to_btree_map(tables),
+    })
+}
 
-named!(table_record<&[u8],TableRecord>,
-    do_parse!(
-        tag: parse_tag >>
-        check_sum: be_u32 >>
-        offset: be_u32 >>
-        length: be_u32 >>
-        (TableRecord { tag, check_sum, offset, length })
-    )
-);
+pub(crate) fn read_table_record(reader: &mut Reader) -> Result<TableRecord, ParserError> {
+    reader.structure(structure::TABLE_RECORD);
+    Ok(TableRecord {
+        tag: reader.read()?,
+        check_sum: reader.read()?,
+        offset: reader.read::<Offset32>()?.0,
+        length: reader.read()?,
+    })
+}
 
-named!(parse_font_collection<&[u8], FontCollection>,
-    do_parse!(
-        tag!("ttcf") >>
-        major_version: be_u16 >>
-        minor_version: be_u16 >>
-        fonts: length_count!(verify!(be_u32, |val| val <= 10000), parse_font) >>
-        sig: cond!(major_version >= 2, tuple!(be_u32, be_u32, be_u32)) >>
-        (FontCollection {
-            major_version,
-            minor_version,
-            fonts,
-            dsig_tag: sig.map(|s| s.0).unwrap_or(0),
-            dsig_length: sig.map(|s| s.1).unwrap_or(0),
-            dsig_offset: sig.map(|s| s.2).unwrap_or(0),
-        })
-    )
-);
+type CollectionHeader = (u16, u16, Vec<Offset32>, Option<(u32, u32, u32)>);
 
-fn parse_slice(input: &[u8]) -> IResult<&[u8], FontFile> {
-    alt!(
-        input,
-        map!(parse_font_collection, FontFile::Collection) | map!(parse_font, FontFile::Single)
-    )
+fn read_collection_header(reader: &mut Reader) -> Result<CollectionHeader, ParserError> {
+    reader.structure(structure::COLLECTION_HEADER);
+    if reader.read::<Tag>()? != Tag(*b"ttcf") {
+        return Err(reader.malformed("Expected the tag ttcf.".to_string()));
+    }
+    let major_version = reader.read()?;
+    let minor_version = reader.read()?;
+    let num_fonts: u32 = reader.read()?;
+    if num_fonts > 10000 {
+        return Err(reader.malformed(format!("Too many fonts ({}).", num_fonts)));
+    }
+    let offsets = (0..num_fonts)
+        .map(|_| reader.read())
+        .collect::<Result<_, _>>()?;
+    let sig = if major_version >= 2 {
+        Some((reader.read()?, reader.read()?, reader.read()?))
+    } else {
+        None
+    };
+    Ok((major_version, minor_version, offsets, sig))
+}
+
+/// Parses the header of a collection and the table directories of its fonts,
+/// which are located at offsets from the start of the collection.
+fn parse_font_collection(input: &[u8]) -> Result<FontCollection, ParserError> {
+    let (major_version, minor_version, offsets, sig) =
+        read_collection_header(&mut Reader::new(input))?;
+    let data = FontData::new(input);
+    let mut fonts = Vec::with_capacity(offsets.len());
+    for offset in offsets {
+        fonts.push(read_font(&mut data.reader_at(offset.to_usize())?)?);
+    }
+    Ok(FontCollection {
+        major_version,
+        minor_version,
+        fonts,
+        dsig_tag: sig.map(|s| s.0).unwrap_or(0),
+        dsig_length: sig.map(|s| s.1).unwrap_or(0),
+        dsig_offset: sig.map(|s| s.2).unwrap_or(0),
+    })
 }
 
 #[derive(Debug)]
@@ -586,11 +613,16 @@ pub enum FontFile {
     Collection(FontCollection),
 }
 
-pub fn parse(data: &[u8]) -> Result<FontFile, nom::Err<&[u8]>> {
-    match parse_slice(data) {
-        Ok((_, parsed)) => Ok(parsed),
-        Err(err) => Err(err),
-    }
+/// Parses the table directory of a font or the table directories of all fonts
+/// in a collection. Errors have offsets from the start of `data`.
+pub fn parse(data: &[u8]) -> Result<FontFile, ParserError> {
+    // decide by the tag, so that errors come from the parser of the actual format
+    let parsed = if data.starts_with(b"ttcf") {
+        parse_font_collection(data).map(FontFile::Collection)
+    } else {
+        read_font(&mut Reader::new(data)).map(FontFile::Single)
+    };
+    parsed.map_err(|err| err.locate(data))
 }
 
 #[cfg(test)]
@@ -647,6 +679,29 @@ mod tests {
         assert_eq!(font.outline_type(), OutlineType::Cff);
     }
 
+    #[test]
+    fn test_font_record_search_parameters() {
+        let record = FontRecord::new(0x0001_0000, BTreeMap::new());
+        assert_eq!(record.search_range, 0);
+        assert_eq!(record.entry_selector, 0);
+        assert_eq!(record.range_shift, 0);
+
+        let tables: BTreeMap<Tag, TableRecord> = (0..5000u32)
+            .map(|index| {
+                let tag = Tag(index.to_be_bytes());
+                let record = TableRecord {
+                    tag,
+                    check_sum: 0,
+                    offset: 0,
+                    length: 0,
+                };
+                (tag, record)
+            })
+            .collect();
+        let record = FontRecord::new(0x0001_0000, tables);
+        assert_eq!(record.entry_selector, 12);
+    }
+
     #[test]
     fn test_write_font() {
         let data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
@@ -708,7 +763,7 @@ mod tests {
             }
             let table_data = font.table_data(tag).unwrap();
             assert_eq!(
-                compute_table_checksum(table_data),
+                crate::checksum::table_checksum(table_data),
                 record.check_sum,
                 "Checksum mismatch for {}",
                 tag
@@ -0,0 +1,684 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! A format independent representation of glyph outlines.
+//!
+//! Both the quadratic TrueType outlines and the cubic CFF outlines can be
+//! converted into an `Outline` which consists of closed contours built from
+//! line, quadratic and cubic segments in absolute font units.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::curve_conversion::elevate;
+use crate::{Fixed16_16, PathInstruction, QuadraticPath};
+
+/// A point (or vector) in font units.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    pub fn dot(self, other: Point) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The z component of the cross product of `self` and `other`.
+    pub fn cross(self, other: Point) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Returns a vector of unit length pointing in the same direction or the
+    /// zero vector if `self` has zero length.
+    pub fn normalize(self) -> Point {
+        let length = self.length();
+        if length == 0.0 {
+            Point::default()
+        } else {
+            self * (1.0 / length)
+        }
+    }
+
+    pub fn lerp(self, other: Point, t: f32) -> Point {
+        self + (other - self) * t
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, rhs: Point) -> Point {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, rhs: Point) -> Point {
+        Point::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl Mul<f32> for Point {
+    type Output = Point;
+
+    fn mul(self, rhs: f32) -> Point {
+        Point::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point::new(-self.x, -self.y)
+    }
+}
+
+/// A single edge of a contour.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Segment {
+    Line(Point, Point),
+    Quadratic(Point, Point, Point),
+    Cubic(Point, Point, Point, Point),
+}
+
+impl Segment {
+    pub fn start(&self) -> Point {
+        match *self {
+            Segment::Line(p0, _) | Segment::Quadratic(p0, _, _) | Segment::Cubic(p0, _, _, _) => p0,
+        }
+    }
+
+    pub fn end(&self) -> Point {
+        match *self {
+            Segment::Line(_, p1) | Segment::Quadratic(_, _, p1) | Segment::Cubic(_, _, _, p1) => p1,
+        }
+    }
+
+    /// Evaluates the segment at parameter `t`.
+    pub fn point(&self, t: f32) -> Point {
+        match *self {
+            Segment::Line(p0, p1) => p0.lerp(p1, t),
+            Segment::Quadratic(p0, p1, p2) => p0.lerp(p1, t).lerp(p1.lerp(p2, t), t),
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let p12 = p1.lerp(p2, t);
+                p0.lerp(p1, t)
+                    .lerp(p12, t)
+                    .lerp(p12.lerp(p2.lerp(p3, t), t), t)
+            }
+        }
+    }
+
+    /// The (non-normalized) tangent of the segment at parameter `t`.
+    ///
+    /// Degenerate control points are handled such that the direction is only
+    /// zero if the whole segment collapses to a point.
+    pub fn direction(&self, t: f32) -> Point {
+        match *self {
+            Segment::Line(p0, p1) => p1 - p0,
+            Segment::Quadratic(p0, p1, p2) => {
+                let tangent = (p1 - p0).lerp(p2 - p1, t);
+                if tangent == Point::default() {
+                    p2 - p0
+                } else {
+                    tangent
+                }
+            }
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let tangent = (p1 - p0)
+                    .lerp(p2 - p1, t)
+                    .lerp((p2 - p1).lerp(p3 - p2, t), t);
+                if tangent == Point::default() {
+                    if t == 0.0 {
+                        p2 - p0
+                    } else if t == 1.0 {
+                        p3 - p1
+                    } else {
+                        p3 - p0
+                    }
+                } else {
+                    tangent
+                }
+            }
+        }
+    }
+
+    /// Splits the segment at parameter `t` using de Casteljau's algorithm.
+    pub fn split(&self, t: f32) -> (Segment, Segment) {
+        match *self {
+            Segment::Line(p0, p1) => {
+                let m = p0.lerp(p1, t);
+                (Segment::Line(p0, m), Segment::Line(m, p1))
+            }
+            Segment::Quadratic(p0, p1, p2) => {
+                let p01 = p0.lerp(p1, t);
+                let p12 = p1.lerp(p2, t);
+                let m = p01.lerp(p12, t);
+                (
+                    Segment::Quadratic(p0, p01, m),
+                    Segment::Quadratic(m, p12, p2),
+                )
+            }
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let p01 = p0.lerp(p1, t);
+                let p12 = p1.lerp(p2, t);
+                let p23 = p2.lerp(p3, t);
+                let p012 = p01.lerp(p12, t);
+                let p123 = p12.lerp(p23, t);
+                let m = p012.lerp(p123, t);
+                (
+                    Segment::Cubic(p0, p01, p012, m),
+                    Segment::Cubic(m, p123, p23, p3),
+                )
+            }
+        }
+    }
+
+    /// Returns the part of the segment between the parameters `t0` and `t1`.
+    pub fn subsegment(&self, t0: f32, t1: f32) -> Segment {
+        let tail = if t0 > 0.0 { self.split(t0).1 } else { *self };
+        if t1 >= 1.0 {
+            tail
+        } else {
+            tail.split((t1 - t0) / (1.0 - t0)).0
+        }
+    }
+
+    /// Returns the same segment traversed in the opposite direction.
+    pub fn reverse(&self) -> Segment {
+        match *self {
+            Segment::Line(p0, p1) => Segment::Line(p1, p0),
+            Segment::Quadratic(p0, p1, p2) => Segment::Quadratic(p2, p1, p0),
+            Segment::Cubic(p0, p1, p2, p3) => Segment::Cubic(p3, p2, p1, p0),
+        }
+    }
+
+    /// Returns the segment with `f` applied to all of its points.
+    pub fn map_points(&self, f: impl Fn(Point) -> Point) -> Segment {
+        match *self {
+            Segment::Line(p0, p1) => Segment::Line(f(p0), f(p1)),
+            Segment::Quadratic(p0, p1, p2) => Segment::Quadratic(f(p0), f(p1), f(p2)),
+            Segment::Cubic(p0, p1, p2, p3) => Segment::Cubic(f(p0), f(p1), f(p2), f(p3)),
+        }
+    }
+
+    /// Returns the control points' bounding box as `(min, max)`. It always
+    /// contains the segment.
+    pub fn control_bounds(&self) -> (Point, Point) {
+        let mut min = self.start();
+        let mut max = self.start();
+        let mut include = |p: Point| {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y));
+        };
+        match *self {
+            Segment::Line(_, p1) => include(p1),
+            Segment::Quadratic(_, p1, p2) => {
+                include(p1);
+                include(p2);
+            }
+            Segment::Cubic(_, p1, p2, p3) => {
+                include(p1);
+                include(p2);
+                include(p3);
+            }
+        }
+        (min, max)
+    }
+}
+
+/// A closed sequence of segments where each segment starts at the end point
+/// of the previous one.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Contour {
+    pub segments: Vec<Segment>,
+}
+
+impl Contour {
+    /// Twice the signed area enclosed by the polygon of the segments' end
+    /// points. It is positive for counter-clockwise contours.
+    pub fn winding_area(&self) -> f32 {
+        self.segments
+            .iter()
+            .map(|segment| segment.start().cross(segment.end()))
+            .sum()
+    }
+
+    /// Returns the same contour traversed in the opposite direction.
+    pub fn reverse(&self) -> Contour {
+        Contour {
+            segments: self.segments.iter().rev().map(Segment::reverse).collect(),
+        }
+    }
+
+    /// Computes the winding number of the contour around `p`. It is positive
+    /// if the contour runs counter-clockwise around the point.
+    pub fn winding_number(&self, p: Point) -> i32 {
+        self.segments
+            .iter()
+            .map(|segment| segment_winding_number(segment, p))
+            .sum()
+    }
+}
+
+/// The outline of a glyph consisting of a number of closed contours.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Outline {
+    pub contours: Vec<Contour>,
+}
+
+impl Outline {
+    /// Builds an outline from the relative cubic path of a CFF charstring.
+    pub fn from_path_instructions(path: impl IntoIterator<Item = PathInstruction>) -> Outline {
+        let mut builder = OutlineBuilder::default();
+        let mut cursor = Point::default();
+        for instr in path {
+            match instr {
+                PathInstruction::MoveTo(dx, dy) => {
+                    cursor = cursor + Point::new(dx.into(), dy.into());
+                    builder.move_to(cursor);
+                }
+                PathInstruction::LineTo(dx, dy) => {
+                    cursor = cursor + Point::new(dx.into(), dy.into());
+                    builder.line_to(cursor);
+                }
+                PathInstruction::CurveTo(dx1, dy1, dx2, dy2, dx, dy) => {
+                    let c1 = cursor + Point::new(dx1.into(), dy1.into());
+                    let c2 = c1 + Point::new(dx2.into(), dy2.into());
+                    cursor = c2 + Point::new(dx.into(), dy.into());
+                    builder.curve_to(c1, c2, cursor);
+                }
+                PathInstruction::Close => builder.close(),
+            }
+        }
+        builder.finish()
+    }
+
+    /// Builds an outline from the absolute quadratic path of a TrueType glyph.
+    pub fn from_quadratic_path(path: impl IntoIterator<Item = QuadraticPath>) -> Outline {
+        let mut builder = OutlineBuilder::default();
+        for instr in path {
+            match instr {
+                QuadraticPath::MoveTo(x, y) => builder.move_to(Point::new(x as f32, y as f32)),
+                QuadraticPath::LineTo(x, y) => builder.line_to(Point::new(x as f32, y as f32)),
+                QuadraticPath::CurveTo(cx, cy, x, y) => builder.quad_to(
+                    Point::new(cx as f32, cy as f32),
+                    Point::new(x as f32, y as f32),
+                ),
+                QuadraticPath::Close => builder.close(),
+            }
+        }
+        builder.finish()
+    }
+
+    /// Computes the winding number of the outline around `p`. The point is
+    /// inside the outline if it is non-zero.
+    pub fn winding_number(&self, p: Point) -> i32 {
+        self.contours
+            .iter()
+            .map(|contour| contour.winding_number(p))
+            .sum()
+    }
+
+    /// Converts the outline into a relative cubic path as used by CFF
+    /// charstrings. Quadratic segments are converted exactly.
+    pub fn to_path_instructions(&self) -> Vec<PathInstruction> {
+        let mut path = Vec::new();
+        let mut cursor = (Fixed16_16::from(0), Fixed16_16::from(0));
+        // Deltas are computed between rounded absolute positions such that
+        // rounding errors do not accumulate.
+        let mut delta = |p: Point| {
+            let (x, y) = (Fixed16_16::from(p.x), Fixed16_16::from(p.y));
+            let delta = (x - cursor.0, y - cursor.1);
+            cursor = (x, y);
+            delta
+        };
+        for contour in &self.contours {
+            let start = match contour.segments.first() {
+                Some(segment) => segment.start(),
+                None => continue,
+            };
+            let (dx, dy) = delta(start);
+            path.push(PathInstruction::MoveTo(dx, dy));
+            let last = contour.segments.len() - 1;
+            for (i, segment) in contour.segments.iter().enumerate() {
+                let (c1, c2, p) = match *segment {
+                    // the closing line is implied
+                    Segment::Line(_, p) if i == last && p == start => continue,
+                    Segment::Line(_, p) => {
+                        let (dx, dy) = delta(p);
+                        path.push(PathInstruction::LineTo(dx, dy));
+                        continue;
+                    }
+                    Segment::Quadratic(p0, p1, p2) => {
+                        let [_, c1, c2, p] = elevate(p0, p1, p2);
+                        (c1, c2, p)
+                    }
+                    Segment::Cubic(_, c1, c2, p) => (c1, c2, p),
+                };
+                let (dx1, dy1) = delta(c1);
+                let (dx2, dy2) = delta(c2);
+                let (dx, dy) = delta(p);
+                path.push(PathInstruction::CurveTo(dx1, dy1, dx2, dy2, dx, dy));
+            }
+            path.push(PathInstruction::Close);
+        }
+        path
+    }
+
+    /// Applies the affine transformation `[a, b, c, d, e, f]` which maps
+    /// `(x, y)` to `(a * x + c * y + e, b * x + d * y + f)`.
+    pub fn transform(&self, matrix: [f32; 6]) -> Outline {
+        let [a, b, c, d, e, f] = matrix;
+        let map = |p: Point| Point::new(a * p.x + c * p.y + e, b * p.x + d * p.y + f);
+        Outline {
+            contours: self
+                .contours
+                .iter()
+                .map(|contour| Contour {
+                    segments: contour
+                        .segments
+                        .iter()
+                        .map(|segment| segment.map_points(map))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the bounding box of all control points as `(min, max)` or
+    /// `None` if the outline is empty.
+    pub fn control_bounds(&self) -> Option<(Point, Point)> {
+        self.contours
+            .iter()
+            .flat_map(|contour| contour.segments.iter())
+            .map(Segment::control_bounds)
+            .fold(None, |acc, (min, max)| match acc {
+                None => Some((min, max)),
+                Some((amin, amax)) => Some((
+                    Point::new(amin.x.min(min.x), amin.y.min(min.y)),
+                    Point::new(amax.x.max(max.x), amax.y.max(max.y)),
+                )),
+            })
+    }
+}
+
+/// Counts the signed crossings of `segment` with the ray from `p` in positive x
+/// direction. The segment is split into parts which are monotonic in y, so
+/// crossings are found exactly and shared end points are counted once.
+fn segment_winding_number(segment: &Segment, p: Point) -> i32 {
+    let (min, max) = segment.control_bounds();
+    if p.y < min.y || p.y > max.y || p.x > max.x {
+        return 0;
+    }
+    let crossing = |a: Point, b: Point| {
+        if a.y <= p.y && b.y > p.y {
+            1
+        } else if a.y > p.y && b.y <= p.y {
+            -1
+        } else {
+            0
+        }
+    };
+    if p.x < min.x {
+        // the net number of crossings equals the one of the chord
+        return crossing(segment.start(), segment.end());
+    }
+
+    let mut winding = 0;
+    let mut t0 = 0.0;
+    let mut a = segment.start();
+    let extrema = y_extrema(segment);
+    for &t1 in extrema.iter().chain(std::iter::once(&1.0)) {
+        let b = if t1 < 1.0 {
+            segment.point(t1)
+        } else {
+            segment.end()
+        };
+        let direction = crossing(a, b);
+        if direction != 0 {
+            let (mut lo, mut hi) = (t0, t1);
+            for _ in 0..32 {
+                let mid = 0.5 * (lo + hi);
+                if (segment.point(mid).y <= p.y) == (a.y <= p.y) {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            if segment.point(0.5 * (lo + hi)).x > p.x {
+                winding += direction;
+            }
+        }
+        t0 = t1;
+        a = b;
+    }
+    winding
+}
+
+/// Returns the sorted parameters in `(0, 1)` where the y coordinate of the
+/// segment has a local extremum.
+fn y_extrema(segment: &Segment) -> Vec<f32> {
+    let mut extrema = match *segment {
+        Segment::Line(..) => vec![],
+        Segment::Quadratic(p0, p1, p2) => {
+            let denominator = p0.y - 2.0 * p1.y + p2.y;
+            if denominator == 0.0 {
+                vec![]
+            } else {
+                vec![(p0.y - p1.y) / denominator]
+            }
+        }
+        Segment::Cubic(p0, p1, p2, p3) => {
+            // the derivative divided by 3 is a * t^2 + b * t + c
+            let a = -p0.y + 3.0 * p1.y - 3.0 * p2.y + p3.y;
+            let b = 2.0 * (p0.y - 2.0 * p1.y + p2.y);
+            let c = p1.y - p0.y;
+            if a.abs() < 1e-6 {
+                if b == 0.0 {
+                    vec![]
+                } else {
+                    vec![-c / b]
+                }
+            } else {
+                let discriminant = b * b - 4.0 * a * c;
+                if discriminant < 0.0 {
+                    vec![]
+                } else {
+                    let root = discriminant.sqrt();
+                    vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+                }
+            }
+        }
+    };
+    extrema.retain(|&t| t > 0.0 && t < 1.0);
+    extrema.sort_by(f32::total_cmp);
+    extrema
+}
+
+/// Incrementally builds an `Outline`, closing contours implicitly where
+/// necessary.
+#[derive(Debug, Default)]
+pub struct OutlineBuilder {
+    outline: Outline,
+    current: Contour,
+    start: Point,
+    cursor: Point,
+}
+
+impl OutlineBuilder {
+    pub fn move_to(&mut self, p: Point) {
+        self.close();
+        self.start = p;
+        self.cursor = p;
+    }
+
+    pub fn line_to(&mut self, p: Point) {
+        self.current.segments.push(Segment::Line(self.cursor, p));
+        self.cursor = p;
+    }
+
+    pub fn quad_to(&mut self, c: Point, p: Point) {
+        self.current
+            .segments
+            .push(Segment::Quadratic(self.cursor, c, p));
+        self.cursor = p;
+    }
+
+    pub fn curve_to(&mut self, c1: Point, c2: Point, p: Point) {
+        self.current
+            .segments
+            .push(Segment::Cubic(self.cursor, c1, c2, p));
+        self.cursor = p;
+    }
+
+    /// Closes the current contour with a straight line if its end point
+    /// differs from its start point.
+    pub fn close(&mut self) {
+        if self.current.segments.is_empty() {
+            return;
+        }
+        if self.cursor != self.start {
+            self.line_to(self.start);
+        }
+        let contour = std::mem::take(&mut self.current);
+        self.outline.contours.push(contour);
+        self.cursor = self.start;
+    }
+
+    pub fn finish(mut self) -> Outline {
+        self.close();
+        self.outline
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relative_cff_path() {
+        let path = vec![
+            PathInstruction::MoveTo(10.into(), 10.into()),
+            PathInstruction::LineTo(100.into(), 0.into()),
+            PathInstruction::CurveTo(
+                10.into(),
+                0.into(),
+                0.into(),
+                10.into(),
+                (-10).into(),
+                10.into(),
+            ),
+        ];
+        let outline = Outline::from_path_instructions(path);
+        assert_eq!(outline.contours.len(), 1);
+        let segments = &outline.contours[0].segments;
+        assert_eq!(segments.len(), 3);
+        assert_eq!(
+            segments[1],
+            Segment::Cubic(
+                Point::new(110.0, 10.0),
+                Point::new(120.0, 10.0),
+                Point::new(120.0, 20.0),
+                Point::new(110.0, 30.0)
+            )
+        );
+        // implicitly closed
+        assert_eq!(
+            segments[2],
+            Segment::Line(Point::new(110.0, 30.0), Point::new(10.0, 10.0))
+        );
+    }
+
+    #[test]
+    fn test_path_instructions_round_trip() {
+        let mut builder = OutlineBuilder::default();
+        builder.move_to(Point::new(10.0, 10.0));
+        builder.line_to(Point::new(110.0, 10.0));
+        builder.quad_to(Point::new(110.0, 60.0), Point::new(60.0, 60.0));
+        builder.curve_to(
+            Point::new(40.0, 60.0),
+            Point::new(10.0, 40.0),
+            Point::new(10.5, 30.25),
+        );
+        builder.move_to(Point::new(200.0, 0.0));
+        builder.line_to(Point::new(300.0, 0.0));
+        builder.line_to(Point::new(300.0, 100.0));
+        let outline = builder.finish();
+
+        let round_trip = Outline::from_path_instructions(outline.to_path_instructions());
+        assert_eq!(round_trip.contours.len(), 2);
+        assert_eq!(round_trip.contours[0].segments.len(), 4);
+        assert_eq!(round_trip.contours[1], outline.contours[1]);
+        for (a, b) in round_trip.contours[0]
+            .segments
+            .iter()
+            .zip(&outline.contours[0].segments)
+        {
+            for &t in &[0.0, 0.3, 0.5, 1.0] {
+                assert!((a.point(t) - b.point(t)).length() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_winding_number() {
+        let mut builder = OutlineBuilder::default();
+        // a counter-clockwise circle-like shape around the origin
+        builder.move_to(Point::new(10.0, 0.0));
+        builder.quad_to(Point::new(10.0, 10.0), Point::new(0.0, 10.0));
+        builder.quad_to(Point::new(-10.0, 10.0), Point::new(-10.0, 0.0));
+        builder.quad_to(Point::new(-10.0, -10.0), Point::new(0.0, -10.0));
+        builder.quad_to(Point::new(10.0, -10.0), Point::new(10.0, 0.0));
+        let outline = builder.finish();
+        assert_eq!(outline.winding_number(Point::new(0.0, 0.0)), 1);
+        // exactly at the height of the end points
+        assert_eq!(outline.winding_number(Point::new(-9.0, 0.0)), 1);
+        assert_eq!(outline.winding_number(Point::new(7.4, 7.4)), 1);
+        assert_eq!(outline.winding_number(Point::new(7.6, 7.6)), 0);
+        assert_eq!(outline.winding_number(Point::new(-20.0, 0.0)), 0);
+        assert_eq!(
+            outline.contours[0]
+                .reverse()
+                .winding_number(Point::default()),
+            -1
+        );
+    }
+
+    #[test]
+    fn test_split_segment() {
+        let segment = Segment::Cubic(
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 10.0),
+            Point::new(10.0, 10.0),
+            Point::new(10.0, 0.0),
+        );
+        let (first, second) = segment.split(0.25);
+        assert_eq!(first.end(), segment.point(0.25));
+        assert_eq!(second.start(), segment.point(0.25));
+        assert!((first.point(0.5) - segment.point(0.125)).length() < 1e-4);
+        assert!((second.point(0.5) - segment.point(0.625)).length() < 1e-4);
+    }
+}
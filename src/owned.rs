@@ -0,0 +1,294 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Fonts which own their data, so they can be stored in caches and shared
+//! between threads.
+//!
+//! An [`OwnedFont`](struct.OwnedFont.html) keeps the font file in reference
+//! counted memory, either on the heap or mapped from a file. Cloning the font
+//! or the data of its tables only increments the reference count.
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
+use std::fs::File;
+use std::io::Read;
+use std::ops::{Deref, Range};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::ParserError;
+use crate::{Font, FontRecord, OpentypeTableAccess, Tag};
+
+/// A read-only memory map of a whole file.
+#[cfg(unix)]
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// the mapping is read-only and not tied to a thread
+#[cfg(unix)]
+unsafe impl Send for Mmap {}
+#[cfg(unix)]
+unsafe impl Sync for Mmap {}
+
+#[cfg(unix)]
+impl Mmap {
+    /// Maps the file into memory.
+    ///
+    /// The file must not be modified while it is mapped.
+    unsafe fn map(file: &File) -> std::io::Result<Mmap> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = usize::try_from(file.metadata()?.len()).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "The file is too large to be mapped.",
+            )
+        })?;
+        if len == 0 {
+            // empty mappings are not allowed
+            return Ok(Mmap {
+                ptr: std::ptr::null_mut(),
+                len,
+            });
+        }
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Mmap { ptr, len })
+    }
+}
+
+#[cfg(unix)]
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Storage {
+    Memory(Arc<[u8]>),
+    #[cfg(unix)]
+    Mapped(Arc<Mmap>),
+}
+
+impl Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Storage::Memory(data) => data,
+            #[cfg(unix)]
+            Storage::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// A reference counted slice of a font file.
+///
+/// This is the table data of an [`OwnedFont`](struct.OwnedFont.html), which
+/// can be cloned cheaply and outlives the font.
+#[derive(Clone)]
+pub struct FontBytes {
+    storage: Storage,
+    range: Range<usize>,
+}
+
+impl FontBytes {
+    fn new(storage: Storage) -> Self {
+        let range = 0..storage.len();
+        FontBytes { storage, range }
+    }
+
+    /// Returns the bytes in `range`, which is relative to this slice.
+    ///
+    /// Returns `None` if the range is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> Option<FontBytes> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        Some(FontBytes {
+            storage: self.storage.clone(),
+            range: self.range.start + range.start..self.range.start + range.end,
+        })
+    }
+}
+
+impl Deref for FontBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.storage[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for FontBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Debug for FontBytes {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "FontBytes({} bytes)", self.len())
+    }
+}
+
+/// A font which owns the data of its font file.
+///
+/// Unlike [`Font`](../struct.Font.html), an `OwnedFont` has no lifetime, is
+/// `Send` and `Sync`, and can be cloned cheaply. Its table data is returned
+/// as [`FontBytes`](struct.FontBytes.html). Use [`as_font`](#method.as_font)
+/// to access the glyphs.
+///
+/// Examples
+/// --------
+///
+/// ```
+/// use std::collections::HashMap;
+/// use font_parse::owned::OwnedFont;
+/// use font_parse::{OpentypeTableAccess, Tag};
+///
+/// let mut cache = HashMap::new();
+/// let font = OwnedFont::from_file("tests/font_files/Inconsolata-Regular.ttf", 0).unwrap();
+/// cache.insert("Inconsolata", font);
+///
+/// let font = cache["Inconsolata"].clone();
+/// let head = std::thread::spawn(move || font.table_data(Tag(*b"head")).unwrap())
+///     .join()
+///     .unwrap();
+/// assert_eq!(head.len(), 54);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OwnedFont {
+    record: FontRecord,
+    directory_offset: usize,
+    data: FontBytes,
+}
+
+impl OwnedFont {
+    fn from_storage(storage: Storage, index: u32) -> Result<Self, ParserError> {
+        let font = Font::from_bytes(&storage, index)?;
+        let (record, directory_offset) = (font.record.into_owned(), font.directory_offset);
+        Ok(OwnedFont {
+            record,
+            directory_offset,
+            data: FontBytes::new(storage),
+        })
+    }
+
+    /// Creates a font from the data of a font file and an index for selecting
+    /// a font from a collection.
+    pub fn from_vec(data: Vec<u8>, index: u32) -> Result<Self, ParserError> {
+        OwnedFont::from_arc(data.into(), index)
+    }
+
+    /// Creates a font from shared data, for example to load several fonts of
+    /// a collection.
+    pub fn from_arc(data: Arc<[u8]>, index: u32) -> Result<Self, ParserError> {
+        OwnedFont::from_storage(Storage::Memory(data), index)
+    }
+
+    /// Reads the font file at `path` into memory.
+    pub fn from_file(path: impl AsRef<Path>, index: u32) -> Result<Self, ParserError> {
+        let mut data = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(ParserError::from_err)?;
+        OwnedFont::from_vec(data, index)
+    }
+
+    /// Maps the font file at `path` into memory.
+    ///
+    /// Only the parts of the file which are accessed are read. On platforms
+    /// other than Unix, the file is read into memory instead.
+    ///
+    /// Safety
+    /// ------
+    ///
+    /// The file must not be modified or truncated while the font or any of
+    /// its table data exists, because the data would change without
+    /// synchronization.
+    pub unsafe fn map_file(path: impl AsRef<Path>, index: u32) -> Result<Self, ParserError> {
+        #[cfg(unix)]
+        {
+            let file = File::open(path).map_err(ParserError::from_err)?;
+            let mmap = Mmap::map(&file).map_err(ParserError::from_err)?;
+            OwnedFont::from_storage(Storage::Mapped(Arc::new(mmap)), index)
+        }
+        #[cfg(not(unix))]
+        {
+            OwnedFont::from_file(path, index)
+        }
+    }
+
+    /// Returns the table directory of the font.
+    pub fn record(&self) -> &FontRecord {
+        &self.record
+    }
+
+    /// Returns the data of the whole font file.
+    pub fn data(&self) -> &FontBytes {
+        &self.data
+    }
+
+    /// Returns a borrowed `Font`, which gives access to the glyphs.
+    pub fn as_font(&self) -> Font<'_> {
+        Font {
+            record: Cow::Borrowed(&self.record),
+            collection: None,
+            directory_offset: self.directory_offset,
+            data: &self.data,
+        }
+    }
+}
+
+impl OpentypeTableAccess<'static> for OwnedFont {
+    type TableData = FontBytes;
+
+    fn table_data(&self, tag: Tag) -> Option<FontBytes> {
+        let record = self.record.tables.get(&tag)?;
+        let start = record.offset as usize;
+        self.data
+            .slice(start..start.checked_add(record.length as usize)?)
+    }
+}
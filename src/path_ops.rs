@@ -0,0 +1,823 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Path operations on glyph outlines.
+//!
+//! This module implements overlap removal, stroking and contour direction
+//! correction. All operations work on `Outline`, so they apply to outlines
+//! built from CFF `PathInstruction`s and TrueType `QuadraticPath`s alike. The
+//! result can be converted back with `Outline::to_path_instructions` or, for
+//! TrueType, with `curve_conversion::cubic_to_quadratic`.
+//!
+//! Outlines are filled using the non-zero winding rule.
+
+use std::f32::consts::PI;
+
+use crate::outline::{Contour, Outline, Point, Segment};
+
+/// The orientation of a contour in a coordinate system with the y axis
+/// pointing upwards.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Direction {
+    /// The direction of outer contours in TrueType fonts.
+    pub const TRUETYPE: Direction = Direction::Clockwise;
+    /// The direction of outer contours in CFF fonts.
+    pub const POSTSCRIPT: Direction = Direction::CounterClockwise;
+
+    fn reversed(self) -> Direction {
+        match self {
+            Direction::Clockwise => Direction::CounterClockwise,
+            Direction::CounterClockwise => Direction::Clockwise,
+        }
+    }
+}
+
+/// Returns the direction of a contour or `None` if it encloses no area.
+pub fn contour_direction(contour: &Contour) -> Option<Direction> {
+    let area = signed_area(contour);
+    if area > 0.0 {
+        Some(Direction::CounterClockwise)
+    } else if area < 0.0 {
+        Some(Direction::Clockwise)
+    } else {
+        None
+    }
+}
+
+/// Orients the contours of `outline` such that outer contours run in the
+/// given direction and holes in the opposite one.
+///
+/// Whether a contour is a hole is decided by the number of contours it lies
+/// in, so overlapping contours should be removed first.
+pub fn correct_directions(outline: &Outline, outer: Direction) -> Outline {
+    let contours = outline
+        .contours
+        .iter()
+        .map(|contour| {
+            let direction = match contour_direction(contour) {
+                Some(direction) => direction,
+                None => return contour.clone(),
+            };
+            let p = contour.segments[0].point(0.5);
+            let depth = outline
+                .contours
+                .iter()
+                .filter(|&other| other != contour && other.winding_number(p) != 0)
+                .count();
+            let expected = if depth % 2 == 0 {
+                outer
+            } else {
+                outer.reversed()
+            };
+            if direction == expected {
+                contour.clone()
+            } else {
+                contour.reverse()
+            }
+        })
+        .collect();
+    Outline { contours }
+}
+
+/// Removes overlapping parts of the contours such that every point of the
+/// filled area is enclosed exactly once.
+///
+/// The resulting outer contours run counter-clockwise and holes clockwise.
+pub fn remove_overlaps(outline: &Outline) -> Outline {
+    let tolerance = tolerance(outline);
+    let segments: Vec<Segment> = outline
+        .contours
+        .iter()
+        .flat_map(|contour| contour.segments.iter().cloned())
+        .filter(|segment| !is_degenerate(segment))
+        .collect();
+
+    // find all intersections between segments
+    let mut splits: Vec<Vec<(f32, Point)>> = vec![vec![]; segments.len()];
+    for i in 0..segments.len() {
+        for j in i + 1..segments.len() {
+            let mut hits = vec![];
+            intersect(
+                &segments[i],
+                (0.0, 1.0),
+                &segments[j],
+                (0.0, 1.0),
+                tolerance,
+                0,
+                &mut hits,
+            );
+            for (s, t, p) in hits {
+                splits[i].push((s, p));
+                splits[j].push((t, p));
+            }
+        }
+    }
+
+    // split the segments at the intersections and keep the pieces that
+    // separate filled from unfilled area
+    let offset = tolerance * 4.0;
+    let mut pieces: Vec<Segment> = vec![];
+    for (segment, splits) in segments.iter().zip(splits) {
+        for piece in split_segment(segment, splits) {
+            let direction = piece.direction(0.5);
+            if direction == Point::default() {
+                continue;
+            }
+            let normal = left_normal(direction);
+            let mid = piece.point(0.5);
+            let filled_left = outline.winding_number(mid + normal * offset) != 0;
+            let filled_right = outline.winding_number(mid - normal * offset) != 0;
+            let piece = match (filled_left, filled_right) {
+                (true, false) => piece,
+                (false, true) => piece.reverse(),
+                _ => continue,
+            };
+            // coincident edges of different contours are only kept once
+            let duplicate = pieces.iter().any(|other| {
+                (other.start() - piece.start()).length() <= offset
+                    && (other.end() - piece.end()).length() <= offset
+                    && (other.point(0.5) - piece.point(0.5)).length() <= offset
+            });
+            if !duplicate {
+                pieces.push(piece);
+            }
+        }
+    }
+
+    Outline {
+        contours: join_pieces(pieces, tolerance * 16.0),
+    }
+}
+
+/// Returns the union of two outlines without overlaps.
+pub fn union(a: &Outline, b: &Outline) -> Outline {
+    let mut combined = remove_overlaps(a);
+    combined.contours.extend(remove_overlaps(b).contours);
+    remove_overlaps(&combined)
+}
+
+/// The shape used at the corners of a stroked path.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineJoin {
+    /// Extends the outer edges until they meet. If the miter would be longer
+    /// than the given multiple of the half stroke width, a bevel is used.
+    Miter(f32),
+    Round,
+    Bevel,
+}
+
+/// The shape used at the ends of an open stroked path.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// Parameters for stroking an outline.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrokeStyle {
+    /// The width of the stroke in font units.
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// The maximal deviation in font units of the stroke from its exact shape.
+    pub tolerance: f32,
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        StrokeStyle {
+            width,
+            join: LineJoin::Miter(4.0),
+            cap: LineCap::Butt,
+            tolerance: 0.1,
+        }
+    }
+}
+
+/// Strokes the (closed) contours of `outline`.
+///
+/// Each contour results in two contours, one on either side. The result may
+/// overlap itself, use `remove_overlaps` to clean it up.
+pub fn stroke(outline: &Outline, style: &StrokeStyle) -> Outline {
+    let half_width = style.width / 2.0;
+    let mut contours = vec![];
+    for contour in &outline.contours {
+        for segments in &[contour.segments.clone(), contour.reverse().segments] {
+            let segments = offset_path(segments, half_width, style, true);
+            if !segments.is_empty() {
+                contours.push(Contour { segments });
+            }
+        }
+    }
+    Outline { contours }
+}
+
+/// Strokes an open path made of consecutive segments, using the line cap of
+/// `style` at both ends.
+pub fn stroke_open(segments: &[Segment], style: &StrokeStyle) -> Outline {
+    let half_width = style.width / 2.0;
+    let segments: Vec<Segment> = segments
+        .iter()
+        .cloned()
+        .filter(|segment| !is_degenerate(segment))
+        .collect();
+    let (first, last) = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return Outline::default(),
+    };
+    let reversed: Vec<Segment> = segments.iter().rev().map(Segment::reverse).collect();
+    let mut result = offset_path(&segments, half_width, style, false);
+    let backward = offset_path(&reversed, half_width, style, false);
+    push_cap(
+        &mut result,
+        last.end(),
+        last.direction(1.0),
+        half_width,
+        style.cap,
+    );
+    result.extend(backward);
+    push_cap(
+        &mut result,
+        first.start(),
+        -first.direction(0.0),
+        half_width,
+        style.cap,
+    );
+    Outline {
+        contours: vec![Contour { segments: result }],
+    }
+}
+
+/// Makes the outline bolder by growing it by `amount` font units in every
+/// direction, as done for synthetic bold faces.
+pub fn embolden(outline: &Outline, amount: f32, join: LineJoin) -> Outline {
+    let style = StrokeStyle {
+        join,
+        ..StrokeStyle::new(2.0 * amount)
+    };
+    union(outline, &stroke(outline, &style))
+}
+
+/// A tolerance for geometric comparisons relative to the size of the outline.
+fn tolerance(outline: &Outline) -> f32 {
+    match outline.control_bounds() {
+        Some((min, max)) => ((max.x - min.x).max(max.y - min.y) * 1e-5).max(1e-4),
+        None => 1e-4,
+    }
+}
+
+fn is_degenerate(segment: &Segment) -> bool {
+    segment.start() == segment.end()
+        && match *segment {
+            Segment::Line(..) => true,
+            Segment::Quadratic(p0, p1, _) => p0 == p1,
+            Segment::Cubic(p0, p1, p2, _) => p0 == p1 && p0 == p2,
+        }
+}
+
+fn left_normal(direction: Point) -> Point {
+    let direction = direction.normalize();
+    Point::new(-direction.y, direction.x)
+}
+
+/// Twice the signed area of the contour, positive for counter-clockwise
+/// contours.
+fn signed_area(contour: &Contour) -> f32 {
+    const STEPS: usize = 8;
+    let mut area = 0.0;
+    for segment in &contour.segments {
+        match segment {
+            Segment::Line(a, b) => area += a.cross(*b),
+            _ => {
+                let mut last = segment.start();
+                for step in 1..=STEPS {
+                    let next = segment.point(step as f32 / STEPS as f32);
+                    area += last.cross(next);
+                    last = next;
+                }
+            }
+        }
+    }
+    area
+}
+
+/// Returns the parameters `(s, t)` where the lines `p + s * r` and `q + t * u`
+/// intersect or `None` if they are parallel.
+fn line_intersection(p: Point, r: Point, q: Point, u: Point) -> Option<(f32, f32)> {
+    let denominator = r.cross(u);
+    if denominator.abs() <= 1e-12 * r.length() * u.length() || denominator == 0.0 {
+        return None;
+    }
+    let s = (q - p).cross(u) / denominator;
+    let t = (q - p).cross(r) / denominator;
+    Some((s, t))
+}
+
+/// Returns `true` if all control points are within `tolerance` of the chord.
+fn is_flat(segment: &Segment, tolerance: f32) -> bool {
+    let (start, end) = (segment.start(), segment.end());
+    let chord = end - start;
+    let length = chord.length();
+    let distance = |p: Point| {
+        if length == 0.0 {
+            (p - start).length()
+        } else {
+            chord.cross(p - start).abs() / length
+        }
+    };
+    match *segment {
+        Segment::Line(..) => true,
+        Segment::Quadratic(_, p1, _) => distance(p1) <= tolerance,
+        Segment::Cubic(_, p1, p2, _) => distance(p1) <= tolerance && distance(p2) <= tolerance,
+    }
+}
+
+fn bounds_overlap(a: &Segment, b: &Segment, margin: f32) -> bool {
+    let (amin, amax) = a.control_bounds();
+    let (bmin, bmax) = b.control_bounds();
+    amin.x <= bmax.x + margin
+        && bmin.x <= amax.x + margin
+        && amin.y <= bmax.y + margin
+        && bmin.y <= amax.y + margin
+}
+
+const MAX_INTERSECTION_DEPTH: u32 = 24;
+
+/// Finds the intersections of two segments by recursive subdivision until
+/// both parts are flat, and collects them as `(s, t, point)` where `s` and
+/// `t` are the parameters of the original segments.
+fn intersect(
+    a: &Segment,
+    a_range: (f32, f32),
+    b: &Segment,
+    b_range: (f32, f32),
+    tolerance: f32,
+    depth: u32,
+    hits: &mut Vec<(f32, f32, Point)>,
+) {
+    if !bounds_overlap(a, b, tolerance) {
+        return;
+    }
+    let a_flat = is_flat(a, tolerance);
+    let b_flat = is_flat(b, tolerance);
+    if (a_flat && b_flat) || depth >= MAX_INTERSECTION_DEPTH {
+        let (p, r) = (a.start(), a.end() - a.start());
+        let (q, u) = (b.start(), b.end() - b.start());
+        if let Some((s, t)) = line_intersection(p, r, q, u) {
+            if (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t) {
+                hits.push((
+                    a_range.0 + s * (a_range.1 - a_range.0),
+                    b_range.0 + t * (b_range.1 - b_range.0),
+                    p + r * s,
+                ));
+            }
+        }
+        return;
+    }
+    let size = |segment: &Segment| {
+        let (min, max) = segment.control_bounds();
+        (max - min).length()
+    };
+    let a_mid = 0.5 * (a_range.0 + a_range.1);
+    let b_mid = 0.5 * (b_range.0 + b_range.1);
+    if !a_flat && (b_flat || size(a) >= size(b)) {
+        let (a0, a1) = a.split(0.5);
+        intersect(
+            &a0,
+            (a_range.0, a_mid),
+            b,
+            b_range,
+            tolerance,
+            depth + 1,
+            hits,
+        );
+        intersect(
+            &a1,
+            (a_mid, a_range.1),
+            b,
+            b_range,
+            tolerance,
+            depth + 1,
+            hits,
+        );
+    } else {
+        let (b0, b1) = b.split(0.5);
+        intersect(
+            a,
+            a_range,
+            &b0,
+            (b_range.0, b_mid),
+            tolerance,
+            depth + 1,
+            hits,
+        );
+        intersect(
+            a,
+            a_range,
+            &b1,
+            (b_mid, b_range.1),
+            tolerance,
+            depth + 1,
+            hits,
+        );
+    }
+}
+
+/// Replaces the end points of a segment, keeping its control points.
+fn with_end_points(segment: Segment, start: Point, end: Point) -> Segment {
+    match segment {
+        Segment::Line(..) => Segment::Line(start, end),
+        Segment::Quadratic(_, p1, _) => Segment::Quadratic(start, p1, end),
+        Segment::Cubic(_, p1, p2, _) => Segment::Cubic(start, p1, p2, end),
+    }
+}
+
+/// Splits a segment at the given parameters. The pieces end exactly at the
+/// given points such that pieces of different segments can be joined.
+fn split_segment(segment: &Segment, mut splits: Vec<(f32, Point)>) -> Vec<Segment> {
+    const PARAMETER_EPSILON: f32 = 1e-4;
+    splits.retain(|&(t, _)| t > PARAMETER_EPSILON && t < 1.0 - PARAMETER_EPSILON);
+    splits.sort_by(|a, b| a.0.total_cmp(&b.0));
+    splits.dedup_by(|b, a| b.0 - a.0 < PARAMETER_EPSILON);
+    splits.push((1.0, segment.end()));
+
+    let mut pieces = vec![];
+    let (mut t0, mut p0) = (0.0, segment.start());
+    for (t1, p1) in splits {
+        let piece = with_end_points(segment.subsegment(t0, t1), p0, p1);
+        if !is_degenerate(&piece) {
+            pieces.push(piece);
+        }
+        t0 = t1;
+        p0 = p1;
+    }
+    pieces
+}
+
+/// Chains pieces into closed contours by connecting end points which are
+/// within `tolerance` of each other.
+fn join_pieces(pieces: Vec<Segment>, tolerance: f32) -> Vec<Contour> {
+    let mut used = vec![false; pieces.len()];
+    let mut contours = vec![];
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let origin = pieces[first].start();
+        let mut segments = vec![pieces[first]];
+        loop {
+            let end = segments[segments.len() - 1].end();
+            if (end - origin).length() <= tolerance {
+                break;
+            }
+            let next = (0..pieces.len())
+                .filter(|&i| !used[i])
+                .map(|i| (i, (pieces[i].start() - end).length()))
+                .filter(|&(_, distance)| distance <= tolerance)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            match next {
+                Some((i, _)) => {
+                    used[i] = true;
+                    segments.push(with_end_points(pieces[i], end, pieces[i].end()));
+                }
+                None => break,
+            }
+        }
+        let last = segments.len() - 1;
+        segments[last] = with_end_points(segments[last], segments[last].start(), origin);
+        let contour = Contour { segments };
+        if signed_area(&contour).abs() > tolerance * tolerance {
+            contours.push(contour);
+        }
+    }
+    contours
+}
+
+const MAX_OFFSET_DEPTH: u32 = 8;
+
+/// Approximates the curve at distance `d` to the left of the segment.
+fn offset_segment(segment: &Segment, d: f32, tolerance: f32, depth: u32, out: &mut Vec<Segment>) {
+    let n0 = left_normal(segment.direction(0.0)) * d;
+    let n1 = left_normal(segment.direction(1.0)) * d;
+    let approximation = match *segment {
+        Segment::Line(p0, p1) => {
+            out.push(Segment::Line(p0 + n0, p1 + n0));
+            return;
+        }
+        Segment::Quadratic(p0, p1, p2) => {
+            let (q0, q2) = (p0 + n0, p2 + n1);
+            // the control point lies on both offset tangents
+            let q1 = match line_intersection(q0, segment.direction(0.0), q2, segment.direction(1.0))
+            {
+                Some((s, _)) => q0 + segment.direction(0.0) * s,
+                None => p1 + left_normal(segment.direction(0.5)) * d,
+            };
+            Segment::Quadratic(q0, q1, q2)
+        }
+        Segment::Cubic(p0, p1, p2, p3) => Segment::Cubic(p0 + n0, p1 + n0, p2 + n1, p3 + n1),
+    };
+    let error = [0.25, 0.5, 0.75]
+        .iter()
+        .map(|&t| {
+            let exact = segment.point(t) + left_normal(segment.direction(t)) * d;
+            (approximation.point(t) - exact).length()
+        })
+        .fold(0.0, f32::max);
+    if error <= tolerance || depth >= MAX_OFFSET_DEPTH {
+        out.push(approximation);
+    } else {
+        let (first, second) = segment.split(0.5);
+        offset_segment(&first, d, tolerance, depth + 1, out);
+        offset_segment(&second, d, tolerance, depth + 1, out);
+    }
+}
+
+/// Offsets a path by `d` to its left, connecting the offset segments with
+/// joins.
+fn offset_path(segments: &[Segment], d: f32, style: &StrokeStyle, closed: bool) -> Vec<Segment> {
+    let segments: Vec<&Segment> = segments
+        .iter()
+        .filter(|segment| !is_degenerate(segment))
+        .collect();
+    let mut result = vec![];
+    for (i, segment) in segments.iter().enumerate() {
+        if i > 0 {
+            push_join(&mut result, segments[i - 1], segment, d, style);
+        }
+        offset_segment(segment, d, style.tolerance, 0, &mut result);
+    }
+    if closed && !segments.is_empty() {
+        push_join(
+            &mut result,
+            segments[segments.len() - 1],
+            segments[0],
+            d,
+            style,
+        );
+    }
+    result
+}
+
+/// Connects the end of `result`, which is the offset of `incoming`, to the
+/// start of the offset of `outgoing`.
+fn push_join(
+    result: &mut Vec<Segment>,
+    incoming: &Segment,
+    outgoing: &Segment,
+    d: f32,
+    style: &StrokeStyle,
+) {
+    let vertex = incoming.end();
+    let a = incoming.direction(1.0).normalize();
+    let b = outgoing.direction(0.0).normalize();
+    let from = match result.last() {
+        Some(segment) => segment.end(),
+        None => return,
+    };
+    let to = vertex + left_normal(b) * d;
+    if (to - from).length() <= style.tolerance * 1e-3 {
+        return;
+    }
+    if a.cross(b) * d > 0.0 {
+        // On the inner side of the corner the offsets overlap. Going through
+        // the vertex keeps the winding numbers consistent.
+        result.push(Segment::Line(from, vertex));
+        result.push(Segment::Line(vertex, to));
+        return;
+    }
+    match style.join {
+        LineJoin::Bevel => result.push(Segment::Line(from, to)),
+        LineJoin::Miter(limit) => {
+            let (na, nb) = (left_normal(a), left_normal(b));
+            let miter = (na + nb) * (d / (1.0 + na.dot(nb)));
+            if 1.0 + na.dot(nb) > 1e-6 && miter.length() <= limit * d.abs() {
+                result.push(Segment::Line(from, vertex + miter));
+                result.push(Segment::Line(vertex + miter, to));
+            } else {
+                result.push(Segment::Line(from, to));
+            }
+        }
+        LineJoin::Round => {
+            let (u, v) = (from - vertex, to - vertex);
+            let sweep = u.cross(v).atan2(u.dot(v));
+            push_arc(result, vertex, from, sweep);
+            let last = result.len() - 1;
+            result[last] = with_end_points(result[last], result[last].start(), to);
+        }
+    }
+}
+
+/// Appends a circular arc around `center` starting at `from` and sweeping
+/// the given angle (counter-clockwise if positive) using cubic segments.
+fn push_arc(result: &mut Vec<Segment>, center: Point, from: Point, sweep: f32) {
+    let count = (sweep.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = sweep / count as f32;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+    let rotate = |v: Point, angle: f32| {
+        let (sin, cos) = angle.sin_cos();
+        Point::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+    };
+    let mut radius = from - center;
+    for _ in 0..count {
+        let next = rotate(radius, step);
+        let tangent0 = Point::new(-radius.y, radius.x) * k;
+        let tangent1 = Point::new(-next.y, next.x) * k;
+        result.push(Segment::Cubic(
+            center + radius,
+            center + radius + tangent0,
+            center + next - tangent1,
+            center + next,
+        ));
+        radius = next;
+    }
+}
+
+/// Appends a cap at the end `point` of a path with the given direction,
+/// going from the left to the right offset.
+fn push_cap(result: &mut Vec<Segment>, point: Point, direction: Point, d: f32, cap: LineCap) {
+    let normal = left_normal(direction) * d;
+    let forward = direction.normalize() * d;
+    let (left, right) = (point + normal, point - normal);
+    match cap {
+        LineCap::Butt => result.push(Segment::Line(left, right)),
+        LineCap::Square => {
+            result.push(Segment::Line(left, left + forward));
+            result.push(Segment::Line(left + forward, right + forward));
+            result.push(Segment::Line(right + forward, right));
+        }
+        LineCap::Round => push_arc(result, point, left, -PI),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::outline::OutlineBuilder;
+
+    fn rectangle(builder: &mut OutlineBuilder, min: (f32, f32), max: (f32, f32)) {
+        builder.move_to(Point::new(min.0, min.1));
+        builder.line_to(Point::new(max.0, min.1));
+        builder.line_to(Point::new(max.0, max.1));
+        builder.line_to(Point::new(min.0, max.1));
+    }
+
+    fn inside(outline: &Outline, x: f32, y: f32) -> bool {
+        outline.winding_number(Point::new(x, y)) != 0
+    }
+
+    #[test]
+    fn test_correct_directions() {
+        let mut builder = OutlineBuilder::default();
+        rectangle(&mut builder, (0.0, 0.0), (100.0, 100.0));
+        rectangle(&mut builder, (25.0, 25.0), (75.0, 75.0));
+        let outline = builder.finish();
+        // both contours run counter-clockwise, so the hole is filled
+        assert!(inside(&outline, 50.0, 50.0));
+
+        let corrected = correct_directions(&outline, Direction::TRUETYPE);
+        assert_eq!(
+            contour_direction(&corrected.contours[0]),
+            Some(Direction::Clockwise)
+        );
+        assert_eq!(
+            contour_direction(&corrected.contours[1]),
+            Some(Direction::CounterClockwise)
+        );
+        assert!(!inside(&corrected, 50.0, 50.0));
+        assert!(inside(&corrected, 10.0, 50.0));
+    }
+
+    #[test]
+    fn test_remove_overlaps() {
+        let mut builder = OutlineBuilder::default();
+        rectangle(&mut builder, (0.0, 0.0), (10.0, 10.0));
+        rectangle(&mut builder, (5.0, 5.0), (15.0, 15.0));
+        let outline = remove_overlaps(&builder.finish());
+        assert_eq!(outline.contours.len(), 1);
+        assert_eq!(outline.contours[0].segments.len(), 8);
+        assert!((outline.contours[0].winding_area() - 350.0).abs() < 1e-3);
+        for &(x, y) in &[(2.0, 2.0), (7.0, 7.0), (12.0, 12.0)] {
+            assert_eq!(outline.winding_number(Point::new(x, y)), 1);
+        }
+        assert!(!inside(&outline, 12.0, 2.0));
+    }
+
+    #[test]
+    fn test_remove_overlaps_duplicate() {
+        let mut builder = OutlineBuilder::default();
+        rectangle(&mut builder, (0.0, 0.0), (10.0, 10.0));
+        rectangle(&mut builder, (0.0, 0.0), (10.0, 10.0));
+        let outline = remove_overlaps(&builder.finish());
+        assert_eq!(outline.contours.len(), 1);
+        assert_eq!(outline.winding_number(Point::new(5.0, 5.0)), 1);
+    }
+
+    #[test]
+    fn test_remove_overlaps_curves() {
+        let mut builder = OutlineBuilder::default();
+        for &cx in &[0.0, 10.0] {
+            builder.move_to(Point::new(cx + 8.0, 0.0));
+            builder.quad_to(Point::new(cx + 8.0, 8.0), Point::new(cx, 8.0));
+            builder.quad_to(Point::new(cx - 8.0, 8.0), Point::new(cx - 8.0, 0.0));
+            builder.quad_to(Point::new(cx - 8.0, -8.0), Point::new(cx, -8.0));
+            builder.quad_to(Point::new(cx + 8.0, -8.0), Point::new(cx + 8.0, 0.0));
+        }
+        let original = builder.finish();
+        let outline = remove_overlaps(&original);
+        assert_eq!(outline.contours.len(), 1);
+        for x in -10..20 {
+            for y in -10..10 {
+                let p = Point::new(x as f32 + 0.3, y as f32 + 0.3);
+                assert_eq!(
+                    outline.winding_number(p),
+                    original.winding_number(p).min(1),
+                    "{:?}",
+                    p
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_stroke_joins() {
+        let mut builder = OutlineBuilder::default();
+        rectangle(&mut builder, (0.0, 0.0), (100.0, 100.0));
+        let outline = builder.finish();
+
+        let mut style = StrokeStyle::new(10.0);
+        let stroked = stroke(&outline, &style);
+        assert_eq!(stroked.contours.len(), 2);
+        assert!(inside(&stroked, 0.0, 50.0));
+        assert!(inside(&stroked, 104.0, 50.0));
+        assert!(!inside(&stroked, 106.0, 50.0));
+        assert!(!inside(&stroked, 50.0, 50.0));
+        // the miter fills the corner
+        assert!(inside(&stroked, -4.5, -4.5));
+
+        style.join = LineJoin::Bevel;
+        let stroked = stroke(&outline, &style);
+        assert!(!inside(&stroked, -4.5, -4.5));
+        assert!(inside(&stroked, -2.0, -2.0));
+
+        style.join = LineJoin::Round;
+        let stroked = stroke(&outline, &style);
+        assert!(!inside(&stroked, -4.0, -4.0));
+        assert!(inside(&stroked, -3.4, -3.4));
+    }
+
+    #[test]
+    fn test_stroke_open_caps() {
+        let line = [Segment::Line(Point::new(0.0, 0.0), Point::new(100.0, 0.0))];
+        let mut style = StrokeStyle::new(10.0);
+        let stroked = stroke_open(&line, &style);
+        assert!(inside(&stroked, 50.0, 4.0));
+        assert!(!inside(&stroked, 50.0, 6.0));
+        assert!(!inside(&stroked, -1.0, 0.0));
+
+        style.cap = LineCap::Square;
+        let stroked = stroke_open(&line, &style);
+        assert!(inside(&stroked, -4.0, 4.0));
+        assert!(inside(&stroked, 104.0, -4.0));
+
+        style.cap = LineCap::Round;
+        let stroked = stroke_open(&line, &style);
+        assert!(inside(&stroked, -4.0, 0.0));
+        assert!(!inside(&stroked, -4.0, 4.0));
+        assert!(inside(&stroked, 103.0, 3.0));
+    }
+
+    #[test]
+    fn test_embolden() {
+        let mut builder = OutlineBuilder::default();
+        rectangle(&mut builder, (0.0, 0.0), (100.0, 100.0));
+        rectangle(&mut builder, (40.0, 40.0), (60.0, 60.0));
+        let outline = correct_directions(&builder.finish(), Direction::POSTSCRIPT);
+        let bold = embolden(&outline, 5.0, LineJoin::Miter(4.0));
+        assert_eq!(bold.contours.len(), 2);
+        assert!(inside(&bold, -4.0, 50.0));
+        assert!(!inside(&bold, -6.0, 50.0));
+        assert!(inside(&bold, 44.0, 50.0));
+        assert!(!inside(&bold, 50.0, 50.0));
+        let (min, max) = bold.control_bounds().unwrap();
+        assert!((min.x + 5.0).abs() < 1e-3 && (max.y - 105.0).abs() < 1e-3);
+    }
+}
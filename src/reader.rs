@@ -0,0 +1,252 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Checked reads of big-endian values from font data.
+//!
+//! A `Reader` keeps track of its offset and of the structure it is reading, so
+//! that its errors can be located within the table or file being parsed.
+
+use std::ops::Range;
+
+use crate::cff::Fixed16_16;
+use crate::error::{ErrorKind, ParserError};
+use crate::Tag;
+
+/// A value of fixed size that is stored in big-endian byte order.
+pub(crate) trait Scalar: Sized {
+    const SIZE: usize;
+
+    /// Converts exactly `SIZE` bytes into the value.
+    fn from_be_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_scalar {
+    ($($ty:ty),*) => {
+        $(
+            impl Scalar for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+
+                fn from_be_slice(bytes: &[u8]) -> Self {
+                    let mut array = [0; std::mem::size_of::<$ty>()];
+                    array.copy_from_slice(bytes);
+                    <$ty>::from_be_bytes(array)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(u8, i8, u16, i16, u32, i32, i64);
+
+impl Scalar for Fixed16_16 {
+    const SIZE: usize = 4;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        Fixed16_16::from_bits(i32::from_be_slice(bytes))
+    }
+}
+
+impl Scalar for Tag {
+    const SIZE: usize = 4;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        Tag(u32::from_be_slice(bytes).to_be_bytes())
+    }
+}
+
+/// A signed fixed-point number with 2 integer and 14 fraction bits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct F2Dot14(pub i16);
+
+impl F2Dot14 {
+    pub fn to_f32(self) -> f32 {
+        f32::from(self.0) / 16384.0
+    }
+}
+
+impl Scalar for F2Dot14 {
+    const SIZE: usize = 2;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        F2Dot14(i16::from_be_slice(bytes))
+    }
+}
+
+/// A 32 bit offset from the start of a table or file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Offset32(pub u32);
+
+impl Offset32 {
+    pub fn to_usize(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Scalar for Offset32 {
+    const SIZE: usize = 4;
+
+    fn from_be_slice(bytes: &[u8]) -> Self {
+        Offset32(u32::from_be_slice(bytes))
+    }
+}
+
+/// The data of a table or file, which is read at arbitrary offsets.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct FontData<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> FontData<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        FontData { bytes }
+    }
+
+    /// Returns a reader starting at `offset`.
+    pub fn reader_at(&self, offset: usize) -> Result<Reader<'a>, ParserError> {
+        if offset > self.bytes.len() {
+            return Err(ParserError::new(ErrorKind::UnexpectedEndOfData));
+        }
+        Ok(Reader {
+            data: self.bytes,
+            offset,
+            structure: None,
+        })
+    }
+
+    /// Returns the bytes in `range`, failing if it is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> Result<&'a [u8], ParserError> {
+        let start = range.start;
+        self.bytes.get(range).ok_or_else(|| {
+            ParserError::new(ErrorKind::UnexpectedEndOfData)
+                .at_data(self.bytes.get(start..).unwrap_or_default())
+        })
+    }
+}
+
+/// Reads values one after another, keeping track of the offset.
+///
+/// Errors are located at the start of the structure that is being read, or at
+/// the current offset if no structure was started.
+#[derive(Debug, Clone)]
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    /// The code of the structure being read and the offset at which it starts.
+    structure: Option<(u32, usize)>,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader {
+            data,
+            offset: 0,
+            structure: None,
+        }
+    }
+
+    /// Returns the offset from the start of the data.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the data that has not been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+
+    /// Starts reading a structure, given by one of the codes in
+    /// `error::structure`, to which the following errors are attributed.
+    pub fn structure(&mut self, code: u32) {
+        self.structure = Some((code, self.offset));
+    }
+
+    #[inline]
+    pub fn read<T: Scalar>(&mut self) -> Result<T, ParserError> {
+        self.read_bytes(T::SIZE).map(T::from_be_slice)
+    }
+
+    #[inline]
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ParserError> {
+        let end = match self.offset.checked_add(len) {
+            Some(end) if end <= self.data.len() => end,
+            _ => return Err(self.error(ErrorKind::UnexpectedEndOfData)),
+        };
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    #[inline]
+    pub fn skip(&mut self, len: usize) -> Result<(), ParserError> {
+        self.read_bytes(len).map(|_| ())
+    }
+
+    /// Returns an error for data that does not have the expected format.
+    pub fn malformed(&self, message: String) -> ParserError {
+        self.error(ErrorKind::Other(message))
+    }
+
+    #[cold]
+    fn error(&self, kind: ErrorKind) -> ParserError {
+        let error = ParserError::new(kind);
+        match self.structure {
+            Some((code, start)) => error.at_data(&self.data[start..]).in_structure(code),
+            None => error.at_data(self.remaining()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::structure;
+
+    #[test]
+    fn test_read() {
+        let data = [0x00, 0x01, 0xff, 0xfe, 0x40, 0x00, 0x00, 0x01, 0x80, 0x00];
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.read::<u16>().unwrap(), 1);
+        assert_eq!(reader.read::<i16>().unwrap(), -2);
+        assert_eq!(reader.read::<F2Dot14>().unwrap().to_f32(), 1.0);
+        assert_eq!(reader.offset(), 6);
+
+        assert_eq!(f32::from(reader.read::<Fixed16_16>().unwrap()), 1.5);
+
+        let mut reader = FontData::new(&data).reader_at(2).unwrap();
+        assert_eq!(reader.read::<Offset32>().unwrap().to_usize(), 0xfffe_4000);
+        assert_eq!(reader.read::<Tag>().unwrap(), Tag([0x00, 0x01, 0x80, 0x00]));
+        assert!(reader.remaining().is_empty());
+    }
+
+    #[test]
+    fn test_error_location() {
+        let data = [0u8; 10];
+        let mut reader = Reader::new(&data);
+        reader.skip(2).unwrap();
+        reader.structure(structure::TABLE_RECORD);
+        reader.skip(4).unwrap();
+        let err = reader.read::<i64>().unwrap_err();
+        // the failed read does not advance the reader
+        assert_eq!(reader.offset(), 6);
+        let err = err.in_table(Tag(*b"test"), &data);
+        assert!(matches!(err.kind(), ErrorKind::UnexpectedEndOfData));
+        assert_eq!(err.structure(), Some("table record"));
+        assert_eq!(err.offset(), Some(2));
+
+        let err = Reader::new(&data[3..]).read_bytes(8).unwrap_err();
+        assert_eq!(err.locate(&data).offset(), Some(3));
+        assert!(FontData::new(&data).reader_at(11).is_err());
+        assert!(FontData::new(&data).slice(4..12).is_err());
+    }
+}
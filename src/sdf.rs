@@ -0,0 +1,705 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Generation of signed distance fields from glyph outlines.
+//!
+//! Distances are computed analytically to every line, quadratic and cubic
+//! segment of an `Outline`. `generate_sdf` produces a single channel field of
+//! true distances while `generate_msdf` produces a three channel field
+//! following the approach of Viktor Chlumský's msdfgen, which preserves sharp
+//! corners when the field is sampled at a larger size.
+//!
+//! Values are normalized such that `0.5` lies exactly on the outline, values
+//! above `0.5` are inside the glyph and `spread` pixels away from the outline
+//! the field reaches `0.0` or `1.0` respectively.
+
+use crate::outline::{Outline, Point, Segment};
+
+const CUBIC_SEARCH_STARTS: usize = 8;
+const CUBIC_SEARCH_STEPS: usize = 8;
+/// Corners with an angle sharper than this (in radians) get differently
+/// colored edges in a multi-channel field.
+const CORNER_ANGLE_THRESHOLD: f32 = 3.0;
+
+/// Describes the size and placement of a distance field bitmap.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SdfParams {
+    /// Width of the bitmap in pixels.
+    pub width: usize,
+    /// Height of the bitmap in pixels.
+    pub height: usize,
+    /// Distance in pixels from the outline at which the field saturates.
+    pub spread: f32,
+    /// Number of pixels per font unit.
+    pub scale: f32,
+    /// Offset in font units that is applied to the outline before scaling.
+    pub translate: Point,
+}
+
+impl SdfParams {
+    /// Chooses `scale` and `translate` such that the outline is centered in a
+    /// bitmap of the given size, leaving room for `spread` pixels on every
+    /// side.
+    pub fn fit(outline: &Outline, width: usize, height: usize, spread: f32) -> SdfParams {
+        let mut params = SdfParams {
+            width,
+            height,
+            spread,
+            scale: 1.0,
+            translate: Point::default(),
+        };
+        let (min, max) = match outline.control_bounds() {
+            Some(bounds) => bounds,
+            None => return params,
+        };
+        let size = max - min;
+        let available_x = (width as f32 - 2.0 * spread).max(1.0);
+        let available_y = (height as f32 - 2.0 * spread).max(1.0);
+        let scale = match (size.x > 0.0, size.y > 0.0) {
+            (true, true) => (available_x / size.x).min(available_y / size.y),
+            (true, false) => available_x / size.x,
+            (false, true) => available_y / size.y,
+            (false, false) => 1.0,
+        };
+        params.scale = scale;
+        params.translate = Point::new(
+            (width as f32 / scale - size.x) / 2.0 - min.x,
+            (height as f32 / scale - size.y) / 2.0 - min.y,
+        );
+        params
+    }
+
+    /// Returns the position in font units of the center of a pixel. Rows are
+    /// counted from the top of the bitmap.
+    fn pixel_center(&self, column: usize, row: usize) -> Point {
+        Point::new(
+            (column as f32 + 0.5) / self.scale - self.translate.x,
+            (self.height as f32 - row as f32 - 0.5) / self.scale - self.translate.y,
+        )
+    }
+
+    /// Maps a distance in font units to the normalized field value.
+    fn normalize(&self, distance: f32) -> f32 {
+        distance * self.scale / (2.0 * self.spread) + 0.5
+    }
+}
+
+/// A bitmap containing a (multi-channel) signed distance field.
+///
+/// Pixels are stored row by row starting with the top row, channels of a
+/// pixel are stored consecutively.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceField {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub data: Vec<f32>,
+}
+
+impl DistanceField {
+    fn new(width: usize, height: usize, channels: usize) -> DistanceField {
+        DistanceField {
+            width,
+            height,
+            channels,
+            data: vec![0.0; width * height * channels],
+        }
+    }
+
+    /// Returns the channels of the pixel at the given position.
+    pub fn pixel(&self, column: usize, row: usize) -> &[f32] {
+        let start = (row * self.width + column) * self.channels;
+        &self.data[start..start + self.channels]
+    }
+
+    /// Quantizes the field to bytes as commonly stored in texture atlases.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data
+            .iter()
+            .map(|&value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect()
+    }
+}
+
+/// Generates a single channel signed distance field of `outline`.
+pub fn generate_sdf(outline: &Outline, params: &SdfParams) -> DistanceField {
+    let mut field = DistanceField::new(params.width, params.height, 1);
+    let segments: Vec<&Segment> = outline
+        .contours
+        .iter()
+        .flat_map(|contour| contour.segments.iter())
+        .collect();
+
+    for row in 0..params.height {
+        for column in 0..params.width {
+            let p = params.pixel_center(column, row);
+            let distance = segments
+                .iter()
+                .map(|segment| signed_distance(segment, p).0.distance.abs())
+                .fold(f32::INFINITY, f32::min);
+            let distance = if outline.winding_number(p) != 0 {
+                distance
+            } else {
+                -distance
+            };
+            field.data[row * params.width + column] = params.normalize(distance);
+        }
+    }
+    field
+}
+
+/// Generates a three channel signed distance field of `outline`.
+///
+/// The true distance can be reconstructed from the field by taking the
+/// median of the three channels.
+pub fn generate_msdf(outline: &Outline, params: &SdfParams) -> DistanceField {
+    let mut field = DistanceField::new(params.width, params.height, 3);
+    let edges = color_edges(outline);
+
+    for row in 0..params.height {
+        for column in 0..params.width {
+            let p = params.pixel_center(column, row);
+
+            let mut channels = [ChannelDistance::default(); 3];
+            for edge in &edges {
+                let (distance, param) = signed_distance(&edge.segment, p);
+                for (bit, channel) in channels.iter_mut().enumerate() {
+                    if edge.color & (1 << bit) != 0 && distance < channel.distance {
+                        *channel = ChannelDistance {
+                            distance,
+                            edge: Some(&edge.segment),
+                            param,
+                        };
+                    }
+                }
+            }
+
+            let mut distances = [0.0; 3];
+            for (distance, channel) in distances.iter_mut().zip(channels.iter()) {
+                *distance = match channel.edge {
+                    Some(segment) => {
+                        pseudo_distance(segment, channel.distance, p, channel.param).distance
+                    }
+                    None => -f32::INFINITY,
+                }
+            }
+
+            // The sign of the pseudo distances depends on the orientation of
+            // the contours, so it is corrected using the winding number.
+            let inside = outline.winding_number(p) != 0;
+            let med = median(distances[0], distances[1], distances[2]);
+            if med != 0.0 && (med > 0.0) != inside {
+                for distance in &mut distances {
+                    *distance = -*distance;
+                }
+            }
+
+            let start = (row * params.width + column) * 3;
+            for (i, &distance) in distances.iter().enumerate() {
+                field.data[start + i] = params.normalize(distance);
+            }
+        }
+    }
+    field
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// A distance together with a measure of how orthogonal the closest point is,
+/// used to break ties between edges sharing an end point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct SignedDistance {
+    distance: f32,
+    dot: f32,
+}
+
+impl SignedDistance {
+    const INFINITE: SignedDistance = SignedDistance {
+        distance: -f32::INFINITY,
+        dot: 1.0,
+    };
+
+    fn new(distance: f32, dot: f32) -> SignedDistance {
+        SignedDistance { distance, dot }
+    }
+}
+
+impl PartialOrd for SignedDistance {
+    fn partial_cmp(&self, other: &SignedDistance) -> Option<std::cmp::Ordering> {
+        let a = (self.distance.abs(), self.dot);
+        let b = (other.distance.abs(), other.dot);
+        a.partial_cmp(&b)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ChannelDistance<'a> {
+    distance: SignedDistance,
+    edge: Option<&'a Segment>,
+    param: f32,
+}
+
+impl<'a> Default for ChannelDistance<'a> {
+    fn default() -> Self {
+        ChannelDistance {
+            distance: SignedDistance::INFINITE,
+            edge: None,
+            param: 0.0,
+        }
+    }
+}
+
+fn non_zero_sign(value: f32) -> f32 {
+    if value > 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// Computes the signed distance from `origin` to the segment and the
+/// parameter of the closest point. The distance is positive if `origin` lies
+/// to the right of the segment.
+fn signed_distance(segment: &Segment, origin: Point) -> (SignedDistance, f32) {
+    match *segment {
+        Segment::Line(p0, p1) => {
+            let aq = origin - p0;
+            let ab = p1 - p0;
+            let length_squared = ab.dot(ab);
+            let param = if length_squared > 0.0 {
+                aq.dot(ab) / length_squared
+            } else {
+                0.0
+            };
+            let eq = if param > 0.5 { p1 } else { p0 } - origin;
+            let endpoint_distance = eq.length();
+            if param > 0.0 && param < 1.0 {
+                let ortho_distance = aq.cross(ab) / length_squared.sqrt();
+                if ortho_distance.abs() < endpoint_distance {
+                    return (SignedDistance::new(ortho_distance, 0.0), param);
+                }
+            }
+            let distance = non_zero_sign(aq.cross(ab)) * endpoint_distance;
+            let dot = ab.normalize().dot(eq.normalize()).abs();
+            (SignedDistance::new(distance, dot), param)
+        }
+        Segment::Quadratic(p0, p1, p2) => {
+            let qa = p0 - origin;
+            let ab = p1 - p0;
+            let br = p2 - p1 - ab;
+            let a = br.dot(br) as f64;
+            let b = 3.0 * ab.dot(br) as f64;
+            let c = 2.0 * ab.dot(ab) as f64 + qa.dot(br) as f64;
+            let d = qa.dot(ab) as f64;
+
+            let (mut min_distance, mut param) = endpoint_distances(segment, origin);
+            for t in solve_cubic(a, b, c, d) {
+                if t > 0.0 && t < 1.0 {
+                    let t = t as f32;
+                    let qe = qa + ab * (2.0 * t) + br * (t * t);
+                    let distance = qe.length();
+                    if distance <= min_distance.abs() {
+                        min_distance = non_zero_sign((ab + br * t).cross(qe)) * distance;
+                        param = t;
+                    }
+                }
+            }
+            curve_distance(segment, origin, min_distance, param)
+        }
+        Segment::Cubic(p0, p1, p2, p3) => {
+            let qa = p0 - origin;
+            let ab = p1 - p0;
+            let br = p2 - p1 - ab;
+            let as_ = (p3 - p2) - (p2 - p1) - br;
+
+            let (mut min_distance, mut param) = endpoint_distances(segment, origin);
+            for i in 0..=CUBIC_SEARCH_STARTS {
+                let mut t = i as f32 / CUBIC_SEARCH_STARTS as f32;
+                let mut qe = qa + ab * (3.0 * t) + br * (3.0 * t * t) + as_ * (t * t * t);
+                for _ in 0..CUBIC_SEARCH_STEPS {
+                    // Newton's method on the derivative of the squared distance
+                    let d1 = ab * 3.0 + br * (6.0 * t) + as_ * (3.0 * t * t);
+                    let d2 = br * 6.0 + as_ * (6.0 * t);
+                    let denominator = d1.dot(d1) + qe.dot(d2);
+                    if denominator == 0.0 {
+                        break;
+                    }
+                    t -= qe.dot(d1) / denominator;
+                    if t <= 0.0 || t >= 1.0 {
+                        break;
+                    }
+                    qe = qa + ab * (3.0 * t) + br * (3.0 * t * t) + as_ * (t * t * t);
+                    let distance = qe.length();
+                    if distance < min_distance.abs() {
+                        let d1 = ab * 3.0 + br * (6.0 * t) + as_ * (3.0 * t * t);
+                        min_distance = non_zero_sign(d1.cross(qe)) * distance;
+                        param = t;
+                    }
+                }
+            }
+            curve_distance(segment, origin, min_distance, param)
+        }
+    }
+}
+
+/// The signed distance to the closer end point of a curve together with the
+/// parameter of the projection onto the end point's tangent.
+fn endpoint_distances(segment: &Segment, origin: Point) -> (f32, f32) {
+    let qa = segment.start() - origin;
+    let direction = segment.direction(0.0);
+    let mut min_distance = non_zero_sign(direction.cross(qa)) * qa.length();
+    let mut param = -qa.dot(direction) / direction.dot(direction);
+
+    let direction = segment.direction(1.0);
+    let qb = segment.end() - origin;
+    let distance = qb.length();
+    if distance < min_distance.abs() {
+        min_distance = non_zero_sign(direction.cross(qb)) * distance;
+        param = (direction - qb).dot(direction) / direction.dot(direction);
+    }
+    (min_distance, param)
+}
+
+fn curve_distance(
+    segment: &Segment,
+    origin: Point,
+    distance: f32,
+    param: f32,
+) -> (SignedDistance, f32) {
+    if (0.0..=1.0).contains(&param) {
+        (SignedDistance::new(distance, 0.0), param)
+    } else if param < 0.5 {
+        let dot = segment
+            .direction(0.0)
+            .normalize()
+            .dot((segment.start() - origin).normalize())
+            .abs();
+        (SignedDistance::new(distance, dot), param)
+    } else {
+        let dot = segment
+            .direction(1.0)
+            .normalize()
+            .dot((segment.end() - origin).normalize())
+            .abs();
+        (SignedDistance::new(distance, dot), param)
+    }
+}
+
+/// Extends the segment along its tangents at the end points and returns the
+/// distance to the extension if it is closer than `distance`.
+fn pseudo_distance(
+    segment: &Segment,
+    distance: SignedDistance,
+    origin: Point,
+    param: f32,
+) -> SignedDistance {
+    if param < 0.0 {
+        let direction = segment.direction(0.0).normalize();
+        let aq = origin - segment.start();
+        if aq.dot(direction) < 0.0 {
+            let pseudo = aq.cross(direction);
+            if pseudo.abs() <= distance.distance.abs() {
+                return SignedDistance::new(pseudo, 0.0);
+            }
+        }
+    } else if param > 1.0 {
+        let direction = segment.direction(1.0).normalize();
+        let bq = origin - segment.end();
+        if bq.dot(direction) > 0.0 {
+            let pseudo = bq.cross(direction);
+            if pseudo.abs() <= distance.distance.abs() {
+                return SignedDistance::new(pseudo, 0.0);
+            }
+        }
+    }
+    distance
+}
+
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 || b.abs() > 1e12 * a.abs() {
+        if b == 0.0 {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant > 0.0 {
+        let root = discriminant.sqrt();
+        vec![(-b + root) / (2.0 * a), (-b - root) / (2.0 * a)]
+    } else if discriminant == 0.0 {
+        vec![-b / (2.0 * a)]
+    } else {
+        vec![]
+    }
+}
+
+/// Solves `x^3 + a x^2 + b x + c = 0`.
+fn solve_cubic_normed(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let a2 = a * a;
+    let q = (a2 - 3.0 * b) / 9.0;
+    let r = (a * (2.0 * a2 - 9.0 * b) + 27.0 * c) / 54.0;
+    let r2 = r * r;
+    let q3 = q * q * q;
+    let a = a / 3.0;
+    if r2 < q3 {
+        let t = (r / q3.sqrt()).clamp(-1.0, 1.0).acos();
+        let q = -2.0 * q.sqrt();
+        let tau = 2.0 * std::f64::consts::PI;
+        vec![
+            q * (t / 3.0).cos() - a,
+            q * ((t + tau) / 3.0).cos() - a,
+            q * ((t - tau) / 3.0).cos() - a,
+        ]
+    } else {
+        let u = if r < 0.0 { 1.0 } else { -1.0 } * (r.abs() + (r2 - q3).sqrt()).cbrt();
+        let v = if u == 0.0 { 0.0 } else { q / u };
+        let mut roots = vec![(u + v) - a];
+        if u == v || (u - v).abs() < 1e-12 * (u + v).abs() {
+            roots.push(-0.5 * (u + v) - a);
+        }
+        roots
+    }
+}
+
+/// Solves `a x^3 + b x^2 + c x + d = 0` for real roots.
+fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a != 0.0 {
+        let bn = b / a;
+        if bn.abs() < 1e6 {
+            return solve_cubic_normed(bn, c / a, d / a);
+        }
+    }
+    solve_quadratic(b, c, d)
+}
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const CYAN: u8 = GREEN | BLUE;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+#[derive(Debug, Copy, Clone)]
+struct ColoredEdge {
+    segment: Segment,
+    color: u8,
+}
+
+fn switch_color(color: u8, banned: u8) -> u8 {
+    let combined = color & banned;
+    if combined == RED || combined == GREEN || combined == BLUE {
+        return combined ^ WHITE;
+    }
+    if color == WHITE || color == 0 {
+        return CYAN;
+    }
+    let shifted = color << 1;
+    (shifted | shifted >> 3) & WHITE
+}
+
+fn is_corner(a: Point, b: Point, cross_threshold: f32) -> bool {
+    a.dot(b) <= 0.0 || a.cross(b).abs() > cross_threshold
+}
+
+fn split_in_thirds(segment: &Segment) -> [Segment; 3] {
+    let (first, rest) = segment.split(1.0 / 3.0);
+    let (second, third) = rest.split(0.5);
+    [first, second, third]
+}
+
+/// Assigns colors to the edges of every contour such that the two edges
+/// meeting at a sharp corner never share more than one channel.
+fn color_edges(outline: &Outline) -> Vec<ColoredEdge> {
+    let cross_threshold = CORNER_ANGLE_THRESHOLD.sin();
+    let mut colored = vec![];
+    for contour in &outline.contours {
+        let segments = &contour.segments;
+        if segments.is_empty() {
+            continue;
+        }
+
+        let mut corners = vec![];
+        let mut previous_direction = segments[segments.len() - 1].direction(1.0);
+        for (i, segment) in segments.iter().enumerate() {
+            if is_corner(
+                previous_direction.normalize(),
+                segment.direction(0.0).normalize(),
+                cross_threshold,
+            ) {
+                corners.push(i);
+            }
+            previous_direction = segment.direction(1.0);
+        }
+
+        let len = segments.len();
+        match corners.len() {
+            0 => colored.extend(segments.iter().map(|&segment| ColoredEdge {
+                segment,
+                color: WHITE,
+            })),
+            1 => {
+                // a "teardrop" shape which needs at least three edges
+                let first = switch_color(WHITE, 0);
+                let colors = [first, WHITE, switch_color(first, 0)];
+                let corner = corners[0];
+                if len >= 3 {
+                    for i in 0..len {
+                        let trichotomy =
+                            (3.0 + 2.875 * i as f32 / (len - 1) as f32 - 1.4375 + 0.5) as i32 - 3;
+                        colored.push(ColoredEdge {
+                            segment: segments[(corner + i) % len],
+                            color: colors[(1 + trichotomy) as usize],
+                        });
+                    }
+                } else {
+                    let mut parts = vec![];
+                    for i in 0..len {
+                        parts.extend_from_slice(&split_in_thirds(&segments[(corner + i) % len]));
+                    }
+                    let part_colors: &[u8] = if len >= 2 {
+                        &[
+                            colors[0], colors[0], colors[1], colors[1], colors[2], colors[2],
+                        ]
+                    } else {
+                        &colors
+                    };
+                    colored.extend(
+                        parts
+                            .into_iter()
+                            .zip(part_colors.iter())
+                            .map(|(segment, &color)| ColoredEdge { segment, color }),
+                    );
+                }
+            }
+            _ => {
+                let start = corners[0];
+                let mut spline = 0;
+                let mut color = switch_color(WHITE, 0);
+                let initial_color = color;
+                for i in 0..len {
+                    let index = (start + i) % len;
+                    if spline + 1 < corners.len() && corners[spline + 1] == index {
+                        spline += 1;
+                        let banned = if spline == corners.len() - 1 {
+                            initial_color
+                        } else {
+                            0
+                        };
+                        color = switch_color(color, banned);
+                    }
+                    colored.push(ColoredEdge {
+                        segment: segments[index],
+                        color,
+                    });
+                }
+            }
+        }
+    }
+    colored
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::outline::OutlineBuilder;
+
+    fn square() -> Outline {
+        let mut builder = OutlineBuilder::default();
+        builder.move_to(Point::new(0.0, 0.0));
+        builder.line_to(Point::new(0.0, 100.0));
+        builder.line_to(Point::new(100.0, 100.0));
+        builder.line_to(Point::new(100.0, 0.0));
+        builder.finish()
+    }
+
+    #[test]
+    fn test_solve_cubic() {
+        // (x - 1)(x - 2)(x - 3)
+        let mut roots = solve_cubic(1.0, -6.0, 11.0, -6.0);
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), 3);
+        for (root, expected) in roots.iter().zip(&[1.0, 2.0, 3.0]) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_curve_distance_matches_sampling() {
+        let origin = Point::new(30.0, 10.0);
+        let segments = [
+            Segment::Quadratic(
+                Point::new(0.0, 0.0),
+                Point::new(50.0, 100.0),
+                Point::new(100.0, 0.0),
+            ),
+            Segment::Cubic(
+                Point::new(0.0, 0.0),
+                Point::new(0.0, 100.0),
+                Point::new(100.0, 100.0),
+                Point::new(100.0, 0.0),
+            ),
+        ];
+        for segment in &segments {
+            let sampled = (0..=10000)
+                .map(|i| (segment.point(i as f32 / 10000.0) - origin).length())
+                .fold(f32::INFINITY, f32::min);
+            let (distance, param) = signed_distance(segment, origin);
+            assert!((distance.distance.abs() - sampled).abs() < 1e-2);
+            assert!(param > 0.0 && param < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_sdf_square() {
+        let outline = square();
+        let params = SdfParams::fit(&outline, 16, 16, 4.0);
+        assert_eq!(params.scale, 0.08);
+
+        let field = generate_sdf(&outline, &params);
+        // the center of this pixel is 3.5 pixels away from the outline
+        assert!((field.pixel(8, 8)[0] - 0.9375).abs() < 1e-4);
+        assert!(field.pixel(0, 0)[0] < 0.5);
+        assert!(field.pixel(4, 8)[0] > 0.5);
+        assert!(field.pixel(3, 8)[0] < 0.5);
+    }
+
+    #[test]
+    fn test_msdf_median_matches_sdf() {
+        let outline = square();
+        let params = SdfParams::fit(&outline, 16, 16, 4.0);
+        let sdf = generate_sdf(&outline, &params);
+        let msdf = generate_msdf(&outline, &params);
+        for row in 0..16 {
+            for column in 0..16 {
+                let rgb = msdf.pixel(column, row);
+                let med = median(rgb[0], rgb[1], rgb[2]);
+                let expected = sdf.pixel(column, row)[0];
+                assert_eq!(med > 0.5, expected > 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_edge_coloring() {
+        let edges = color_edges(&square());
+        assert_eq!(edges.len(), 4);
+        for (i, edge) in edges.iter().enumerate() {
+            let next = edges[(i + 1) % edges.len()];
+            // adjacent edges at a corner share at most one channel
+            assert!((edge.color & next.color).count_ones() <= 1);
+            assert!(edge.color.count_ones() >= 2);
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Reduces fonts to a subset of their glyphs.
+//!
+//! The subset contains the requested glyphs together with all glyphs they are
+//! built from: the components of TrueType composite glyphs and the base and
+//! accent characters of CFF glyphs using `seac`. Glyph outlines are copied
+//! unchanged, only component references are renumbered and CFF subroutines
+//! that are no longer called are pruned.
+//!
+//! The outline tables (`glyf` and `loca` or `CFF `), `head`, `hhea`, `hmtx`,
+//! `maxp`, `post`, `cmap` and `OS/2` are rewritten, and `name`, `cvt `,
+//! `fpgm`, `prep` and `gasp` are copied. All other tables are dropped, in
+//! particular the layout tables (`GSUB`, `GPOS`, `GDEF`, `kern`, ...) which
+//! refer to glyphs by their index.
+//!
+//! The bounding box in `head` is recomputed from the glyph headers of
+//! TrueType fonts but left unchanged for CFF fonts. Of the `OS/2` fields only
+//! the first and last character index are updated; the metrics and the
+//! unicode ranges still describe the original font.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use crate::cff::standard_encoding;
+use crate::cff::subset::{scan_charstring, subset_cff, SubrUsage};
+use crate::error::ParserError;
+use crate::tables::cff::Cff;
+use crate::tables::cmap::{write_cmap, Cmap};
+use crate::tables::glyf::{parse_composite_glyph, parse_header, remap_components, Glyf, Header};
+use crate::tables::head::Head;
+use crate::tables::hhea::Hhea;
+use crate::tables::hmtx::{Hmtx, HmtxContext};
+use crate::tables::loca::Loca;
+use crate::tables::maxp::Maxp;
+use crate::tables::os2::Os2;
+use crate::tables::post::Post;
+use crate::tables::SfntTable;
+use crate::{write_font, GlyphIndex, OpentypeTableAccess, Tag};
+
+/// Options for creating a subset of a font.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SubsetOptions {
+    /// Keeps every glyph at its original index. The glyphs that are not part
+    /// of the subset are left empty.
+    pub retain_gids: bool,
+    /// Writes a version 3.0 `post` table, which contains no glyph names.
+    pub drop_glyph_names: bool,
+}
+
+impl SubsetOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Tables which do not refer to glyphs and are copied unchanged.
+const COPIED_TABLES: [&[u8; 4]; 5] = [b"name", b"cvt ", b"fpgm", b"prep", b"gasp"];
+
+/// The maximal nesting depth of composite glyphs.
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+fn table<'a, F: OpentypeTableAccess<'a>>(
+    font: &F,
+    tag: &[u8; 4],
+) -> Result<F::TableData, ParserError> {
+    font.table_data(Tag(*tag))
+        .ok_or_else(|| ParserError::expected_table(Tag(*tag)))
+}
+
+fn missing_glyph(glyph: GlyphIndex) -> ParserError {
+    ParserError::from_string(format!("Glyph {} does not exist.", glyph))
+}
+
+/// Returns the glyphs that the `cmap` table maps the code points to. Code
+/// points that are not mapped are ignored.
+pub fn glyphs_for_codepoints<'a, F, I>(
+    font: &F,
+    codepoints: I,
+) -> Result<BTreeSet<GlyphIndex>, ParserError>
+where
+    F: OpentypeTableAccess<'a>,
+    I: IntoIterator<Item = u32>,
+{
+    let data = table(font, Cmap::TAG)?;
+    let subtable = Cmap::from_data(&data, ())?
+        .unicode_subtable()
+        .ok_or_else(|| ParserError::from_string("Font has no unicode cmap.".to_string()))?;
+    Ok(codepoints
+        .into_iter()
+        .filter_map(|codepoint| subtable.glyph_index(codepoint))
+        .collect())
+}
+
+/// Returns the glyphs together with `.notdef` and all glyphs they are built
+/// from.
+pub fn glyph_closure<'a, F: OpentypeTableAccess<'a>>(
+    font: &F,
+    glyphs: &BTreeSet<GlyphIndex>,
+) -> Result<BTreeSet<GlyphIndex>, ParserError> {
+    let mut closure = BTreeSet::new();
+    let mut pending: Vec<(GlyphIndex, usize)> = glyphs.iter().map(|&glyph| (glyph, 0)).collect();
+    pending.push((0, 0));
+
+    if let Some(data) = font.table_data(Tag(*Cff::TAG)) {
+        let cff = Cff::from_data(&data, ())?;
+        while let Some((glyph, depth)) = pending.pop() {
+            if !closure.insert(glyph) {
+                continue;
+            }
+            let seac = scan_charstring(&cff, u32::from(glyph), &mut SubrUsage::default())?;
+            if let Some((base, accent)) = seac {
+                if depth >= MAX_COMPONENT_DEPTH {
+                    return Err(ParserError::glyph_parse(
+                        u32::from(glyph),
+                        ParserError::from_string("Accented glyphs nest too deeply.".to_string()),
+                    ));
+                }
+                for &code in &[base, accent] {
+                    let sid = standard_encoding(code);
+                    let component = cff
+                        .charset
+                        .iter()
+                        .position(|&other| other == sid)
+                        .ok_or_else(|| {
+                            ParserError::glyph_parse(
+                                u32::from(glyph),
+                                ParserError::from_string(format!(
+                                    "No glyph for standard encoding {}.",
+                                    code
+                                )),
+                            )
+                        })?;
+                    pending.push((component as GlyphIndex, depth + 1));
+                }
+            }
+        }
+        return Ok(closure);
+    }
+
+    let glyf = TrueTypeGlyphs::new(font)?;
+    while let Some((glyph, depth)) = pending.pop() {
+        if !closure.insert(glyph) {
+            continue;
+        }
+        let data = glyf.glyph(glyph)?;
+        if data.len() < 10 || parse_header(data)?.1.number_of_contours >= 0 {
+            continue;
+        }
+        if depth >= MAX_COMPONENT_DEPTH {
+            return Err(ParserError::glyph_parse(
+                u32::from(glyph),
+                ParserError::from_string("Components are nested too deeply.".to_string()),
+            ));
+        }
+        let (_, composite) = parse_composite_glyph(data)?;
+        let components: Vec<_> = composite
+            .components()
+            .map(|component| component.glyph_index)
+            .collect();
+        pending.extend(
+            components
+                .into_iter()
+                .map(|component| (component, depth + 1)),
+        );
+    }
+    Ok(closure)
+}
+
+/// The raw glyph data of a TrueType font.
+struct TrueTypeGlyphs<D> {
+    head: Head,
+    loca: Loca,
+    glyf: D,
+}
+
+impl<D: std::ops::Deref<Target = [u8]>> TrueTypeGlyphs<D> {
+    fn new<'a, F: OpentypeTableAccess<'a, TableData = D>>(font: &F) -> Result<Self, ParserError> {
+        let head = Head::from_data(&table(font, Head::TAG)?, ())?;
+        let loca = Loca::from_data(&table(font, Loca::TAG)?, head.index_to_loc_format)?;
+        let glyf = table(font, Glyf::TAG)?;
+        Ok(TrueTypeGlyphs { head, loca, glyf })
+    }
+
+    fn glyph(&self, glyph: GlyphIndex) -> Result<&[u8], ParserError> {
+        if usize::from(glyph) + 1 >= self.loca.num_entries() {
+            return Err(missing_glyph(glyph));
+        }
+        let start = self.loca.offset(glyph)? as usize;
+        let end = self.loca.offset(glyph + 1)? as usize;
+        self.glyf.get(start..end).ok_or_else(|| {
+            ParserError::glyph_parse(
+                u32::from(glyph),
+                ParserError::from_string("Glyph data out of bounds.".to_string()),
+            )
+        })
+    }
+}
+
+/// Creates the tables of a font containing only the closure of `glyphs`.
+///
+/// Glyphs are renumbered in the order of their original index unless
+/// `options.retain_gids` is set.
+pub fn subset_tables<'a, F: OpentypeTableAccess<'a>>(
+    font: &F,
+    glyphs: &BTreeSet<GlyphIndex>,
+    options: &SubsetOptions,
+) -> Result<BTreeMap<Tag, Vec<u8>>, ParserError> {
+    let closure = glyph_closure(font, glyphs)?;
+
+    let maxp_data = table(font, Maxp::TAG)?;
+    let mut maxp = Maxp::from_data(&maxp_data, ())?;
+    if let Some(&glyph) = closure.iter().find(|&&glyph| glyph >= maxp.num_glyphs) {
+        return Err(missing_glyph(glyph));
+    }
+
+    // the original index of every glyph of the subset
+    let new_glyphs: Vec<Option<GlyphIndex>> = if options.retain_gids {
+        let last = *closure.iter().next_back().unwrap_or(&0);
+        (0..=last)
+            .map(|glyph| Some(glyph).filter(|glyph| closure.contains(glyph)))
+            .collect()
+    } else {
+        closure.iter().cloned().map(Some).collect()
+    };
+    let new_indices: BTreeMap<GlyphIndex, GlyphIndex> = new_glyphs
+        .iter()
+        .enumerate()
+        .filter_map(|(new_index, &glyph)| Some((glyph?, new_index as GlyphIndex)))
+        .collect();
+
+    let mut tables = BTreeMap::new();
+    if let Some(data) = font.table_data(Tag(*Cff::TAG)) {
+        let cff = Cff::from_data(&data, ())?;
+        let cff_data = subset_cff(&data, &cff, &new_glyphs)?;
+        tables.insert(Tag(*Cff::TAG), cff_data);
+        tables.insert(Tag(*Head::TAG), table(font, Head::TAG)?.to_vec());
+    } else {
+        let mut glyf = TrueTypeGlyphs::new(font)?;
+        let mut glyf_data = vec![];
+        let mut offsets = Vec::with_capacity(new_glyphs.len() + 1);
+        let mut bounds: Option<Header> = None;
+        for &glyph in &new_glyphs {
+            offsets.push(glyf_data.len() as u32);
+            let glyph = match glyph {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            let start = glyf_data.len();
+            glyf_data.extend_from_slice(glyf.glyph(glyph)?);
+            let data = &mut glyf_data[start..];
+            // glyphs without data are empty and do not extend the bounds
+            let number_of_contours = if data.len() >= 10 {
+                let header = parse_header(data)?.1;
+                bounds = Some(match bounds {
+                    Some(bounds) => Header {
+                        xmin: bounds.xmin.min(header.xmin),
+                        ymin: bounds.ymin.min(header.ymin),
+                        xmax: bounds.xmax.max(header.xmax),
+                        ymax: bounds.ymax.max(header.ymax),
+                        ..bounds
+                    },
+                    None => header,
+                });
+                header.number_of_contours
+            } else {
+                0
+            };
+            if number_of_contours < 0 {
+                remap_components(data, |component| {
+                    new_indices
+                        .get(&component)
+                        .cloned()
+                        .ok_or_else(|| missing_glyph(component))
+                })
+                .map_err(|err| ParserError::glyph_parse(u32::from(glyph), err))?;
+            }
+            while glyf_data.len() % 4 != 0 {
+                glyf_data.push(0);
+            }
+        }
+        offsets.push(glyf_data.len() as u32);
+
+        let loca = Loca::from_offsets(offsets);
+        glyf.head.index_to_loc_format = loca.format();
+        let bounds = bounds.unwrap_or_default();
+        glyf.head.x_min = bounds.xmin;
+        glyf.head.y_min = bounds.ymin;
+        glyf.head.x_max = bounds.xmax;
+        glyf.head.y_max = bounds.ymax;
+        let mut loca_data = vec![];
+        loca.write_to(loca.format(), &mut loca_data)
+            .map_err(ParserError::from_err)?;
+        let mut head_data = vec![];
+        glyf.head
+            .write_to(&mut head_data)
+            .map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Glyf::TAG), glyf_data);
+        tables.insert(Tag(*Loca::TAG), loca_data);
+        tables.insert(Tag(*Head::TAG), head_data);
+    }
+
+    let hhea_data = table(font, Hhea::TAG)?;
+    let mut hhea = Hhea::from_data(&hhea_data, ())?;
+    let context = HmtxContext {
+        number_of_h_metrics: hhea.number_of_h_metrics,
+        num_glyphs: maxp.num_glyphs,
+    };
+    let hmtx = Hmtx::from_data(&table(font, Hmtx::TAG)?, context)?;
+    let metrics: Vec<_> = new_glyphs
+        .iter()
+        .map(|glyph| {
+            glyph
+                .and_then(|glyph| hmtx.metric(glyph))
+                .unwrap_or_default()
+        })
+        .collect();
+    let hmtx = Hmtx::from_metrics(&metrics);
+    hhea.number_of_h_metrics = hmtx.h_metrics.len() as u16;
+    maxp.num_glyphs = new_glyphs.len() as u16;
+
+    let mut hmtx_data = vec![];
+    hmtx.write_to(&mut hmtx_data)
+        .map_err(ParserError::from_err)?;
+    let mut hhea_data = vec![];
+    hhea.write_to(&mut hhea_data)
+        .map_err(ParserError::from_err)?;
+    let mut maxp_data = vec![];
+    maxp.write_to(&mut maxp_data)
+        .map_err(ParserError::from_err)?;
+    tables.insert(Tag(*Hmtx::TAG), hmtx_data);
+    tables.insert(Tag(*Hhea::TAG), hhea_data);
+    tables.insert(Tag(*Maxp::TAG), maxp_data);
+
+    if let Some(data) = font.table_data(Tag(*Post::TAG)) {
+        let post = Post::from_data(&data, ())?;
+        let post = subset_post(post, &new_glyphs, options);
+        let mut post_data = vec![];
+        post.write_to(&mut post_data)
+            .map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Post::TAG), post_data);
+    }
+
+    let mut mappings = BTreeMap::new();
+    if let Some(data) = font.table_data(Tag(*Cmap::TAG)) {
+        mappings = Cmap::from_data(&data, ())?
+            .mappings()
+            .into_iter()
+            .filter_map(|(codepoint, glyph)| Some((codepoint, *new_indices.get(&glyph)?)))
+            .collect();
+        let mut cmap_data = vec![];
+        write_cmap(&mappings, &mut cmap_data).map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Cmap::TAG), cmap_data);
+    }
+
+    if let Some(data) = font.table_data(Tag(*Os2::TAG)) {
+        let mut os2 = Os2::from_data(&data, ())?;
+        // both indices are capped at 0xFFFF for supplementary characters
+        let char_index = |codepoint: Option<&u32>| codepoint.map_or(0, |&c| c.min(0xFFFF) as u16);
+        os2.first_char_index = char_index(mappings.keys().next());
+        os2.last_char_index = char_index(mappings.keys().next_back());
+        let mut os2_data = vec![];
+        os2.write_to(&mut os2_data).map_err(ParserError::from_err)?;
+        tables.insert(Tag(*Os2::TAG), os2_data);
+    }
+
+    for tag in COPIED_TABLES.iter() {
+        if let Some(data) = font.table_data(Tag(**tag)) {
+            tables.insert(Tag(**tag), data.to_vec());
+        }
+    }
+    Ok(tables)
+}
+
+/// Keeps the names of the glyphs in the subset. Version 1.0 tables are
+/// converted to version 2.0 since the glyph order changes.
+fn subset_post<'a>(
+    mut post: Post<'a>,
+    new_glyphs: &[Option<GlyphIndex>],
+    options: &SubsetOptions,
+) -> Post<'a> {
+    const NUM_MAC_GLYPH_NAMES: u16 = 258;
+
+    let old_names: Option<Vec<u16>> = match post.version {
+        0x0001_0000 => Some((0..NUM_MAC_GLYPH_NAMES).collect()),
+        0x0002_0000 => Some(post.glyph_name_index.clone()),
+        _ => None,
+    };
+    let old_names = match old_names {
+        Some(old_names) if !options.drop_glyph_names => old_names,
+        _ => {
+            post.version = 0x0003_0000;
+            post.glyph_name_index.clear();
+            post.names.clear();
+            return post;
+        }
+    };
+
+    let mut names = vec![];
+    let mut new_name_indices = BTreeMap::new();
+    post.glyph_name_index = new_glyphs
+        .iter()
+        .map(|glyph| {
+            let name_index = glyph
+                .and_then(|glyph| old_names.get(usize::from(glyph)).cloned())
+                .unwrap_or(0);
+            if name_index < NUM_MAC_GLYPH_NAMES {
+                return name_index;
+            }
+            let name = match post
+                .names
+                .get(usize::from(name_index - NUM_MAC_GLYPH_NAMES))
+            {
+                Some(&name) => name,
+                None => return 0,
+            };
+            *new_name_indices.entry(name).or_insert_with(|| {
+                names.push(name);
+                NUM_MAC_GLYPH_NAMES + names.len() as u16 - 1
+            })
+        })
+        .collect();
+    post.names = names;
+    post.version = 0x0002_0000;
+    post
+}
+
+/// Writes a font containing only the closure of `glyphs`.
+///
+/// See [`subset_tables`](fn.subset_tables.html) for the tables of the subset.
+pub fn subset_font<'a, F: OpentypeTableAccess<'a>>(
+    font: &F,
+    glyphs: &BTreeSet<GlyphIndex>,
+    options: &SubsetOptions,
+    sink: &mut dyn Write,
+) -> Result<(), ParserError> {
+    let tables = subset_tables(font, glyphs, options)?;
+    let version = if tables.contains_key(&Tag(*Cff::TAG)) {
+        Tag(*b"OTTO")
+    } else {
+        Tag([0, 1, 0, 0])
+    };
+    let tags: Vec<Tag> = tables.keys().cloned().collect();
+    write_font(&&tables, version, &tags, sink).map_err(ParserError::from_err)
+}
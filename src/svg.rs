@@ -0,0 +1,318 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Export of glyph outlines to SVG.
+//!
+//! Font coordinates have the y axis pointing upwards while SVG has it
+//! pointing downwards. All path data produced by this module is flipped
+//! vertically, such that it can be used without an additional transform.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::error::ParserError;
+use crate::outline::{Outline, Point, Segment};
+use crate::tables::cff::Cff;
+use crate::tables::head::Head;
+use crate::tables::hhea::Hhea;
+use crate::tables::hmtx::{Hmtx, HmtxContext};
+use crate::tables::maxp::Maxp;
+use crate::tables::post::Post;
+use crate::{GlyphAccessor, OpentypeTableAccess, ParseTable};
+
+impl Outline {
+    /// Returns the outline as SVG path data with the y axis flipped.
+    pub fn to_svg_path(&self) -> String {
+        let mut path = String::new();
+        for contour in &self.contours {
+            let start = match contour.segments.first() {
+                Some(segment) => segment.start(),
+                None => continue,
+            };
+            push_command(&mut path, 'M', &[start]);
+            for segment in &contour.segments {
+                match *segment {
+                    Segment::Line(_, p) => push_command(&mut path, 'L', &[p]),
+                    Segment::Quadratic(_, c, p) => push_command(&mut path, 'Q', &[c, p]),
+                    Segment::Cubic(_, c1, c2, p) => push_command(&mut path, 'C', &[c1, c2, p]),
+                }
+            }
+            path.push('Z');
+        }
+        path
+    }
+}
+
+fn push_command(path: &mut String, command: char, points: &[Point]) {
+    path.push(command);
+    for (i, p) in points.iter().enumerate() {
+        if i > 0 {
+            path.push(' ');
+        }
+        // adding zero avoids printing negative zero
+        write!(path, "{} {}", p.x + 0.0, -p.y + 0.0).unwrap();
+    }
+}
+
+impl<'font> GlyphAccessor<'font> {
+    /// Returns the outline of the glyph with the given index as SVG path data
+    /// in font units with the y axis flipped.
+    ///
+    /// Returns `None` if the glyph does not exist or has no outline.
+    pub fn to_svg_path(&mut self, index: u32) -> Result<Option<String>, ParserError> {
+        Ok(self.outline(index)?.map(|outline| outline.to_svg_path()))
+    }
+}
+
+/// Options controlling the layout of a specimen sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecimenOptions {
+    /// The glyphs shown on the sheet. If `None`, all glyphs are shown.
+    pub glyphs: Option<Range<u32>>,
+    /// The number of glyphs per row.
+    pub columns: usize,
+    /// The width and height in pixels of the area reserved for each glyph.
+    pub cell_size: f32,
+    /// Whether to draw the baseline, ascender, descender and advance width.
+    pub metric_lines: bool,
+}
+
+impl Default for SpecimenOptions {
+    fn default() -> Self {
+        SpecimenOptions {
+            glyphs: None,
+            columns: 16,
+            cell_size: 96.0,
+            metric_lines: true,
+        }
+    }
+}
+
+const LABEL_HEIGHT: f32 = 24.0;
+
+/// Renders glyphs of a font into a single SVG document, labelled with their
+/// glyph ids and names.
+///
+/// Glyphs whose outline can not be read are shown as empty cells.
+pub fn specimen_sheet<'a, F>(font: &F, options: &SpecimenOptions) -> Result<String, ParserError>
+where
+    F: OpentypeTableAccess<'a>,
+    F::TableData: Into<&'a [u8]>,
+{
+    let mut glyphs = font.glyphs()?;
+    let metrics = FontMetrics::new(font)?;
+    let names = glyph_names(font);
+
+    let glyph_range = options
+        .glyphs
+        .clone()
+        .unwrap_or(0..glyphs.num_glyphs());
+    let count = glyph_range.len();
+    let columns = options.columns.max(1);
+    let rows = count.div_ceil(columns);
+    let cell_height = options.cell_size + LABEL_HEIGHT;
+    let width = columns as f32 * options.cell_size;
+    let height = rows as f32 * cell_height;
+
+    let vertical_extent = (metrics.ascender - metrics.descender).max(1) as f32;
+    let scale = options.cell_size * 0.8 / vertical_extent;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<style>.metric {{ stroke-width: 1px; fill: none; vector-effect: non-scaling-stroke; }} text {{ font: 10px sans-serif; }}</style>"#
+    )
+    .unwrap();
+
+    for (i, index) in glyph_range.enumerate() {
+        let cell_x = (i % columns) as f32 * options.cell_size;
+        let cell_y = (i / columns) as f32 * cell_height;
+        let advance = metrics.advance_width(index as u16) as f32;
+        let origin_x = cell_x + (options.cell_size - advance * scale) / 2.0;
+        let baseline = cell_y + options.cell_size * 0.1 + metrics.ascender as f32 * scale;
+
+        writeln!(
+            svg,
+            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="#ddd"/>"##,
+            cell_x, cell_y, options.cell_size, cell_height
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<g transform="translate({} {}) scale({})">"#,
+            origin_x, baseline, scale
+        )
+        .unwrap();
+        if options.metric_lines {
+            let left = -(origin_x - cell_x) / scale;
+            let right = left + options.cell_size / scale;
+            for &(y, color) in &[
+                (0, "#e33"),
+                (metrics.ascender, "#39e"),
+                (metrics.descender, "#39e"),
+            ] {
+                writeln!(
+                    svg,
+                    r#"<line class="metric" x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#,
+                    left, -y, right, -y, color
+                )
+                .unwrap();
+            }
+            for &x in &[0.0, advance] {
+                writeln!(
+                    svg,
+                    r##"<line class="metric" x1="{x}" y1="{}" x2="{x}" y2="{}" stroke="#3a3"/>"##,
+                    -metrics.ascender,
+                    -metrics.descender,
+                    x = x
+                )
+                .unwrap();
+            }
+        }
+        if let Ok(Some(path)) = glyphs.to_svg_path(index) {
+            if !path.is_empty() {
+                writeln!(svg, r#"<path d="{}"/>"#, path).unwrap();
+            }
+        }
+        writeln!(svg, "</g>").unwrap();
+
+        let label = match names.get(index as usize) {
+            Some(Some(name)) => format!("{} {}", index, name),
+            _ => index.to_string(),
+        };
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            cell_x + 4.0,
+            cell_y + cell_height - 8.0,
+            escape_xml(&label)
+        )
+        .unwrap();
+    }
+    writeln!(svg, "</svg>").unwrap();
+    Ok(svg)
+}
+
+/// Vertical metrics and advance widths used for drawing metric lines.
+#[derive(Debug)]
+struct FontMetrics {
+    ascender: i32,
+    descender: i32,
+    hmtx: Option<Hmtx>,
+}
+
+impl FontMetrics {
+    fn new<'a, F: OpentypeTableAccess<'a>>(font: &F) -> Result<Self, ParserError> {
+        let head: Head = font.parse_table()?.get()?;
+        let hhea: Option<Hhea> = font.parse_table().ok().and_then(|t| t.get().ok());
+        let (ascender, descender) = match hhea {
+            Some(ref hhea) => (hhea.ascender, hhea.descender),
+            None => (head.y_max, head.y_min),
+        };
+        let (ascender, descender) = (i32::from(ascender), i32::from(descender));
+        let num_glyphs = font
+            .parse_table::<Maxp>()
+            .ok()
+            .and_then(|t| t.get().ok().map(|maxp| maxp.num_glyphs));
+        let hmtx = match (hhea, num_glyphs) {
+            (Some(hhea), Some(num_glyphs)) => {
+                let context = HmtxContext {
+                    number_of_h_metrics: hhea.number_of_h_metrics,
+                    num_glyphs,
+                };
+                font.parse_table_context::<Hmtx, _>(context)
+                    .ok()
+                    .and_then(|t| t.get().ok())
+            }
+            _ => None,
+        };
+        Ok(FontMetrics {
+            ascender,
+            descender,
+            hmtx,
+        })
+    }
+
+    fn advance_width(&self, index: u16) -> u16 {
+        self.hmtx
+            .as_ref()
+            .and_then(|hmtx| hmtx.advance_width(index))
+            .unwrap_or(0)
+    }
+}
+
+/// Collects the names of all glyphs from the CFF charset or the `post` table.
+fn glyph_names<'a, F: OpentypeTableAccess<'a>>(font: &F) -> Vec<Option<String>> {
+    if let Ok(table) = font.parse_table::<Cff>() {
+        if let Ok(cff) = table.get() {
+            return (0..cff.num_glyphs())
+                .map(|index| cff.glyph_name(index).map(Cow::into_owned))
+                .collect();
+        }
+    }
+    if let Ok(table) = font.parse_table::<Post>() {
+        if let Ok(post) = table.get() {
+            return (0..post.glyph_name_index.len())
+                .map(|index| post.glyph_name(index as u16).map(Cow::into_owned))
+                .collect();
+        }
+    }
+    vec![]
+}
+
+fn escape_xml(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::outline::OutlineBuilder;
+
+    #[test]
+    fn test_outline_to_svg_path() {
+        let mut builder = OutlineBuilder::default();
+        builder.move_to(Point::new(0.0, 0.0));
+        builder.line_to(Point::new(100.0, 0.0));
+        builder.quad_to(Point::new(100.0, 100.0), Point::new(0.0, 100.0));
+        let path = builder.finish().to_svg_path();
+        assert_eq!(path, "M0 0L100 0Q100 -100 0 -100L0 0Z");
+    }
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("a<b&c"), "a&lt;b&amp;c");
+    }
+}
@@ -12,13 +12,26 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use nom::be_u8;
+use nom::{be_u16, be_u8};
 use serde::Deserialize;
 
-use super::SfntTable;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+use super::{parse_table_data, SfntTable};
 use crate::cff;
-use crate::cff::parse_index;
-use crate::error::ParserError;
+use crate::cff::{read_index, Fixed16_16, PathInstruction};
+use crate::error::{structure, ParserError};
+use crate::outline::Outline;
+use crate::reader::Reader;
+use crate::GlyphIndex;
+
+pub use crate::cff::{
+    encode_charset, encode_charstring, subroutinize, write_index, DictWriter, Index, StringTable,
+    Subroutinized,
+};
 
 #[derive(Debug, Clone)]
 pub struct Cff<'font> {
@@ -29,6 +42,10 @@ pub struct Cff<'font> {
     pub(crate) char_strings: cff::Index<'font>,
     pub(crate) global_subrs: cff::Index<'font>,
     pub(crate) local_subrs: cff::Index<'font>,
+    pub(crate) string_index: cff::Index<'font>,
+    /// The string id of the name of every glyph.
+    pub(crate) charset: Vec<u16>,
+    pub(crate) encoding: BTreeMap<u8, GlyphIndex>,
 }
 
 impl<'font> Cff<'font> {
@@ -57,17 +74,17 @@ impl<'font> Cff<'font> {
         let char_strings = cff::Index::parse_from(char_strings)?;
 
         let p_data_start = top_dict_data.private.1;
-        let p_data_end = top_dict_data.private.0 + p_data_start;
-        let private_dict_data = data
-            .get(p_data_start..p_data_end)
+        let private_dict_data = p_data_start
+            .checked_add(top_dict_data.private.0)
+            .and_then(|p_data_end| data.get(p_data_start..p_data_end))
             .ok_or(ParserError::from_string(format!("no private dict")))?;
         let mut dictionary_deserializer =
             cff::DictionaryDeserializer::new(private_dict_data, &string_index);
         let private_dict_data = PrivateDictData::deserialize(&mut dictionary_deserializer)?;
 
         let local_subrs = if private_dict_data.subrs != 0 {
-            let subrs_start = p_data_start + private_dict_data.subrs;
-            if let Some(subrs_data) = data.get(subrs_start..) {
+            let subrs_start = p_data_start.checked_add(private_dict_data.subrs);
+            if let Some(subrs_data) = subrs_start.and_then(|start| data.get(start..)) {
                 cff::Index::parse_from(subrs_data)?
             } else {
                 cff::Index::empty()
@@ -76,6 +93,32 @@ impl<'font> Cff<'font> {
             cff::Index::empty()
         };
 
+        let num_glyphs = char_strings.len();
+        let charset = match top_dict_data.charset {
+            // ISOAdobe charset
+            0 => (0..num_glyphs.min(229) as u16).collect(),
+            // Expert and ExpertSubset charsets are not supported
+            1 | 2 => vec![],
+            offset => {
+                let charset_data = data
+                    .get(offset..)
+                    .ok_or_else(|| ParserError::from_string("no charset".to_string()))?;
+                parse_charset(charset_data, num_glyphs)?.1
+            }
+        };
+
+        let encoding = match top_dict_data.encoding {
+            0 => standard_encoding(&charset),
+            // the Expert encoding is not supported
+            1 => BTreeMap::new(),
+            // the encoding only provides the character codes of the glyphs,
+            // so a broken one must not make the whole font unusable
+            offset => data
+                .get(offset..)
+                .and_then(|encoding_data| parse_encoding(encoding_data, &charset).ok())
+                .map_or_else(BTreeMap::new, |(_, encoding)| encoding),
+        };
+
         Ok(Cff {
             header: cffdata.header,
             name,
@@ -84,9 +127,36 @@ impl<'font> Cff<'font> {
             private_dict_data,
             global_subrs: cffdata.global_subr_index,
             local_subrs,
+            string_index,
+            charset,
+            encoding,
         })
     }
 
+    /// Returns the string with the given string id, which is either one of the
+    /// standard strings or contained in the string index of the font.
+    pub fn string(&self, sid: u16) -> Option<Cow<'font, str>> {
+        let sid = sid as usize;
+        if sid < cff::STANDARD_STRINGS.len() {
+            Some(Cow::Borrowed(cff::STANDARD_STRINGS[sid]))
+        } else {
+            let bytes = self.string_index.get(sid - cff::STANDARD_STRINGS.len())?;
+            Some(String::from_utf8_lossy(bytes))
+        }
+    }
+
+    /// Returns the name of a glyph as given by the charset of the font.
+    pub fn glyph_name(&self, glyph_index: u32) -> Option<Cow<'font, str>> {
+        let sid = *self.charset.get(glyph_index as usize)?;
+        self.string(sid)
+    }
+
+    /// Returns the glyphs assigned to character codes by the encoding of the
+    /// font.
+    pub fn encoding(&self) -> &BTreeMap<u8, GlyphIndex> {
+        &self.encoding
+    }
+
     pub fn num_glyphs(&self) -> u32 {
         self.char_strings.len() as u32
     }
@@ -102,9 +172,9 @@ impl<'a> SfntTable<'a> for Cff<'a> {
     type Err = ParserError;
 
     fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
-        parse_cff_table(data)
-            .map_err(|err| err.into())
-            .and_then(|result| Cff::from_cffdata(result.1, data))
+        parse_table_data(Self::TAG, data, |data| {
+            Cff::from_cffdata(parse_cff_table(data)?, data)
+        })
     }
 }
 
@@ -117,22 +187,124 @@ struct CffData<'data> {
     global_subr_index: cff::Index<'data>,
 }
 
-named!(parse_cff_table<&[u8], CffData>,
-    do_parse!(
-        header: parse_header >>
-        name_index: parse_index >>
-        top_dict_index: parse_index >>
-        string_index: parse_index >>
-        global_subr_index: parse_index >>
-        (CffData {
-            header,
-            name_index,
-            top_dict_index,
-            string_index,
-            global_subr_index
-        })
-    )
-);
+fn parse_cff_table(data: &[u8]) -> Result<CffData<'_>, ParserError> {
+    let mut reader = Reader::new(data);
+    Ok(CffData {
+        header: read_header(&mut reader)?,
+        name_index: read_index(&mut reader)?,
+        top_dict_index: read_index(&mut reader)?,
+        string_index: read_index(&mut reader)?,
+        global_subr_index: read_index(&mut reader)?,
+    })
+}
+
+/// Parses a charset in format 0, 1 or 2 into a list containing the string id
+/// of every glyph.
+fn parse_charset(input: &[u8], num_glyphs: usize) -> nom::IResult<&[u8], Vec<u16>> {
+    let mut sids = Vec::with_capacity(num_glyphs);
+    if num_glyphs == 0 {
+        return Ok((input, sids));
+    }
+    // the first glyph is always .notdef
+    sids.push(0);
+
+    let (mut input, format) = be_u8(input)?;
+    while sids.len() < num_glyphs {
+        match format {
+            0 => {
+                let (rem, sid) = be_u16(input)?;
+                sids.push(sid);
+                input = rem;
+            }
+            1 | 2 => {
+                let (rem, first) = be_u16(input)?;
+                let (rem, n_left) = if format == 1 {
+                    map!(rem, be_u8, |x| x as u16)?
+                } else {
+                    be_u16(rem)?
+                };
+                for i in 0..=n_left {
+                    if sids.len() >= num_glyphs {
+                        break;
+                    }
+                    sids.push(first.wrapping_add(i));
+                }
+                input = rem;
+            }
+            _ => {
+                return Err(nom::Err::Error(error_position!(
+                    input,
+                    nom::ErrorKind::Switch
+                )))
+            }
+        }
+    }
+    Ok((input, sids))
+}
+
+/// Maps the codes of the standard encoding to the glyphs with the
+/// corresponding names.
+fn standard_encoding(charset: &[u16]) -> BTreeMap<u8, GlyphIndex> {
+    let glyphs: HashMap<u16, GlyphIndex> = charset
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(index, &sid)| (sid, index as GlyphIndex))
+        .collect();
+    (0..=255u8)
+        .filter_map(|code| Some((code, *glyphs.get(&cff::standard_encoding(code))?)))
+        .collect()
+}
+
+/// Parses an encoding in format 0 or 1 with optional supplements into a map
+/// from character codes to glyphs.
+fn parse_encoding<'a>(
+    input: &'a [u8],
+    charset: &[u16],
+) -> nom::IResult<&'a [u8], BTreeMap<u8, GlyphIndex>> {
+    let mut encoding = BTreeMap::new();
+    let (mut input, format) = be_u8(input)?;
+    // codes are assigned to the glyphs following .notdef
+    let mut glyph: GlyphIndex = 1;
+    match format & 0x7F {
+        0 => {
+            let (rem, codes) = length_data!(input, be_u8)?;
+            for &code in codes {
+                encoding.insert(code, glyph);
+                glyph = glyph.wrapping_add(1);
+            }
+            input = rem;
+        }
+        1 => {
+            let (rem, ranges) = length_count!(input, be_u8, tuple!(be_u8, be_u8))?;
+            for (first, n_left) in ranges {
+                for code in u16::from(first)..=u16::from(first) + u16::from(n_left) {
+                    encoding.insert(code as u8, glyph);
+                    glyph = glyph.wrapping_add(1);
+                }
+            }
+            input = rem;
+        }
+        _ => {
+            return Err(nom::Err::Error(error_position!(
+                input,
+                nom::ErrorKind::Switch
+            )))
+        }
+    }
+
+    // supplements assign additional codes to glyphs by their name
+    if format & 0x80 != 0 {
+        let (rem, supplements) = length_count!(input, be_u8, tuple!(be_u8, be_u16))?;
+        for (code, sid) in supplements {
+            if let Some(index) = charset.iter().position(|&glyph_sid| glyph_sid == sid) {
+                encoding.insert(code, index as GlyphIndex);
+            }
+        }
+        input = rem;
+    }
+    Ok((input, encoding))
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Header {
@@ -142,23 +314,22 @@ pub struct Header {
     pub offset_size: u8,
 }
 
-named!(parse_header<&[u8], Header>,
-    do_parse!(
-        major: be_u8 >>
-        minor: be_u8 >>
-        header_size: be_u8 >>
-        offset_size: be_u8 >>
-        take!(header_size.saturating_sub(4)) >>
-        (Header {
-            major,
-            minor,
-            header_size,
-            offset_size
-        })
-    )
-);
+fn read_header(reader: &mut Reader) -> Result<Header, ParserError> {
+    reader.structure(structure::CFF_HEADER);
+    let major = reader.read()?;
+    let minor = reader.read()?;
+    let header_size: u8 = reader.read()?;
+    let offset_size = reader.read()?;
+    reader.skip(usize::from(header_size.saturating_sub(4)))?;
+    Ok(Header {
+        major,
+        minor,
+        header_size,
+        offset_size,
+    })
+}
 
-#[derive(Default, Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 #[serde(default)]
 pub struct TopDictData {
@@ -169,9 +340,50 @@ pub struct TopDictData {
     pub full_name: String,
     pub family_name: String,
     pub weight: String,
-    char_strings: usize,
+    #[serde(rename = "isFixedPitch")]
+    pub(crate) is_fixed_pitch: i32,
+    pub italic_angle: f32,
+    pub underline_position: f32,
+    pub underline_thickness: f32,
+    pub(crate) char_strings: usize,
+    #[serde(rename = "FontBBox")]
+    pub font_bbox: Vec<f32>,
+    pub font_matrix: Vec<f32>,
+    #[serde(rename = "charset")]
+    pub(crate) charset: usize,
+    pub(crate) encoding: usize,
     // size and offset of private dict
-    private: (usize, usize),
+    pub(crate) private: (usize, usize),
+}
+
+impl Default for TopDictData {
+    fn default() -> Self {
+        TopDictData {
+            version: 0,
+            notice: String::new(),
+            copyright: String::new(),
+            full_name: String::new(),
+            family_name: String::new(),
+            weight: String::new(),
+            is_fixed_pitch: 0,
+            italic_angle: 0.0,
+            underline_position: -100.0,
+            underline_thickness: 50.0,
+            char_strings: 0,
+            font_bbox: Vec::new(),
+            font_matrix: Vec::new(),
+            charset: 0,
+            encoding: 0,
+            private: (0, 0),
+        }
+    }
+}
+
+impl TopDictData {
+    /// Returns whether all glyphs have the same advance width.
+    pub fn is_fixed_pitch(&self) -> bool {
+        self.is_fixed_pitch != 0
+    }
 }
 
 #[derive(Default, Deserialize, Debug, Clone, Copy)]
@@ -184,3 +396,257 @@ pub(crate) struct PrivateDictData {
     #[serde(rename = "nominalWidthX")]
     pub(crate) nominal_width_x: i32,
 }
+
+/// Writes a CFF table from glyph outlines.
+///
+/// The first glyph that is added is glyph 0, which should be `.notdef`.
+#[derive(Debug, Clone, Default)]
+pub struct CffBuilder {
+    /// The PostScript name of the font.
+    pub name: String,
+    pub version: String,
+    pub notice: String,
+    pub copyright: String,
+    pub full_name: String,
+    pub family_name: String,
+    pub weight: String,
+    /// The italic angle in degrees counter-clockwise from the vertical.
+    pub italic_angle: f32,
+    /// Whether all glyphs have the same advance width.
+    pub is_fixed_pitch: bool,
+    /// Whether repeated parts of the charstrings are moved into subroutines.
+    pub subroutinize: bool,
+    glyphs: Vec<(String, i32, Vec<PathInstruction>)>,
+}
+
+impl CffBuilder {
+    pub fn new(name: &str) -> Self {
+        CffBuilder {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a glyph with the given name, advance width and relative path.
+    pub fn add_glyph(
+        &mut self,
+        name: &str,
+        advance_width: i32,
+        path: &[PathInstruction],
+    ) -> &mut Self {
+        self.glyphs
+            .push((name.to_string(), advance_width, path.to_vec()));
+        self
+    }
+
+    /// Returns `defaultWidthX` and `nominalWidthX` for the added glyphs.
+    fn width_defaults(&self) -> (i32, i32) {
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for &(_, width, _) in &self.glyphs {
+            *counts.entry(width).or_default() += 1;
+        }
+        let default_width = counts
+            .into_iter()
+            .max_by_key(|&(width, count)| (count, -width))
+            .map(|(width, _)| width)
+            .unwrap_or(0);
+
+        // the median of all other widths keeps the encoded differences small
+        let mut widths: Vec<i32> = self
+            .glyphs
+            .iter()
+            .map(|&(_, width, _)| width)
+            .filter(|&width| width != default_width)
+            .collect();
+        widths.sort_unstable();
+        let nominal_width = widths
+            .get(widths.len() / 2)
+            .cloned()
+            .unwrap_or(default_width);
+        (default_width, nominal_width)
+    }
+
+    fn font_bbox(&self) -> [i32; 4] {
+        self.glyphs
+            .iter()
+            .filter_map(|(_, _, path)| {
+                Outline::from_path_instructions(path.iter().cloned()).control_bounds()
+            })
+            .fold(None, |bbox: Option<[i32; 4]>, (min, max)| {
+                let glyph_bbox = [
+                    min.x.floor() as i32,
+                    min.y.floor() as i32,
+                    max.x.ceil() as i32,
+                    max.y.ceil() as i32,
+                ];
+                Some(match bbox {
+                    None => glyph_bbox,
+                    Some(bbox) => [
+                        bbox[0].min(glyph_bbox[0]),
+                        bbox[1].min(glyph_bbox[1]),
+                        bbox[2].max(glyph_bbox[2]),
+                        bbox[3].max(glyph_bbox[3]),
+                    ],
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// Writes the `CFF ` table.
+    pub fn write_to<W: Write>(&self, sink: &mut W) -> io::Result<()> {
+        if self.glyphs.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Too many glyphs for a cff table.",
+            ));
+        }
+
+        let mut strings = StringTable::new();
+        let mut top_dict = DictWriter::new();
+        for &(string, operator) in &[
+            (&self.version, 0),
+            (&self.notice, 1),
+            (&self.full_name, 2),
+            (&self.family_name, 3),
+            (&self.weight, 4),
+        ] {
+            if !string.is_empty() {
+                top_dict
+                    .integer(i32::from(strings.sid(string)))
+                    .operator(operator);
+            }
+        }
+        if !self.copyright.is_empty() {
+            top_dict
+                .integer(i32::from(strings.sid(&self.copyright)))
+                .escaped_operator(0);
+        }
+        if self.is_fixed_pitch {
+            top_dict.integer(1).escaped_operator(1);
+        }
+        if self.italic_angle != 0.0 {
+            top_dict
+                .real(f64::from(self.italic_angle))
+                .escaped_operator(2);
+        }
+        for &value in &self.font_bbox() {
+            top_dict.integer(value);
+        }
+        top_dict.operator(5);
+
+        // .notdef is not part of the charset
+        let sids: Vec<u16> = self
+            .glyphs
+            .iter()
+            .skip(1)
+            .map(|(name, _, _)| strings.sid(name))
+            .collect();
+        let charset = encode_charset(&sids);
+
+        let (default_width, nominal_width) = self.width_defaults();
+        let mut charstrings = Vec::with_capacity(self.glyphs.len());
+        for (_, width, path) in &self.glyphs {
+            let width = if *width == default_width {
+                None
+            } else {
+                let delta = i16::try_from(width - nominal_width).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Advance width out of range.")
+                })?;
+                Some(Fixed16_16::from(delta))
+            };
+            charstrings.push(encode_charstring(path, width));
+        }
+        let (charstrings, global_subrs, local_subrs) = if self.subroutinize {
+            let result = subroutinize(&charstrings)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+            (result.charstrings, result.global_subrs, result.local_subrs)
+        } else {
+            (charstrings, vec![], vec![])
+        };
+
+        let mut private_dict = DictWriter::new();
+        if default_width != 0 {
+            private_dict.integer(default_width).operator(20);
+        }
+        if nominal_width != 0 {
+            private_dict.integer(nominal_width).operator(21);
+        }
+
+        let mut name_index = Vec::new();
+        write_index(&[self.name.as_bytes()], &mut name_index)?;
+        let mut string_index = Vec::new();
+        strings.write_to(&mut string_index)?;
+
+        cff::CffParts {
+            name_index: &name_index,
+            top_dict: top_dict.as_bytes(),
+            string_index: &string_index,
+            global_subrs: &global_subrs,
+            charset: &charset,
+            charstrings: &charstrings,
+            private_dict: private_dict.as_bytes(),
+            local_subrs: &local_subrs,
+        }
+        .write_to(sink)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_encoding() {
+        // .notdef, space, A, B, C
+        let charset = [0, 1, 34, 35, 36];
+        let format0 = [0, 3, 65, 66, 67];
+        let encoding = parse_encoding(&format0, &charset).unwrap().1;
+        let expected: BTreeMap<u8, GlyphIndex> =
+            vec![(65, 1), (66, 2), (67, 3)].into_iter().collect();
+        assert_eq!(encoding, expected);
+
+        // one range of four codes and a supplement for code 200
+        let format1 = [0x81, 1, 32, 3, 1, 200, 0, 35];
+        let encoding = parse_encoding(&format1, &charset).unwrap().1;
+        let expected: BTreeMap<u8, GlyphIndex> = vec![(32, 1), (33, 2), (34, 3), (35, 4), (200, 3)]
+            .into_iter()
+            .collect();
+        assert_eq!(encoding, expected);
+
+        assert!(parse_encoding(&[2, 0], &charset).is_err());
+        assert!(parse_encoding(&[0x80, 0, 1], &charset).is_err());
+
+        let encoding = standard_encoding(&charset);
+        let expected: BTreeMap<u8, GlyphIndex> = vec![(32, 1), (65, 2), (66, 3), (67, 4)]
+            .into_iter()
+            .collect();
+        assert_eq!(encoding, expected);
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let mut name_index = vec![];
+        write_index(&[b"Test"], &mut name_index).unwrap();
+        // the encoding offsets point to the header and past the end
+        for &offset in &[2, 100_000] {
+            let mut top_dict = DictWriter::new();
+            top_dict.integer(offset).operator(16);
+            let mut data = vec![];
+            cff::CffParts {
+                name_index: &name_index,
+                top_dict: top_dict.as_bytes(),
+                string_index: &[0, 0],
+                global_subrs: &[],
+                charset: &encode_charset(&[]),
+                charstrings: &[vec![14]],
+                private_dict: &[],
+                local_subrs: &[],
+            }
+            .write_to(&mut data)
+            .unwrap();
+            let cff = Cff::from_data(&data, ()).unwrap();
+            assert_eq!(cff.num_glyphs(), 1);
+            assert!(cff.encoding().is_empty());
+        }
+    }
+}
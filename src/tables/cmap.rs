@@ -0,0 +1,569 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! The `cmap` table mapping character codes to glyph indices.
+
+use nom::{be_i16, be_u16, be_u32, be_u8};
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use super::{parse_table_data, SfntTable};
+use crate::error::{structure, ParserError};
+use crate::GlyphIndex;
+
+/// Identifies the encoding of a subtable and its offset from the start of the
+/// `cmap` table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EncodingRecord {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub offset: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cmap<'a> {
+    pub version: u16,
+    pub encoding_records: Vec<EncodingRecord>,
+    data: &'a [u8],
+}
+
+impl<'a> SfntTable<'a> for Cmap<'a> {
+    const TAG: &'static [u8; 4] = b"cmap";
+    type Context = ();
+    type Err = ParserError;
+
+    fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
+        let (_, (version, encoding_records)) =
+            parse_table_data(Self::TAG, data, |data| Ok(parse_cmap_header(data)?))?;
+        Ok(Cmap {
+            version,
+            encoding_records,
+            data,
+        })
+    }
+}
+
+/// Platform and encoding ids of Unicode subtables in the order of preference.
+const UNICODE_ENCODINGS: [(u16, u16); 8] = [
+    (3, 10),
+    (0, 6),
+    (0, 4),
+    (3, 1),
+    (0, 3),
+    (0, 2),
+    (0, 1),
+    (0, 0),
+];
+
+impl<'a> Cmap<'a> {
+    /// Parses the subtable the record points to.
+    pub fn subtable(&self, record: &EncodingRecord) -> Result<CmapSubtable, ParserError> {
+        parse_table_data(Cmap::TAG, self.data, |data| {
+            let subtable = data.get(record.offset as usize..).ok_or_else(|| {
+                ParserError::from_string("cmap subtable offset out of bounds".to_string())
+                    .at_offset(record.offset as usize)
+            })?;
+            parse_subtable(subtable)
+        })
+    }
+
+    /// Returns the preferred subtable that maps Unicode code points, if the
+    /// font contains one in a supported format.
+    pub fn unicode_subtable(&self) -> Option<CmapSubtable> {
+        UNICODE_ENCODINGS
+            .iter()
+            .find_map(|&(platform_id, encoding_id)| {
+                let record = self.encoding_records.iter().find(|record| {
+                    record.platform_id == platform_id && record.encoding_id == encoding_id
+                })?;
+                self.subtable(record).ok()
+            })
+    }
+
+    /// Returns all Unicode mappings of the font.
+    pub fn mappings(&self) -> BTreeMap<u32, GlyphIndex> {
+        self.unicode_subtable()
+            .map(|subtable| subtable.mappings())
+            .unwrap_or_default()
+    }
+}
+
+/// A segment of a format 4 subtable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Segment {
+    pub start_code: u16,
+    pub end_code: u16,
+    pub id_delta: i16,
+    /// The offset in bytes from this value in the subtable to the glyph ids
+    /// of the segment, or 0 if `id_delta` is used.
+    pub id_range_offset: u16,
+}
+
+/// A range of character codes of a format 12 or 13 subtable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SequentialMapGroup {
+    pub start_char_code: u32,
+    pub end_char_code: u32,
+    pub start_glyph_id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CmapSubtable {
+    /// Byte encoding table
+    Format0(Vec<u8>),
+    /// Segment mapping to delta values
+    Format4 {
+        segments: Vec<Segment>,
+        glyph_ids: Vec<u16>,
+    },
+    /// Trimmed table mapping
+    Format6 {
+        first_code: u16,
+        glyph_ids: Vec<u16>,
+    },
+    /// Segmented coverage
+    Format12(Vec<SequentialMapGroup>),
+    /// Many-to-one range mappings
+    Format13(Vec<SequentialMapGroup>),
+}
+
+impl CmapSubtable {
+    /// Returns the glyph for the character code or `None` if the code is not
+    /// mapped.
+    pub fn glyph_index(&self, code: u32) -> Option<GlyphIndex> {
+        let glyph = match self {
+            CmapSubtable::Format0(glyph_ids) => u16::from(*glyph_ids.get(code as usize)?),
+            CmapSubtable::Format4 {
+                segments,
+                glyph_ids,
+            } => {
+                if code > 0xffff {
+                    return None;
+                }
+                let code = code as u16;
+                let (i, segment) = segments
+                    .iter()
+                    .enumerate()
+                    .find(|(_, segment)| segment.end_code >= code)?;
+                if segment.start_code > code {
+                    return None;
+                }
+                if segment.id_range_offset == 0 {
+                    code.wrapping_add(segment.id_delta as u16)
+                } else {
+                    let index = (segment.id_range_offset / 2) as usize
+                        + (code - segment.start_code) as usize;
+                    let index = index.checked_sub(segments.len() - i)?;
+                    match *glyph_ids.get(index)? {
+                        0 => 0,
+                        glyph => glyph.wrapping_add(segment.id_delta as u16),
+                    }
+                }
+            }
+            CmapSubtable::Format6 {
+                first_code,
+                glyph_ids,
+            } => {
+                let index = code.checked_sub(u32::from(*first_code))?;
+                *glyph_ids.get(index as usize)?
+            }
+            CmapSubtable::Format12(groups) | CmapSubtable::Format13(groups) => {
+                let group = groups
+                    .iter()
+                    .find(|group| group.start_char_code <= code && code <= group.end_char_code)?;
+                let glyph = match self {
+                    CmapSubtable::Format12(_) => group
+                        .start_glyph_id
+                        .checked_add(code - group.start_char_code)?,
+                    _ => group.start_glyph_id,
+                };
+                if glyph > u32::from(u16::MAX) {
+                    return None;
+                }
+                glyph as u16
+            }
+        };
+        if glyph == 0 {
+            None
+        } else {
+            Some(glyph)
+        }
+    }
+
+    /// Returns all mappings from character codes to glyphs.
+    pub fn mappings(&self) -> BTreeMap<u32, GlyphIndex> {
+        let codes: Box<dyn Iterator<Item = u32>> = match self {
+            CmapSubtable::Format0(glyph_ids) => Box::new(0..glyph_ids.len() as u32),
+            CmapSubtable::Format4 { segments, .. } => Box::new(
+                segments
+                    .iter()
+                    .flat_map(|s| u32::from(s.start_code)..=u32::from(s.end_code)),
+            ),
+            CmapSubtable::Format6 {
+                first_code,
+                glyph_ids,
+            } => {
+                let first_code = u32::from(*first_code);
+                Box::new(first_code..first_code + glyph_ids.len() as u32)
+            }
+            CmapSubtable::Format12(groups) | CmapSubtable::Format13(groups) => Box::new(
+                groups
+                    .iter()
+                    .flat_map(|g| g.start_char_code..=g.end_char_code.min(0x10ffff)),
+            ),
+        };
+        codes
+            .filter_map(|code| Some((code, self.glyph_index(code)?)))
+            .collect()
+    }
+}
+
+named!(parse_encoding_record<&[u8], EncodingRecord>,
+    return_error!(nom::ErrorKind::Custom(structure::ENCODING_RECORD), complete!(
+        do_parse!(
+            platform_id: be_u16 >>
+            encoding_id: be_u16 >>
+            offset: be_u32 >>
+            (EncodingRecord { platform_id, encoding_id, offset })
+        )
+    ))
+);
+
+named!(parse_cmap_header<&[u8], (u16, Vec<EncodingRecord>)>,
+    do_parse!(
+        version: be_u16 >>
+        encoding_records: length_count!(be_u16, parse_encoding_record) >>
+        ((version, encoding_records))
+    )
+);
+
+named!(parse_segments<&[u8], Vec<Segment>>,
+    add_return_error!(nom::ErrorKind::Custom(structure::CMAP_SEGMENTS), complete!(
+        do_parse!(
+            seg_count: map!(be_u16, |x| x as usize / 2) >>
+            // searchRange, entrySelector and rangeShift
+            take!(6) >>
+            end_codes: count!(be_u16, seg_count) >>
+            // reserved padding
+            take!(2) >>
+            start_codes: count!(be_u16, seg_count) >>
+            id_deltas: count!(be_i16, seg_count) >>
+            id_range_offsets: count!(be_u16, seg_count) >>
+            (end_codes
+                .into_iter()
+                .zip(start_codes)
+                .zip(id_deltas)
+                .zip(id_range_offsets)
+                .map(|(((end_code, start_code), id_delta), id_range_offset)| Segment {
+                    start_code,
+                    end_code,
+                    id_delta,
+                    id_range_offset,
+                })
+                .collect())
+        )
+    ))
+);
+
+named!(parse_sequential_map_group<&[u8], SequentialMapGroup>,
+    return_error!(nom::ErrorKind::Custom(structure::SEQUENTIAL_MAP_GROUP), complete!(
+        do_parse!(
+            start_char_code: be_u32 >>
+            end_char_code: be_u32 >>
+            start_glyph_id: be_u32 >>
+            (SequentialMapGroup { start_char_code, end_char_code, start_glyph_id })
+        )
+    ))
+);
+
+fn parse_subtable(data: &[u8]) -> Result<CmapSubtable, ParserError> {
+    let (rest, format) = be_u16(data)?;
+    let subtable = match format {
+        0 | 4 | 6 => {
+            let (_, length) = be_u16(rest)?;
+            // the length of large format 4 subtables is sometimes truncated
+            let end = (length as usize).max(6).min(data.len());
+            let rest = &data[4..end];
+            // language
+            let (rest, _) = be_u16(rest)?;
+            match format {
+                0 => CmapSubtable::Format0(count!(rest, be_u8, 256)?.1),
+                4 => {
+                    let (rest, segments) = parse_segments(rest)?;
+                    let (_, glyph_ids) = many0!(rest, complete!(be_u16))?;
+                    CmapSubtable::Format4 {
+                        segments,
+                        glyph_ids,
+                    }
+                }
+                _ => {
+                    let (rest, first_code) = be_u16(rest)?;
+                    let (rest, entry_count) = be_u16(rest)?;
+                    let (_, glyph_ids) = count!(rest, be_u16, entry_count as usize)?;
+                    CmapSubtable::Format6 {
+                        first_code,
+                        glyph_ids,
+                    }
+                }
+            }
+        }
+        12 | 13 => {
+            // reserved, length and language
+            let (_, groups) = preceded!(
+                rest,
+                take!(10),
+                length_count!(be_u32, parse_sequential_map_group)
+            )?;
+            if format == 12 {
+                CmapSubtable::Format12(groups)
+            } else {
+                CmapSubtable::Format13(groups)
+            }
+        }
+        _ => {
+            return Err(ParserError::from_string(format!(
+                "Unsupported cmap subtable format {}.",
+                format
+            )))
+        }
+    };
+    Ok(subtable)
+}
+
+/// Encodes a format 4 subtable for the mappings of the Basic Multilingual
+/// Plane.
+fn encode_format4(mappings: &BTreeMap<u32, GlyphIndex>) -> io::Result<Vec<u8>> {
+    let bmp: Vec<(u16, u16)> = mappings
+        .range(..0xffff)
+        .filter(|&(_, &glyph)| glyph != 0)
+        .map(|(&code, &glyph)| (code as u16, glyph))
+        .collect();
+
+    // runs of consecutive character codes
+    let mut runs: Vec<&[(u16, u16)]> = Vec::new();
+    let mut start = 0;
+    for i in 1..=bmp.len() {
+        if i == bmp.len() || bmp[i].0 != bmp[i - 1].0 + 1 {
+            runs.push(&bmp[start..i]);
+            start = i;
+        }
+    }
+
+    // every run is either split into segments with a constant delta between
+    // character codes and glyphs, or stored as one segment with glyph ids
+    let mut segments: Vec<(u16, u16, u16, Option<Vec<u16>>)> = Vec::new();
+    for run in runs {
+        let mut delta_segments = Vec::new();
+        let mut start = 0;
+        for i in 1..=run.len() {
+            if i == run.len()
+                || run[i].1.wrapping_sub(run[i].0) != run[start].1.wrapping_sub(run[start].0)
+            {
+                delta_segments.push((
+                    run[start].0,
+                    run[i - 1].0,
+                    run[start].1.wrapping_sub(run[start].0),
+                    None,
+                ));
+                start = i;
+            }
+        }
+        if delta_segments.len() * 8 <= 8 + run.len() * 2 {
+            segments.extend(delta_segments);
+        } else {
+            let glyph_ids = run.iter().map(|&(_, glyph)| glyph).collect();
+            segments.push((run[0].0, run[run.len() - 1].0, 0, Some(glyph_ids)));
+        }
+    }
+    // the last segment has to map 0xffff
+    segments.push((0xffff, 0xffff, 1, None));
+
+    let seg_count = segments.len();
+    let num_glyph_ids: usize = segments
+        .iter()
+        .filter_map(|s| s.3.as_ref().map(Vec::len))
+        .sum();
+    let length = 16 + seg_count * 8 + num_glyph_ids * 2;
+    if length > 0xffff {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too many mappings for a format 4 cmap subtable.",
+        ));
+    }
+
+    let entry_selector = (seg_count as f64).log2().floor() as u16;
+    let search_range = 2 * (1u16 << entry_selector);
+    let range_shift = 2 * seg_count as u16 - search_range;
+
+    let mut out = Vec::with_capacity(length);
+    for &value in &[
+        4,
+        length as u16,
+        0,
+        2 * seg_count as u16,
+        search_range,
+        entry_selector,
+        range_shift,
+    ] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    for segment in &segments {
+        out.extend_from_slice(&segment.1.to_be_bytes());
+    }
+    out.extend_from_slice(&[0, 0]);
+    for segment in &segments {
+        out.extend_from_slice(&segment.0.to_be_bytes());
+    }
+    for segment in &segments {
+        out.extend_from_slice(&segment.2.to_be_bytes());
+    }
+    let mut glyph_id_offset = 0;
+    for (i, segment) in segments.iter().enumerate() {
+        let id_range_offset = match segment.3 {
+            Some(ref glyph_ids) => {
+                let offset = 2 * (seg_count - i + glyph_id_offset);
+                glyph_id_offset += glyph_ids.len();
+                offset as u16
+            }
+            None => 0,
+        };
+        out.extend_from_slice(&id_range_offset.to_be_bytes());
+    }
+    for glyph_ids in segments.iter().filter_map(|s| s.3.as_ref()) {
+        for glyph in glyph_ids {
+            out.extend_from_slice(&glyph.to_be_bytes());
+        }
+    }
+    Ok(out)
+}
+
+/// Encodes a format 12 subtable for all mappings.
+fn encode_format12(mappings: &BTreeMap<u32, GlyphIndex>) -> Vec<u8> {
+    let mut groups: Vec<(u32, u32, u32)> = Vec::new();
+    for (&code, &glyph) in mappings.iter().filter(|&(_, &glyph)| glyph != 0) {
+        match groups.last_mut() {
+            Some((start, end, start_glyph))
+                if *end + 1 == code && *start_glyph + (code - *start) == u32::from(glyph) =>
+            {
+                *end = code;
+            }
+            _ => groups.push((code, code, u32::from(glyph))),
+        }
+    }
+
+    let mut out = Vec::with_capacity(16 + groups.len() * 12);
+    out.extend_from_slice(&[0, 12, 0, 0]);
+    out.extend_from_slice(&(16 + groups.len() as u32 * 12).to_be_bytes());
+    // language
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+    for (start, end, start_glyph) in groups {
+        out.extend_from_slice(&start.to_be_bytes());
+        out.extend_from_slice(&end.to_be_bytes());
+        out.extend_from_slice(&start_glyph.to_be_bytes());
+    }
+    out
+}
+
+/// Writes a `cmap` table for the given mappings from Unicode code points to
+/// glyphs.
+///
+/// The Basic Multilingual Plane is stored in a format 4 subtable. If there
+/// are supplementary code points, all mappings are additionally stored in a
+/// format 12 subtable.
+pub fn write_cmap<W: Write>(mappings: &BTreeMap<u32, GlyphIndex>, mut sink: W) -> io::Result<()> {
+    let format4 = encode_format4(mappings)?;
+    let format12 = if mappings.range(0x10000..).next().is_some() {
+        Some(encode_format12(mappings))
+    } else {
+        None
+    };
+
+    let num_records: u16 = if format12.is_some() { 4 } else { 2 };
+    let format4_offset = 4 + 8 * u32::from(num_records);
+    let format12_offset = format4_offset + format4.len() as u32;
+    let mut records = vec![(0, 3, format4_offset), (3, 1, format4_offset)];
+    if format12.is_some() {
+        records.insert(1, (0, 4, format12_offset));
+        records.push((3, 10, format12_offset));
+    }
+
+    sink.write_all(&[0, 0])?;
+    sink.write_all(&num_records.to_be_bytes())?;
+    for (platform_id, encoding_id, offset) in records {
+        sink.write_all(&u16::to_be_bytes(platform_id))?;
+        sink.write_all(&u16::to_be_bytes(encoding_id))?;
+        sink.write_all(&u32::to_be_bytes(offset))?;
+    }
+    sink.write_all(&format4)?;
+    if let Some(format12) = format12 {
+        sink.write_all(&format12)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_format4() {
+        let data = [
+            0x00, 0x04, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x06, 0x00, 0x04, 0x00, 0x01, 0x00, 0x02,
+            // end codes
+            0x00, 0x42, 0x00, 0x61, 0xff, 0xff, 0x00, 0x00, // start codes
+            0x00, 0x41, 0x00, 0x60, 0xff, 0xff, // deltas
+            0xff, 0xc2, 0x00, 0x00, 0x00, 0x01, // range offsets
+            0x00, 0x00, 0x00, 0x04, 0x00, 0x00, // glyph ids
+            0x00, 0x07, 0x00, 0x00,
+        ];
+        let subtable = parse_subtable(&data).unwrap();
+        assert_eq!(subtable.glyph_index(0x41), Some(3));
+        assert_eq!(subtable.glyph_index(0x42), Some(4));
+        assert_eq!(subtable.glyph_index(0x43), None);
+        assert_eq!(subtable.glyph_index(0x60), Some(7));
+        assert_eq!(subtable.glyph_index(0x61), None);
+        assert_eq!(subtable.mappings().len(), 3);
+    }
+
+    #[test]
+    fn test_write_cmap() {
+        let mut mappings = BTreeMap::new();
+        for code in 0x20..0x7f {
+            mappings.insert(code, code as u16 - 29);
+        }
+        // scattered glyphs are stored with glyph ids
+        for (i, &glyph) in [40, 12, 90, 3, 77, 5, 61].iter().enumerate() {
+            mappings.insert(0x100 + i as u32, glyph);
+        }
+        mappings.insert(0x1f600, 200);
+        mappings.insert(0x1f601, 201);
+
+        let mut data = vec![];
+        write_cmap(&mappings, &mut data).unwrap();
+        let cmap = Cmap::from_data(&data, ()).unwrap();
+        assert_eq!(cmap.encoding_records.len(), 4);
+        assert_eq!(cmap.mappings(), mappings);
+
+        let bmp = cmap.subtable(&cmap.encoding_records[0]).unwrap();
+        match bmp {
+            CmapSubtable::Format4 { ref segments, .. } => assert_eq!(segments.len(), 3),
+            _ => panic!("Expected format 4 subtable"),
+        }
+        mappings.remove(&0x1f600);
+        mappings.remove(&0x1f601);
+        assert_eq!(bmp.mappings(), mappings);
+    }
+}
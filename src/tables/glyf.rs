@@ -14,10 +14,15 @@
 
 //! Structures describing the `glyf` table which contains glyph data for ttf fonts.
 
-use nom::{self, be_i16, be_u16, rest};
+use std::convert::TryFrom;
 
-use crate::error::ParserError;
+use crate::error::{structure, ParserError};
+use crate::reader::{F2Dot14, FontData, Reader};
+use crate::tables::head::Head;
+use crate::tables::hmtx::LongHorMetric;
+use crate::tables::loca::Loca;
 use crate::tables::SfntTable;
+use crate::Tag;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Glyf<'a> {
@@ -35,8 +40,21 @@ impl<'a> SfntTable<'a> for Glyf<'a> {
 }
 
 impl<'a> Glyf<'a> {
-    pub fn at_offset(&self, start: usize, end: usize) -> &[u8] {
-        &self.data[start..end]
+    /// Returns the data of a glyph given its offsets from the `loca` table.
+    pub fn at_offset(&self, start: usize, end: usize) -> Result<&'a [u8], ParserError> {
+        FontData::new(self.data).slice(start..end).map_err(|_| {
+            ParserError::from_string(format!(
+                "The glyph data from {} to {} is out of bounds.",
+                start, end
+            ))
+            .in_table(Tag(*Self::TAG), self.data)
+            .at_offset(start)
+        })
+    }
+
+    /// Returns the data of the whole table.
+    pub(crate) fn data(&self) -> &'a [u8] {
+        self.data
     }
 }
 
@@ -49,22 +67,23 @@ pub struct Header {
     pub ymax: i16,
 }
 
-named!(pub parse_header<&[u8], Header>,
-    do_parse!(
-        number_of_contours: be_i16 >>
-        xmin: be_i16 >>
-        ymin: be_i16 >>
-        xmax: be_i16 >>
-        ymax: be_i16 >>
-        (Header {
-            number_of_contours,
-            xmin,
-            ymin,
-            xmax,
-            ymax
-        })
-    )
-);
+/// Parses the header of a glyph, returning the data following it.
+pub fn parse_header(input: &[u8]) -> Result<(&[u8], Header), ParserError> {
+    let mut reader = Reader::new(input);
+    let header = read_header(&mut reader)?;
+    Ok((reader.remaining(), header))
+}
+
+fn read_header(reader: &mut Reader) -> Result<Header, ParserError> {
+    reader.structure(structure::GLYPH_HEADER);
+    Ok(Header {
+        number_of_contours: reader.read()?,
+        xmin: reader.read()?,
+        ymin: reader.read()?,
+        xmax: reader.read()?,
+        ymax: reader.read()?,
+    })
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct SimpleGlyph<'a> {
@@ -72,64 +91,90 @@ pub struct SimpleGlyph<'a> {
     pub(crate) end_pts_of_contours: &'a [u8],
     pub(crate) instructions: &'a [u8],
     pub(crate) outline: &'a [u8],
+    /// The size in bytes of the flags and of the x coordinates in `outline`,
+    /// which are checked while parsing.
+    layout: (usize, usize),
 }
 
+/// Flag of a point in a simple glyph: the point is on the curve.
+pub const ON_CURVE_POINT: u8 = 0x01;
+/// Flag of a point in a simple glyph: the x coordinate is one byte long.
+pub const X_SHORT_VECTOR: u8 = 0x02;
+/// Flag of a point in a simple glyph: the y coordinate is one byte long.
+pub const Y_SHORT_VECTOR: u8 = 0x04;
+/// Flag of a point in a simple glyph: the flag is repeated the number of
+/// times given by the next byte.
+pub const REPEAT_FLAG: u8 = 0x08;
+/// Flag of a point in a simple glyph: the short x coordinate is positive, or
+/// the x coordinate is the same as the previous one if it is not short.
+pub const X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR: u8 = 0x10;
+/// Flag of a point in a simple glyph: the short y coordinate is positive, or
+/// the y coordinate is the same as the previous one if it is not short.
+pub const Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR: u8 = 0x20;
+/// Flag of the first point in a simple glyph: the contours may overlap.
+pub const OVERLAP_SIMPLE: u8 = 0x40;
+
 impl<'a> SimpleGlyph<'a> {
     pub fn num_points(self) -> u16 {
-        let num_contours = self.end_pts_of_contours.len() / 2;
-        if num_contours < 1 {
-            0
-        } else {
-            let high = self.end_pts_of_contours[2 * num_contours - 2] as u16;
-            let low = self.end_pts_of_contours[2 * num_contours - 1] as u16;
-            (high << 8 | low) + 1
-        }
+        end_point_count(self.end_pts_of_contours)
+    }
+
+    /// Returns the indices of the last point of each contour.
+    pub fn end_points(&self) -> Vec<u16> {
+        self.end_pts_of_contours
+            .chunks(2)
+            .map(|bytes| u16::from(bytes[0]) << 8 | u16::from(bytes[1]))
+            .collect()
+    }
+
+    /// Returns the raw TrueType instructions of the glyph.
+    pub fn instructions(&self) -> &'a [u8] {
+        self.instructions
     }
 
+    /// Returns an iterator over the encoded flags. Repeated flags are
+    /// returned once for every point they apply to.
     pub fn flags_iter(self) -> FlagsIter<'a> {
+        let (flags_size, _) = self.layout;
         FlagsIter {
-            bytes: self.outline,
+            bytes: &self.outline[0..flags_size],
             repeat: (0, 0),
         }
     }
 
-    pub fn point_iter(&self) -> GlyphPointIter<'a> {
-        info!("{:02x?}", self.outline);
-
-        let mut remaining_points = self.num_points();
-        let mut x_size = 0;
-        let mut flags = self.outline.iter();
-
-        while remaining_points > 0 {
-            let byte = *flags.next().unwrap();
+    /// Returns the flags of all points without the `REPEAT_FLAG`.
+    pub fn flags(&self) -> Vec<u8> {
+        self.flags_iter()
+            .take(self.num_points() as usize)
+            .map(|flag| flag & !REPEAT_FLAG)
+            .collect()
+    }
 
-            let delta_x_size = if byte & 0x02 > 0 {
-                // 0x02 flag indicates a single byte x vector
-                1
-            } else {
-                if byte & 0x10 > 0 {
-                    // 0x10 flag means same x value as before
-                    0
-                } else {
-                    // x vector is a two byte integer
-                    2
-                }
-            };
-            x_size += delta_x_size;
-            remaining_points -= 1;
+    /// Returns `true` if the glyph is marked as having overlapping contours.
+    pub fn overlap_simple(&self) -> bool {
+        self.outline
+            .first()
+            .is_some_and(|&flag| flag & OVERLAP_SIMPLE > 0)
+    }
 
-            let repeat_count = if (byte & 0x08) > 0 {
-                *flags.next().unwrap()
-            } else {
-                0
-            };
+    /// Returns the points of the glyph grouped by contour.
+    pub fn contours(&self) -> Vec<Vec<GlyphPoint>> {
+        let mut points = self.point_iter();
+        let mut start = 0;
+        self.end_points()
+            .into_iter()
+            .map(|end| {
+                let count = (usize::from(end) + 1).saturating_sub(start);
+                start = usize::from(end) + 1;
+                points.by_ref().take(count).collect()
+            })
+            .collect()
+    }
 
-            x_size += delta_x_size * repeat_count;
-            remaining_points -= repeat_count as u16;
-        }
+    pub fn point_iter(&self) -> GlyphPointIter<'a> {
+        info!("{:02x?}", self.outline);
 
-        let flags_size = self.outline.len() - flags.len();
-        let x_size = x_size as usize;
+        let (flags_size, x_size) = self.layout;
 
         GlyphPointIter {
             flags: FlagsIter {
@@ -139,10 +184,61 @@ impl<'a> SimpleGlyph<'a> {
             x_coordinates: &self.outline[flags_size..flags_size + x_size],
             y_coordinates: &self.outline[flags_size + x_size..],
             cursor: (0, 0),
+            remaining_points: self.num_points(),
         }
     }
 }
 
+/// Returns the number of points from the encoded end points of the contours,
+/// which is one more than the last end point.
+fn end_point_count(end_pts_of_contours: &[u8]) -> u16 {
+    match *end_pts_of_contours {
+        [.., high, low] => u16::from_be_bytes([high, low]).saturating_add(1),
+        _ => 0,
+    }
+}
+
+/// Returns the size in bytes of a coordinate with the given flag, where
+/// `short` and `same_or_positive` are the flags for the coordinate.
+fn coordinate_size(flag: u8, short: u8, same_or_positive: u8) -> usize {
+    if flag & short > 0 {
+        1
+    } else if flag & same_or_positive > 0 {
+        0
+    } else {
+        2
+    }
+}
+
+/// Reads the flags of a simple glyph with `num_points` points and checks that
+/// the coordinates follow them. Returns the size in bytes of the flags and of
+/// the x coordinates.
+///
+/// Repeated flags beyond the last point are ignored.
+fn read_point_layout(reader: &mut Reader, num_points: u16) -> Result<(usize, usize), ParserError> {
+    let start = reader.offset();
+    let mut remaining_points = usize::from(num_points);
+    let mut x_size = 0;
+    let mut y_size = 0;
+    while remaining_points > 0 {
+        let flag: u8 = reader.read()?;
+        let repeat_count: u8 = if flag & REPEAT_FLAG > 0 {
+            reader.read()?
+        } else {
+            0
+        };
+        let count = (usize::from(repeat_count) + 1).min(remaining_points);
+        remaining_points -= count;
+        x_size +=
+            count * coordinate_size(flag, X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR);
+        y_size +=
+            count * coordinate_size(flag, Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR);
+    }
+    let flags_size = reader.offset() - start;
+    reader.skip(x_size + y_size)?;
+    Ok((flags_size, x_size))
+}
+
 /// A struct that represents a Point of a TrueType Outline.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct GlyphPoint {
@@ -160,6 +256,42 @@ impl GlyphPoint {
     }
 }
 
+/// The vertical metrics of a glyph.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct VerticalMetric {
+    pub advance_height: u16,
+    pub top_side_bearing: i16,
+}
+
+impl VerticalMetric {
+    /// Derives the vertical metrics from the horizontal ascender and
+    /// descender, as done for fonts without a `vmtx` table.
+    pub fn from_ascender(header: &Header, ascender: i16, descender: i16) -> Self {
+        VerticalMetric {
+            advance_height: (i32::from(ascender) - i32::from(descender)) as u16,
+            top_side_bearing: (i32::from(ascender) - i32::from(header.ymax)) as i16,
+        }
+    }
+}
+
+/// Computes the four phantom points which are appended to the points of a
+/// glyph during hinting: the horizontal origin, the horizontal advance, the
+/// top origin and the vertical advance.
+pub fn phantom_points(
+    header: &Header,
+    horizontal: LongHorMetric,
+    vertical: VerticalMetric,
+) -> [GlyphPoint; 4] {
+    let left = i32::from(header.xmin) - i32::from(horizontal.lsb);
+    let top = i32::from(header.ymax) + i32::from(vertical.top_side_bearing);
+    [
+        GlyphPoint::new(left, 0, true),
+        GlyphPoint::new(left + i32::from(horizontal.advance_width), 0, true),
+        GlyphPoint::new(0, top, true),
+        GlyphPoint::new(0, top - i32::from(vertical.advance_height), true),
+    ]
+}
+
 /// An iterator over the glyph points on a TrueType outline.
 #[derive(Debug, Clone)]
 pub struct GlyphPointIter<'a> {
@@ -167,12 +299,27 @@ pub struct GlyphPointIter<'a> {
     x_coordinates: &'a [u8],
     y_coordinates: &'a [u8],
     cursor: (i32, i32),
+    remaining_points: u16,
+}
+
+/// Splits off a big-endian 16 bit integer from the start of `bytes`.
+fn split_i16(bytes: &mut &[u8]) -> Option<i16> {
+    let value = match *bytes {
+        [high, low, ..] => i16::from_be_bytes([*high, *low]),
+        _ => return None,
+    };
+    *bytes = &bytes[2..];
+    Some(value)
 }
 
 impl<'a> Iterator for GlyphPointIter<'a> {
     type Item = GlyphPoint;
 
     fn next(&mut self) -> Option<GlyphPoint> {
+        if self.remaining_points == 0 {
+            return None;
+        }
+        self.remaining_points -= 1;
         let flag = self.flags.next()?;
 
         let x = if flag & 0x02 > 0 {
@@ -187,9 +334,7 @@ impl<'a> Iterator for GlyphPointIter<'a> {
             if flag & 0x10 > 0 {
                 0
             } else {
-                let (x, remaining_x_coordinates) = self.x_coordinates.split_at(2);
-                self.x_coordinates = remaining_x_coordinates;
-                (*x.get(0)? as i16) << 8 | *x.get(1)? as i16
+                split_i16(&mut self.x_coordinates)?
             }
         };
 
@@ -205,9 +350,7 @@ impl<'a> Iterator for GlyphPointIter<'a> {
             if flag & 0x20 > 0 {
                 0
             } else {
-                let (y, remaining_y_coordinates) = self.y_coordinates.split_at(2);
-                self.y_coordinates = remaining_y_coordinates;
-                (*y.get(0)? as i16) << 8 | *y.get(1)? as i16
+                split_i16(&mut self.y_coordinates)?
             }
         };
 
@@ -241,7 +384,8 @@ impl<'a> Iterator for FlagsIter<'a> {
         let (&byte, remaining_bytes) = self.bytes.split_first()?;
         self.bytes = remaining_bytes;
         if (byte & 0x08) > 0 {
-            let repeat_count = *self.bytes.get(0)?;
+            let (&repeat_count, remaining_bytes) = self.bytes.split_first()?;
+            self.bytes = remaining_bytes;
             self.repeat = (byte, repeat_count);
         }
 
@@ -249,20 +393,36 @@ impl<'a> Iterator for FlagsIter<'a> {
     }
 }
 
-named!(pub parse_simple_glyph<&[u8], SimpleGlyph>,
-    dbg_dmp!(do_parse!(
-        header: verify!(parse_header, |Header { number_of_contours, .. }| number_of_contours >= 0) >>
-        end_pts_of_contours: return_error!(nom::ErrorKind::Custom(0), complete!(take!(header.number_of_contours as u16 * 2))) >>
-        instructions: return_error!(nom::ErrorKind::Custom(1), complete!(length_data!(be_u16))) >>
-        outline: rest >>
-        (SimpleGlyph {
+/// Parses a glyph with a non-negative number of contours. The glyph extends to
+/// the end of `input`.
+pub fn parse_simple_glyph(input: &[u8]) -> Result<(&[u8], SimpleGlyph<'_>), ParserError> {
+    let mut reader = Reader::new(input);
+    let header = read_header(&mut reader)?;
+    if header.number_of_contours < 0 {
+        return Err(reader.malformed(format!(
+            "Expected a simple glyph, found {} contours.",
+            header.number_of_contours
+        )));
+    }
+    reader.structure(structure::END_PTS_OF_CONTOURS);
+    let end_pts_of_contours = reader.read_bytes(header.number_of_contours as usize * 2)?;
+    reader.structure(structure::INSTRUCTIONS);
+    let instructions_length: u16 = reader.read()?;
+    let instructions = reader.read_bytes(usize::from(instructions_length))?;
+    let outline = reader.remaining();
+    reader.structure(structure::POINTS);
+    let layout = read_point_layout(&mut reader, end_point_count(end_pts_of_contours))?;
+    Ok((
+        &input[input.len()..],
+        SimpleGlyph {
             header,
             end_pts_of_contours,
             instructions,
-            outline
-        })
+            outline,
+            layout,
+        },
     ))
-);
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct CompositeGlyph<'a> {
@@ -270,16 +430,644 @@ pub struct CompositeGlyph<'a> {
     data: &'a [u8],
 }
 
-named!(pub parse_composite_glyph<&[u8], CompositeGlyph>,
-    do_parse!(
-        header: verify!(parse_header, |Header { number_of_contours, .. }| number_of_contours < 0) >>
-        data: rest >>
-        (CompositeGlyph {
+impl<'a> CompositeGlyph<'a> {
+    /// Returns an iterator over the components that make up this glyph.
+    pub fn components(&self) -> ComponentIter<'a> {
+        ComponentIter {
+            data: self.data,
+            done: false,
+        }
+    }
+
+    /// Returns the raw TrueType instructions following the components.
+    pub fn instructions(&self) -> &'a [u8] {
+        let mut components = self.components();
+        let mut has_instructions = false;
+        for component in components.by_ref() {
+            has_instructions = component.flags & WE_HAVE_INSTRUCTIONS > 0;
+        }
+        if !has_instructions {
+            return &[];
+        }
+        let mut reader = Reader::new(components.data);
+        reader
+            .read::<u16>()
+            .and_then(|length| reader.read_bytes(usize::from(length)))
+            .unwrap_or(&[])
+    }
+
+    /// Returns `true` if the glyph is marked as having overlapping components.
+    pub fn overlap_compound(&self) -> bool {
+        self.components()
+            .next()
+            .is_some_and(|component| component.flags & OVERLAP_COMPOUND > 0)
+    }
+}
+
+/// Replaces the glyph indices of the components in the raw data of a
+/// composite glyph, for example when glyphs are renumbered.
+pub fn remap_components<F>(glyph: &mut [u8], mut map: F) -> Result<(), ParserError>
+where
+    F: FnMut(u16) -> Result<u16, ParserError>,
+{
+    let mut position = 10;
+    loop {
+        let data = glyph
+            .get(position..)
+            .ok_or_else(|| ParserError::from_string("Truncated composite glyph.".to_string()))?;
+        let (remaining, component) = parse_component(data)?;
+        let end = glyph.len() - remaining.len();
+        let glyph_index = map(component.glyph_index)?;
+        glyph[position + 2..position + 4].copy_from_slice(&glyph_index.to_be_bytes());
+        if component.flags & MORE_COMPONENTS == 0 {
+            return Ok(());
+        }
+        position = end;
+    }
+}
+
+/// Flag of a component: the arguments are 16 bit instead of 8 bit values.
+pub const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+/// Flag of a component: the arguments are an offset instead of point indices.
+pub const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+/// Flag of a component: offsets are rounded to the grid.
+pub const ROUND_XY_TO_GRID: u16 = 0x0004;
+/// Flag of a component: the component is scaled uniformly.
+pub const WE_HAVE_A_SCALE: u16 = 0x0008;
+/// Flag of a component: another component follows.
+pub const MORE_COMPONENTS: u16 = 0x0020;
+/// Flag of a component: x and y are scaled separately.
+pub const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+/// Flag of a component: the component is transformed by a 2x2 matrix.
+pub const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+/// Flag of the last component: instructions follow the components.
+pub const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+/// Flag of a component: the composite glyph uses the metrics of this component.
+pub const USE_MY_METRICS: u16 = 0x0200;
+/// Flag of the first component: the components may overlap.
+pub const OVERLAP_COMPOUND: u16 = 0x0400;
+/// Flag of a component: the offset is transformed along with the component.
+pub const SCALED_COMPONENT_OFFSET: u16 = 0x0800;
+/// Flag of a component: the offset is not transformed.
+pub const UNSCALED_COMPONENT_OFFSET: u16 = 0x1000;
+
+/// Describes how a component is positioned relative to the composite glyph.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ComponentOffset {
+    /// The component is moved by the given vector.
+    Offset(i16, i16),
+    /// The point with the first index in the composite glyph is matched with
+    /// the point with the second index in the component.
+    Anchor(u16, u16),
+}
+
+/// A reference to another glyph that is part of a composite glyph.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Component {
+    pub flags: u16,
+    pub glyph_index: u16,
+    pub offset: ComponentOffset,
+    /// The linear part `[a, b, c, d]` of the transformation which maps
+    /// `(x, y)` to `(a * x + c * y, b * x + d * y)`.
+    pub transform: [f32; 4],
+}
+
+impl Component {
+    /// Returns `true` if the offset is transformed along with the component.
+    pub fn scaled_offset(&self) -> bool {
+        self.flags & SCALED_COMPONENT_OFFSET > 0
+    }
+}
+
+/// An iterator over the components of a composite glyph.
+#[derive(Debug, Clone)]
+pub struct ComponentIter<'a> {
+    data: &'a [u8],
+    done: bool,
+}
+
+impl<'a> Iterator for ComponentIter<'a> {
+    type Item = Component;
+
+    fn next(&mut self) -> Option<Component> {
+        if self.done {
+            return None;
+        }
+        match parse_component(self.data) {
+            Ok((remaining, component)) => {
+                self.data = remaining;
+                self.done = component.flags & MORE_COMPONENTS == 0;
+                Some(component)
+            }
+            Err(_) => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+fn read_component_offset(reader: &mut Reader, flags: u16) -> Result<ComponentOffset, ParserError> {
+    let words = flags & ARG_1_AND_2_ARE_WORDS > 0;
+    let xy_values = flags & ARGS_ARE_XY_VALUES > 0;
+    Ok(match (words, xy_values) {
+        (true, true) => ComponentOffset::Offset(reader.read()?, reader.read()?),
+        (true, false) => ComponentOffset::Anchor(reader.read()?, reader.read()?),
+        (false, true) => {
+            ComponentOffset::Offset(reader.read::<i8>()?.into(), reader.read::<i8>()?.into())
+        }
+        (false, false) => {
+            ComponentOffset::Anchor(reader.read::<u8>()?.into(), reader.read::<u8>()?.into())
+        }
+    })
+}
+
+fn read_component_transform(reader: &mut Reader, flags: u16) -> Result<[f32; 4], ParserError> {
+    let mut read = || reader.read::<F2Dot14>().map(F2Dot14::to_f32);
+    Ok(if flags & WE_HAVE_A_SCALE > 0 {
+        let scale = read()?;
+        [scale, 0.0, 0.0, scale]
+    } else if flags & WE_HAVE_AN_X_AND_Y_SCALE > 0 {
+        let x_scale = read()?;
+        [x_scale, 0.0, 0.0, read()?]
+    } else if flags & WE_HAVE_A_TWO_BY_TWO > 0 {
+        [read()?, read()?, read()?, read()?]
+    } else {
+        [1.0, 0.0, 0.0, 1.0]
+    })
+}
+
+/// Parses a component of a composite glyph, returning the data following it.
+fn parse_component(input: &[u8]) -> Result<(&[u8], Component), ParserError> {
+    let mut reader = Reader::new(input);
+    reader.structure(structure::COMPONENT);
+    let flags = reader.read()?;
+    let glyph_index = reader.read()?;
+    let offset = read_component_offset(&mut reader, flags)?;
+    let transform = read_component_transform(&mut reader, flags)?;
+    Ok((
+        reader.remaining(),
+        Component {
+            flags,
+            glyph_index,
+            offset,
+            transform,
+        },
+    ))
+}
+
+/// Parses a glyph with a negative number of contours. The glyph extends to the
+/// end of `input`.
+pub fn parse_composite_glyph(input: &[u8]) -> Result<(&[u8], CompositeGlyph<'_>), ParserError> {
+    let mut reader = Reader::new(input);
+    let header = read_header(&mut reader)?;
+    if header.number_of_contours >= 0 {
+        return Err(reader.malformed(format!(
+            "Expected a composite glyph, found {} contours.",
+            header.number_of_contours
+        )));
+    }
+    Ok((
+        &input[input.len()..],
+        CompositeGlyph {
             header,
-            data,
-        })
-    )
-);
+            data: reader.remaining(),
+        },
+    ))
+}
+
+/// An owned glyph which can be modified and encoded into the `glyf` table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlyphBuf {
+    /// A glyph without outline, such as the space.
+    Empty,
+    Simple {
+        contours: Vec<Vec<GlyphPoint>>,
+        instructions: Vec<u8>,
+        /// Sets the `OVERLAP_SIMPLE` flag.
+        overlap: bool,
+    },
+    Composite {
+        /// The components of the glyph. Only the flags that are not needed
+        /// for decoding (such as `USE_MY_METRICS`) are taken from the
+        /// components, all others are recomputed.
+        components: Vec<Component>,
+        instructions: Vec<u8>,
+    },
+}
+
+impl<'a> From<SimpleGlyph<'a>> for GlyphBuf {
+    fn from(glyph: SimpleGlyph<'a>) -> GlyphBuf {
+        GlyphBuf::Simple {
+            contours: glyph.contours(),
+            instructions: glyph.instructions().to_vec(),
+            overlap: glyph.overlap_simple(),
+        }
+    }
+}
+
+impl<'a> From<CompositeGlyph<'a>> for GlyphBuf {
+    fn from(glyph: CompositeGlyph<'a>) -> GlyphBuf {
+        GlyphBuf::Composite {
+            components: glyph.components().collect(),
+            instructions: glyph.instructions().to_vec(),
+        }
+    }
+}
+
+/// The encoded `glyf` table together with the matching `loca` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedGlyf {
+    pub glyf: Vec<u8>,
+    pub loca: Loca,
+    /// The union of the bounding boxes of all glyphs, `None` if all glyphs
+    /// are empty. Only the bounding box fields of the header are used.
+    pub bounds: Option<Header>,
+}
+
+impl EncodedGlyf {
+    /// Sets the loca format and the bounding box of the `head` table to match
+    /// the encoded glyphs.
+    pub fn update_head(&self, head: &mut Head) {
+        head.index_to_loc_format = self.loca.format();
+        let bounds = self.bounds.unwrap_or_default();
+        head.x_min = bounds.xmin;
+        head.y_min = bounds.ymin;
+        head.x_max = bounds.xmax;
+        head.y_max = bounds.ymax;
+    }
+
+    /// Returns the data of the `loca` table in the smallest possible format.
+    pub fn loca_data(&self) -> Vec<u8> {
+        let mut data = vec![];
+        self.loca
+            .write_to(self.loca.format(), &mut data)
+            .expect("writing to a Vec does not fail");
+        data
+    }
+}
+
+/// Encodes glyphs into the `glyf` table, computing their bounding boxes.
+///
+/// Glyphs are padded to a multiple of four bytes. Bounding boxes of composite
+/// glyphs are computed from the points of their transformed components, which
+/// therefore have to be part of `glyphs`.
+pub fn encode_glyf(glyphs: &[GlyphBuf]) -> Result<EncodedGlyf, ParserError> {
+    let mut glyf = vec![];
+    let mut offsets = Vec::with_capacity(glyphs.len() + 1);
+    let mut bounds: Option<Header> = None;
+    for (index, glyph) in glyphs.iter().enumerate() {
+        offsets.push(glyf.len() as u32);
+        let header = match glyph_bounds(glyphs, index, 0)? {
+            Some(header) => header,
+            None => continue,
+        };
+        encode_glyph(glyph, header, &mut glyf)
+            .map_err(|err| ParserError::glyph_parse(index as u32, err))?;
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+        bounds = Some(match bounds {
+            None => header,
+            Some(bounds) => Header {
+                number_of_contours: 0,
+                xmin: bounds.xmin.min(header.xmin),
+                ymin: bounds.ymin.min(header.ymin),
+                xmax: bounds.xmax.max(header.xmax),
+                ymax: bounds.ymax.max(header.ymax),
+            },
+        });
+    }
+    offsets.push(glyf.len() as u32);
+    Ok(EncodedGlyf {
+        glyf,
+        loca: Loca::from_offsets(offsets),
+        bounds,
+    })
+}
+
+fn coordinate(value: i32) -> Result<i16, ParserError> {
+    if value < i32::from(i16::MIN) || value > i32::from(i16::MAX) {
+        return Err(ParserError::from_string(format!(
+            "Coordinate {} does not fit into 16 bits.",
+            value
+        )));
+    }
+    Ok(value as i16)
+}
+
+/// Returns the header with the number of contours and the bounding box of a
+/// glyph or `None` if it is empty.
+fn glyph_bounds(
+    glyphs: &[GlyphBuf],
+    index: usize,
+    depth: usize,
+) -> Result<Option<Header>, ParserError> {
+    let number_of_contours = match glyphs[index] {
+        GlyphBuf::Empty => return Ok(None),
+        GlyphBuf::Simple { ref contours, .. } if contours.is_empty() => return Ok(None),
+        GlyphBuf::Simple { ref contours, .. } => contours.len() as i16,
+        GlyphBuf::Composite { .. } => -1,
+    };
+    let points = glyph_points(glyphs, index, depth)?;
+    let (min, max) = points.iter().fold(
+        ((i32::MAX, i32::MAX), (i32::MIN, i32::MIN)),
+        |(min, max), &(x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+    );
+    if points.is_empty() {
+        return Ok(Some(Header {
+            number_of_contours,
+            ..Header::default()
+        }));
+    }
+    Ok(Some(Header {
+        number_of_contours,
+        xmin: coordinate(min.0)?,
+        ymin: coordinate(min.1)?,
+        xmax: coordinate(max.0)?,
+        ymax: coordinate(max.1)?,
+    }))
+}
+
+/// The maximal nesting depth of components.
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+/// Returns all points of a glyph, resolving components.
+fn glyph_points(
+    glyphs: &[GlyphBuf],
+    index: usize,
+    depth: usize,
+) -> Result<Vec<(i32, i32)>, ParserError> {
+    if depth > MAX_COMPONENT_DEPTH {
+        return Err(ParserError::from_string(format!(
+            "Components of glyph {} are nested too deeply.",
+            index
+        )));
+    }
+    let components = match glyphs.get(index) {
+        Some(GlyphBuf::Simple { contours, .. }) => {
+            return Ok(contours.iter().flatten().map(|p| (p.x, p.y)).collect())
+        }
+        Some(GlyphBuf::Composite { components, .. }) => components,
+        Some(GlyphBuf::Empty) => return Ok(vec![]),
+        None => {
+            return Err(ParserError::from_string(format!(
+                "Component glyph {} does not exist.",
+                index
+            )))
+        }
+    };
+    let mut points = vec![];
+    for component in components {
+        let [a, b, c, d] = component.transform;
+        let transformed: Vec<(f32, f32)> =
+            glyph_points(glyphs, usize::from(component.glyph_index), depth + 1)?
+                .into_iter()
+                .map(|(x, y)| {
+                    let (x, y) = (x as f32, y as f32);
+                    (a * x + c * y, b * x + d * y)
+                })
+                .collect();
+        let (dx, dy) = match component.offset {
+            ComponentOffset::Offset(dx, dy) => {
+                let (dx, dy) = (f32::from(dx), f32::from(dy));
+                if component.scaled_offset() {
+                    (a * dx + c * dy, b * dx + d * dy)
+                } else {
+                    (dx, dy)
+                }
+            }
+            ComponentOffset::Anchor(parent, child) => {
+                match (
+                    points.get(usize::from(parent)),
+                    transformed.get(usize::from(child)),
+                ) {
+                    (Some(&(px, py)), Some(&(cx, cy))) => (px as f32 - cx, py as f32 - cy),
+                    _ => {
+                        return Err(ParserError::from_string(format!(
+                            "Anchor points of component {} do not exist.",
+                            component.glyph_index
+                        )))
+                    }
+                }
+            }
+        };
+        points.extend(
+            transformed
+                .into_iter()
+                .map(|(x, y)| ((x + dx).round() as i32, (y + dy).round() as i32)),
+        );
+    }
+    Ok(points)
+}
+
+pub(crate) fn encode_glyph(glyph: &GlyphBuf, header: Header, out: &mut Vec<u8>) -> Result<(), ParserError> {
+    out.extend_from_slice(&header.number_of_contours.to_be_bytes());
+    for &value in &[header.xmin, header.ymin, header.xmax, header.ymax] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    match *glyph {
+        GlyphBuf::Empty => Ok(()),
+        GlyphBuf::Simple {
+            ref contours,
+            ref instructions,
+            overlap,
+        } => encode_simple_glyph(contours, instructions, overlap, out),
+        GlyphBuf::Composite {
+            ref components,
+            ref instructions,
+        } => encode_composite_glyph(components, instructions, out),
+    }
+}
+
+fn push_instructions(instructions: &[u8], out: &mut Vec<u8>) -> Result<(), ParserError> {
+    if instructions.len() > usize::from(u16::MAX) {
+        return Err(ParserError::from_string(
+            "Glyph instructions are too long.".to_string(),
+        ));
+    }
+    out.extend_from_slice(&(instructions.len() as u16).to_be_bytes());
+    out.extend_from_slice(instructions);
+    Ok(())
+}
+
+fn encode_simple_glyph(
+    contours: &[Vec<GlyphPoint>],
+    instructions: &[u8],
+    overlap: bool,
+    out: &mut Vec<u8>,
+) -> Result<(), ParserError> {
+    let mut end_point = 0usize;
+    for contour in contours {
+        end_point += contour.len();
+        if end_point == 0 || end_point > usize::from(u16::MAX) {
+            return Err(ParserError::from_string(
+                "Glyphs must have between 1 and 65535 points and no empty contours.".to_string(),
+            ));
+        }
+        out.extend_from_slice(&(end_point as u16 - 1).to_be_bytes());
+    }
+    push_instructions(instructions, out)?;
+
+    let mut flags = vec![];
+    let mut x_coordinates = vec![];
+    let mut y_coordinates = vec![];
+    let mut last = (0, 0);
+    for point in contours.iter().flatten() {
+        let dx = coordinate(point.x)? as i32 - last.0;
+        let dy = coordinate(point.y)? as i32 - last.1;
+        last = (point.x, point.y);
+        let mut flag = if point.on_curve { ON_CURVE_POINT } else { 0 };
+        flag |= encode_delta(
+            dx,
+            X_SHORT_VECTOR,
+            X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR,
+            &mut x_coordinates,
+        )?;
+        flag |= encode_delta(
+            dy,
+            Y_SHORT_VECTOR,
+            Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR,
+            &mut y_coordinates,
+        )?;
+        flags.push(flag);
+    }
+    if overlap {
+        if let Some(flag) = flags.first_mut() {
+            *flag |= OVERLAP_SIMPLE;
+        }
+    }
+
+    // A repeated flag takes two bytes, so it pays off for three flags or more.
+    let mut i = 0;
+    while i < flags.len() {
+        let flag = flags[i];
+        let mut count = 1;
+        while i + count < flags.len() && flags[i + count] == flag && count <= 255 {
+            count += 1;
+        }
+        if count >= 3 {
+            out.push(flag | REPEAT_FLAG);
+            out.push((count - 1) as u8);
+        } else {
+            count = 1;
+            out.push(flag);
+        }
+        i += count;
+    }
+    out.extend(x_coordinates);
+    out.extend(y_coordinates);
+    Ok(())
+}
+
+/// Appends the shortest encoding of a coordinate delta and returns the flags.
+///
+/// Fails if the delta does not fit into 16 bits, which happens for points
+/// that are more than 32767 units apart.
+fn encode_delta(
+    delta: i32,
+    short_flag: u8,
+    same_or_positive_flag: u8,
+    out: &mut Vec<u8>,
+) -> Result<u8, ParserError> {
+    if delta == 0 {
+        Ok(same_or_positive_flag)
+    } else if delta.abs() <= 255 {
+        out.push(delta.unsigned_abs() as u8);
+        if delta > 0 {
+            Ok(short_flag | same_or_positive_flag)
+        } else {
+            Ok(short_flag)
+        }
+    } else {
+        let delta = i16::try_from(delta).map_err(|_| {
+            ParserError::from_string(format!(
+                "The distance {} between two points does not fit into 16 bits.",
+                delta
+            ))
+        })?;
+        out.extend_from_slice(&delta.to_be_bytes());
+        Ok(0)
+    }
+}
+
+fn to_f2dot14(value: f32) -> i16 {
+    (value * 16384.0)
+        .round()
+        .max(f32::from(i16::MIN))
+        .min(f32::from(i16::MAX)) as i16
+}
+
+fn encode_composite_glyph(
+    components: &[Component],
+    instructions: &[u8],
+    out: &mut Vec<u8>,
+) -> Result<(), ParserError> {
+    const KEPT_FLAGS: u16 = ROUND_XY_TO_GRID
+        | USE_MY_METRICS
+        | OVERLAP_COMPOUND
+        | SCALED_COMPONENT_OFFSET
+        | UNSCALED_COMPONENT_OFFSET;
+
+    for (i, component) in components.iter().enumerate() {
+        let mut flags = component.flags & KEPT_FLAGS;
+        if i + 1 < components.len() {
+            flags |= MORE_COMPONENTS;
+        } else if !instructions.is_empty() {
+            flags |= WE_HAVE_INSTRUCTIONS;
+        }
+
+        let mut arguments = vec![];
+        match component.offset {
+            ComponentOffset::Offset(dx, dy) => {
+                flags |= ARGS_ARE_XY_VALUES;
+                if i8::try_from(dx).is_ok() && i8::try_from(dy).is_ok() {
+                    arguments.extend_from_slice(&[dx as u8, dy as u8]);
+                } else {
+                    flags |= ARG_1_AND_2_ARE_WORDS;
+                    arguments.extend_from_slice(&dx.to_be_bytes());
+                    arguments.extend_from_slice(&dy.to_be_bytes());
+                }
+            }
+            ComponentOffset::Anchor(parent, child) => {
+                if parent <= 255 && child <= 255 {
+                    arguments.extend_from_slice(&[parent as u8, child as u8]);
+                } else {
+                    flags |= ARG_1_AND_2_ARE_WORDS;
+                    arguments.extend_from_slice(&parent.to_be_bytes());
+                    arguments.extend_from_slice(&child.to_be_bytes());
+                }
+            }
+        }
+
+        let [a, b, c, d] = component.transform;
+        let scale: Vec<i16> = if b == 0.0 && c == 0.0 {
+            if a == 1.0 && d == 1.0 {
+                vec![]
+            } else if a == d {
+                flags |= WE_HAVE_A_SCALE;
+                vec![to_f2dot14(a)]
+            } else {
+                flags |= WE_HAVE_AN_X_AND_Y_SCALE;
+                vec![to_f2dot14(a), to_f2dot14(d)]
+            }
+        } else {
+            flags |= WE_HAVE_A_TWO_BY_TWO;
+            vec![to_f2dot14(a), to_f2dot14(b), to_f2dot14(c), to_f2dot14(d)]
+        };
+
+        out.extend_from_slice(&flags.to_be_bytes());
+        out.extend_from_slice(&component.glyph_index.to_be_bytes());
+        out.extend(arguments);
+        for value in scale {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+    if !instructions.is_empty() {
+        push_instructions(instructions, out)?;
+    }
+    Ok(())
+}
 
 #[cfg(test)]
 mod test {
@@ -290,6 +1078,42 @@ mod test {
     const CONTOUR_END_PTS: &'static [u8] = &[0x00, 0x02];
     const INSTRUCTIONS: &'static [u8] = &[0x00, 0x00];
 
+    #[test]
+    fn test_composite_glyph_components() {
+        let glyph_data = [
+            0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x10, // header
+            0x00, 0x23, 0x00, 0x05, 0x00, 0x0a, 0xff, 0xf6, // words, xy values, more
+            0x00, 0x08, 0x00, 0x07, 0x03, 0x04, 0x20, 0x00, // anchors, scale 0.5
+        ];
+        let (_, glyph) = parse_composite_glyph(&glyph_data).unwrap();
+        let components: Vec<_> = glyph.components().collect();
+        assert_eq!(
+            components,
+            vec![
+                Component {
+                    flags: 0x23,
+                    glyph_index: 5,
+                    offset: ComponentOffset::Offset(10, -10),
+                    transform: [1.0, 0.0, 0.0, 1.0],
+                },
+                Component {
+                    flags: 0x08,
+                    glyph_index: 7,
+                    offset: ComponentOffset::Anchor(3, 4),
+                    transform: [0.5, 0.0, 0.0, 0.5],
+                },
+            ]
+        );
+
+        let mut remapped = glyph_data;
+        remap_components(&mut remapped, |index| Ok(index * 2)).unwrap();
+        assert_eq!(remapped[12..14], [0x00, 0x0a]);
+        assert_eq!(remapped[20..22], [0x00, 0x0e]);
+        assert_eq!(remapped[..12], glyph_data[..12]);
+        assert_eq!(remapped[14..20], glyph_data[14..20]);
+        assert_eq!(remapped[22..], glyph_data[22..]);
+    }
+
     #[test]
     fn test_simple_glyph_header() {
         let (_, header) = parse_header(HEADER).unwrap();
@@ -318,6 +1142,38 @@ mod test {
         assert_eq!(glyph.num_points(), 0);
     }
 
+    #[test]
+    fn test_simple_glyph_repeated_flags() {
+        // the first flag is repeated once, the repeat count is followed by
+        // the flag of the last point
+        const FLAGS: &'static [u8] = &[
+            0x02 | 0x04 | 0x08 | 0x10 | 0x20,
+            0x01,
+            0x01 | 0x02 | 0x04 | 0x10 | 0x20,
+        ];
+        const X_VALUES: &'static [u8] = &[0x01, 0x02, 0x03];
+        const Y_VALUES: &'static [u8] = &[0x04, 0x05, 0x06];
+
+        let mut glyph_data = vec![];
+        glyph_data.extend(HEADER);
+        glyph_data.extend(CONTOUR_END_PTS);
+        glyph_data.extend(INSTRUCTIONS);
+        glyph_data.extend(FLAGS);
+        glyph_data.extend(X_VALUES);
+        glyph_data.extend(Y_VALUES);
+        let (_, glyph) = parse_simple_glyph(&glyph_data).unwrap();
+
+        let points: Vec<_> = glyph.point_iter().collect();
+        assert_eq!(
+            points,
+            vec![
+                GlyphPoint::new(1, 4, false),
+                GlyphPoint::new(3, 9, false),
+                GlyphPoint::new(6, 15, true),
+            ]
+        );
+    }
+
     #[test]
     fn test_simple_glyph() {
         // we have 3 positive short vectors
@@ -347,4 +1203,183 @@ mod test {
         assert_eq!(iter.next().unwrap(), GlyphPoint::new(6, 15, false));
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_simple_glyph_contours() {
+        // two contours with 1 and 3 points, the last three flags are repeated
+        const HEADER_2: &'static [u8] =
+            &[0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1e, 0x00, 0x06];
+        const CONTOUR_END_PTS: &'static [u8] = &[0x00, 0x00, 0x00, 0x03];
+        const INSTRUCTIONS: &'static [u8] = &[0x00, 0x02, 0xb0, 0x01];
+        const FLAGS: &'static [u8] = &[
+            0x01 | 0x10 | 0x20 | 0x40,
+            0x01 | 0x02 | 0x04 | 0x08 | 0x10 | 0x20,
+            0x02,
+        ];
+        const X_VALUES: &'static [u8] = &[0x0a, 0x0a, 0x0a];
+        const Y_VALUES: &'static [u8] = &[0x01, 0x02, 0x03];
+
+        let mut glyph_data = vec![];
+        glyph_data.extend(HEADER_2);
+        glyph_data.extend(CONTOUR_END_PTS);
+        glyph_data.extend(INSTRUCTIONS);
+        glyph_data.extend(FLAGS);
+        glyph_data.extend(X_VALUES);
+        glyph_data.extend(Y_VALUES);
+        let (_, glyph) = parse_simple_glyph(&glyph_data).unwrap();
+
+        assert_eq!(glyph.end_points(), vec![0, 3]);
+        assert_eq!(glyph.instructions(), &[0xb0, 0x01]);
+        assert!(glyph.overlap_simple());
+        assert_eq!(glyph.flags(), vec![0x71, 0x37, 0x37, 0x37]);
+        assert_eq!(
+            glyph.contours(),
+            vec![
+                vec![GlyphPoint::new(0, 0, true)],
+                vec![
+                    GlyphPoint::new(10, 1, true),
+                    GlyphPoint::new(20, 3, true),
+                    GlyphPoint::new(30, 6, true),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_distant_points() {
+        let glyph = |x: i32| GlyphBuf::Simple {
+            contours: vec![vec![
+                GlyphPoint::new(-20000, 0, true),
+                GlyphPoint::new(x, 0, true),
+            ]],
+            instructions: vec![],
+            overlap: false,
+        };
+        assert!(encode_glyf(&[glyph(12767)]).is_ok());
+        assert!(encode_glyf(&[glyph(12768)]).is_err());
+        assert!(encode_glyf(&[glyph(20000)]).is_err());
+    }
+
+    #[test]
+    fn test_encode_simple_glyph() {
+        let contours = vec![
+            vec![GlyphPoint::new(0, 0, true)],
+            vec![
+                GlyphPoint::new(10, 1, true),
+                GlyphPoint::new(20, 3, true),
+                GlyphPoint::new(30, 6, true),
+                GlyphPoint::new(-300, 6, false),
+            ],
+        ];
+        let glyph = GlyphBuf::Simple {
+            contours: contours.clone(),
+            instructions: vec![0xb0, 0x01],
+            overlap: true,
+        };
+        let encoded = encode_glyf(&[GlyphBuf::Empty, glyph.clone()]).unwrap();
+        assert_eq!(encoded.loca, Loca::from_offsets(vec![0, 0, 32]));
+        assert_eq!(
+            encoded.bounds,
+            Some(Header {
+                number_of_contours: 2,
+                xmin: -300,
+                ymin: 0,
+                xmax: 30,
+                ymax: 6,
+            })
+        );
+        #[rustfmt::skip]
+        assert_eq!(
+            &encoded.glyf[10..],
+            &[
+                0x00, 0x00, 0x00, 0x04, // end points
+                0x00, 0x02, 0xb0, 0x01, // instructions
+                0x71, 0x3f, 0x02, 0x20, // flags, the second one is repeated
+                0x0a, 0x0a, 0x0a, 0xfe, 0xb6, // x coordinates
+                0x01, 0x02, 0x03, // y coordinates
+                0x00, 0x00, // padding
+            ][..]
+        );
+
+        let (_, parsed) = parse_simple_glyph(&encoded.glyf).unwrap();
+        assert_eq!(GlyphBuf::from(parsed), glyph);
+    }
+
+    #[test]
+    fn test_encode_composite_glyph() {
+        let square = GlyphBuf::Simple {
+            contours: vec![vec![
+                GlyphPoint::new(0, 0, true),
+                GlyphPoint::new(100, 0, true),
+                GlyphPoint::new(100, 100, true),
+                GlyphPoint::new(0, 100, true),
+            ]],
+            instructions: vec![],
+            overlap: false,
+        };
+        let components = vec![
+            Component {
+                flags: USE_MY_METRICS | MORE_COMPONENTS,
+                glyph_index: 0,
+                offset: ComponentOffset::Offset(-200, 0),
+                transform: [1.0, 0.0, 0.0, 1.0],
+            },
+            Component {
+                flags: 0,
+                glyph_index: 0,
+                offset: ComponentOffset::Offset(10, 20),
+                transform: [0.5, 0.0, 0.0, 0.5],
+            },
+        ];
+        let composite = GlyphBuf::Composite {
+            components: components.clone(),
+            instructions: vec![],
+        };
+        let encoded = encode_glyf(&[square, composite]).unwrap();
+        let start = encoded.loca.offset(1).unwrap() as usize;
+        let (_, parsed) = parse_composite_glyph(&encoded.glyf[start..]).unwrap();
+        assert_eq!(
+            parsed.header,
+            Header {
+                number_of_contours: -1,
+                xmin: -200,
+                ymin: 0,
+                xmax: 60,
+                ymax: 100,
+            }
+        );
+        let parsed: Vec<_> = parsed.components().collect();
+        assert_eq!(
+            parsed[0].flags,
+            USE_MY_METRICS | MORE_COMPONENTS | ARGS_ARE_XY_VALUES | ARG_1_AND_2_ARE_WORDS
+        );
+        assert_eq!(parsed[1].flags, ARGS_ARE_XY_VALUES | WE_HAVE_A_SCALE);
+        assert_eq!(parsed[0].offset, components[0].offset);
+        assert_eq!(parsed[1].transform, components[1].transform);
+    }
+
+    #[test]
+    fn test_phantom_points() {
+        let header = Header {
+            number_of_contours: 1,
+            xmin: 20,
+            ymin: -10,
+            xmax: 480,
+            ymax: 700,
+        };
+        let horizontal = LongHorMetric {
+            advance_width: 500,
+            lsb: 20,
+        };
+        let vertical = VerticalMetric::from_ascender(&header, 800, -200);
+        assert_eq!(
+            phantom_points(&header, horizontal, vertical),
+            [
+                GlyphPoint::new(0, 0, true),
+                GlyphPoint::new(500, 0, true),
+                GlyphPoint::new(0, 800, true),
+                GlyphPoint::new(0, -200, true),
+            ]
+        );
+    }
 }
@@ -12,13 +12,14 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use nom::{be_i16, be_i64, be_u16, be_u32};
+use std::io::{self, Write};
 
 use super::loca::LocFormat;
-use super::SfntTable;
+use super::{parse_table_data, SfntTable};
 use crate::error::ParserError;
+use crate::reader::Reader;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Head {
     pub major_version: u16,
     pub minor_version: u16,
@@ -46,57 +47,64 @@ impl<'a> SfntTable<'a> for Head {
     type Err = ParserError;
 
     fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
-        parse_head(data)
-            .map(|(_, result)| result)
-            .map_err(|err| err.into())
+        parse_table_data(Self::TAG, data, parse_head)
     }
 }
 
-named!(parse_head<&[u8], Head>,
-    do_parse!(
-        major_version: be_u16 >>
-        minor_version: be_u16 >>
-        font_revision: be_u32 >>
-        check_sum_adjustment: be_u32 >>
-        magic_number: be_u32 >>
-        flags: be_u16 >>
-        units_per_em: be_u16 >>
-        created: be_i64 >>
-        modified: be_i64 >>
-        x_min: be_i16 >>
-        y_min: be_i16 >>
-        x_max: be_i16 >>
-        y_max: be_i16 >>
-        mac_style: be_u16 >>
-        lowest_rec_ppem: be_u16 >>
-        font_direction_hint: be_i16 >>
-        index_to_loc_format: be_i16 >>
-        glyph_data_format: be_i16 >>
-        (Head {
-            major_version,
-            minor_version,
-            font_revision,
-            check_sum_adjustment,
-            magic_number,
-            flags,
-            units_per_em,
-            created,
-            modified,
-            x_min,
-            y_min,
-            x_max,
-            y_max,
-            mac_style,
-            lowest_rec_ppem,
-            font_direction_hint,
-            index_to_loc_format: match index_to_loc_format {
-                0 => LocFormat::Short,
-                _ => LocFormat::Long,   
-            },
-            glyph_data_format
-        })
-    )
-);
+impl Head {
+    /// Writes the table in its binary representation.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        sink.write_all(&self.major_version.to_be_bytes())?;
+        sink.write_all(&self.minor_version.to_be_bytes())?;
+        sink.write_all(&self.font_revision.to_be_bytes())?;
+        sink.write_all(&self.check_sum_adjustment.to_be_bytes())?;
+        sink.write_all(&self.magic_number.to_be_bytes())?;
+        sink.write_all(&self.flags.to_be_bytes())?;
+        sink.write_all(&self.units_per_em.to_be_bytes())?;
+        sink.write_all(&self.created.to_be_bytes())?;
+        sink.write_all(&self.modified.to_be_bytes())?;
+        sink.write_all(&self.x_min.to_be_bytes())?;
+        sink.write_all(&self.y_min.to_be_bytes())?;
+        sink.write_all(&self.x_max.to_be_bytes())?;
+        sink.write_all(&self.y_max.to_be_bytes())?;
+        sink.write_all(&self.mac_style.to_be_bytes())?;
+        sink.write_all(&self.lowest_rec_ppem.to_be_bytes())?;
+        sink.write_all(&self.font_direction_hint.to_be_bytes())?;
+        let index_to_loc_format: i16 = match self.index_to_loc_format {
+            LocFormat::Short => 0,
+            LocFormat::Long => 1,
+        };
+        sink.write_all(&index_to_loc_format.to_be_bytes())?;
+        sink.write_all(&self.glyph_data_format.to_be_bytes())
+    }
+}
+
+fn parse_head(data: &[u8]) -> Result<Head, ParserError> {
+    let mut reader = Reader::new(data);
+    Ok(Head {
+        major_version: reader.read()?,
+        minor_version: reader.read()?,
+        font_revision: reader.read()?,
+        check_sum_adjustment: reader.read()?,
+        magic_number: reader.read()?,
+        flags: reader.read()?,
+        units_per_em: reader.read()?,
+        created: reader.read()?,
+        modified: reader.read()?,
+        x_min: reader.read()?,
+        y_min: reader.read()?,
+        x_max: reader.read()?,
+        y_max: reader.read()?,
+        mac_style: reader.read()?,
+        lowest_rec_ppem: reader.read()?,
+        font_direction_hint: reader.read()?,
+        index_to_loc_format: match reader.read::<i16>()? {
+            0 => LocFormat::Short,
+            _ => LocFormat::Long,
+        },
+        glyph_data_format: reader.read()?,
+    })
+}
 
 #[cfg(test)]
 mod test {
@@ -126,4 +134,12 @@ mod test {
         assert_eq!(head.index_to_loc_format, LocFormat::Short);
     }
 
+    #[test]
+    fn test_write_head_table() {
+        let bytes = include_bytes!("../../tests/font_fragments/head_table.2");
+        let head = Head::from_data(&*bytes, ()).unwrap();
+        let mut written = vec![];
+        head.write_to(&mut written).unwrap();
+        assert_eq!(&written[..], &bytes[..written.len()]);
+    }
 }
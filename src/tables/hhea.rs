@@ -12,22 +12,127 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use super::SfntTable;
+use nom::{be_i16, be_u16};
+
+use std::io::{self, Write};
+
+use super::{parse_table_data, SfntTable};
 
 use crate::error::ParserError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Hhea {
-    major_version: u16,
-    minor_version: u16,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub ascender: i16,
+    pub descender: i16,
+    pub line_gap: i16,
+    pub advance_width_max: u16,
+    pub min_left_side_bearing: i16,
+    pub min_right_side_bearing: i16,
+    pub x_max_extent: i16,
+    pub caret_slope_rise: i16,
+    pub caret_slope_run: i16,
+    pub caret_offset: i16,
+    pub metric_data_format: i16,
+    pub number_of_h_metrics: u16,
 }
 
-impl<'a> SfntTable<'a> for &'a Hhea {
+impl<'a> SfntTable<'a> for Hhea {
     const TAG: &'static [u8; 4] = b"hhea";
     type Context = ();
     type Err = ParserError;
 
     fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
-        unsafe { Ok(&*(data as *const [u8] as *const Hhea)) }
+        parse_table_data(Self::TAG, data, |data| Ok(parse_hhea(data)?.1))
+    }
+}
+
+impl Hhea {
+    /// Writes the table in its binary representation.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        sink.write_all(&self.major_version.to_be_bytes())?;
+        sink.write_all(&self.minor_version.to_be_bytes())?;
+        sink.write_all(&self.ascender.to_be_bytes())?;
+        sink.write_all(&self.descender.to_be_bytes())?;
+        sink.write_all(&self.line_gap.to_be_bytes())?;
+        sink.write_all(&self.advance_width_max.to_be_bytes())?;
+        sink.write_all(&self.min_left_side_bearing.to_be_bytes())?;
+        sink.write_all(&self.min_right_side_bearing.to_be_bytes())?;
+        sink.write_all(&self.x_max_extent.to_be_bytes())?;
+        sink.write_all(&self.caret_slope_rise.to_be_bytes())?;
+        sink.write_all(&self.caret_slope_run.to_be_bytes())?;
+        sink.write_all(&self.caret_offset.to_be_bytes())?;
+        // reserved
+        sink.write_all(&[0; 8])?;
+        sink.write_all(&self.metric_data_format.to_be_bytes())?;
+        sink.write_all(&self.number_of_h_metrics.to_be_bytes())
+    }
+}
+
+named!(parse_hhea<&[u8], Hhea>,
+    do_parse!(
+        major_version: be_u16 >>
+        minor_version: be_u16 >>
+        ascender: be_i16 >>
+        descender: be_i16 >>
+        line_gap: be_i16 >>
+        advance_width_max: be_u16 >>
+        min_left_side_bearing: be_i16 >>
+        min_right_side_bearing: be_i16 >>
+        x_max_extent: be_i16 >>
+        caret_slope_rise: be_i16 >>
+        caret_slope_run: be_i16 >>
+        caret_offset: be_i16 >>
+        // reserved
+        take!(8) >>
+        metric_data_format: be_i16 >>
+        number_of_h_metrics: be_u16 >>
+        (Hhea {
+            major_version,
+            minor_version,
+            ascender,
+            descender,
+            line_gap,
+            advance_width_max,
+            min_left_side_bearing,
+            min_right_side_bearing,
+            x_max_extent,
+            caret_slope_rise,
+            caret_slope_run,
+            caret_offset,
+            metric_data_format,
+            number_of_h_metrics,
+        })
+    )
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_hhea() {
+        // version 1.0
+        let mut data = vec![0x00, 0x01, 0x00, 0x00];
+        for value in &[800i16, -200, 90, 1000, -50, -30, 950, 1, 0, 0] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        // reserved, metricDataFormat and numberOfHMetrics
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&[0x00, 0x00, 0x01, 0x02]);
+
+        let hhea = Hhea::from_data(&data, ()).unwrap();
+        assert_eq!(hhea.major_version, 1);
+        assert_eq!(hhea.ascender, 800);
+        assert_eq!(hhea.descender, -200);
+        assert_eq!(hhea.line_gap, 90);
+        assert_eq!(hhea.advance_width_max, 1000);
+        assert_eq!(hhea.min_left_side_bearing, -50);
+        assert_eq!(hhea.x_max_extent, 950);
+        assert_eq!(hhea.caret_slope_rise, 1);
+        assert_eq!(hhea.number_of_h_metrics, 0x0102);
+
+        assert!(Hhea::from_data(&data[..34], ()).is_err());
     }
 }
@@ -0,0 +1,178 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! The `hmtx` table containing the horizontal metrics of every glyph.
+
+use nom::{be_i16, be_u16};
+
+use std::io::{self, Write};
+
+use super::{parse_table_data, SfntTable};
+use crate::error::{structure, ParserError};
+
+/// The advance width and left side bearing of a glyph.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LongHorMetric {
+    pub advance_width: u16,
+    pub lsb: i16,
+}
+
+/// The number of long metrics (from `hhea`) and the number of glyphs (from
+/// `maxp`) which are needed to parse the `hmtx` table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HmtxContext {
+    pub number_of_h_metrics: u16,
+    pub num_glyphs: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hmtx {
+    pub h_metrics: Vec<LongHorMetric>,
+    /// Left side bearings of the glyphs following the last long metric. These
+    /// glyphs share the advance width of the last long metric.
+    pub left_side_bearings: Vec<i16>,
+}
+
+impl<'a> SfntTable<'a> for Hmtx {
+    const TAG: &'static [u8; 4] = b"hmtx";
+    type Context = HmtxContext;
+    type Err = ParserError;
+
+    fn from_data(data: &'a [u8], context: HmtxContext) -> Result<Self, Self::Err> {
+        let num_lsbs = context
+            .num_glyphs
+            .saturating_sub(context.number_of_h_metrics) as usize;
+        parse_table_data(Self::TAG, data, |data| {
+            Ok(parse_hmtx(data, context.number_of_h_metrics as usize, num_lsbs)?.1)
+        })
+    }
+}
+
+impl Hmtx {
+    /// Creates the table from the metrics of every glyph, storing the advance
+    /// width only once for glyphs at the end that share it.
+    pub fn from_metrics(metrics: &[LongHorMetric]) -> Self {
+        let mut num_long_metrics = metrics.len();
+        while num_long_metrics > 1
+            && metrics[num_long_metrics - 2].advance_width
+                == metrics[num_long_metrics - 1].advance_width
+        {
+            num_long_metrics -= 1;
+        }
+        Hmtx {
+            h_metrics: metrics[..num_long_metrics].to_vec(),
+            left_side_bearings: metrics[num_long_metrics..]
+                .iter()
+                .map(|metric| metric.lsb)
+                .collect(),
+        }
+    }
+
+    /// Returns the metrics of the glyph or `None` if the index is out of
+    /// range.
+    pub fn metric(&self, index: u16) -> Option<LongHorMetric> {
+        let index = index as usize;
+        if let Some(&metric) = self.h_metrics.get(index) {
+            return Some(metric);
+        }
+        let lsb = *self
+            .left_side_bearings
+            .get(index - self.h_metrics.len())?;
+        let advance_width = self.h_metrics.last()?.advance_width;
+        Some(LongHorMetric { advance_width, lsb })
+    }
+
+    pub fn advance_width(&self, index: u16) -> Option<u16> {
+        self.metric(index).map(|metric| metric.advance_width)
+    }
+
+    pub fn num_glyphs(&self) -> usize {
+        self.h_metrics.len() + self.left_side_bearings.len()
+    }
+
+    /// Writes the table in its binary representation. The `hhea` table has to
+    /// contain the number of long metrics.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        for metric in &self.h_metrics {
+            sink.write_all(&metric.advance_width.to_be_bytes())?;
+            sink.write_all(&metric.lsb.to_be_bytes())?;
+        }
+        for lsb in &self.left_side_bearings {
+            sink.write_all(&lsb.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+named!(parse_long_hor_metric<&[u8], LongHorMetric>,
+    return_error!(nom::ErrorKind::Custom(structure::LONG_HOR_METRIC), complete!(
+        do_parse!(
+            advance_width: be_u16 >>
+            lsb: be_i16 >>
+            (LongHorMetric { advance_width, lsb })
+        )
+    ))
+);
+
+named_args!(parse_hmtx(num_metrics: usize, num_lsbs: usize)<&[u8], Hmtx>,
+    do_parse!(
+        h_metrics: count!(parse_long_hor_metric, num_metrics) >>
+        left_side_bearings: count!(be_i16, num_lsbs) >>
+        (Hmtx { h_metrics, left_side_bearings })
+    )
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_hmtx() {
+        let data = [0x01, 0xf4, 0x00, 0x0a, 0x02, 0x58, 0xff, 0xf6, 0x00, 0x05];
+        let context = HmtxContext {
+            number_of_h_metrics: 2,
+            num_glyphs: 3,
+        };
+        let hmtx = Hmtx::from_data(&data, context).unwrap();
+        assert_eq!(hmtx.num_glyphs(), 3);
+        assert_eq!(
+            hmtx.metric(1),
+            Some(LongHorMetric {
+                advance_width: 600,
+                lsb: -10
+            })
+        );
+        assert_eq!(
+            hmtx.metric(2),
+            Some(LongHorMetric {
+                advance_width: 600,
+                lsb: 5
+            })
+        );
+        assert_eq!(hmtx.metric(3), None);
+
+        let mut out = vec![];
+        hmtx.write_to(&mut out).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn test_hmtx_from_metrics() {
+        let metric = |advance_width, lsb| LongHorMetric { advance_width, lsb };
+        let hmtx = Hmtx::from_metrics(&[metric(500, 1), metric(600, 2), metric(600, 3)]);
+        assert_eq!(hmtx.h_metrics, vec![metric(500, 1), metric(600, 2)]);
+        assert_eq!(hmtx.left_side_bearings, vec![3]);
+        assert_eq!(Hmtx::from_metrics(&[]).num_glyphs(), 0);
+    }
+}
@@ -12,46 +12,11 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use super::SfntTable;
+use super::{parse_table_data, SfntTable};
 
-use std::ops::RangeFrom;
+use std::io::{self, Write};
 
 use crate::error::ParserError;
-use nom::types::CompleteByteSlice;
-use nom::{need_more, AsBytes, AtEof, IResult, InputLength, Needed, Slice};
-
-fn be_u16<T>(i: T) -> IResult<T, u16>
-where
-    T: AsBytes,
-    T: InputLength + AtEof,
-    T: Slice<RangeFrom<usize>>,
-{
-    if i.input_len() < 2 {
-        need_more(i, Needed::Size(2))
-    } else {
-        let bytes = i.as_bytes();
-        let res = ((bytes[0] as u16) << 8) + bytes[1] as u16;
-        Ok((i.slice(2..), res))
-    }
-}
-
-fn be_u32<T>(i: T) -> IResult<T, u32>
-where
-    T: AsBytes,
-    T: InputLength + AtEof,
-    T: Slice<RangeFrom<usize>>,
-{
-    if i.input_len() < 4 {
-        need_more(i, Needed::Size(4))
-    } else {
-        let bytes = i.as_bytes();
-        let res = ((bytes[0] as u32) << 24)
-            + ((bytes[1] as u32) << 16)
-            + ((bytes[2] as u32) << 8)
-            + bytes[3] as u32;
-        Ok((i.slice(4..), res))
-    }
-}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum LocFormat {
@@ -68,22 +33,93 @@ impl<'a> SfntTable<'a> for Loca {
     type Err = ParserError;
 
     fn from_data(data: &'a [u8], format: LocFormat) -> Result<Self, Self::Err> {
-        let data = CompleteByteSlice(data);
-        let loca = match format {
-            LocFormat::Short => many0!(data, map!(be_u16, |x| x as u32 * 2))?.1,
-            LocFormat::Long => many0!(data, be_u32)?.1,
-        };
-        Ok(Loca(loca))
+        parse_table_data(Self::TAG, data, |data| {
+            // a trailing partial entry is ignored
+            let loca = match format {
+                LocFormat::Short => data
+                    .chunks_exact(2)
+                    .map(|offset| u32::from(u16::from_be_bytes([offset[0], offset[1]])) * 2)
+                    .collect(),
+                LocFormat::Long => data
+                    .chunks_exact(4)
+                    .map(|offset| u32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]))
+                    .collect(),
+            };
+            Ok(Loca(loca))
+        })
     }
 }
 
 impl Loca {
-    // TODO: Error handling
-    pub fn offset(&self, index: u16) -> u32 {
-        self.0[index as usize]
+    /// Creates a `loca` table from the offsets of all glyphs into the `glyf`
+    /// table followed by the length of the `glyf` table.
+    pub fn from_offsets(offsets: Vec<u32>) -> Self {
+        Loca(offsets)
+    }
+
+    /// Returns the offset of the glyph with the given index, where the index
+    /// one past the last glyph gives the end of the last glyph.
+    pub fn offset(&self, index: u16) -> Result<u32, ParserError> {
+        self.0.get(usize::from(index)).cloned().ok_or_else(|| {
+            ParserError::from_string(format!("The loca table has no entry for glyph {}.", index))
+        })
     }
 
     pub fn num_entries(&self) -> usize {
         self.0.len()
     }
+
+    /// Returns the smallest format which can represent all offsets.
+    pub fn format(&self) -> LocFormat {
+        if self
+            .0
+            .iter()
+            .all(|&offset| offset % 2 == 0 && offset / 2 <= 0xffff)
+        {
+            LocFormat::Short
+        } else {
+            LocFormat::Long
+        }
+    }
+
+    /// Writes the table in the given format.
+    ///
+    /// Fails with `InvalidInput` if an offset can not be represented in the
+    /// short format.
+    pub fn write_to<W: Write>(&self, format: LocFormat, mut sink: W) -> io::Result<()> {
+        for &offset in &self.0 {
+            match format {
+                LocFormat::Short => {
+                    if offset % 2 != 0 || offset / 2 > 0xffff {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "offset can not be stored in the short loca format",
+                        ));
+                    }
+                    sink.write_all(&((offset / 2) as u16).to_be_bytes())?
+                }
+                LocFormat::Long => sink.write_all(&offset.to_be_bytes())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_loca() {
+        let loca = Loca::from_offsets(vec![0, 12, 12, 40]);
+        assert_eq!(loca.format(), LocFormat::Short);
+        let mut data = vec![];
+        loca.write_to(LocFormat::Short, &mut data).unwrap();
+        assert_eq!(data, [0, 0, 0, 6, 0, 6, 0, 20]);
+        assert_eq!(Loca::from_data(&data, LocFormat::Short).unwrap(), loca);
+
+        let loca = Loca::from_offsets(vec![0, 0x20000]);
+        assert_eq!(loca.format(), LocFormat::Long);
+        assert!(loca.write_to(LocFormat::Short, &mut vec![]).is_err());
+    }
 }
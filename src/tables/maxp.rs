@@ -12,10 +12,11 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use nom::{be_u16, be_u32};
+use std::io::{self, Write};
 
-use super::SfntTable;
+use super::{parse_table_data, SfntTable};
 use crate::error::ParserError;
+use crate::reader::Reader;
 
 #[derive(Debug)]
 pub struct Maxp<'a> {
@@ -30,21 +31,46 @@ impl<'a> SfntTable<'a> for Maxp<'a> {
     type Err = ParserError;
 
     fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
-        parse_maxp(data)
-            .map(|result| result.1)
-            .map_err(|err| err.into())
+        parse_table_data(Self::TAG, data, |data| Ok(parse_maxp(data)?.1))
     }
 }
 
-named!(pub parse_maxp<&[u8], Maxp>,
-    do_parse!(
-        version: be_u32 >>
-        num_glyphs: be_u16 >>
-        remainder: cond!(version >= 0x10000000, take!(26)) >>
-        (Maxp {
+impl<'a> Maxp<'a> {
+    /// Creates a table in version 0.5, which is used by fonts with CFF
+    /// outlines.
+    pub fn version_0_5(num_glyphs: u16) -> Self {
+        Maxp {
+            version: 0x0000_5000,
+            num_glyphs,
+            remainder: &[],
+        }
+    }
+
+    /// Writes the table in its binary representation.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        sink.write_all(&self.version.to_be_bytes())?;
+        sink.write_all(&self.num_glyphs.to_be_bytes())?;
+        sink.write_all(self.remainder)
+    }
+}
+
+/// Parses the table, returning the data following it.
+pub fn parse_maxp(data: &[u8]) -> Result<(&[u8], Maxp<'_>), ParserError> {
+    let mut reader = Reader::new(data);
+    let version = reader.read()?;
+    let num_glyphs = reader.read()?;
+    // the fields of version 1.0 are kept as they are
+    let remainder = if version >= 0x0001_0000 {
+        reader.read_bytes(26)?
+    } else {
+        &[]
+    };
+    Ok((
+        reader.remaining(),
+        Maxp {
             version,
             num_glyphs,
-            remainder: remainder.unwrap_or(&[]),
-        })
-    )
-);
+            remainder,
+        },
+    ))
+}
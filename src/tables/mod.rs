@@ -13,11 +13,19 @@
 //    limitations under the License.
 
 pub mod cff;
+pub mod cmap;
 pub mod glyf;
 pub mod head;
 pub mod hhea;
+pub mod hmtx;
 pub mod loca;
 pub mod maxp;
+pub mod name;
+pub mod os2;
+pub mod post;
+
+use crate::error::ParserError;
+use crate::Tag;
 
 pub trait SfntTable<'a>: Sized {
     const TAG: &'static [u8; 4];
@@ -27,3 +35,13 @@ pub trait SfntTable<'a>: Sized {
 
     fn from_data(data: &'a [u8], context: Self::Context) -> Result<Self, Self::Err>;
 }
+
+/// Runs `parse` on the data of a table, adding the tag of the table and the
+/// offset within it to errors.
+pub(crate) fn parse_table_data<'a, T>(
+    tag: &[u8; 4],
+    data: &'a [u8],
+    parse: impl FnOnce(&'a [u8]) -> Result<T, ParserError>,
+) -> Result<T, ParserError> {
+    parse(data).map_err(|err| err.in_table(Tag(*tag), data))
+}
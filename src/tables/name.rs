@@ -0,0 +1,295 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! The `name` table containing the names and other strings of the font.
+
+use nom::be_u16;
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use super::{parse_table_data, SfntTable};
+use crate::error::{structure, ParserError};
+
+pub const COPYRIGHT: u16 = 0;
+pub const FAMILY_NAME: u16 = 1;
+pub const SUBFAMILY_NAME: u16 = 2;
+pub const UNIQUE_ID: u16 = 3;
+pub const FULL_NAME: u16 = 4;
+pub const VERSION: u16 = 5;
+pub const POSTSCRIPT_NAME: u16 = 6;
+
+/// The platform id of Unicode strings.
+const PLATFORM_UNICODE: u16 = 0;
+/// The platform id of Macintosh strings.
+const PLATFORM_MACINTOSH: u16 = 1;
+/// The platform id of Windows strings.
+const PLATFORM_WINDOWS: u16 = 3;
+/// The Windows language id of US English.
+const LANGUAGE_EN_US: u16 = 0x0409;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameRecord<'a> {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub language_id: u16,
+    pub name_id: u16,
+    /// The encoded string.
+    pub string: Cow<'a, [u8]>,
+}
+
+impl<'a> NameRecord<'a> {
+    /// Creates a record with a US English string for the Windows platform.
+    pub fn windows(name_id: u16, string: &str) -> Self {
+        NameRecord {
+            platform_id: PLATFORM_WINDOWS,
+            encoding_id: 1,
+            language_id: LANGUAGE_EN_US,
+            name_id,
+            string: Cow::Owned(
+                string
+                    .encode_utf16()
+                    .flat_map(|unit| unit.to_be_bytes().to_vec())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Decodes the string.
+    ///
+    /// Returns `None` for encodings other than UTF-16 and Mac Roman. Mac Roman
+    /// strings are only decoded if they are ASCII.
+    pub fn decode(&self) -> Option<String> {
+        match (self.platform_id, self.encoding_id) {
+            (PLATFORM_UNICODE, _)
+            | (PLATFORM_WINDOWS, 0)
+            | (PLATFORM_WINDOWS, 1)
+            | (PLATFORM_WINDOWS, 10) => {
+                if !self.string.len().is_multiple_of(2) {
+                    return None;
+                }
+                let units: Vec<u16> = self
+                    .string
+                    .chunks(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                String::from_utf16(&units).ok()
+            }
+            (PLATFORM_MACINTOSH, 0) if self.string.is_ascii() => {
+                Some(String::from_utf8_lossy(&self.string).into_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The `name` table.
+///
+/// Tables in format 1 additionally contain language tags, which are used by
+/// records with language ids starting at 0x8000.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Name<'a> {
+    pub records: Vec<NameRecord<'a>>,
+    pub language_tags: Vec<Cow<'a, [u8]>>,
+}
+
+impl<'a> SfntTable<'a> for Name<'a> {
+    const TAG: &'static [u8; 4] = b"name";
+    type Context = ();
+    type Err = ParserError;
+
+    fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
+        parse_table_data(Self::TAG, data, parse_name)
+    }
+}
+
+impl<'a> Name<'a> {
+    /// Returns the decoded string with the given name id.
+    ///
+    /// US English Windows strings are preferred over strings for other
+    /// platforms and languages.
+    pub fn string(&self, name_id: u16) -> Option<String> {
+        let mut records: Vec<_> = self
+            .records
+            .iter()
+            .filter(|record| record.name_id == name_id)
+            .collect();
+        records.sort_by_key(|record| {
+            (
+                record.platform_id != PLATFORM_WINDOWS,
+                record.language_id != LANGUAGE_EN_US,
+            )
+        });
+        records.iter().filter_map(|record| record.decode()).next()
+    }
+
+    /// Writes the table in its binary representation.
+    ///
+    /// The records are written in the given order, which should be sorted by
+    /// platform, encoding, language and name id. Format 1 is written if there
+    /// are language tags.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        let format: u16 = if self.language_tags.is_empty() { 0 } else { 1 };
+        let mut header_len = 6 + 12 * self.records.len();
+        if format == 1 {
+            header_len += 2 + 4 * self.language_tags.len();
+        }
+        if header_len > usize::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Too many name records.",
+            ));
+        }
+
+        let mut storage: Vec<u8> = vec![];
+        let mut store = |string: &[u8]| {
+            let offset = storage.len();
+            storage.extend_from_slice(string);
+            if string.len() > usize::from(u16::MAX) || offset > usize::from(u16::MAX) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The name strings are too long.",
+                ));
+            }
+            Ok((string.len() as u16, offset as u16))
+        };
+
+        let mut header = vec![];
+        header.extend_from_slice(&format.to_be_bytes());
+        header.extend_from_slice(&(self.records.len() as u16).to_be_bytes());
+        header.extend_from_slice(&(header_len as u16).to_be_bytes());
+        for record in &self.records {
+            let (length, offset) = store(&record.string)?;
+            for value in &[
+                record.platform_id,
+                record.encoding_id,
+                record.language_id,
+                record.name_id,
+                length,
+                offset,
+            ] {
+                header.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        if format == 1 {
+            header.extend_from_slice(&(self.language_tags.len() as u16).to_be_bytes());
+            for tag in &self.language_tags {
+                let (length, offset) = store(tag)?;
+                header.extend_from_slice(&length.to_be_bytes());
+                header.extend_from_slice(&offset.to_be_bytes());
+            }
+        }
+        sink.write_all(&header)?;
+        sink.write_all(&storage)
+    }
+}
+
+// Parses the format, the number of records and the offset of the strings.
+fn parse_name(data: &[u8]) -> Result<Name<'_>, ParserError> {
+    let (rest, (format, count, string_offset)) = parse_name_header(data)?;
+    let (rest, records) = count!(rest, parse_name_record, usize::from(count))?;
+    let storage = data.get(usize::from(string_offset)..).unwrap_or(&[]);
+    let string = |length: u16, offset: u16| {
+        storage
+            .get(usize::from(offset)..usize::from(offset) + usize::from(length))
+            .map(Cow::Borrowed)
+            .ok_or_else(|| ParserError::from_string("Name string out of bounds.".to_string()))
+    };
+
+    let records = records
+        .into_iter()
+        .map(
+            |(platform_id, encoding_id, language_id, name_id, length, offset)| {
+                Ok(NameRecord {
+                    platform_id,
+                    encoding_id,
+                    language_id,
+                    name_id,
+                    string: string(length, offset)?,
+                })
+            },
+        )
+        .collect::<Result<_, ParserError>>()?;
+
+    let mut language_tags = vec![];
+    if format == 1 {
+        let (rest, count) = be_u16(rest)?;
+        let (_, tag_records) = count!(rest, tuple!(be_u16, be_u16), usize::from(count))?;
+        for (length, offset) in tag_records {
+            language_tags.push(string(length, offset)?);
+        }
+    }
+
+    Ok(Name {
+        records,
+        language_tags,
+    })
+}
+
+named!(parse_name_header<&[u8], (u16, u16, u16)>,
+    tuple!(be_u16, be_u16, be_u16)
+);
+
+// Parses a name record into the platform, encoding, language and name ids and
+// the length and offset of the string.
+named!(parse_name_record<&[u8], (u16, u16, u16, u16, u16, u16)>,
+    return_error!(nom::ErrorKind::Custom(structure::NAME_RECORD), complete!(
+        tuple!(be_u16, be_u16, be_u16, be_u16, be_u16, be_u16)
+    ))
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_name_round_trip() {
+        let name = Name {
+            records: vec![
+                NameRecord {
+                    platform_id: PLATFORM_MACINTOSH,
+                    encoding_id: 0,
+                    language_id: 0,
+                    name_id: FAMILY_NAME,
+                    string: Cow::Borrowed(b"Mac Family"),
+                },
+                NameRecord::windows(FAMILY_NAME, "Family \u{E9}"),
+                NameRecord::windows(FULL_NAME, "Family Bold"),
+            ],
+            language_tags: vec![],
+        };
+        let mut data = vec![];
+        name.write_to(&mut data).unwrap();
+        assert_eq!(&data[..6], &[0, 0, 0, 3, 0, 42]);
+
+        let parsed = Name::from_data(&data, ()).unwrap();
+        assert_eq!(parsed, name);
+        assert_eq!(parsed.string(FAMILY_NAME).unwrap(), "Family \u{E9}");
+        assert_eq!(parsed.string(FULL_NAME).unwrap(), "Family Bold");
+        assert_eq!(parsed.records[0].decode().unwrap(), "Mac Family");
+        assert_eq!(parsed.string(COPYRIGHT), None);
+
+        // format 1 with a language tag
+        let mut tagged = name.clone();
+        tagged.records[2].language_id = 0x8000;
+        tagged
+            .language_tags
+            .push(Cow::Owned(vec![0, b'd', 0, b'e']));
+        let mut data = vec![];
+        tagged.write_to(&mut data).unwrap();
+        assert_eq!(Name::from_data(&data, ()).unwrap(), tagged);
+
+        assert!(Name::from_data(&data[..data.len() - 1], ()).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! The `OS/2` table containing metrics and classification of the font.
+
+use nom::{be_i16, be_u16, be_u32};
+
+use std::io::{self, Write};
+
+use super::{parse_table_data, SfntTable};
+use crate::error::ParserError;
+use crate::{parse_tag, Tag};
+
+/// The `OS/2` table.
+///
+/// All fields of versions 0 to 5 are available. Fields which are not present
+/// in the version of a parsed table are zero, and fields which are not
+/// present in `version` are not written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Os2 {
+    pub version: u16,
+    pub x_avg_char_width: i16,
+    pub weight_class: u16,
+    pub width_class: u16,
+    /// The embedding permissions.
+    pub fs_type: u16,
+    pub subscript_x_size: i16,
+    pub subscript_y_size: i16,
+    pub subscript_x_offset: i16,
+    pub subscript_y_offset: i16,
+    pub superscript_x_size: i16,
+    pub superscript_y_size: i16,
+    pub superscript_x_offset: i16,
+    pub superscript_y_offset: i16,
+    pub strikeout_size: i16,
+    pub strikeout_position: i16,
+    pub family_class: i16,
+    pub panose: [u8; 10],
+    pub unicode_range: [u32; 4],
+    pub vendor_id: Tag,
+    pub fs_selection: u16,
+    pub first_char_index: u16,
+    pub last_char_index: u16,
+    pub typo_ascender: i16,
+    pub typo_descender: i16,
+    pub typo_line_gap: i16,
+    pub win_ascent: u16,
+    pub win_descent: u16,
+    /// Version 1 and later.
+    pub code_page_range: [u32; 2],
+    /// Version 2 and later.
+    pub x_height: i16,
+    /// Version 2 and later.
+    pub cap_height: i16,
+    /// Version 2 and later.
+    pub default_char: u16,
+    /// Version 2 and later.
+    pub break_char: u16,
+    /// Version 2 and later.
+    pub max_context: u16,
+    /// Version 5 and later, in twentieths of a point.
+    pub lower_optical_point_size: u16,
+    /// Version 5 and later, in twentieths of a point.
+    pub upper_optical_point_size: u16,
+}
+
+impl Default for Os2 {
+    fn default() -> Self {
+        Os2 {
+            version: 4,
+            x_avg_char_width: 0,
+            weight_class: 400,
+            width_class: 5,
+            fs_type: 0,
+            subscript_x_size: 0,
+            subscript_y_size: 0,
+            subscript_x_offset: 0,
+            subscript_y_offset: 0,
+            superscript_x_size: 0,
+            superscript_y_size: 0,
+            superscript_x_offset: 0,
+            superscript_y_offset: 0,
+            strikeout_size: 0,
+            strikeout_position: 0,
+            family_class: 0,
+            panose: [0; 10],
+            unicode_range: [0; 4],
+            vendor_id: Tag(*b"    "),
+            fs_selection: 0,
+            first_char_index: 0,
+            last_char_index: 0,
+            typo_ascender: 0,
+            typo_descender: 0,
+            typo_line_gap: 0,
+            win_ascent: 0,
+            win_descent: 0,
+            code_page_range: [0; 2],
+            x_height: 0,
+            cap_height: 0,
+            default_char: 0,
+            break_char: 0x20,
+            max_context: 0,
+            lower_optical_point_size: 0,
+            upper_optical_point_size: 0xFFFF,
+        }
+    }
+}
+
+impl<'a> SfntTable<'a> for Os2 {
+    const TAG: &'static [u8; 4] = b"OS/2";
+    type Context = ();
+    type Err = ParserError;
+
+    fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
+        parse_table_data(Self::TAG, data, |data| Ok(parse_os2(data)?.1))
+    }
+}
+
+impl Os2 {
+    /// Writes the table in its binary representation.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        sink.write_all(&self.version.to_be_bytes())?;
+        sink.write_all(&self.x_avg_char_width.to_be_bytes())?;
+        sink.write_all(&self.weight_class.to_be_bytes())?;
+        sink.write_all(&self.width_class.to_be_bytes())?;
+        sink.write_all(&self.fs_type.to_be_bytes())?;
+        for value in &[
+            self.subscript_x_size,
+            self.subscript_y_size,
+            self.subscript_x_offset,
+            self.subscript_y_offset,
+            self.superscript_x_size,
+            self.superscript_y_size,
+            self.superscript_x_offset,
+            self.superscript_y_offset,
+            self.strikeout_size,
+            self.strikeout_position,
+            self.family_class,
+        ] {
+            sink.write_all(&value.to_be_bytes())?;
+        }
+        sink.write_all(&self.panose)?;
+        for range in &self.unicode_range {
+            sink.write_all(&range.to_be_bytes())?;
+        }
+        sink.write_all(&self.vendor_id.0)?;
+        sink.write_all(&self.fs_selection.to_be_bytes())?;
+        sink.write_all(&self.first_char_index.to_be_bytes())?;
+        sink.write_all(&self.last_char_index.to_be_bytes())?;
+        sink.write_all(&self.typo_ascender.to_be_bytes())?;
+        sink.write_all(&self.typo_descender.to_be_bytes())?;
+        sink.write_all(&self.typo_line_gap.to_be_bytes())?;
+        sink.write_all(&self.win_ascent.to_be_bytes())?;
+        sink.write_all(&self.win_descent.to_be_bytes())?;
+        if self.version >= 1 {
+            for range in &self.code_page_range {
+                sink.write_all(&range.to_be_bytes())?;
+            }
+        }
+        if self.version >= 2 {
+            sink.write_all(&self.x_height.to_be_bytes())?;
+            sink.write_all(&self.cap_height.to_be_bytes())?;
+            sink.write_all(&self.default_char.to_be_bytes())?;
+            sink.write_all(&self.break_char.to_be_bytes())?;
+            sink.write_all(&self.max_context.to_be_bytes())?;
+        }
+        if self.version >= 5 {
+            sink.write_all(&self.lower_optical_point_size.to_be_bytes())?;
+            sink.write_all(&self.upper_optical_point_size.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+named!(parse_panose<&[u8], [u8; 10]>,
+    map!(take!(10), |bytes| {
+        let mut panose = [0; 10];
+        panose.copy_from_slice(bytes);
+        panose
+    })
+);
+
+named!(parse_unicode_range<&[u8], [u32; 4]>,
+    do_parse!(
+        range_1: be_u32 >>
+        range_2: be_u32 >>
+        range_3: be_u32 >>
+        range_4: be_u32 >>
+        ([range_1, range_2, range_3, range_4])
+    )
+);
+
+// Parses the fields added in version 2.
+named!(parse_version_2<&[u8], (i16, i16, u16, u16, u16)>,
+    tuple!(be_i16, be_i16, be_u16, be_u16, be_u16)
+);
+
+named!(parse_os2<&[u8], Os2>,
+    do_parse!(
+        version: be_u16 >>
+        x_avg_char_width: be_i16 >>
+        weight_class: be_u16 >>
+        width_class: be_u16 >>
+        fs_type: be_u16 >>
+        // the sub- and superscript and strikeout metrics and the family class
+        script_metrics: count!(be_i16, 11) >>
+        panose: parse_panose >>
+        unicode_range: parse_unicode_range >>
+        vendor_id: parse_tag >>
+        fs_selection: be_u16 >>
+        first_char_index: be_u16 >>
+        last_char_index: be_u16 >>
+        typo_ascender: be_i16 >>
+        typo_descender: be_i16 >>
+        typo_line_gap: be_i16 >>
+        win_ascent: be_u16 >>
+        win_descent: be_u16 >>
+        code_page_range: cond!(version >= 1, tuple!(be_u32, be_u32)) >>
+        version_2: cond!(version >= 2, parse_version_2) >>
+        optical_point_sizes: cond!(version >= 5, tuple!(be_u16, be_u16)) >>
+        ({
+            let (x_height, cap_height, default_char, break_char, max_context) =
+                version_2.unwrap_or_default();
+            let (lower_optical_point_size, upper_optical_point_size) =
+                optical_point_sizes.unwrap_or_default();
+            Os2 {
+                version,
+                x_avg_char_width,
+                weight_class,
+                width_class,
+                fs_type,
+                subscript_x_size: script_metrics[0],
+                subscript_y_size: script_metrics[1],
+                subscript_x_offset: script_metrics[2],
+                subscript_y_offset: script_metrics[3],
+                superscript_x_size: script_metrics[4],
+                superscript_y_size: script_metrics[5],
+                superscript_x_offset: script_metrics[6],
+                superscript_y_offset: script_metrics[7],
+                strikeout_size: script_metrics[8],
+                strikeout_position: script_metrics[9],
+                family_class: script_metrics[10],
+                panose,
+                unicode_range,
+                vendor_id,
+                fs_selection,
+                first_char_index,
+                last_char_index,
+                typo_ascender,
+                typo_descender,
+                typo_line_gap,
+                win_ascent,
+                win_descent,
+                code_page_range: code_page_range.map_or([0; 2], |(range_1, range_2)| [range_1, range_2]),
+                x_height,
+                cap_height,
+                default_char,
+                break_char,
+                max_context,
+                lower_optical_point_size,
+                upper_optical_point_size,
+            }
+        })
+    )
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{Font, OpentypeTableAccess};
+
+    #[test]
+    fn test_os2_round_trip() {
+        let data = include_bytes!("../../tests/font_files/LinBiolinum_R.otf");
+        let font = Font::from_bytes(data, 0).unwrap();
+        let table = font.table_data(Tag(*Os2::TAG)).unwrap();
+        let os2 = Os2::from_data(table, ()).unwrap();
+        assert_eq!(os2.vendor_id, Tag(*b"PfEd"));
+        assert!(os2.weight_class == 400 || os2.weight_class == 500);
+        let mut written = vec![];
+        os2.write_to(&mut written).unwrap();
+        assert_eq!(&written[..], &table[..written.len()]);
+    }
+}
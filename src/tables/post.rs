@@ -0,0 +1,423 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! The `post` table containing PostScript information and glyph names.
+
+use nom::{be_i16, be_u16, be_u32, be_u8};
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use super::{parse_table_data, SfntTable};
+use crate::error::ParserError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Post<'a> {
+    pub version: u32,
+    /// Italic angle as a 16.16 fixed point number.
+    pub italic_angle: i32,
+    pub underline_position: i16,
+    pub underline_thickness: i16,
+    pub is_fixed_pitch: u32,
+    pub min_mem_type42: u32,
+    pub max_mem_type42: u32,
+    pub min_mem_type1: u32,
+    pub max_mem_type1: u32,
+    /// For version 2.0 tables, the index into the standard Macintosh names or
+    /// (offset by 258) into `names` for every glyph.
+    pub glyph_name_index: Vec<u16>,
+    /// The custom glyph names of a version 2.0 table.
+    pub names: Vec<&'a [u8]>,
+}
+
+impl<'a> SfntTable<'a> for Post<'a> {
+    const TAG: &'static [u8; 4] = b"post";
+    type Context = ();
+    type Err = ParserError;
+
+    fn from_data(data: &'a [u8], _: ()) -> Result<Self, Self::Err> {
+        parse_table_data(Self::TAG, data, |data| Ok(parse_post(data)?.1))
+    }
+}
+
+impl<'a> Post<'a> {
+    /// Returns the name of the glyph with the given index if the table
+    /// contains glyph names.
+    pub fn glyph_name(&self, index: u16) -> Option<Cow<'a, str>> {
+        match self.version {
+            0x0001_0000 => MAC_GLYPH_NAMES
+                .get(index as usize)
+                .map(|&name| Cow::Borrowed(name)),
+            0x0002_0000 => {
+                let name_index = *self.glyph_name_index.get(index as usize)? as usize;
+                if name_index < MAC_GLYPH_NAMES.len() {
+                    Some(Cow::Borrowed(MAC_GLYPH_NAMES[name_index]))
+                } else {
+                    let name = self.names.get(name_index - MAC_GLYPH_NAMES.len())?;
+                    Some(String::from_utf8_lossy(name))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes the table in its binary representation.
+    ///
+    /// Glyph names are only written for version 2.0 tables.
+    pub fn write_to<W: Write>(&self, mut sink: W) -> io::Result<()> {
+        sink.write_all(&self.version.to_be_bytes())?;
+        sink.write_all(&self.italic_angle.to_be_bytes())?;
+        sink.write_all(&self.underline_position.to_be_bytes())?;
+        sink.write_all(&self.underline_thickness.to_be_bytes())?;
+        sink.write_all(&self.is_fixed_pitch.to_be_bytes())?;
+        sink.write_all(&self.min_mem_type42.to_be_bytes())?;
+        sink.write_all(&self.max_mem_type42.to_be_bytes())?;
+        sink.write_all(&self.min_mem_type1.to_be_bytes())?;
+        sink.write_all(&self.max_mem_type1.to_be_bytes())?;
+        if self.version != 0x0002_0000 {
+            return Ok(());
+        }
+
+        sink.write_all(&(self.glyph_name_index.len() as u16).to_be_bytes())?;
+        for index in &self.glyph_name_index {
+            sink.write_all(&index.to_be_bytes())?;
+        }
+        for name in &self.names {
+            if name.len() > 255 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Glyph names can not be longer than 255 bytes.",
+                ));
+            }
+            sink.write_all(&[name.len() as u8])?;
+            sink.write_all(name)?;
+        }
+        Ok(())
+    }
+}
+
+named!(parse_post<&[u8], Post<'_>>,
+    do_parse!(
+        version: be_u32 >>
+        italic_angle: be_u32 >>
+        underline_position: be_i16 >>
+        underline_thickness: be_i16 >>
+        is_fixed_pitch: be_u32 >>
+        min_mem_type42: be_u32 >>
+        max_mem_type42: be_u32 >>
+        min_mem_type1: be_u32 >>
+        max_mem_type1: be_u32 >>
+        glyph_name_index: cond!(version == 0x0002_0000, length_count!(be_u16, be_u16)) >>
+        names: cond!(version == 0x0002_0000, many0!(complete!(length_data!(be_u8)))) >>
+        (Post {
+            version,
+            italic_angle: italic_angle as i32,
+            underline_position,
+            underline_thickness,
+            is_fixed_pitch,
+            min_mem_type42,
+            max_mem_type42,
+            min_mem_type1,
+            max_mem_type1,
+            glyph_name_index: glyph_name_index.unwrap_or_default(),
+            names: names.unwrap_or_default(),
+        })
+    )
+);
+
+/// The 258 standard Macintosh glyph names in the order used by `post` tables.
+pub const MAC_GLYPH_NAMES: [&str; 258] = [
+    ".notdef",
+    ".null",
+    "nonmarkingreturn",
+    "space",
+    "exclam",
+    "quotedbl",
+    "numbersign",
+    "dollar",
+    "percent",
+    "ampersand",
+    "quotesingle",
+    "parenleft",
+    "parenright",
+    "asterisk",
+    "plus",
+    "comma",
+    "hyphen",
+    "period",
+    "slash",
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "colon",
+    "semicolon",
+    "less",
+    "equal",
+    "greater",
+    "question",
+    "at",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "bracketleft",
+    "backslash",
+    "bracketright",
+    "asciicircum",
+    "underscore",
+    "grave",
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "braceleft",
+    "bar",
+    "braceright",
+    "asciitilde",
+    "Adieresis",
+    "Aring",
+    "Ccedilla",
+    "Eacute",
+    "Ntilde",
+    "Odieresis",
+    "Udieresis",
+    "aacute",
+    "agrave",
+    "acircumflex",
+    "adieresis",
+    "atilde",
+    "aring",
+    "ccedilla",
+    "eacute",
+    "egrave",
+    "ecircumflex",
+    "edieresis",
+    "iacute",
+    "igrave",
+    "icircumflex",
+    "idieresis",
+    "ntilde",
+    "oacute",
+    "ograve",
+    "ocircumflex",
+    "odieresis",
+    "otilde",
+    "uacute",
+    "ugrave",
+    "ucircumflex",
+    "udieresis",
+    "dagger",
+    "degree",
+    "cent",
+    "sterling",
+    "section",
+    "bullet",
+    "paragraph",
+    "germandbls",
+    "registered",
+    "copyright",
+    "trademark",
+    "acute",
+    "dieresis",
+    "notequal",
+    "AE",
+    "Oslash",
+    "infinity",
+    "plusminus",
+    "lessequal",
+    "greaterequal",
+    "yen",
+    "mu",
+    "partialdiff",
+    "summation",
+    "product",
+    "pi",
+    "integral",
+    "ordfeminine",
+    "ordmasculine",
+    "Omega",
+    "ae",
+    "oslash",
+    "questiondown",
+    "exclamdown",
+    "logicalnot",
+    "radical",
+    "florin",
+    "approxequal",
+    "Delta",
+    "guillemotleft",
+    "guillemotright",
+    "ellipsis",
+    "nonbreakingspace",
+    "Agrave",
+    "Atilde",
+    "Otilde",
+    "OE",
+    "oe",
+    "endash",
+    "emdash",
+    "quotedblleft",
+    "quotedblright",
+    "quoteleft",
+    "quoteright",
+    "divide",
+    "lozenge",
+    "ydieresis",
+    "Ydieresis",
+    "fraction",
+    "currency",
+    "guilsinglleft",
+    "guilsinglright",
+    "fi",
+    "fl",
+    "daggerdbl",
+    "periodcentered",
+    "quotesinglbase",
+    "quotedblbase",
+    "perthousand",
+    "Acircumflex",
+    "Ecircumflex",
+    "Aacute",
+    "Edieresis",
+    "Egrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Igrave",
+    "Oacute",
+    "Ocircumflex",
+    "apple",
+    "Ograve",
+    "Uacute",
+    "Ucircumflex",
+    "Ugrave",
+    "dotlessi",
+    "circumflex",
+    "tilde",
+    "macron",
+    "breve",
+    "dotaccent",
+    "ring",
+    "cedilla",
+    "hungarumlaut",
+    "ogonek",
+    "caron",
+    "Lslash",
+    "lslash",
+    "Scaron",
+    "scaron",
+    "Zcaron",
+    "zcaron",
+    "brokenbar",
+    "Eth",
+    "eth",
+    "Yacute",
+    "yacute",
+    "Thorn",
+    "thorn",
+    "minus",
+    "multiply",
+    "onesuperior",
+    "twosuperior",
+    "threesuperior",
+    "onehalf",
+    "onequarter",
+    "threequarters",
+    "franc",
+    "Gbreve",
+    "gbreve",
+    "Idotaccent",
+    "Scedilla",
+    "scedilla",
+    "Cacute",
+    "cacute",
+    "Ccaron",
+    "ccaron",
+    "dcroat",
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_post_v2_names() {
+        let mut data = vec![0x00, 0x02, 0x00, 0x00];
+        data.extend(&[0; 28]);
+        // three glyphs: .notdef, A and a custom name
+        data.extend(&[0x00, 0x03, 0x00, 0x00, 0x00, 0x24, 0x01, 0x02]);
+        data.extend(&[0x04]);
+        data.extend(b"Abar");
+        let post = Post::from_data(&data, ()).unwrap();
+        assert_eq!(post.glyph_name(0).unwrap(), ".notdef");
+        assert_eq!(post.glyph_name(1).unwrap(), "A");
+        assert_eq!(post.glyph_name(2).unwrap(), "Abar");
+        assert_eq!(post.glyph_name(3), None);
+
+        let mut out = vec![];
+        post.write_to(&mut out).unwrap();
+        assert_eq!(out, data);
+    }
+}
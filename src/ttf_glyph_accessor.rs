@@ -13,13 +13,15 @@
 //    limitations under the License.
 
 use crate::error::ParserError;
+use crate::outline::Outline;
 use crate::tables::glyf::{
-    self, parse_composite_glyph, parse_header, parse_simple_glyph, CompositeGlyph, Glyf,
-    GlyphPoint, GlyphPointIter, SimpleGlyph,
+    self, parse_composite_glyph, parse_header, parse_simple_glyph, ComponentOffset, CompositeGlyph,
+    Glyf, GlyphBuf, GlyphPoint, GlyphPointIter, SimpleGlyph,
 };
 use crate::tables::head::Head;
 use crate::tables::loca::Loca;
-use crate::{OpentypeTableAccess, ParseTable};
+use crate::tables::SfntTable;
+use crate::{OpentypeTableAccess, ParseTable, Tag};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum QuadraticPath {
@@ -43,19 +45,35 @@ impl<'a> Glyph<'a> {
         }
     }
 
-    pub fn contour_iter(&self) -> impl 'a + Iterator<Item = QuadraticPath> {
+    /// Returns an iterator over the contours of a simple glyph.
+    ///
+    /// Composite glyphs need access to their components and fail with an
+    /// error, their outlines are resolved by `GlyphAccessor::outline`.
+    pub fn contour_iter(&self) -> Result<impl 'a + Iterator<Item = QuadraticPath>, ParserError> {
         match self {
             Glyph::Simple(g) => {
                 let point_iter = g.point_iter();
                 let end_pts_of_contours = g.end_pts_of_contours;
-                ContourIterator {
+                Ok(ContourIterator {
                     point_iter,
                     end_pts_of_contours,
                     last_pt: None,
                     index: 0,
-                }
+                })
             }
-            Glyph::Composite(_) => unimplemented!(),
+            Glyph::Composite(_) => Err(ParserError::from_string(
+                "Outlines of composite glyphs can only be read through the glyph accessor."
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+impl<'a> From<Glyph<'a>> for GlyphBuf {
+    fn from(glyph: Glyph<'a>) -> GlyphBuf {
+        match glyph {
+            Glyph::Simple(glyph) => glyph.into(),
+            Glyph::Composite(glyph) => glyph.into(),
         }
     }
 }
@@ -116,8 +134,9 @@ impl<'a> Iterator for ContourIterator<'a> {
             // the maximal depth of recursion is 1
             return self.next();
         } else {
-            let mid_point_x = (point.x + last_pt.x) / 2;
-            let mid_point_y = (point.y + last_pt.y) / 2;
+            let mid_point = |a: i32, b: i32| ((i64::from(a) + i64::from(b)) / 2) as i32;
+            let mid_point_x = mid_point(point.x, last_pt.x);
+            let mid_point_y = mid_point(point.y, last_pt.y);
             QuadraticPath::CurveTo(last_pt.x, last_pt.y, mid_point_x, mid_point_y)
         };
 
@@ -125,13 +144,22 @@ impl<'a> Iterator for ContourIterator<'a> {
     }
 }
 
-named!(parse_glyph<&[u8], Glyph>,
-    switch!(
-        map!(peek!(parse_header), |header| header.number_of_contours < 0),
-        true => map!(parse_composite_glyph, Glyph::Composite) |
-        false => map!(parse_simple_glyph, Glyph::Simple)
-    )
-);
+fn parse_glyph(input: &[u8]) -> Result<Glyph<'_>, ParserError> {
+    if parse_header(input)?.1.number_of_contours < 0 {
+        Ok(Glyph::Composite(parse_composite_glyph(input)?.1))
+    } else {
+        Ok(Glyph::Simple(parse_simple_glyph(input)?.1))
+    }
+}
+
+/// The maximal nesting depth of composite glyphs, which guards against
+/// components that reference themselves.
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+/// The maximal number of components resolved for one outline, counting shared
+/// components every time they are used. Composite glyphs that reuse other
+/// composites could otherwise expand to exponentially many components.
+const MAX_COMPONENTS: usize = 1024;
 
 #[derive(Debug, Clone)]
 pub struct GlyphAccessor<'font> {
@@ -140,12 +168,20 @@ pub struct GlyphAccessor<'font> {
 }
 
 impl<'font> GlyphAccessor<'font> {
-    pub fn new(font: &impl OpentypeTableAccess<'font>) -> Result<Self, ParserError> {
-        todo!()
-        // let head: Head = font.parse_table()?;
-        // let loca = font.parse_table_context(head.index_to_loc_format)?;
-        // let glyf = font.parse_table()?;
-        // Ok(GlyphAccessor { loca, glyf })
+    pub fn new<F>(font: &F) -> Result<Self, ParserError>
+    where
+        F: OpentypeTableAccess<'font>,
+        F::TableData: Into<&'font [u8]>,
+    {
+        let head: Head = font.parse_table()?.get()?;
+        let loca = font
+            .parse_table_context::<Loca, _>(head.index_to_loc_format)?
+            .get()?;
+        let glyf_data = font
+            .table_data(Tag(*Glyf::TAG))
+            .ok_or_else(|| ParserError::expected_table(Tag(*Glyf::TAG)))?;
+        let glyf = Glyf::from_data(glyf_data.into(), ())?;
+        Ok(GlyphAccessor { loca, glyf })
     }
 
     pub fn num_glyphs(&self) -> u32 {
@@ -156,24 +192,87 @@ impl<'font> GlyphAccessor<'font> {
         if self.num_glyphs() <= index as u32 {
             return Ok(None);
         }
-        let start = self.loca.offset(index);
-        let end = self.loca.offset(index + 1);
-        assert!(start <= end);
-
+        let start = self.loca.offset(index)?;
+        let end = self.loca.offset(index + 1)?;
         if start == end {
             return Ok(None);
         }
 
-        let glyph_data = self.glyf.at_offset(start as usize, end as usize);
-        let (_, glyph) = parse_glyph(glyph_data)
-            .map_err(|err| ParserError::glyph_parse(index as u32, err.into()))?;
+        let glyph_data = self
+            .glyf
+            .at_offset(start as usize, end as usize)
+            .map_err(|err| ParserError::glyph_parse(index as u32, err))?;
+        let glyph = parse_glyph(glyph_data).map_err(|err| {
+            let err = err.in_table(Tag(*Glyf::TAG), self.glyf.data());
+            ParserError::glyph_parse(index as u32, err)
+        })?;
         Ok(Some(glyph))
     }
+
+    /// Returns the outline of the glyph with the given index, resolving the
+    /// components of composite glyphs.
+    pub fn outline(&self, index: u16) -> Result<Option<Outline>, ParserError> {
+        let mut remaining_components = MAX_COMPONENTS;
+        self.resolve_outline(index, 0, &mut remaining_components)
+    }
+
+    fn resolve_outline(
+        &self,
+        index: u16,
+        depth: usize,
+        remaining_components: &mut usize,
+    ) -> Result<Option<Outline>, ParserError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(ParserError::from_string(format!(
+                "Components of glyph {} are nested too deeply.",
+                index
+            )));
+        }
+        let glyph = match self.index(index)? {
+            Some(Glyph::Composite(glyph)) => glyph,
+            Some(glyph) => return Ok(Some(Outline::from_quadratic_path(glyph.contour_iter()?))),
+            None => return Ok(None),
+        };
+
+        let mut outline = Outline::default();
+        for component in glyph.components() {
+            if *remaining_components == 0 {
+                return Err(ParserError::from_string(format!(
+                    "Glyph {} consists of more than {} components.",
+                    index, MAX_COMPONENTS
+                )));
+            }
+            *remaining_components -= 1;
+            let (dx, dy) = match component.offset {
+                ComponentOffset::Offset(dx, dy) => (f32::from(dx), f32::from(dy)),
+                ComponentOffset::Anchor(..) => {
+                    return Err(ParserError::from_string(format!(
+                        "Glyph {} positions components by matching points which is not supported.",
+                        index
+                    )))
+                }
+            };
+            let [a, b, c, d] = component.transform;
+            let (dx, dy) = if component.scaled_offset() {
+                (a * dx + c * dy, b * dx + d * dy)
+            } else {
+                (dx, dy)
+            };
+            if let Some(component_outline) =
+                self.resolve_outline(component.glyph_index, depth + 1, remaining_components)?
+            {
+                let transformed = component_outline.transform([a, b, c, d, dx, dy]);
+                outline.contours.extend(transformed.contours);
+            }
+        }
+        Ok(Some(outline))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::tables::glyf::{encode_glyf, Component};
     use crate::Font;
 
     #[test]
@@ -198,4 +297,53 @@ mod test {
             glyphs.push(glyph_accessor.index(index as u16));
         }
     }
+
+    #[test]
+    fn test_composite_outline() {
+        let data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
+        let font = Font::from_bytes(data, 0).expect("Could not read font.");
+
+        let glyph_accessor = GlyphAccessor::new(&font).unwrap();
+        let composite = (0..glyph_accessor.num_glyphs() as u16)
+            .find(|&index| matches!(glyph_accessor.index(index), Ok(Some(Glyph::Composite(_)))))
+            .expect("Inconsolata contains composite glyphs");
+        let outline = glyph_accessor.outline(composite).unwrap().unwrap();
+        assert!(!outline.contours.is_empty());
+    }
+
+    #[test]
+    fn test_shared_components_limit() {
+        // every composite uses the previous glyph twice, so the last one
+        // expands to 2^12 copies of the first glyph
+        let mut glyphs = vec![GlyphBuf::Simple {
+            contours: vec![vec![
+                GlyphPoint::new(0, 0, true),
+                GlyphPoint::new(10, 0, true),
+                GlyphPoint::new(0, 10, true),
+            ]],
+            instructions: vec![],
+            overlap: false,
+        }];
+        for index in 0..12 {
+            let component = Component {
+                flags: 0,
+                glyph_index: index,
+                offset: ComponentOffset::Offset(0, 0),
+                transform: [1.0, 0.0, 0.0, 1.0],
+            };
+            glyphs.push(GlyphBuf::Composite {
+                components: vec![component; 2],
+                instructions: vec![],
+            });
+        }
+        let encoded = encode_glyf(&glyphs).unwrap();
+        let glyph_accessor = GlyphAccessor {
+            loca: encoded.loca,
+            glyf: Glyf::from_data(&encoded.glyf, ()).unwrap(),
+        };
+
+        let outline = glyph_accessor.outline(8).unwrap().unwrap();
+        assert_eq!(outline.contours.len(), 256);
+        assert!(glyph_accessor.outline(12).is_err());
+    }
 }
@@ -0,0 +1,1580 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Reading PostScript Type 1 fonts in the binary (PFB) and ASCII (PFA)
+//! formats.
+//!
+//! Glyphs are interpreted into the same [`PathInstruction`]s that are produced
+//! for CFF outlines, so they can be turned into an
+//! [`Outline`](../outline/struct.Outline.html) the same way.
+
+use nom::{be_u8, le_u32};
+
+use std::collections::BTreeMap;
+
+use crate::cff::{standard_encoding, STANDARD_STRINGS};
+use crate::error::ParserError;
+use crate::outline::Outline;
+use crate::tables::cff::CffBuilder;
+use crate::{Fixed16_16, PathInstruction};
+
+/// The initial key of the eexec encryption.
+const EEXEC_KEY: u16 = 55665;
+/// The initial key of the charstring encryption.
+const CHARSTRING_KEY: u16 = 4330;
+/// The maximum nesting depth of subroutine calls allowed by the specification.
+const SUBROUTINE_MAX_DEPTH: usize = 10;
+
+/// The maximal number of subroutines, which is also the limit of a CFF
+/// subroutine index.
+const MAX_SUBRS: usize = 65536;
+
+/// Decrypts data encrypted with the Type 1 encryption and drops the first
+/// `skip` random bytes.
+fn decrypt(data: &[u8], key: u16, skip: usize) -> Vec<u8> {
+    let mut r = key;
+    data.iter()
+        .map(|&cipher| {
+            let plain = cipher ^ (r >> 8) as u8;
+            r = u16::from(cipher)
+                .wrapping_add(r)
+                .wrapping_mul(52845)
+                .wrapping_add(22719);
+            plain
+        })
+        .skip(skip)
+        .collect()
+}
+
+// Parses a PFB segment into its type and data.
+named!(parse_pfb_segment<&[u8], (u8, &[u8])>,
+    do_parse!(
+        tag!(&[0x80]) >>
+        segment_type: verify!(be_u8, |t: u8| t == 1 || t == 2) >>
+        data: length_data!(le_u32) >>
+        (segment_type, data)
+    )
+);
+
+/// Splits a PFB file into the clear text and the eexec encrypted part.
+fn read_pfb(mut data: &[u8]) -> Result<(Vec<u8>, Vec<u8>), ParserError> {
+    let mut clear_text = vec![];
+    let mut encrypted = vec![];
+    // segment type 3 marks the end of the file
+    while !data.starts_with(&[0x80, 3]) {
+        let (rest, (segment_type, segment)) = parse_pfb_segment(data)?;
+        match segment_type {
+            1 if encrypted.is_empty() => clear_text.extend_from_slice(segment),
+            // the clear text after the encrypted part only contains zeros
+            // and `cleartomark`
+            1 => {}
+            _ => encrypted.extend_from_slice(segment),
+        }
+        data = rest;
+    }
+    Ok((clear_text, encrypted))
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    match byte {
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%' => true,
+        _ => is_whitespace(byte),
+    }
+}
+
+/// Decodes hexadecimal digits up to the first other character, ignoring
+/// whitespace.
+fn decode_hex(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data
+        .iter()
+        .cloned()
+        .filter(|&byte| !is_whitespace(byte))
+        .map(hex_value)
+        .take_while(Option::is_some)
+        .map(Option::unwrap)
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair.get(1).cloned().unwrap_or(0))
+        .collect()
+}
+
+/// Splits a PFA file into the clear text and the eexec encrypted part.
+fn read_pfa(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>), ParserError> {
+    let eexec = data
+        .windows(5)
+        .position(|window| window == b"eexec")
+        .ok_or_else(|| ParserError::from_string("Missing eexec section.".to_string()))?
+        + 5;
+    let mut encrypted = &data[eexec..];
+    while encrypted.first().cloned().is_some_and(is_whitespace) {
+        encrypted = &encrypted[1..];
+    }
+    // the encrypted part is either hexadecimal or binary
+    let is_hex = encrypted.len() >= 4 && encrypted[..4].iter().all(|&b| hex_value(b).is_some());
+    let encrypted = if is_hex {
+        decode_hex(encrypted)
+    } else {
+        encrypted.to_vec()
+    };
+    Ok((data[..eexec].to_vec(), encrypted))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Integer(i32),
+    Real(f32),
+    /// A literal name such as `/FontName`, without the slash.
+    LiteralName(&'a [u8]),
+    /// An executable name such as `def`.
+    Name(&'a [u8]),
+    String(Vec<u8>),
+    /// Binary data read with `RD` or `-|`.
+    Binary(&'a [u8]),
+    ArrayStart,
+    ArrayEnd,
+    ProcStart,
+    ProcEnd,
+    DictStart,
+    DictEnd,
+}
+
+/// Splits PostScript code into tokens.
+///
+/// This only does as much as is needed to read the dictionaries of a font
+/// program. The binary data following `RD` and `-|` is returned as a single
+/// token.
+#[derive(Debug, Clone)]
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+    last_integer: Option<i32>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Lexer {
+            data,
+            pos: 0,
+            last_integer: None,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).cloned()
+    }
+
+    fn read_string(&mut self) -> Vec<u8> {
+        let mut string = vec![];
+        let mut depth = 0;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            match byte {
+                b'(' => depth += 1,
+                b')' if depth == 0 => break,
+                b')' => depth -= 1,
+                b'\\' => {
+                    let escaped = match self.peek() {
+                        Some(escaped) => escaped,
+                        None => break,
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => string.push(b'\n'),
+                        b'r' => string.push(b'\r'),
+                        b't' => string.push(b'\t'),
+                        b'b' => string.push(8),
+                        b'f' => string.push(12),
+                        b'\r' | b'\n' => {}
+                        b'0'..=b'7' => {
+                            let mut value = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        value = value * 8 + u32::from(digit - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            string.push(value as u8);
+                        }
+                        _ => string.push(escaped),
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            string.push(byte);
+        }
+        string
+    }
+
+    fn read_regular(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek().is_some_and(|byte| !is_delimiter(byte)) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        let token = loop {
+            loop {
+                let byte = self.peek()?;
+                if is_whitespace(byte) {
+                    self.pos += 1;
+                } else if byte == b'%' {
+                    while self
+                        .peek()
+                        .is_some_and(|byte| byte != b'\r' && byte != b'\n')
+                    {
+                        self.pos += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+            let byte = self.peek()?;
+            self.pos += 1;
+            break match byte {
+                b'(' => Token::String(self.read_string()),
+                b'<' if self.peek() == Some(b'<') => {
+                    self.pos += 1;
+                    Token::DictStart
+                }
+                b'<' => {
+                    let end = self.data[self.pos..]
+                        .iter()
+                        .position(|&byte| byte == b'>')
+                        .map_or(self.data.len(), |end| self.pos + end);
+                    let string = decode_hex(&self.data[self.pos..end]);
+                    self.pos = (end + 1).min(self.data.len());
+                    Token::String(string)
+                }
+                b'>' if self.peek() == Some(b'>') => {
+                    self.pos += 1;
+                    Token::DictEnd
+                }
+                b'[' => Token::ArrayStart,
+                b']' => Token::ArrayEnd,
+                b'{' => Token::ProcStart,
+                b'}' => Token::ProcEnd,
+                b'/' => Token::LiteralName(self.read_regular()),
+                _ => {
+                    self.pos -= 1;
+                    let regular = self.read_regular();
+                    if regular.is_empty() {
+                        // a stray closing delimiter
+                        self.pos += 1;
+                        continue;
+                    }
+                    match (regular, self.last_integer) {
+                        (b"RD", Some(len)) | (b"-|", Some(len)) if len >= 0 => {
+                            // a single space separates the operator from the data
+                            let start = (self.pos + 1).min(self.data.len());
+                            let end = (start + len as usize).min(self.data.len());
+                            self.pos = end;
+                            Token::Binary(&self.data[start..end])
+                        }
+                        _ => parse_number(regular).unwrap_or(Token::Name(regular)),
+                    }
+                }
+            };
+        };
+        self.last_integer = match token {
+            Token::Integer(value) => Some(value),
+            _ => None,
+        };
+        Some(token)
+    }
+
+    /// Reads a number, which may be an integer or a real.
+    fn read_number(&mut self) -> Option<f32> {
+        match self.next_token()? {
+            Token::Integer(value) => Some(value as f32),
+            Token::Real(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Reads an array or procedure of numbers.
+    fn read_numbers(&mut self) -> Vec<f32> {
+        let mut numbers = vec![];
+        match self.next_token() {
+            Some(Token::ArrayStart) | Some(Token::ProcStart) => {}
+            _ => return numbers,
+        }
+        loop {
+            match self.next_token() {
+                Some(Token::Integer(value)) => numbers.push(value as f32),
+                Some(Token::Real(value)) => numbers.push(value),
+                _ => return numbers,
+            }
+        }
+    }
+
+    fn read_bool(&mut self) -> Option<bool> {
+        match self.next_token()? {
+            Token::Name(b"true") => Some(true),
+            Token::Name(b"false") => Some(false),
+            _ => None,
+        }
+    }
+
+    fn read_string_value(&mut self) -> Option<String> {
+        match self.next_token()? {
+            Token::String(string) => Some(String::from_utf8_lossy(&string).into_owned()),
+            _ => None,
+        }
+    }
+}
+
+/// Parses integers, reals and radix numbers such as `8#1777`.
+fn parse_number(token: &[u8]) -> Option<Token<'_>> {
+    let first = *token.first()?;
+    if !(first.is_ascii_digit() || first == b'-' || first == b'+' || first == b'.') {
+        return None;
+    }
+    let text = std::str::from_utf8(token).ok()?;
+    if let Ok(value) = text.parse::<i32>() {
+        return Some(Token::Integer(value));
+    }
+    if let Some(hash) = text.find('#') {
+        let radix = text[..hash]
+            .parse::<u32>()
+            .ok()
+            .filter(|r| (2..=36).contains(r))?;
+        return i32::from_str_radix(&text[hash + 1..], radix)
+            .ok()
+            .map(Token::Integer);
+    }
+    text.parse::<f32>().ok().map(Token::Real)
+}
+
+/// The entries of the `FontInfo` dictionary.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FontInfo {
+    pub version: Option<String>,
+    pub notice: Option<String>,
+    pub full_name: Option<String>,
+    pub family_name: Option<String>,
+    pub weight: Option<String>,
+    pub italic_angle: f32,
+    pub is_fixed_pitch: bool,
+    pub underline_position: f32,
+    pub underline_thickness: f32,
+}
+
+/// The hinting entries of the `Private` dictionary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateDict {
+    pub blue_values: Vec<f32>,
+    pub other_blues: Vec<f32>,
+    pub family_blues: Vec<f32>,
+    pub family_other_blues: Vec<f32>,
+    pub blue_scale: f32,
+    pub blue_shift: f32,
+    pub blue_fuzz: f32,
+    pub std_hw: Option<f32>,
+    pub std_vw: Option<f32>,
+    pub stem_snap_h: Vec<f32>,
+    pub stem_snap_v: Vec<f32>,
+    pub force_bold: bool,
+    /// The number of random bytes at the start of each encrypted charstring,
+    /// or -1 if the charstrings are not encrypted.
+    pub len_iv: i32,
+}
+
+impl Default for PrivateDict {
+    fn default() -> Self {
+        PrivateDict {
+            blue_values: vec![],
+            other_blues: vec![],
+            family_blues: vec![],
+            family_other_blues: vec![],
+            blue_scale: 0.039_625,
+            blue_shift: 7.0,
+            blue_fuzz: 1.0,
+            std_hw: None,
+            std_vw: None,
+            stem_snap_h: vec![],
+            stem_snap_v: vec![],
+            force_bold: false,
+            len_iv: 4,
+        }
+    }
+}
+
+/// The outline and metrics of a Type 1 glyph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type1Glyph {
+    /// The advance width set by `hsbw` or `sbw`.
+    pub width: Fixed16_16,
+    /// The x coordinate of the side bearing point.
+    pub left_side_bearing: Fixed16_16,
+    pub path: Vec<PathInstruction>,
+}
+
+/// A PostScript Type 1 font.
+///
+/// Examples
+/// --------
+///
+/// ```no_run
+/// use font_parse::outline::Outline;
+/// use font_parse::type1::Type1Font;
+///
+/// let data = std::fs::read("font.pfb").unwrap();
+/// let font = Type1Font::from_bytes(&data).unwrap();
+/// let glyph = font.glyph("A").unwrap().unwrap();
+/// let outline = Outline::from_path_instructions(glyph.path);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type1Font {
+    pub font_name: String,
+    pub font_info: FontInfo,
+    pub font_matrix: [f32; 6],
+    pub font_bbox: [f32; 4],
+    /// Maps character codes to glyph names.
+    pub encoding: BTreeMap<u8, String>,
+    pub private: PrivateDict,
+    /// The decrypted subroutines.
+    pub subrs: Vec<Vec<u8>>,
+    /// The glyph names and decrypted charstrings in the order of the font
+    /// program.
+    pub charstrings: Vec<(String, Vec<u8>)>,
+}
+
+impl Default for Type1Font {
+    fn default() -> Self {
+        Type1Font {
+            font_name: String::new(),
+            font_info: FontInfo::default(),
+            font_matrix: [0.001, 0.0, 0.0, 0.001, 0.0, 0.0],
+            font_bbox: [0.0; 4],
+            encoding: BTreeMap::new(),
+            private: PrivateDict::default(),
+            subrs: vec![],
+            charstrings: vec![],
+        }
+    }
+}
+
+/// The standard encoding as glyph names.
+fn standard_encoding_names() -> BTreeMap<u8, String> {
+    (0..=255u8)
+        .filter_map(|code| match standard_encoding(code) {
+            0 => None,
+            sid => Some((code, STANDARD_STRINGS[usize::from(sid)].to_string())),
+        })
+        .collect()
+}
+
+fn name_to_string(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+impl Type1Font {
+    /// Reads a font in the PFB or the PFA format.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParserError> {
+        let (clear_text, encrypted) = if data.starts_with(&[0x80, 1]) {
+            read_pfb(data)?
+        } else {
+            read_pfa(data)?
+        };
+        let private = decrypt(&encrypted, EEXEC_KEY, 4);
+
+        let mut font = Type1Font::default();
+        let mut encoding = None;
+        let mut subrs = BTreeMap::new();
+        let mut charstrings = vec![];
+        for data in &[&clear_text[..], &private[..]] {
+            font.read_dicts(data, &mut encoding, &mut subrs, &mut charstrings);
+        }
+        if charstrings.is_empty() {
+            return Err(ParserError::from_string(
+                "The font contains no charstrings.".to_string(),
+            ));
+        }
+
+        // lenIV is only known after reading the private dictionary
+        let len_iv = font.private.len_iv;
+        let decrypt_charstring = |data: &[u8]| {
+            if len_iv < 0 {
+                data.to_vec()
+            } else {
+                decrypt(data, CHARSTRING_KEY, len_iv as usize)
+            }
+        };
+        if let Some(&max_index) = subrs.keys().next_back() {
+            if max_index >= MAX_SUBRS {
+                return Err(ParserError::from_string(format!(
+                    "Subroutine {} exceeds the limit of {} subroutines.",
+                    max_index, MAX_SUBRS
+                )));
+            }
+            font.subrs = vec![vec![]; max_index + 1];
+            for (index, data) in subrs {
+                font.subrs[index] = decrypt_charstring(data);
+            }
+        }
+        font.charstrings = charstrings
+            .into_iter()
+            .map(|(name, data)| (name_to_string(name), decrypt_charstring(data)))
+            .collect();
+        font.encoding = encoding.unwrap_or_else(standard_encoding_names);
+        Ok(font)
+    }
+
+    /// Reads the entries of the font dictionaries in `data`.
+    ///
+    /// The encrypted subroutines and charstrings are collected for decryption
+    /// once `lenIV` is known.
+    fn read_dicts<'a>(
+        &mut self,
+        data: &'a [u8],
+        encoding: &mut Option<BTreeMap<u8, String>>,
+        subrs: &mut BTreeMap<usize, &'a [u8]>,
+        charstrings: &mut Vec<(&'a [u8], &'a [u8])>,
+    ) {
+        let mut lexer = Lexer::new(data);
+        // the two tokens preceding the current one
+        let mut previous: [Option<Token<'a>>; 2] = [None, None];
+        let mut in_charstrings = false;
+        while let Some(token) = lexer.next_token() {
+            match (&previous, &token) {
+                // `/name len RD <data> ND`
+                (
+                    [Some(Token::LiteralName(name)), Some(Token::Integer(_))],
+                    Token::Binary(data),
+                ) if in_charstrings => charstrings.push((*name, *data)),
+                // `dup index len RD <data> NP`
+                ([Some(Token::Integer(index)), Some(Token::Integer(_))], Token::Binary(data))
+                    if *index >= 0 && !in_charstrings =>
+                {
+                    subrs.insert(*index as usize, data);
+                }
+                (_, Token::LiteralName(key)) if !in_charstrings => match *key {
+                    b"FontName" => {
+                        if let Some(Token::LiteralName(name)) = lexer.next_token() {
+                            self.font_name = name_to_string(name);
+                        }
+                    }
+                    b"FontMatrix" => {
+                        let matrix = lexer.read_numbers();
+                        if matrix.len() == 6 {
+                            self.font_matrix.copy_from_slice(&matrix);
+                        }
+                    }
+                    b"FontBBox" => {
+                        let bbox = lexer.read_numbers();
+                        if bbox.len() == 4 {
+                            self.font_bbox.copy_from_slice(&bbox);
+                        }
+                    }
+                    b"Encoding" => *encoding = read_encoding(&mut lexer),
+                    b"version" => self.font_info.version = lexer.read_string_value(),
+                    b"Notice" => self.font_info.notice = lexer.read_string_value(),
+                    b"FullName" => self.font_info.full_name = lexer.read_string_value(),
+                    b"FamilyName" => self.font_info.family_name = lexer.read_string_value(),
+                    b"Weight" => self.font_info.weight = lexer.read_string_value(),
+                    b"ItalicAngle" => read_into(&mut lexer, &mut self.font_info.italic_angle),
+                    b"isFixedPitch" => {
+                        if let Some(value) = lexer.read_bool() {
+                            self.font_info.is_fixed_pitch = value;
+                        }
+                    }
+                    b"UnderlinePosition" => {
+                        read_into(&mut lexer, &mut self.font_info.underline_position)
+                    }
+                    b"UnderlineThickness" => {
+                        read_into(&mut lexer, &mut self.font_info.underline_thickness)
+                    }
+                    b"BlueValues" => self.private.blue_values = lexer.read_numbers(),
+                    b"OtherBlues" => self.private.other_blues = lexer.read_numbers(),
+                    b"FamilyBlues" => self.private.family_blues = lexer.read_numbers(),
+                    b"FamilyOtherBlues" => self.private.family_other_blues = lexer.read_numbers(),
+                    b"BlueScale" => read_into(&mut lexer, &mut self.private.blue_scale),
+                    b"BlueShift" => read_into(&mut lexer, &mut self.private.blue_shift),
+                    b"BlueFuzz" => read_into(&mut lexer, &mut self.private.blue_fuzz),
+                    b"StdHW" => self.private.std_hw = lexer.read_numbers().first().cloned(),
+                    b"StdVW" => self.private.std_vw = lexer.read_numbers().first().cloned(),
+                    b"StemSnapH" => self.private.stem_snap_h = lexer.read_numbers(),
+                    b"StemSnapV" => self.private.stem_snap_v = lexer.read_numbers(),
+                    b"ForceBold" => {
+                        if let Some(value) = lexer.read_bool() {
+                            self.private.force_bold = value;
+                        }
+                    }
+                    b"lenIV" => {
+                        if let Some(Token::Integer(value)) = lexer.next_token() {
+                            self.private.len_iv = value;
+                        }
+                    }
+                    b"CharStrings" => in_charstrings = true,
+                    _ => {}
+                },
+                _ => {}
+            }
+            previous = [previous[1].take(), Some(token)];
+        }
+    }
+
+    /// Returns the number of glyphs.
+    pub fn num_glyphs(&self) -> usize {
+        self.charstrings.len()
+    }
+
+    /// Returns the names of all glyphs in the order of the font program.
+    pub fn glyph_names(&self) -> impl Iterator<Item = &str> {
+        self.charstrings.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the decrypted charstring of the glyph with the given name.
+    pub fn charstring(&self, name: &str) -> Option<&[u8]> {
+        self.charstrings
+            .iter()
+            .find(|(glyph_name, _)| glyph_name == name)
+            .map(|(_, charstring)| charstring.as_slice())
+    }
+
+    /// Interprets the charstring of the glyph with the given name.
+    ///
+    /// Returns `Ok(None)` if the font has no glyph with this name.
+    pub fn glyph(&self, name: &str) -> Result<Option<Type1Glyph>, ParserError> {
+        let charstring = match self.charstring(name) {
+            Some(charstring) => charstring,
+            None => return Ok(None),
+        };
+        let mut interpreter = Interpreter::new(self);
+        interpreter.run(charstring, 0)?;
+        if interpreter.open {
+            interpreter.path.push(PathInstruction::Close);
+        }
+        Ok(Some(Type1Glyph {
+            width: Fixed16_16::from(interpreter.width),
+            left_side_bearing: Fixed16_16::from(interpreter.left_side_bearing),
+            path: interpreter.path,
+        }))
+    }
+
+    /// Returns the outline of the glyph with the given name.
+    pub fn outline(&self, name: &str) -> Result<Option<Outline>, ParserError> {
+        Ok(self
+            .glyph(name)?
+            .map(|glyph| Outline::from_path_instructions(glyph.path)))
+    }
+
+    /// Converts the font into a CFF font program.
+    ///
+    /// `.notdef` becomes the first glyph and the advance widths are rounded.
+    /// Glyphs of fonts with a `FontMatrix` other than the default are
+    /// transformed into a 1000 unit em. Hints and the encoding are not
+    /// converted, so the CFF font program uses the standard encoding.
+    ///
+    /// [`CffFont::write_otf`](../cff_font/struct.CffFont.html#method.write_otf)
+    /// turns the result into an OpenType font file.
+    pub fn to_cff(&self) -> Result<Vec<u8>, ParserError> {
+        let info = &self.font_info;
+        let mut builder = CffBuilder::new(&self.font_name);
+        builder.version = info.version.clone().unwrap_or_default();
+        builder.notice = info.notice.clone().unwrap_or_default();
+        builder.full_name = info.full_name.clone().unwrap_or_default();
+        builder.family_name = info.family_name.clone().unwrap_or_default();
+        builder.weight = info.weight.clone().unwrap_or_default();
+        builder.italic_angle = info.italic_angle;
+        builder.is_fixed_pitch = info.is_fixed_pitch;
+
+        let matrix = Type1Font::default().font_matrix;
+        let transform = if self.font_matrix == matrix {
+            None
+        } else {
+            let mut transform = self.font_matrix;
+            for value in &mut transform {
+                *value *= 1000.0;
+            }
+            Some(transform)
+        };
+
+        let names = Some(".notdef")
+            .into_iter()
+            .chain(self.glyph_names().filter(|&name| name != ".notdef"));
+        for name in names {
+            let (width, path) = match self.glyph(name)? {
+                Some(glyph) => (f32::from(glyph.width), glyph.path),
+                // fonts without .notdef get an empty one
+                None => (0.0, vec![]),
+            };
+            let (width, path) = match transform {
+                Some(transform) => (
+                    width * transform[0],
+                    Outline::from_path_instructions(path)
+                        .transform(transform)
+                        .to_path_instructions(),
+                ),
+                None => (width, path),
+            };
+            builder.add_glyph(name, width.round() as i32, &path);
+        }
+
+        let mut data = vec![];
+        builder.write_to(&mut data).map_err(ParserError::from_err)?;
+        Ok(data)
+    }
+}
+
+fn read_into(lexer: &mut Lexer<'_>, value: &mut f32) {
+    if let Some(number) = lexer.read_number() {
+        *value = number;
+    }
+}
+
+/// Reads `StandardEncoding` or an encoding array built with
+/// `dup code /name put`.
+fn read_encoding(lexer: &mut Lexer<'_>) -> Option<BTreeMap<u8, String>> {
+    match lexer.next_token()? {
+        Token::Name(b"StandardEncoding") => return Some(standard_encoding_names()),
+        Token::Integer(_) => {}
+        _ => return None,
+    }
+    let mut encoding = BTreeMap::new();
+    let mut previous: [Option<Token<'_>>; 2] = [None, None];
+    while let Some(token) = lexer.next_token() {
+        match (&previous, &token) {
+            ([Some(Token::Integer(code)), Some(Token::LiteralName(name))], Token::Name(b"put"))
+                if (0..=255).contains(code) && *name != b".notdef" =>
+            {
+                encoding.insert(*code as u8, name_to_string(name));
+            }
+            (_, Token::Name(b"def")) => break,
+            _ => {}
+        }
+        previous = [previous[1].take(), Some(token)];
+    }
+    Some(encoding)
+}
+
+type Point = (f32, f32);
+
+/// Interprets Type 1 charstrings into relative path instructions.
+#[derive(Debug)]
+struct Interpreter<'f> {
+    font: &'f Type1Font,
+    stack: Vec<f32>,
+    // the PostScript operand stack used by `callothersubr` and `pop`
+    ps_stack: Vec<f32>,
+    // the current point
+    x: f32,
+    y: f32,
+    // added to all points, used to place the accent of `seac`
+    offset: Point,
+    // the last point written to the path
+    cursor: (Fixed16_16, Fixed16_16),
+    // the points collected between the start and the end of a flex
+    flex: Option<Vec<Point>>,
+    width: f32,
+    left_side_bearing: f32,
+    open: bool,
+    in_seac: bool,
+    path: Vec<PathInstruction>,
+}
+
+impl<'f> Interpreter<'f> {
+    fn new(font: &'f Type1Font) -> Self {
+        Interpreter {
+            font,
+            stack: vec![],
+            ps_stack: vec![],
+            x: 0.0,
+            y: 0.0,
+            offset: (0.0, 0.0),
+            cursor: (0.into(), 0.into()),
+            flex: None,
+            width: 0.0,
+            left_side_bearing: 0.0,
+            open: false,
+            in_seac: false,
+            path: vec![],
+        }
+    }
+
+    fn args(&self, count: usize) -> Result<&[f32], ParserError> {
+        if self.stack.len() < count {
+            return Err(ParserError::from_string(format!(
+                "Type 1 charstring operator expects {} operands, found {}.",
+                count,
+                self.stack.len()
+            )));
+        }
+        Ok(&self.stack[..count])
+    }
+
+    fn pop(&mut self) -> Result<f32, ParserError> {
+        self.stack.pop().ok_or_else(|| {
+            ParserError::from_string("Type 1 charstring stack underflow.".to_string())
+        })
+    }
+
+    /// Returns the offset of a point from the last point of the path.
+    fn delta(&mut self, (x, y): Point) -> (Fixed16_16, Fixed16_16) {
+        let point = (
+            Fixed16_16::from(x + self.offset.0),
+            Fixed16_16::from(y + self.offset.1),
+        );
+        let delta = (point.0 - self.cursor.0, point.1 - self.cursor.1);
+        self.cursor = point;
+        delta
+    }
+
+    fn move_by(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        // the points of a flex are collected by othersubr 2
+        if self.flex.is_some() {
+            return;
+        }
+        if self.open {
+            self.path.push(PathInstruction::Close);
+        }
+        let (dx, dy) = self.delta((self.x, self.y));
+        self.path.push(PathInstruction::MoveTo(dx, dy));
+        self.open = true;
+    }
+
+    fn line_by(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        let (dx, dy) = self.delta((self.x, self.y));
+        self.path.push(PathInstruction::LineTo(dx, dy));
+    }
+
+    fn curve_to(&mut self, c1: Point, c2: Point, end: Point) {
+        let (dx1, dy1) = self.delta(c1);
+        let (dx2, dy2) = self.delta(c2);
+        let (dx, dy) = self.delta(end);
+        self.path
+            .push(PathInstruction::CurveTo(dx1, dy1, dx2, dy2, dx, dy));
+        self.x = end.0;
+        self.y = end.1;
+    }
+
+    fn curve_by(&mut self, dx1: f32, dy1: f32, dx2: f32, dy2: f32, dx3: f32, dy3: f32) {
+        let c1 = (self.x + dx1, self.y + dy1);
+        let c2 = (c1.0 + dx2, c1.1 + dy2);
+        let end = (c2.0 + dx3, c2.1 + dy3);
+        self.curve_to(c1, c2, end);
+    }
+
+    fn call_other_subr(&mut self) -> Result<(), ParserError> {
+        let other_subr = self.pop()? as i32;
+        let num_args = self.pop()? as usize;
+        if num_args > self.stack.len() {
+            return Err(ParserError::from_string(
+                "Type 1 charstring stack underflow.".to_string(),
+            ));
+        }
+        let args = self.stack.split_off(self.stack.len() - num_args);
+        match other_subr {
+            // end of a flex: the reference point and six curve points
+            0 => {
+                let points = self.flex.take().unwrap_or_default();
+                if points.len() != 7 || args.len() != 3 {
+                    return Err(ParserError::from_string("Invalid flex.".to_string()));
+                }
+                self.curve_to(points[1], points[2], points[3]);
+                self.curve_to(points[4], points[5], points[6]);
+                // the end point is returned for `setcurrentpoint`
+                self.ps_stack.push(args[2]);
+                self.ps_stack.push(args[1]);
+            }
+            1 => self.flex = Some(vec![]),
+            2 => match self.flex {
+                Some(ref mut points) => points.push((self.x, self.y)),
+                None => {
+                    return Err(ParserError::from_string(
+                        "Flex point outside of a flex.".to_string(),
+                    ))
+                }
+            },
+            // other subroutines return their arguments, for hint replacement
+            // (3) this is the subroutine called with `pop callsubr`
+            _ => self.ps_stack.extend(args.iter().rev()),
+        }
+        Ok(())
+    }
+
+    fn seac(&mut self, depth: usize) -> Result<(), ParserError> {
+        let a = self.args(5)?;
+        let (asb, adx, ady, bchar, achar) = (a[0], a[1], a[2], a[3], a[4]);
+        if self.in_seac {
+            return Err(ParserError::from_string("Nested seac.".to_string()));
+        }
+        let font = self.font;
+        let component = |code: f32| {
+            let name = STANDARD_STRINGS[usize::from(standard_encoding(code as u8))];
+            font.charstring(name).ok_or_else(|| {
+                ParserError::from_string(format!("Missing seac component {}.", name))
+            })
+        };
+        let (base, accent) = (component(bchar)?, component(achar)?);
+        let (width, left_side_bearing) = (self.width, self.left_side_bearing);
+        self.in_seac = true;
+        for &(charstring, offset) in &[(base, (0.0, 0.0)), (accent, (adx - asb, ady))] {
+            self.stack.clear();
+            self.x = 0.0;
+            self.y = 0.0;
+            self.offset = offset;
+            self.run(charstring, depth)?;
+            if self.open {
+                self.path.push(PathInstruction::Close);
+                self.open = false;
+            }
+        }
+        self.width = width;
+        self.left_side_bearing = left_side_bearing;
+        Ok(())
+    }
+
+    /// Runs a charstring and returns whether `endchar` or `seac` finished the
+    /// glyph.
+    fn run(&mut self, code: &[u8], depth: usize) -> Result<bool, ParserError> {
+        let mut code = code.iter().cloned();
+        let mut next = || {
+            code.next().ok_or_else(|| {
+                ParserError::from_string("Unexpected end of Type 1 charstring.".to_string())
+            })
+        };
+        loop {
+            let byte = match next() {
+                Ok(byte) => byte,
+                // charstrings of subroutines may end without `return`
+                Err(_) if depth > 0 => return Ok(false),
+                Err(err) => return Err(err),
+            };
+            let value = match byte {
+                32..=246 => Some(i32::from(byte) - 139),
+                247..=250 => Some((i32::from(byte) - 247) * 256 + i32::from(next()?) + 108),
+                251..=254 => Some(-(i32::from(byte) - 251) * 256 - i32::from(next()?) - 108),
+                255 => {
+                    let bytes = [next()?, next()?, next()?, next()?];
+                    Some(i32::from_be_bytes(bytes))
+                }
+                _ => None,
+            };
+            if let Some(value) = value {
+                self.stack.push(value as f32);
+                continue;
+            }
+            match byte {
+                // hstem, vstem
+                1 | 3 => {}
+                // vmoveto
+                4 => {
+                    let dy = self.args(1)?[0];
+                    self.move_by(0.0, dy);
+                }
+                // rlineto
+                5 => {
+                    let (dx, dy) = (self.args(2)?[0], self.args(2)?[1]);
+                    self.line_by(dx, dy);
+                }
+                // hlineto
+                6 => {
+                    let dx = self.args(1)?[0];
+                    self.line_by(dx, 0.0);
+                }
+                // vlineto
+                7 => {
+                    let dy = self.args(1)?[0];
+                    self.line_by(0.0, dy);
+                }
+                // rrcurveto
+                8 => {
+                    let a = self.args(6)?.to_vec();
+                    self.curve_by(a[0], a[1], a[2], a[3], a[4], a[5]);
+                }
+                // closepath, which does not change the current point
+                9 => {
+                    if self.open {
+                        self.path.push(PathInstruction::Close);
+                        self.open = false;
+                    }
+                }
+                // callsubr
+                10 => {
+                    let index = self.pop()?;
+                    let font = self.font;
+                    let subr = font
+                        .subrs
+                        .get(index as usize)
+                        .filter(|_| index >= 0.0)
+                        .ok_or_else(|| {
+                            ParserError::from_string(format!("Missing subroutine {}.", index))
+                        })?;
+                    if depth >= SUBROUTINE_MAX_DEPTH {
+                        return Err(ParserError::from_string(
+                            "Type 1 subroutines are nested too deeply.".to_string(),
+                        ));
+                    }
+                    if self.run(subr, depth + 1)? {
+                        return Ok(true);
+                    }
+                    continue;
+                }
+                // return
+                11 => return Ok(false),
+                12 => match next()? {
+                    // dotsection, vstem3, hstem3
+                    0..=2 => {}
+                    6 => {
+                        self.seac(depth)?;
+                        return Ok(true);
+                    }
+                    // sbw
+                    7 => {
+                        let a = self.args(4)?;
+                        let (sbx, sby, wx) = (a[0], a[1], a[2]);
+                        self.left_side_bearing = sbx;
+                        self.width = wx;
+                        self.x = sbx;
+                        self.y = sby;
+                    }
+                    // div
+                    12 => {
+                        let divisor = self.pop()?;
+                        let dividend = self.pop()?;
+                        if divisor == 0.0 {
+                            return Err(ParserError::from_string(
+                                "Division by zero in Type 1 charstring.".to_string(),
+                            ));
+                        }
+                        self.stack.push(dividend / divisor);
+                        continue;
+                    }
+                    16 => {
+                        self.call_other_subr()?;
+                        continue;
+                    }
+                    // pop
+                    17 => {
+                        let value = self.ps_stack.pop().ok_or_else(|| {
+                            ParserError::from_string(
+                                "Type 1 PostScript stack underflow.".to_string(),
+                            )
+                        })?;
+                        self.stack.push(value);
+                        continue;
+                    }
+                    // setcurrentpoint
+                    33 => {
+                        let (x, y) = (self.args(2)?[0], self.args(2)?[1]);
+                        self.x = x;
+                        self.y = y;
+                    }
+                    op => warn!("Unknown Type 1 charstring operator 12 {}", op),
+                },
+                // hsbw
+                13 => {
+                    let a = self.args(2)?;
+                    let (sbx, wx) = (a[0], a[1]);
+                    self.left_side_bearing = sbx;
+                    self.width = wx;
+                    self.x = sbx;
+                    self.y = 0.0;
+                }
+                // endchar
+                14 => return Ok(true),
+                // rmoveto
+                21 => {
+                    let (dx, dy) = (self.args(2)?[0], self.args(2)?[1]);
+                    self.move_by(dx, dy);
+                }
+                // hmoveto
+                22 => {
+                    let dx = self.args(1)?[0];
+                    self.move_by(dx, 0.0);
+                }
+                // vhcurveto
+                30 => {
+                    let a = self.args(4)?.to_vec();
+                    self.curve_by(0.0, a[0], a[1], a[2], a[3], 0.0);
+                }
+                // hvcurveto
+                31 => {
+                    let a = self.args(4)?.to_vec();
+                    self.curve_by(a[0], 0.0, a[1], a[2], 0.0, a[3]);
+                }
+                op => warn!("Unknown Type 1 charstring operator {}", op),
+            }
+            self.stack.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encrypt(data: &[u8], key: u16) -> Vec<u8> {
+        let mut r = key;
+        data.iter()
+            .map(|&plain| {
+                let cipher = plain ^ (r >> 8) as u8;
+                r = u16::from(cipher)
+                    .wrapping_add(r)
+                    .wrapping_mul(52845)
+                    .wrapping_add(22719);
+                cipher
+            })
+            .collect()
+    }
+
+    /// Assembles and encrypts a charstring such as `0 500 hsbw endchar`.
+    fn charstring(program: &str) -> Vec<u8> {
+        let mut out = vec![0; 4];
+        for word in program.split_whitespace() {
+            if let Ok(value) = word.parse::<i32>() {
+                match value {
+                    -107..=107 => out.push((value + 139) as u8),
+                    108..=1131 => {
+                        let value = value - 108;
+                        out.extend_from_slice(&[(value >> 8) as u8 + 247, value as u8]);
+                    }
+                    -1131..=-108 => {
+                        let value = -value - 108;
+                        out.extend_from_slice(&[(value >> 8) as u8 + 251, value as u8]);
+                    }
+                    _ => {
+                        out.push(255);
+                        out.extend_from_slice(&value.to_be_bytes());
+                    }
+                }
+                continue;
+            }
+            let op: &[u8] = match word {
+                "hstem" => &[1],
+                "rlineto" => &[5],
+                "closepath" => &[9],
+                "callsubr" => &[10],
+                "return" => &[11],
+                "seac" => &[12, 6],
+                "sbw" => &[12, 7],
+                "div" => &[12, 12],
+                "callothersubr" => &[12, 16],
+                "pop" => &[12, 17],
+                "setcurrentpoint" => &[12, 33],
+                "hsbw" => &[13],
+                "endchar" => &[14],
+                "rmoveto" => &[21],
+                "hvcurveto" => &[31],
+                _ => panic!("unknown operator {}", word),
+            };
+            out.extend_from_slice(op);
+        }
+        encrypt(&out, CHARSTRING_KEY)
+    }
+
+    const SUBRS: [&str; 6] = [
+        "3 0 callothersubr pop pop setcurrentpoint return",
+        "0 1 callothersubr return",
+        "0 2 callothersubr return",
+        "return",
+        "100 50 hstem return",
+        "-250 700 rlineto return",
+    ];
+
+    const CHARSTRINGS: [(&str, &str); 5] = [
+        (".notdef", "0 500 hsbw endchar"),
+        (
+            "A",
+            "50 600 hsbw 4 1 3 callothersubr pop callsubr \
+             0 0 rmoveto 1000 2 div 0 rlineto 5 callsubr closepath endchar",
+        ),
+        (
+            "acute",
+            "0 0 300 0 sbw 100 600 rmoveto 100 100 rlineto -50 0 rlineto closepath endchar",
+        ),
+        ("Aacute", "50 600 hsbw 50 150 100 65 194 seac"),
+        (
+            "flex",
+            "0 500 hsbw 0 0 rmoveto 1 callsubr \
+             200 0 rmoveto 2 callsubr -150 0 rmoveto 2 callsubr \
+             50 10 rmoveto 2 callsubr 100 0 rmoveto 2 callsubr \
+             100 0 rmoveto 2 callsubr 50 -10 rmoveto 2 callsubr \
+             50 0 rmoveto 2 callsubr 50 400 0 0 callsubr \
+             0 100 rlineto 50 50 50 50 hvcurveto closepath endchar",
+        ),
+    ];
+
+    const CLEAR_TEXT: &str = "%!PS-AdobeFont-1.0: Test-Regular 001.000
+%%CreationDate: today
+11 dict begin
+/FontInfo 9 dict dup begin
+/version (001.000) readonly def
+/Notice (Copyright \\(c\\) nobody) readonly def
+/FullName (Test Regular) readonly def
+/FamilyName (Test) readonly def
+/Weight (Regular) readonly def
+/ItalicAngle -12.5 def
+/isFixedPitch false def
+/UnderlinePosition -100 def
+/UnderlineThickness 50 def
+end readonly def
+/FontName /Test-Regular def
+/PaintType 0 def
+/FontType 1 def
+/FontMatrix [0.001 0 0 0.001 0 0] readonly def
+/Encoding 256 array
+0 1 255 {1 index exch /.notdef put} for
+dup 65 /A put
+dup 8#301 /Aacute put
+dup 194 /acute put
+readonly def
+/FontBBox {0 -200 1000 800} readonly def
+currentdict end
+currentfile eexec
+";
+
+    /// Returns the clear text and the encrypted part of the test font.
+    fn font_program() -> (Vec<u8>, Vec<u8>) {
+        let mut private = b"dup /Private 8 dict dup begin
+/RD {string currentfile exch readstring pop} executeonly def
+/ND {noaccess def} executeonly def
+/NP {noaccess put} executeonly def
+/BlueValues [-10 0 500 510] def
+/BlueScale 0.05 def
+/StdHW [50] def
+/StemSnapV [60 70] def
+/ForceBold true def
+/lenIV 4 def
+"
+        .to_vec();
+        private.extend_from_slice(format!("/Subrs {} array\n", SUBRS.len()).as_bytes());
+        for (index, program) in SUBRS.iter().enumerate() {
+            let data = charstring(program);
+            private.extend_from_slice(format!("dup {} {} RD ", index, data.len()).as_bytes());
+            private.extend_from_slice(&data);
+            private.extend_from_slice(b" NP\n");
+        }
+        private.extend_from_slice(b"ND\n2 index /CharStrings 5 dict dup begin\n");
+        for (name, program) in CHARSTRINGS.iter() {
+            let data = charstring(program);
+            private.extend_from_slice(format!("/{} {} -| ", name, data.len()).as_bytes());
+            private.extend_from_slice(&data);
+            private.extend_from_slice(b" |-\n");
+        }
+        private.extend_from_slice(
+            b"end\nend\nreadonly put\nnoaccess put\n\
+              dup /FontName get exch definefont pop\nmark currentfile closefile\n",
+        );
+        let mut plain = vec![0; 4];
+        plain.extend_from_slice(&private);
+        (CLEAR_TEXT.as_bytes().to_vec(), encrypt(&plain, EEXEC_KEY))
+    }
+
+    fn trailer() -> Vec<u8> {
+        let mut trailer = vec![];
+        for _ in 0..8 {
+            trailer.extend_from_slice(&[b'0'; 64]);
+            trailer.push(b'\n');
+        }
+        trailer.extend_from_slice(b"cleartomark\n");
+        trailer
+    }
+
+    fn pfb() -> Vec<u8> {
+        let (clear_text, encrypted) = font_program();
+        let mut out = vec![];
+        for &(segment_type, ref data) in &[(1, clear_text), (2, encrypted), (1, trailer())] {
+            out.extend_from_slice(&[0x80, segment_type]);
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(data);
+        }
+        out.extend_from_slice(&[0x80, 3]);
+        out
+    }
+
+    fn pfa(hex: bool) -> Vec<u8> {
+        let (mut out, encrypted) = font_program();
+        if hex {
+            for line in encrypted.chunks(32) {
+                for byte in line {
+                    out.extend_from_slice(format!("{:02x}", byte).as_bytes());
+                }
+                out.push(b'\n');
+            }
+        } else {
+            out.extend_from_slice(&encrypted);
+        }
+        out.extend_from_slice(&trailer());
+        out
+    }
+
+    /// Returns the on-curve and off-curve points of the path.
+    fn points(path: &[PathInstruction]) -> Vec<(f32, f32)> {
+        let mut points = vec![];
+        let (mut x, mut y) = (0.0, 0.0);
+        let mut add = |dx: Fixed16_16, dy: Fixed16_16| {
+            x += f32::from(dx);
+            y += f32::from(dy);
+            points.push((x, y));
+        };
+        for &instruction in path {
+            match instruction {
+                PathInstruction::MoveTo(dx, dy) | PathInstruction::LineTo(dx, dy) => add(dx, dy),
+                PathInstruction::CurveTo(dx1, dy1, dx2, dy2, dx, dy) => {
+                    add(dx1, dy1);
+                    add(dx2, dy2);
+                    add(dx, dy);
+                }
+                PathInstruction::Close => {}
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let data = b"some charstring data";
+        let encrypted = encrypt(data, CHARSTRING_KEY);
+        assert_ne!(&encrypted[..], &data[..]);
+        assert_eq!(decrypt(&encrypted, CHARSTRING_KEY, 0), &data[..]);
+        assert_eq!(decrypt(&encrypted, CHARSTRING_KEY, 5), &data[5..]);
+    }
+
+    #[test]
+    fn test_lexer() {
+        let mut lexer =
+            Lexer::new(b"/Name 12 -3.5 16#1F (a\\(b\\)\\101) <4142> [{}] % comment\n 3 RD xyz def");
+        let tokens: Vec<_> = std::iter::from_fn(|| lexer.next_token()).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::LiteralName(b"Name"),
+                Token::Integer(12),
+                Token::Real(-3.5),
+                Token::Integer(31),
+                Token::String(b"a(b)A".to_vec()),
+                Token::String(b"AB".to_vec()),
+                Token::ArrayStart,
+                Token::ProcStart,
+                Token::ProcEnd,
+                Token::ArrayEnd,
+                Token::Integer(3),
+                Token::Binary(b"xyz"),
+                Token::Name(b"def"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_stray_delimiters() {
+        let mut data = vec![b')'; 500_000];
+        data.extend_from_slice(b"> /Name");
+        let mut lexer = Lexer::new(&data);
+        assert_eq!(lexer.next_token(), Some(Token::LiteralName(b"Name")));
+        assert_eq!(lexer.next_token(), None);
+    }
+
+    #[test]
+    fn test_read_type1() {
+        let font = Type1Font::from_bytes(&pfb()).unwrap();
+        assert_eq!(font.font_name, "Test-Regular");
+        assert_eq!(font.font_info.full_name.as_ref().unwrap(), "Test Regular");
+        assert_eq!(
+            font.font_info.notice.as_ref().unwrap(),
+            "Copyright (c) nobody"
+        );
+        assert_eq!(font.font_info.italic_angle, -12.5);
+        assert_eq!(font.font_info.underline_position, -100.0);
+        assert_eq!(font.font_matrix, [0.001, 0.0, 0.0, 0.001, 0.0, 0.0]);
+        assert_eq!(font.font_bbox, [0.0, -200.0, 1000.0, 800.0]);
+        let encoding: Vec<_> = font
+            .encoding
+            .iter()
+            .map(|(&code, name)| (code, name.as_str()))
+            .collect();
+        assert_eq!(encoding, vec![(65, "A"), (193, "Aacute"), (194, "acute")]);
+        assert_eq!(font.private.blue_values, vec![-10.0, 0.0, 500.0, 510.0]);
+        assert_eq!(font.private.blue_scale, 0.05);
+        assert_eq!(font.private.std_hw, Some(50.0));
+        assert_eq!(font.private.std_vw, None);
+        assert_eq!(font.private.stem_snap_v, vec![60.0, 70.0]);
+        assert!(font.private.force_bold);
+        assert_eq!(font.subrs.len(), SUBRS.len());
+        assert_eq!(
+            font.glyph_names().collect::<Vec<_>>(),
+            vec![".notdef", "A", "acute", "Aacute", "flex"]
+        );
+
+        for &hex in &[true, false] {
+            assert_eq!(Type1Font::from_bytes(&pfa(hex)).unwrap(), font);
+        }
+        assert!(Type1Font::from_bytes(CLEAR_TEXT.as_bytes()).is_err());
+        assert!(Type1Font::from_bytes(&pfb()[..100]).is_err());
+
+        // subroutine indices are not trusted for allocating the subroutines
+        let (_, encrypted) = font_program();
+        let mut data = CLEAR_TEXT
+            .replace(
+                "currentfile eexec",
+                "dup 2000000000 1 RD x NP\ncurrentfile eexec",
+            )
+            .into_bytes();
+        data.extend_from_slice(&encrypted);
+        data.extend_from_slice(&trailer());
+        assert!(Type1Font::from_bytes(&data).is_err());
+    }
+
+    #[test]
+    fn test_type1_glyphs() {
+        let font = Type1Font::from_bytes(&pfb()).unwrap();
+        assert!(font.glyph("missing").unwrap().is_none());
+
+        let notdef = font.glyph(".notdef").unwrap().unwrap();
+        assert_eq!(notdef.width, Fixed16_16::from(500));
+        assert!(notdef.path.is_empty());
+
+        let a = font.glyph("A").unwrap().unwrap();
+        assert_eq!(a.width, Fixed16_16::from(600));
+        assert_eq!(a.left_side_bearing, Fixed16_16::from(50));
+        let a_points = vec![(50.0, 0.0), (550.0, 0.0), (300.0, 700.0)];
+        assert_eq!(points(&a.path), a_points);
+        assert_eq!(a.path.last(), Some(&PathInstruction::Close));
+
+        let acute = font.glyph("acute").unwrap().unwrap();
+        assert_eq!(acute.width, Fixed16_16::from(300));
+        let acute_points = vec![(100.0, 600.0), (200.0, 700.0), (150.0, 700.0)];
+        assert_eq!(points(&acute.path), acute_points);
+
+        // the accent is moved by adx - asb
+        let aacute = font.glyph("Aacute").unwrap().unwrap();
+        assert_eq!(aacute.width, Fixed16_16::from(600));
+        let mut expected = a_points;
+        expected.extend(acute_points.iter().map(|&(x, y)| (x + 100.0, y + 100.0)));
+        assert_eq!(points(&aacute.path), expected);
+        let closes = aacute
+            .path
+            .iter()
+            .filter(|&&instr| instr == PathInstruction::Close)
+            .count();
+        assert_eq!(closes, 2);
+
+        // flex is drawn as two curves
+        let flex = font.glyph("flex").unwrap().unwrap();
+        assert_eq!(
+            points(&flex.path),
+            vec![
+                (0.0, 0.0),
+                (50.0, 0.0),
+                (100.0, 10.0),
+                (200.0, 10.0),
+                (300.0, 10.0),
+                (350.0, 0.0),
+                (400.0, 0.0),
+                (400.0, 100.0),
+                (450.0, 100.0),
+                (500.0, 150.0),
+                (500.0, 200.0),
+            ]
+        );
+        assert!(font.outline("flex").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_invalid_charstrings() {
+        let mut font = Type1Font::from_bytes(&pfb()).unwrap();
+        let decrypt = |program: &str| decrypt(&charstring(program), CHARSTRING_KEY, 4);
+        for program in &[
+            "0 500 hsbw 9 callsubr endchar",
+            "0 500 hsbw rlineto endchar",
+            "0 500 hsbw 1 0 div endchar",
+            "0 500 hsbw 0 0 rmoveto",
+            "0 500 hsbw 0 0 50 66 65 seac",
+        ] {
+            font.charstrings[0].1 = decrypt(program);
+            assert!(font.glyph(".notdef").is_err(), "{}", program);
+        }
+
+        // endless recursion is stopped
+        font.subrs[3] = decrypt("3 callsubr");
+        font.charstrings[0].1 = decrypt("0 500 hsbw 3 callsubr endchar");
+        assert!(font.glyph(".notdef").is_err());
+    }
+
+    #[test]
+    fn test_type1_to_otf() {
+        use crate::cff_font::CffFont;
+        use crate::tables::cmap::Cmap;
+        use crate::tables::name::{self, Name};
+        use crate::tables::post::Post;
+        use crate::{Font, OpentypeTableAccess, ParseTable};
+
+        let mut font = Type1Font::from_bytes(&pfb()).unwrap();
+        let cff_data = font.to_cff().unwrap();
+        let cff_font = CffFont::from_bytes(&cff_data).unwrap();
+        let cff = cff_font.cff();
+        assert_eq!(cff.name, "Test-Regular");
+        assert_eq!(cff.top_dict_data.italic_angle, -12.5);
+        let names: Vec<_> = (0..cff.num_glyphs())
+            .map(|index| cff.glyph_name(index).unwrap())
+            .collect();
+        assert_eq!(names, vec![".notdef", "A", "acute", "Aacute", "flex"]);
+        let mut glyphs = (&cff_font).glyphs().unwrap();
+        assert_eq!(glyphs.outline(3).unwrap(), font.outline("Aacute").unwrap());
+
+        let mut otf = vec![];
+        cff_font.write_otf(&mut otf).unwrap();
+        let otf = Font::from_bytes(&otf, 0).unwrap();
+        let cmap = otf.parse_table::<Cmap>().unwrap();
+        let mappings: Vec<_> = cmap.get().unwrap().mappings().into_iter().collect();
+        assert_eq!(mappings, vec![(0x41, 1), (0xB4, 2), (0xC1, 3)]);
+        let name = otf.parse_table::<Name>().unwrap();
+        let name = name.get().unwrap();
+        assert_eq!(name.string(name::FAMILY_NAME).unwrap(), "Test");
+        assert_eq!(name.string(name::SUBFAMILY_NAME).unwrap(), "Italic");
+        assert_eq!(name.string(name::VERSION).unwrap(), "Version 001.000");
+        assert_eq!(name.string(name::POSTSCRIPT_NAME).unwrap(), "Test-Regular");
+        let post = otf.parse_table::<Post>().unwrap();
+        let post = post.get().unwrap();
+        assert_eq!(post.version, 0x0003_0000);
+        assert_eq!(post.italic_angle, -12 * 65536 - 32768);
+        assert_eq!(post.underline_position, -75);
+
+        // glyphs are scaled into a 1000 unit em
+        font.font_matrix = [0.002, 0.0, 0.0, 0.002, 0.0, 0.0];
+        let cff_data = font.to_cff().unwrap();
+        let cff_font = CffFont::from_bytes(&cff_data).unwrap();
+        let mut glyphs = (&cff_font).glyphs().unwrap();
+        let expected = font.outline("A").unwrap().unwrap();
+        assert_eq!(
+            glyphs.outline(1).unwrap(),
+            Some(expected.transform([2.0, 0.0, 0.0, 2.0, 0.0, 0.0]))
+        );
+    }
+}
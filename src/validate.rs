@@ -0,0 +1,741 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Validation of untrusted fonts.
+//!
+//! [`validate`](fn.validate.html) parses the tables which are needed to
+//! display a font and checks that they are consistent with each other, for
+//! example that `loca`, `hmtx` and `CFF ` describe as many glyphs as `maxp`
+//! and that `cmap` only maps to existing glyphs. Every problem is reported
+//! with the table and, where it applies, the glyph it was found in.
+//!
+//! [`sanitize`](fn.sanitize.html) writes a copy of a font without the tables
+//! that failed validation.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+
+use crate::cff::GlyphAccessor as CffGlyphAccessor;
+use crate::outline::Outline;
+use crate::tables::cff::Cff;
+use crate::tables::cmap::{Cmap, CmapSubtable};
+use crate::tables::glyf::{parse_composite_glyph, parse_header, parse_simple_glyph};
+use crate::tables::head::Head;
+use crate::tables::hhea::Hhea;
+use crate::tables::hmtx::{Hmtx, HmtxContext};
+use crate::tables::loca::{LocFormat, Loca};
+use crate::tables::maxp::Maxp;
+use crate::tables::name::Name;
+use crate::tables::os2::Os2;
+use crate::tables::post::{Post, MAC_GLYPH_NAMES};
+use crate::tables::SfntTable;
+use crate::{FontBuilder, OpentypeTableAccess, Tag};
+
+const CFF: Tag = Tag(*b"CFF ");
+const CMAP: Tag = Tag(*b"cmap");
+const GLYF: Tag = Tag(*b"glyf");
+const HEAD: Tag = Tag(*b"head");
+const HHEA: Tag = Tag(*b"hhea");
+const HMTX: Tag = Tag(*b"hmtx");
+const LOCA: Tag = Tag(*b"loca");
+const MAXP: Tag = Tag(*b"maxp");
+const NAME: Tag = Tag(*b"name");
+const OS2: Tag = Tag(*b"OS/2");
+const POST: Tag = Tag(*b"post");
+
+/// The magic number of the `head` table.
+const MAGIC_NUMBER: u32 = 0x5F0F_3CF5;
+
+/// Tables without which the glyphs can not be displayed. Fonts lacking one
+/// of them can not be sanitized.
+const REQUIRED_TABLES: [Tag; 4] = [HEAD, HHEA, HMTX, MAXP];
+
+/// Tables which are only usable together.
+const DEPENDENT_TABLES: [(Tag, Tag); 2] = [(GLYF, LOCA), (HHEA, HMTX)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The font violates the specification, but the table can still be used.
+    Warning,
+    /// The table can not be used safely.
+    Error,
+}
+
+/// A problem found while validating a font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// The table the problem was found in.
+    pub table: Tag,
+    /// The glyph the problem was found in, if it concerns a single glyph.
+    pub glyph: Option<u32>,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}", severity, self.table)?;
+        if let Some(glyph) = self.glyph {
+            write!(f, ", glyph {}", glyph)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The result of [`validate`](fn.validate.html).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+    }
+
+    /// Returns the tables which have to be dropped from the font, including
+    /// tables which can not be used without a table with errors.
+    pub fn bad_tables(&self) -> BTreeSet<Tag> {
+        let mut tables: BTreeSet<Tag> = self.errors().map(|issue| issue.table).collect();
+        for &(first, second) in &DEPENDENT_TABLES {
+            if tables.contains(&first) || tables.contains(&second) {
+                tables.insert(first);
+                tables.insert(second);
+            }
+        }
+        tables
+    }
+
+    fn push(&mut self, severity: Severity, table: Tag, glyph: Option<u32>, message: String) {
+        self.issues.push(Issue {
+            severity,
+            table,
+            glyph,
+            message,
+        });
+    }
+
+    fn error(&mut self, table: Tag, message: String) {
+        self.push(Severity::Error, table, None, message);
+    }
+
+    fn warning(&mut self, table: Tag, message: String) {
+        self.push(Severity::Warning, table, None, message);
+    }
+
+    /// Parses a table, reporting an error if it fails.
+    fn parse<'d, T: SfntTable<'d>>(&mut self, data: &'d [u8], context: T::Context) -> Option<T> {
+        match T::from_data(data, context) {
+            Ok(table) => Some(table),
+            Err(err) => {
+                self.error(
+                    Tag(*T::TAG),
+                    format!("The table can not be parsed: {}", err),
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Checks the tables of a font and their consistency.
+///
+/// Examples
+/// --------
+///
+/// ```
+/// use font_parse::{validate, Font};
+///
+/// let font_data = include_bytes!("../tests/font_files/Inconsolata-Regular.ttf");
+/// let font = Font::from_bytes(font_data, 0).unwrap();
+/// let report = validate::validate(&font);
+/// for issue in &report.issues {
+///     println!("{}", issue);
+/// }
+/// assert!(!report.has_errors());
+/// ```
+pub fn validate<'a>(font: &impl OpentypeTableAccess<'a>) -> Report {
+    let mut report = Report::default();
+    for &tag in &REQUIRED_TABLES {
+        if !font.has_table(tag) {
+            report.error(tag, "The table is missing.".to_string());
+        }
+    }
+
+    let head_data = font.table_data(HEAD);
+    let head = head_data
+        .as_ref()
+        .and_then(|data| report.parse::<Head>(data, ()));
+    if let Some(head) = &head {
+        check_head(head, &mut report);
+    }
+
+    let maxp_data = font.table_data(MAXP);
+    let num_glyphs = maxp_data
+        .as_ref()
+        .and_then(|data| report.parse::<Maxp>(data, ()))
+        .map(|maxp| maxp.num_glyphs);
+    if num_glyphs == Some(0) {
+        report.error(MAXP, "The font contains no glyphs.".to_string());
+    }
+    let num_glyphs = match num_glyphs {
+        Some(num_glyphs) if num_glyphs > 0 => num_glyphs,
+        // the other tables can not be checked against the number of glyphs
+        _ => return report,
+    };
+
+    check_metrics(font, num_glyphs, &mut report);
+    match (font.table_data(CFF), font.table_data(GLYF)) {
+        (Some(cff), _) => check_cff(&cff, head.as_ref(), num_glyphs, &mut report),
+        (None, Some(glyf)) => match head.as_ref() {
+            Some(head) => check_glyf(font, &glyf, head, num_glyphs, &mut report),
+            None => report.error(GLYF, "The glyphs can not be read without head.".to_string()),
+        },
+        (None, None) => report.error(
+            GLYF,
+            "The font contains neither TrueType nor CFF outlines.".to_string(),
+        ),
+    }
+    if let Some(cmap) = font.table_data(CMAP) {
+        check_cmap(&cmap, num_glyphs, &mut report);
+    }
+    if let Some(post) = font.table_data(POST) {
+        check_post(&post, num_glyphs, &mut report);
+    }
+    if let Some(os2) = font.table_data(OS2) {
+        report.parse::<Os2>(&os2, ());
+    }
+    if let Some(name) = font.table_data(NAME) {
+        report.parse::<Name>(&name, ());
+    }
+    report
+}
+
+/// Validates a font and writes it without the tables that have errors.
+///
+/// Like [`write_font`](../fn.write_font.html), only the given tables are
+/// written and fails with `InvalidInput` if the font does not contain one of
+/// them. Fails with `InvalidData` if a table required to display the font is
+/// missing or has errors. Returns the report of the validation.
+pub fn sanitize<'a>(
+    font: &impl OpentypeTableAccess<'a>,
+    version_tag: Tag,
+    tables: &[Tag],
+    sink: &mut dyn Write,
+) -> io::Result<Report> {
+    let report = validate(font);
+    let bad_tables = report.bad_tables();
+    let required = REQUIRED_TABLES.iter().chain(match font.outline_type() {
+        crate::OutlineType::Cff => &[CFF][..],
+        _ => &[GLYF, LOCA][..],
+    });
+    for tag in required {
+        if bad_tables.contains(tag) {
+            let message = report
+                .errors()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+    }
+
+    let mut builder = FontBuilder::new(version_tag);
+    for &tag in tables {
+        let data = font.table_data(tag).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Font does not contain table {}.", tag),
+            )
+        })?;
+        if !bad_tables.contains(&tag) {
+            builder.add_table(tag, data.to_vec());
+        }
+    }
+    builder.write_to(sink)?;
+    Ok(report)
+}
+
+fn check_head(head: &Head, report: &mut Report) {
+    if head.magic_number != MAGIC_NUMBER {
+        report.error(
+            HEAD,
+            format!("Invalid magic number {:#010x}.", head.magic_number),
+        );
+    }
+    if !(16..=16384).contains(&head.units_per_em) {
+        report.error(
+            HEAD,
+            format!("Units per em {} are out of range.", head.units_per_em),
+        );
+    }
+    if head.x_min > head.x_max || head.y_min > head.y_max {
+        report.warning(
+            HEAD,
+            format!(
+                "The bounding box ({}, {}, {}, {}) is empty.",
+                head.x_min, head.y_min, head.x_max, head.y_max
+            ),
+        );
+    }
+}
+
+fn check_metrics<'a>(font: &impl OpentypeTableAccess<'a>, num_glyphs: u16, report: &mut Report) {
+    let hhea = match font.table_data(HHEA) {
+        Some(data) => report.parse::<Hhea>(&data, ()),
+        None => return,
+    };
+    let number_of_h_metrics = match hhea {
+        Some(hhea) => hhea.number_of_h_metrics,
+        None => return,
+    };
+    if number_of_h_metrics == 0 || number_of_h_metrics > num_glyphs {
+        report.error(
+            HHEA,
+            format!(
+                "The number of horizontal metrics {} is not between 1 and the number of glyphs {}.",
+                number_of_h_metrics, num_glyphs
+            ),
+        );
+        return;
+    }
+
+    let hmtx = match font.table_data(HMTX) {
+        Some(hmtx) => hmtx,
+        None => return,
+    };
+    let expected_length =
+        4 * usize::from(number_of_h_metrics) + 2 * usize::from(num_glyphs - number_of_h_metrics);
+    if hmtx.len() < expected_length {
+        report.error(
+            HMTX,
+            format!(
+                "The table has {} bytes, but {} glyphs need {} bytes.",
+                hmtx.len(),
+                num_glyphs,
+                expected_length
+            ),
+        );
+        return;
+    }
+    if hmtx.len() > expected_length {
+        report.warning(
+            HMTX,
+            format!(
+                "The table has {} bytes after the metrics of all glyphs.",
+                hmtx.len() - expected_length
+            ),
+        );
+    }
+    let context = HmtxContext {
+        number_of_h_metrics,
+        num_glyphs,
+    };
+    report.parse::<Hmtx>(&hmtx, context);
+}
+
+/// Reports a warning if the bounding box of a glyph exceeds the bounding box
+/// in the `head` table.
+fn check_bounds(
+    head: &Head,
+    table: Tag,
+    glyph: u32,
+    (x_min, y_min, x_max, y_max): (i32, i32, i32, i32),
+    report: &mut Report,
+) {
+    if x_min < i32::from(head.x_min)
+        || y_min < i32::from(head.y_min)
+        || x_max > i32::from(head.x_max)
+        || y_max > i32::from(head.y_max)
+    {
+        report.push(
+            Severity::Warning,
+            table,
+            Some(glyph),
+            format!(
+                "The bounding box ({}, {}, {}, {}) exceeds the font bounding box.",
+                x_min, y_min, x_max, y_max
+            ),
+        );
+    }
+}
+
+fn check_glyf<'a>(
+    font: &impl OpentypeTableAccess<'a>,
+    glyf: &[u8],
+    head: &Head,
+    num_glyphs: u16,
+    report: &mut Report,
+) {
+    let loca = match font.table_data(LOCA) {
+        Some(loca) => loca,
+        None => {
+            report.error(LOCA, "The table is missing.".to_string());
+            return;
+        }
+    };
+    let entry_size = match head.index_to_loc_format {
+        LocFormat::Short => 2,
+        LocFormat::Long => 4,
+    };
+    if loca.len() % entry_size != 0 {
+        report.warning(
+            LOCA,
+            format!(
+                "The length {} is not a multiple of the entry size.",
+                loca.len()
+            ),
+        );
+    }
+    let loca = match report.parse::<Loca>(&loca, head.index_to_loc_format) {
+        Some(loca) => loca,
+        None => return,
+    };
+    let num_entries = usize::from(num_glyphs) + 1;
+    if loca.num_entries() < num_entries {
+        report.error(
+            LOCA,
+            format!(
+                "The table has {} entries, but {} glyphs need {}.",
+                loca.num_entries(),
+                num_glyphs,
+                num_entries
+            ),
+        );
+        return;
+    }
+    if loca.num_entries() > num_entries {
+        report.warning(
+            LOCA,
+            format!(
+                "The table has {} entries for {} glyphs.",
+                loca.num_entries(),
+                num_glyphs
+            ),
+        );
+    }
+
+    for glyph in 0..num_glyphs {
+        // the number of entries was checked above
+        let (start, end) = match (loca.offset(glyph), loca.offset(glyph + 1)) {
+            (Ok(start), Ok(end)) => (start as usize, end as usize),
+            _ => break,
+        };
+        if start > end {
+            report.push(
+                Severity::Error,
+                LOCA,
+                Some(glyph.into()),
+                format!("The offsets {} and {} are decreasing.", start, end),
+            );
+            continue;
+        }
+        let data = match glyf.get(start..end) {
+            Some(data) => data,
+            None => {
+                report.push(
+                    Severity::Error,
+                    LOCA,
+                    Some(glyph.into()),
+                    format!(
+                        "The end offset {} exceeds the length {} of glyf.",
+                        end,
+                        glyf.len()
+                    ),
+                );
+                continue;
+            }
+        };
+        if !data.is_empty() {
+            check_ttf_glyph(data, head, glyph, num_glyphs, report);
+        }
+    }
+}
+
+fn check_ttf_glyph(data: &[u8], head: &Head, glyph: u16, num_glyphs: u16, report: &mut Report) {
+    let mut glyph_error = |message: String| {
+        report.push(Severity::Error, GLYF, Some(glyph.into()), message);
+    };
+    let header = match parse_header(data) {
+        Ok((_, header)) => header,
+        Err(_) => {
+            glyph_error(format!("The glyph has only {} bytes.", data.len()));
+            return;
+        }
+    };
+    if header.number_of_contours >= 0 {
+        if let Err(err) = parse_simple_glyph(data) {
+            glyph_error(format!("The glyph can not be parsed: {}", err));
+            return;
+        }
+    } else {
+        let composite = match parse_composite_glyph(data) {
+            Ok((_, composite)) => composite,
+            Err(err) => {
+                glyph_error(format!("The glyph can not be parsed: {}", err));
+                return;
+            }
+        };
+        if composite.components().next().is_none() {
+            glyph_error("The composite glyph has no valid components.".to_string());
+            return;
+        }
+        for component in composite.components() {
+            if component.glyph_index >= num_glyphs {
+                glyph_error(format!(
+                    "The component {} does not exist.",
+                    component.glyph_index
+                ));
+                return;
+            }
+        }
+    }
+
+    let bounds = (
+        i32::from(header.xmin),
+        i32::from(header.ymin),
+        i32::from(header.xmax),
+        i32::from(header.ymax),
+    );
+    if bounds.0 > bounds.2 || bounds.1 > bounds.3 {
+        report.push(
+            Severity::Warning,
+            GLYF,
+            Some(glyph.into()),
+            format!("The bounding box {:?} is empty.", bounds),
+        );
+    } else {
+        check_bounds(head, GLYF, glyph.into(), bounds, report);
+    }
+}
+
+fn check_cff(data: &[u8], head: Option<&Head>, num_glyphs: u16, report: &mut Report) {
+    let cff = match report.parse::<Cff>(data, ()) {
+        Some(cff) => cff,
+        None => return,
+    };
+    if cff.num_glyphs() != u32::from(num_glyphs) {
+        report.error(
+            CFF,
+            format!(
+                "The table contains {} glyphs, but maxp {}.",
+                cff.num_glyphs(),
+                num_glyphs
+            ),
+        );
+        return;
+    }
+    let head = match head {
+        Some(head) => head,
+        None => return,
+    };
+    let mut glyphs = CffGlyphAccessor::from_table(cff);
+    for glyph in 0..u32::from(num_glyphs) {
+        let outline = match glyphs.index(glyph) {
+            Some(mut charstring) => Outline::from_path_instructions(charstring.contour_iter()),
+            None => {
+                report.push(
+                    Severity::Error,
+                    CFF,
+                    Some(glyph),
+                    "The charstring is missing.".to_string(),
+                );
+                continue;
+            }
+        };
+        if let Some((min, max)) = outline.control_bounds() {
+            let bounds = (
+                min.x.floor() as i32,
+                min.y.floor() as i32,
+                max.x.ceil() as i32,
+                max.y.ceil() as i32,
+            );
+            check_bounds(head, CFF, glyph, bounds, report);
+        }
+    }
+}
+
+fn check_cmap(data: &[u8], num_glyphs: u16, report: &mut Report) {
+    let cmap = match report.parse::<Cmap>(data, ()) {
+        Some(cmap) => cmap,
+        None => return,
+    };
+    for record in &cmap.encoding_records {
+        let subtable = match cmap.subtable(record) {
+            Ok(subtable) => subtable,
+            Err(err) => {
+                report.warning(
+                    CMAP,
+                    format!(
+                        "The subtable for platform {} and encoding {} can not be read: {}",
+                        record.platform_id, record.encoding_id, err
+                    ),
+                );
+                continue;
+            }
+        };
+        if let Some((code, glyph)) = missing_glyph_mapping(&subtable, num_glyphs) {
+            report.push(
+                Severity::Error,
+                CMAP,
+                Some(glyph),
+                format!(
+                    "The subtable for platform {} and encoding {} maps {:#x} to a glyph which does not exist.",
+                    record.platform_id, record.encoding_id, code
+                ),
+            );
+        }
+    }
+}
+
+/// Returns the first code which the subtable maps to a glyph that does not
+/// exist, together with the glyph.
+///
+/// Ranges of codes are checked as a whole, so the work does not depend on the
+/// number of codes they cover.
+fn missing_glyph_mapping(subtable: &CmapSubtable, num_glyphs: u16) -> Option<(u32, u32)> {
+    let num_glyphs = u32::from(num_glyphs);
+    // glyph 0 means that the code is not mapped
+    let missing = |glyph: u32| glyph != 0 && glyph >= num_glyphs;
+    match subtable {
+        CmapSubtable::Format0(glyph_ids) => glyph_ids
+            .iter()
+            .enumerate()
+            .map(|(code, &glyph)| (code as u32, u32::from(glyph)))
+            .find(|&(_, glyph)| missing(glyph)),
+        CmapSubtable::Format4 {
+            segments,
+            glyph_ids,
+        } => {
+            // codes belong to the first segment ending at or after them
+            let mut first_code = 0;
+            for (i, segment) in segments.iter().enumerate() {
+                let start = u32::from(segment.start_code).max(first_code);
+                let end = u32::from(segment.end_code);
+                first_code = first_code.max(end + 1);
+                if start > end {
+                    continue;
+                }
+                let delta = segment.id_delta as u16;
+                if segment.id_range_offset == 0 {
+                    // the glyphs increase with the codes until they wrap
+                    // around at 0xFFFF, which never exists
+                    let first_glyph = u32::from((start as u16).wrapping_add(delta));
+                    if missing(first_glyph) {
+                        return Some((start, first_glyph));
+                    }
+                    let glyph = num_glyphs.max(1);
+                    let code = start + (glyph - first_glyph);
+                    if code <= end {
+                        return Some((code, glyph));
+                    }
+                } else {
+                    for code in start..=end {
+                        let index = (usize::from(segment.id_range_offset / 2)
+                            + (code - u32::from(segment.start_code)) as usize)
+                            .checked_sub(segments.len() - i);
+                        let glyph = match index.and_then(|index| glyph_ids.get(index)) {
+                            Some(0) | None => continue,
+                            Some(glyph) => u32::from(glyph.wrapping_add(delta)),
+                        };
+                        if missing(glyph) {
+                            return Some((code, glyph));
+                        }
+                    }
+                }
+            }
+            None
+        }
+        CmapSubtable::Format6 {
+            first_code,
+            glyph_ids,
+        } => glyph_ids
+            .iter()
+            .enumerate()
+            .map(|(index, &glyph)| (u32::from(*first_code) + index as u32, u32::from(glyph)))
+            .find(|&(_, glyph)| missing(glyph)),
+        CmapSubtable::Format12(groups) => groups.iter().find_map(|group| {
+            let end = group.end_char_code.min(0x10ffff);
+            if group.start_char_code > end {
+                return None;
+            }
+            let start_glyph = u64::from(group.start_glyph_id);
+            let glyph = if missing(group.start_glyph_id) {
+                start_glyph
+            } else {
+                u64::from(num_glyphs.max(1))
+            };
+            let code = u64::from(group.start_char_code) + (glyph - start_glyph);
+            if code <= u64::from(end) {
+                Some((code as u32, glyph as u32))
+            } else {
+                None
+            }
+        }),
+        CmapSubtable::Format13(groups) => groups
+            .iter()
+            .find(|group| {
+                group.start_char_code <= group.end_char_code.min(0x10ffff)
+                    && missing(group.start_glyph_id)
+            })
+            .map(|group| (group.start_char_code, group.start_glyph_id)),
+    }
+}
+
+fn check_post(data: &[u8], num_glyphs: u16, report: &mut Report) {
+    let post = match report.parse::<Post>(data, ()) {
+        Some(post) => post,
+        None => return,
+    };
+    if post.version != 0x0002_0000 {
+        return;
+    }
+    if post.glyph_name_index.len() != usize::from(num_glyphs) {
+        report.error(
+            POST,
+            format!(
+                "The table contains names for {} glyphs, but maxp {}.",
+                post.glyph_name_index.len(),
+                num_glyphs
+            ),
+        );
+    }
+    let num_names = MAC_GLYPH_NAMES.len() + post.names.len();
+    let invalid = post
+        .glyph_name_index
+        .iter()
+        .position(|&index| usize::from(index) >= num_names);
+    if let Some(glyph) = invalid {
+        report.push(
+            Severity::Error,
+            POST,
+            Some(glyph as u32),
+            format!(
+                "The name index {} does not exist.",
+                post.glyph_name_index[glyph]
+            ),
+        );
+    }
+}
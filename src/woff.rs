@@ -0,0 +1,385 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Reading and writing fonts in the Web Open Font Format 1.0.
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use nom::{be_u16, be_u32};
+
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use crate::error::ParserError;
+use crate::font_builder::{padded_len, table_order};
+use crate::{parse_tag, write_font, Font, OpentypeTableAccess, Tag};
+
+const WOFF_SIGNATURE: u32 = 0x774F_4646;
+const HEADER_LEN: usize = 44;
+const TABLE_ENTRY_LEN: usize = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WoffHeader {
+    /// The sfnt version of the contained font.
+    pub flavor: u32,
+    pub length: u32,
+    pub num_tables: u16,
+    /// The size of the uncompressed font.
+    pub total_sfnt_size: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub meta_offset: u32,
+    pub meta_length: u32,
+    pub meta_orig_length: u32,
+    pub priv_offset: u32,
+    pub priv_length: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WoffTableEntry {
+    pub tag: Tag,
+    pub offset: u32,
+    /// The length of the compressed data, which equals `orig_length` if the
+    /// table is stored uncompressed.
+    pub comp_length: u32,
+    pub orig_length: u32,
+    pub orig_checksum: u32,
+}
+
+named!(parse_woff_header<&[u8], WoffHeader>,
+    do_parse!(
+        verify!(be_u32, |signature| signature == WOFF_SIGNATURE) >>
+        flavor: be_u32 >>
+        length: be_u32 >>
+        num_tables: be_u16 >>
+        // reserved
+        be_u16 >>
+        total_sfnt_size: be_u32 >>
+        major_version: be_u16 >>
+        minor_version: be_u16 >>
+        meta_offset: be_u32 >>
+        meta_length: be_u32 >>
+        meta_orig_length: be_u32 >>
+        priv_offset: be_u32 >>
+        priv_length: be_u32 >>
+        (WoffHeader {
+            flavor,
+            length,
+            num_tables,
+            total_sfnt_size,
+            major_version,
+            minor_version,
+            meta_offset,
+            meta_length,
+            meta_orig_length,
+            priv_offset,
+            priv_length,
+        })
+    )
+);
+
+named!(parse_table_entry<&[u8], WoffTableEntry>,
+    do_parse!(
+        tag: parse_tag >>
+        offset: be_u32 >>
+        comp_length: be_u32 >>
+        orig_length: be_u32 >>
+        orig_checksum: be_u32 >>
+        (WoffTableEntry { tag, offset, comp_length, orig_length, orig_checksum })
+    )
+);
+
+/// Decompresses zlib data, which has to have the given length.
+fn decompress(data: &[u8], orig_length: u32) -> Result<Vec<u8>, ParserError> {
+    // the header is untrusted, so don't preallocate more than a few times the
+    // compressed length
+    let capacity = cmp::min(orig_length as usize, data.len().saturating_mul(4));
+    let mut out = Vec::with_capacity(capacity);
+    ZlibDecoder::new(data)
+        .take(u64::from(orig_length) + 1)
+        .read_to_end(&mut out)
+        .map_err(ParserError::from_err)?;
+    if out.len() != orig_length as usize {
+        return Err(ParserError::from_string(format!(
+            "Decompressed data has length {} instead of {}.",
+            out.len(),
+            orig_length
+        )));
+    }
+    Ok(out)
+}
+
+fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// A font in the WOFF 1.0 format.
+///
+/// Tables are decompressed whenever they are accessed. To parse glyphs, which
+/// needs borrowed table data, decompress all tables with
+/// [`decompress_tables`](#method.decompress_tables) first.
+#[derive(Debug, Clone)]
+pub struct Woff<'a> {
+    pub header: WoffHeader,
+    pub tables: BTreeMap<Tag, WoffTableEntry>,
+    data: &'a [u8],
+}
+
+impl<'a> Woff<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParserError> {
+        let (rest, header) = parse_woff_header(data)?;
+        if header.length as usize > data.len() {
+            return Err(ParserError::from_string(
+                "The woff file is truncated.".to_string(),
+            ));
+        }
+        let (_, entries) = count!(rest, parse_table_entry, header.num_tables as usize)?;
+        let tables = entries
+            .into_iter()
+            .map(|entry| (entry.tag, entry))
+            .collect();
+        Ok(Woff {
+            header,
+            tables,
+            data: &data[..header.length as usize],
+        })
+    }
+
+    fn block(&self, offset: u32, length: u32) -> Result<&'a [u8], ParserError> {
+        let start = offset as usize;
+        self.data
+            .get(start..start + length as usize)
+            .ok_or_else(|| ParserError::from_string("Woff data out of bounds.".to_string()))
+    }
+
+    /// Returns the uncompressed data of a table.
+    pub fn decompress_table(&self, entry: &WoffTableEntry) -> Result<Cow<'a, [u8]>, ParserError> {
+        let data = self.block(entry.offset, entry.comp_length)?;
+        if entry.comp_length == entry.orig_length {
+            Ok(Cow::Borrowed(data))
+        } else if entry.comp_length < entry.orig_length {
+            Ok(Cow::Owned(decompress(data, entry.orig_length)?))
+        } else {
+            Err(ParserError::from_string(format!(
+                "Compressed table {} is larger than the original.",
+                entry.tag
+            )))
+        }
+    }
+
+    /// Decompresses all tables.
+    pub fn decompress_tables(&self) -> Result<BTreeMap<Tag, Vec<u8>>, ParserError> {
+        self.tables
+            .values()
+            .map(|entry| Ok((entry.tag, self.decompress_table(entry)?.into_owned())))
+            .collect()
+    }
+
+    /// Returns the extended metadata, an XML document, if present.
+    pub fn metadata(&self) -> Result<Option<String>, ParserError> {
+        if self.header.meta_length == 0 {
+            return Ok(None);
+        }
+        let data = self.block(self.header.meta_offset, self.header.meta_length)?;
+        let metadata = decompress(data, self.header.meta_orig_length)?;
+        String::from_utf8(metadata)
+            .map(Some)
+            .map_err(ParserError::from_err)
+    }
+
+    /// Returns the private data block if present.
+    pub fn private_data(&self) -> Result<Option<&'a [u8]>, ParserError> {
+        if self.header.priv_length == 0 {
+            return Ok(None);
+        }
+        self.block(self.header.priv_offset, self.header.priv_length)
+            .map(Some)
+    }
+}
+
+impl<'a> OpentypeTableAccess<'a> for Woff<'a> {
+    type TableData = Cow<'a, [u8]>;
+
+    /// Returns `None` if the table can not be decompressed.
+    fn table_data(&self, tag: Tag) -> Option<Self::TableData> {
+        let entry = self.tables.get(&tag)?;
+        match self.decompress_table(entry) {
+            Ok(data) => Some(data),
+            Err(err) => {
+                warn!("Could not decompress table {}: {}", tag, err);
+                None
+            }
+        }
+    }
+}
+
+/// Options for writing WOFF files.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct WoffOptions<'m> {
+    /// The version of the font.
+    pub major_version: u16,
+    pub minor_version: u16,
+    /// The extended metadata, an XML document.
+    pub metadata: Option<&'m str>,
+    pub private_data: Option<&'m [u8]>,
+}
+
+/// Writes the given tables of `font` as a WOFF file.
+///
+/// Tables are compressed if that makes them smaller. The checksums and the
+/// `head` table are those of the font written by
+/// [`write_font`](../fn.write_font.html).
+pub fn write_woff<'a>(
+    font: &impl OpentypeTableAccess<'a>,
+    version_tag: Tag,
+    tables: &[Tag],
+    options: &WoffOptions<'_>,
+    sink: &mut dyn Write,
+) -> io::Result<()> {
+    let mut sfnt = vec![];
+    write_font(font, version_tag, tables, &mut sfnt)?;
+    let sfnt_font = Font::from_bytes(&sfnt, 0)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+    let order = table_order(sfnt_font.record().tables.keys().cloned());
+    let mut offset = HEADER_LEN + TABLE_ENTRY_LEN * order.len();
+    let mut entries = BTreeMap::new();
+    let mut table_data = Vec::with_capacity(order.len());
+    for tag in order {
+        let record = sfnt_font.record().tables[&tag];
+        let data = sfnt_font.table_data(tag).unwrap_or(&[]);
+        let compressed = compress(data)?;
+        let data = if compressed.len() < data.len() {
+            Cow::Owned(compressed)
+        } else {
+            Cow::Borrowed(data)
+        };
+        entries.insert(
+            tag,
+            WoffTableEntry {
+                tag,
+                offset: offset as u32,
+                comp_length: data.len() as u32,
+                orig_length: record.length,
+                orig_checksum: record.check_sum,
+            },
+        );
+        offset += padded_len(data.len());
+        table_data.push(data);
+    }
+
+    let metadata = match options.metadata {
+        Some(metadata) => compress(metadata.as_bytes())?,
+        None => vec![],
+    };
+    let meta_offset = if metadata.is_empty() { 0 } else { offset };
+    offset += metadata.len();
+    let private_data = options.private_data.unwrap_or(&[]);
+    if !private_data.is_empty() {
+        offset = padded_len(offset);
+    }
+    let priv_offset = if private_data.is_empty() { 0 } else { offset };
+    offset += private_data.len();
+    if offset > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too much data for a woff file.",
+        ));
+    }
+
+    let header = [
+        WOFF_SIGNATURE,
+        u32::from_be_bytes(version_tag.0),
+        offset as u32,
+        (entries.len() as u32) << 16,
+        sfnt.len() as u32,
+        u32::from(options.major_version) << 16 | u32::from(options.minor_version),
+        meta_offset as u32,
+        metadata.len() as u32,
+        options.metadata.map_or(0, str::len) as u32,
+        priv_offset as u32,
+        private_data.len() as u32,
+    ];
+    for value in &header {
+        sink.write_all(&value.to_be_bytes())?;
+    }
+    for entry in entries.values() {
+        sink.write_all(&entry.tag.0)?;
+        sink.write_all(&entry.offset.to_be_bytes())?;
+        sink.write_all(&entry.comp_length.to_be_bytes())?;
+        sink.write_all(&entry.orig_length.to_be_bytes())?;
+        sink.write_all(&entry.orig_checksum.to_be_bytes())?;
+    }
+    for data in &table_data {
+        sink.write_all(data)?;
+        sink.write_all(&[0; 4][..padded_len(data.len()) - data.len()])?;
+    }
+    sink.write_all(&metadata)?;
+    if !private_data.is_empty() {
+        sink.write_all(&[0; 4][..padded_len(metadata.len()) - metadata.len()])?;
+        sink.write_all(private_data)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_woff_header() {
+        let mut data = vec![];
+        for &value in &[
+            WOFF_SIGNATURE,
+            0x0001_0000,
+            44,
+            0,
+            12,
+            0x0001_0002,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ] {
+            data.extend_from_slice(&u32::to_be_bytes(value));
+        }
+        let woff = Woff::from_bytes(&data).unwrap();
+        assert_eq!(woff.header.flavor, 0x0001_0000);
+        assert_eq!(woff.header.major_version, 1);
+        assert_eq!(woff.header.minor_version, 2);
+        assert!(woff.tables.is_empty());
+        assert_eq!(woff.metadata().unwrap(), None);
+
+        // wrong signature
+        data[0] = b'W';
+        assert!(Woff::from_bytes(&data).is_err());
+        // truncated
+        data[0] = b'w';
+        assert!(Woff::from_bytes(&data[..40]).is_err());
+    }
+
+    #[test]
+    fn test_decompress() {
+        let data = compress(b"abcabcabc").unwrap();
+        assert_eq!(decompress(&data, 9).unwrap(), b"abcabcabc");
+        assert!(decompress(&data, 8).is_err());
+        assert!(decompress(&data, u32::MAX).is_err());
+    }
+}
@@ -0,0 +1,1475 @@
+//    Copyright 2018 Manuel Reinhardt
+//
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+//
+//        http://www.apache.org/licenses/LICENSE-2.0
+//
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Reading and writing fonts in the Web Open Font Format 2.0.
+
+use brotli::{CompressorWriter, Decompressor};
+use nom::{be_i16, be_u16, be_u32, be_u8, IResult};
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use crate::collection::{write_font_collection, CollectionFont};
+use crate::error::ParserError;
+use crate::font_builder::padded_len;
+use crate::tables::glyf::{
+    encode_glyph, parse_header, parse_simple_glyph, GlyphBuf, GlyphPoint, Header,
+    ARG_1_AND_2_ARE_WORDS, MORE_COMPONENTS, WE_HAVE_AN_X_AND_Y_SCALE, WE_HAVE_A_SCALE,
+    WE_HAVE_A_TWO_BY_TWO, WE_HAVE_INSTRUCTIONS,
+};
+use crate::tables::head::Head;
+use crate::tables::hhea::Hhea;
+use crate::tables::hmtx::{Hmtx, HmtxContext, LongHorMetric};
+use crate::tables::loca::{LocFormat, Loca};
+use crate::tables::maxp::Maxp;
+use crate::tables::SfntTable;
+use crate::{
+    parse, parse_tag, Font, FontBuilder, FontCollection, FontFile, FontRecord, OpentypeTableAccess,
+    Tag,
+};
+
+const WOFF2_SIGNATURE: u32 = 0x774F_4632;
+const TTC_TAG: u32 = 0x7474_6366;
+const HEADER_LEN: usize = 48;
+
+/// The tags which are encoded by their index in the table directory.
+pub(crate) const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Woff2Header {
+    /// The sfnt version of the contained font, `ttcf` for collections.
+    pub flavor: u32,
+    pub length: u32,
+    pub num_tables: u16,
+    /// The size of the uncompressed font.
+    pub total_sfnt_size: u32,
+    /// The length of the Brotli stream containing all tables.
+    pub total_compressed_size: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub meta_offset: u32,
+    pub meta_length: u32,
+    pub meta_orig_length: u32,
+    pub priv_offset: u32,
+    pub priv_length: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Woff2TableEntry {
+    pub tag: Tag,
+    /// The transformation version stored in the two high bits of the flags.
+    pub transform_version: u8,
+    pub orig_length: u32,
+    /// The length of the transformed data, `None` if the table is stored
+    /// without transformation.
+    pub transform_length: Option<u32>,
+}
+
+impl Woff2TableEntry {
+    /// Returns `true` if the table data is transformed. Version 0 is the
+    /// transformation of `glyf` and `loca` and the null transform of all
+    /// other tables.
+    pub fn is_transformed(&self) -> bool {
+        if self.tag == Tag(*b"glyf") || self.tag == Tag(*b"loca") {
+            self.transform_version != 3
+        } else {
+            self.transform_version != 0
+        }
+    }
+
+    /// The length of the table in the decompressed stream.
+    fn stored_length(&self) -> u32 {
+        self.transform_length.unwrap_or(self.orig_length)
+    }
+}
+
+/// A font in a WOFF2 collection, given by the indices of its tables in the
+/// table directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Woff2FontEntry {
+    pub flavor: u32,
+    pub table_indices: Vec<u16>,
+}
+
+named!(parse_woff2_header<&[u8], Woff2Header>,
+    do_parse!(
+        verify!(be_u32, |signature| signature == WOFF2_SIGNATURE) >>
+        flavor: be_u32 >>
+        length: be_u32 >>
+        num_tables: be_u16 >>
+        // reserved
+        be_u16 >>
+        total_sfnt_size: be_u32 >>
+        total_compressed_size: be_u32 >>
+        major_version: be_u16 >>
+        minor_version: be_u16 >>
+        meta_offset: be_u32 >>
+        meta_length: be_u32 >>
+        meta_orig_length: be_u32 >>
+        priv_offset: be_u32 >>
+        priv_length: be_u32 >>
+        (Woff2Header {
+            flavor,
+            length,
+            num_tables,
+            total_sfnt_size,
+            total_compressed_size,
+            major_version,
+            minor_version,
+            meta_offset,
+            meta_length,
+            meta_orig_length,
+            priv_offset,
+            priv_length,
+        })
+    )
+);
+
+/// Parses a variable length unsigned integer of up to five bytes, seven bits
+/// per byte.
+fn parse_uint_base128(mut input: &[u8]) -> IResult<&[u8], u32> {
+    let mut value = 0u32;
+    for i in 0..5 {
+        let (rest, byte) = be_u8(input)?;
+        // leading zeros and values which overflow 32 bits are invalid
+        if (i == 0 && byte == 0x80) || value & 0xFE00_0000 != 0 {
+            return Err(nom::Err::Error(error_position!(
+                input,
+                nom::ErrorKind::Verify
+            )));
+        }
+        value = value << 7 | u32::from(byte & 0x7F);
+        input = rest;
+        if byte & 0x80 == 0 {
+            return Ok((input, value));
+        }
+    }
+    Err(nom::Err::Error(error_position!(
+        input,
+        nom::ErrorKind::TooLarge
+    )))
+}
+
+/// Parses a 16 bit integer which is stored in one to three bytes.
+fn parse_255_uint16(input: &[u8]) -> IResult<&[u8], u16> {
+    let (rest, code) = be_u8(input)?;
+    match code {
+        253 => be_u16(rest),
+        254 => map!(rest, be_u8, |value| u16::from(value) + 506),
+        255 => map!(rest, be_u8, |value| u16::from(value) + 253),
+        _ => Ok((rest, u16::from(code))),
+    }
+}
+
+fn parse_table_entry(input: &[u8]) -> IResult<&[u8], Woff2TableEntry> {
+    let (input, flags) = be_u8(input)?;
+    let (input, tag) = match KNOWN_TAGS.get(usize::from(flags & 0x3F)) {
+        Some(&&tag) => (input, Tag(tag)),
+        None => parse_tag(input)?,
+    };
+    let (input, orig_length) = parse_uint_base128(input)?;
+    let mut entry = Woff2TableEntry {
+        tag,
+        transform_version: flags >> 6,
+        orig_length,
+        transform_length: None,
+    };
+    if !entry.is_transformed() {
+        return Ok((input, entry));
+    }
+    let (input, transform_length) = parse_uint_base128(input)?;
+    entry.transform_length = Some(transform_length);
+    Ok((input, entry))
+}
+
+named!(parse_collection_font<&[u8], Woff2FontEntry>,
+    do_parse!(
+        num_tables: parse_255_uint16 >>
+        flavor: be_u32 >>
+        table_indices: count!(parse_255_uint16, num_tables as usize) >>
+        (Woff2FontEntry { flavor, table_indices })
+    )
+);
+
+named!(parse_collection_directory<&[u8], Vec<Woff2FontEntry>>,
+    do_parse!(
+        // the version of the collection header
+        be_u32 >>
+        num_fonts: parse_255_uint16 >>
+        fonts: count!(parse_collection_font, num_fonts as usize) >>
+        (fonts)
+    )
+);
+
+/// Decompresses Brotli data, which has to have the given length.
+fn decompress(data: &[u8], orig_length: u64) -> Result<Vec<u8>, ParserError> {
+    let mut out = Vec::new();
+    Decompressor::new(data, 4096)
+        .take(orig_length + 1)
+        .read_to_end(&mut out)
+        .map_err(ParserError::from_err)?;
+    if out.len() as u64 != orig_length {
+        return Err(ParserError::from_string(format!(
+            "Decompressed data has length {} instead of {}.",
+            out.len(),
+            orig_length
+        )));
+    }
+    Ok(out)
+}
+
+/// Splits the first `len` bytes off a stream.
+fn take_stream<'d>(stream: &mut &'d [u8], len: usize) -> Result<&'d [u8], ParserError> {
+    if len > stream.len() {
+        return Err(ParserError::from_string(
+            "Stream of transformed table is truncated.".to_string(),
+        ));
+    }
+    let (head, rest) = stream.split_at(len);
+    *stream = rest;
+    Ok(head)
+}
+
+fn bit_set(bitmap: &[u8], index: usize) -> bool {
+    bitmap
+        .get(index >> 3)
+        .is_some_and(|byte| byte & (0x80 >> (index & 7)) != 0)
+}
+
+#[derive(Debug, Copy, Clone)]
+struct TransformedGlyfHeader {
+    option_flags: u16,
+    num_glyphs: u16,
+    index_format: u16,
+    stream_sizes: [u32; 7],
+}
+
+named!(parse_transformed_glyf_header<&[u8], TransformedGlyfHeader>,
+    do_parse!(
+        // reserved
+        be_u16 >>
+        option_flags: be_u16 >>
+        num_glyphs: be_u16 >>
+        index_format: be_u16 >>
+        n_contour: be_u32 >>
+        n_points: be_u32 >>
+        flag: be_u32 >>
+        glyph: be_u32 >>
+        composite: be_u32 >>
+        bbox: be_u32 >>
+        instruction: be_u32 >>
+        (TransformedGlyfHeader {
+            option_flags,
+            num_glyphs,
+            index_format,
+            stream_sizes: [n_contour, n_points, flag, glyph, composite, bbox, instruction],
+        })
+    )
+);
+
+/// Decodes the coordinate deltas of a point from the glyph stream.
+fn parse_triplet(input: &[u8], flag: u8) -> IResult<&[u8], (i32, i32)> {
+    let flag = flag & 0x7F;
+    let len = match flag {
+        0..=83 => 1,
+        84..=119 => 2,
+        120..=123 => 3,
+        _ => 4,
+    };
+    let (rest, bytes) = take!(input, len)?;
+    let byte = |index: usize| i32::from(bytes[index]);
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let code = i32::from(flag);
+    let delta = if flag < 10 {
+        (0, with_sign(flag, ((code & 14) << 7) + byte(0)))
+    } else if flag < 20 {
+        (with_sign(flag, (((code - 10) & 14) << 7) + byte(0)), 0)
+    } else if flag < 84 {
+        let b0 = code - 20;
+        let b1 = byte(0);
+        (
+            with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+            with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+        )
+    } else if flag < 120 {
+        let b0 = code - 84;
+        (
+            with_sign(flag, 1 + ((b0 / 12) << 8) + byte(0)),
+            with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + byte(1)),
+        )
+    } else if flag < 124 {
+        (
+            with_sign(flag, (byte(0) << 4) + (byte(1) >> 4)),
+            with_sign(flag >> 1, ((byte(1) & 0x0F) << 8) + byte(2)),
+        )
+    } else {
+        (
+            with_sign(flag, (byte(0) << 8) + byte(1)),
+            with_sign(flag >> 1, (byte(2) << 8) + byte(3)),
+        )
+    };
+    Ok((rest, delta))
+}
+
+named!(parse_bbox<&[u8], Header>,
+    do_parse!(
+        xmin: be_i16 >>
+        ymin: be_i16 >>
+        xmax: be_i16 >>
+        ymax: be_i16 >>
+        (Header { number_of_contours: 0, xmin, ymin, xmax, ymax })
+    )
+);
+
+/// Computes the bounding box of a simple glyph.
+fn contour_bounds(contours: &[Vec<GlyphPoint>]) -> Result<Header, ParserError> {
+    let mut points = contours.iter().flatten();
+    let first = match points.next() {
+        Some(point) => point,
+        None => return Ok(Header::default()),
+    };
+    let (min, max) = points.fold(((first.x, first.y), (first.x, first.y)), |(min, max), p| {
+        (
+            (min.0.min(p.x), min.1.min(p.y)),
+            (max.0.max(p.x), max.1.max(p.y)),
+        )
+    });
+    let coordinate = |value: i32| {
+        if value < i32::from(i16::MIN) || value > i32::from(i16::MAX) {
+            Err(ParserError::from_string(format!(
+                "Coordinate {} does not fit into 16 bits.",
+                value
+            )))
+        } else {
+            Ok(value as i16)
+        }
+    };
+    Ok(Header {
+        number_of_contours: contours.len() as i16,
+        xmin: coordinate(min.0)?,
+        ymin: coordinate(min.1)?,
+        xmax: coordinate(max.0)?,
+        ymax: coordinate(max.1)?,
+    })
+}
+
+/// Returns the length of a component of a composite glyph including its
+/// flags.
+fn component_len(flags: u16) -> usize {
+    let mut len = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+        8
+    } else {
+        6
+    };
+    if flags & WE_HAVE_A_SCALE != 0 {
+        len += 2;
+    } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+        len += 4;
+    } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+        len += 8;
+    }
+    len
+}
+
+/// The `glyf` and `loca` tables reconstructed from a transformed `glyf`
+/// table.
+#[derive(Debug, Clone)]
+struct ReconstructedGlyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    /// The minimal x coordinate of every glyph, which is the left side
+    /// bearing if the `hmtx` table is transformed.
+    x_mins: Vec<i16>,
+}
+
+/// The streams of a transformed `glyf` table.
+fn coordinate_overflow() -> ParserError {
+    ParserError::from_string("A glyph coordinate overflows.".to_string())
+}
+
+struct GlyfStreams<'d> {
+    n_contour: &'d [u8],
+    n_points: &'d [u8],
+    flag: &'d [u8],
+    glyph: &'d [u8],
+    composite: &'d [u8],
+    bbox_bitmap: &'d [u8],
+    bbox: &'d [u8],
+    instruction: &'d [u8],
+    overlap_bitmap: &'d [u8],
+}
+
+impl<'d> GlyfStreams<'d> {
+    fn read_bbox(&mut self) -> Result<Header, ParserError> {
+        let (rest, bbox) = parse_bbox(self.bbox)?;
+        self.bbox = rest;
+        Ok(bbox)
+    }
+
+    fn read_instructions(&mut self) -> Result<&'d [u8], ParserError> {
+        let (rest, len) = parse_255_uint16(self.glyph)?;
+        self.glyph = rest;
+        take_stream(&mut self.instruction, usize::from(len))
+    }
+
+    fn simple_glyph(
+        &mut self,
+        index: usize,
+        number_of_contours: i16,
+        out: &mut Vec<u8>,
+    ) -> Result<Header, ParserError> {
+        let mut contours = Vec::with_capacity(number_of_contours as usize);
+        let (mut x, mut y) = (0i32, 0i32);
+        for _ in 0..number_of_contours {
+            let (rest, num_points) = parse_255_uint16(self.n_points)?;
+            self.n_points = rest;
+            let mut contour = Vec::with_capacity(usize::from(num_points));
+            for _ in 0..num_points {
+                let (rest, flag) = be_u8(self.flag)?;
+                self.flag = rest;
+                let (rest, (dx, dy)) = parse_triplet(self.glyph, flag)?;
+                self.glyph = rest;
+                x = x.checked_add(dx).ok_or_else(coordinate_overflow)?;
+                y = y.checked_add(dy).ok_or_else(coordinate_overflow)?;
+                contour.push(GlyphPoint::new(x, y, flag & 0x80 == 0));
+            }
+            contours.push(contour);
+        }
+        let instructions = self.read_instructions()?.to_vec();
+        let header = if bit_set(self.bbox_bitmap, index) {
+            Header {
+                number_of_contours,
+                ..self.read_bbox()?
+            }
+        } else {
+            contour_bounds(&contours)?
+        };
+        let glyph = GlyphBuf::Simple {
+            contours,
+            instructions,
+            overlap: bit_set(self.overlap_bitmap, index),
+        };
+        encode_glyph(&glyph, header, out)?;
+        Ok(header)
+    }
+
+    fn composite_glyph(&mut self, index: usize, out: &mut Vec<u8>) -> Result<Header, ParserError> {
+        if !bit_set(self.bbox_bitmap, index) {
+            return Err(ParserError::from_string(
+                "Composite glyph without bounding box.".to_string(),
+            ));
+        }
+        let header = Header {
+            number_of_contours: -1,
+            ..self.read_bbox()?
+        };
+        out.extend_from_slice(&header.number_of_contours.to_be_bytes());
+        for &value in &[header.xmin, header.ymin, header.xmax, header.ymax] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        let mut have_instructions = false;
+        loop {
+            let (_, flags) = be_u16(self.composite)?;
+            out.extend_from_slice(take_stream(&mut self.composite, component_len(flags))?);
+            have_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+            if flags & MORE_COMPONENTS == 0 {
+                break;
+            }
+        }
+        if have_instructions {
+            let instructions = self.read_instructions()?;
+            out.extend_from_slice(&(instructions.len() as u16).to_be_bytes());
+            out.extend_from_slice(instructions);
+        }
+        Ok(header)
+    }
+}
+
+/// Reconstructs the `glyf` and `loca` tables from a transformed `glyf` table.
+fn reconstruct_glyf(data: &[u8]) -> Result<ReconstructedGlyf, ParserError> {
+    let (mut rest, header) = parse_transformed_glyf_header(data)?;
+    let num_glyphs = usize::from(header.num_glyphs);
+    let [n_contour, n_points, flag, glyph, composite, bbox, instruction] = header.stream_sizes;
+    let n_contour = take_stream(&mut rest, n_contour as usize)?;
+    let n_points = take_stream(&mut rest, n_points as usize)?;
+    let flag = take_stream(&mut rest, flag as usize)?;
+    let glyph = take_stream(&mut rest, glyph as usize)?;
+    let composite = take_stream(&mut rest, composite as usize)?;
+    let mut bbox = take_stream(&mut rest, bbox as usize)?;
+    let instruction = take_stream(&mut rest, instruction as usize)?;
+    let overlap_bitmap = if header.option_flags & 1 != 0 {
+        take_stream(&mut rest, num_glyphs.div_ceil(8))?
+    } else {
+        &[]
+    };
+    let bbox_bitmap = take_stream(&mut bbox, num_glyphs.div_ceil(32) * 4)?;
+    let mut streams = GlyfStreams {
+        n_contour,
+        n_points,
+        flag,
+        glyph,
+        composite,
+        bbox_bitmap,
+        bbox,
+        instruction,
+        overlap_bitmap,
+    };
+
+    let mut glyf = vec![];
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for index in 0..num_glyphs {
+        offsets.push(glyf.len() as u32);
+        let (rest, number_of_contours) = be_i16(streams.n_contour)?;
+        streams.n_contour = rest;
+        let header = match number_of_contours {
+            0 if bit_set(bbox_bitmap, index) => {
+                return Err(ParserError::glyph_parse(
+                    index as u32,
+                    ParserError::from_string("Empty glyph with bounding box.".to_string()),
+                ))
+            }
+            0 => Ok(Header::default()),
+            -1 => streams.composite_glyph(index, &mut glyf),
+            n if n > 0 => streams.simple_glyph(index, n, &mut glyf),
+            n => Err(ParserError::from_string(format!(
+                "Invalid number of contours {}.",
+                n
+            ))),
+        }
+        .map_err(|err| ParserError::glyph_parse(index as u32, err))?;
+        x_mins.push(header.xmin);
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+        if glyf.len() > u32::MAX as usize {
+            return Err(ParserError::from_string(
+                "Reconstructed glyf table is too large.".to_string(),
+            ));
+        }
+    }
+    offsets.push(glyf.len() as u32);
+
+    let format = if header.index_format == 0 {
+        LocFormat::Short
+    } else {
+        LocFormat::Long
+    };
+    let mut loca = vec![];
+    Loca::from_offsets(offsets)
+        .write_to(format, &mut loca)
+        .map_err(ParserError::from_err)?;
+    Ok(ReconstructedGlyf { glyf, loca, x_mins })
+}
+
+/// Reconstructs the `hmtx` table from its transformed version, taking the
+/// omitted left side bearings from the bounding boxes of the glyphs.
+fn reconstruct_hmtx(
+    data: &[u8],
+    number_of_h_metrics: u16,
+    x_mins: &[i16],
+) -> Result<Vec<u8>, ParserError> {
+    let (rest, flags) = be_u8(data)?;
+    if flags & 0xFC != 0 || flags & 0x03 == 0 {
+        return Err(ParserError::from_string(format!(
+            "Invalid flags {:#x} of transformed hmtx table.",
+            flags
+        )));
+    }
+    let num_long = usize::from(number_of_h_metrics);
+    if num_long == 0 || num_long > x_mins.len() {
+        return Err(ParserError::from_string(format!(
+            "Invalid number of horizontal metrics {}.",
+            num_long
+        )));
+    }
+    let (rest, advance_widths) = count!(rest, be_u16, num_long)?;
+    let (rest, lsbs) = if flags & 1 == 0 {
+        count!(rest, be_i16, num_long)?
+    } else {
+        (rest, x_mins[..num_long].to_vec())
+    };
+    let (_, left_side_bearings) = if flags & 2 == 0 {
+        count!(rest, be_i16, x_mins.len() - num_long)?
+    } else {
+        (rest, x_mins[num_long..].to_vec())
+    };
+    let hmtx = Hmtx {
+        h_metrics: advance_widths
+            .into_iter()
+            .zip(lsbs)
+            .map(|(advance_width, lsb)| LongHorMetric { advance_width, lsb })
+            .collect(),
+        left_side_bearings,
+    };
+    let mut out = vec![];
+    hmtx.write_to(&mut out).map_err(ParserError::from_err)?;
+    Ok(out)
+}
+
+/// Reconstructs the tables of a font from the decompressed table data.
+///
+/// Transformed `hmtx` tables are reconstructed for every font because they
+/// depend on the `glyf`, `hhea` and `maxp` tables of the font.
+fn reconstruct_tables(
+    entries: &[Woff2TableEntry],
+    fonts: &[Woff2FontEntry],
+    data: &[u8],
+) -> Result<Vec<Vec<u8>>, ParserError> {
+    let mut tables: Vec<Option<Vec<u8>>> = vec![None; entries.len()];
+    let mut x_mins: BTreeMap<usize, Vec<i16>> = BTreeMap::new();
+    let mut offset = 0usize;
+    for (index, entry) in entries.iter().enumerate() {
+        let stored = &data[offset..offset + entry.stored_length() as usize];
+        offset += stored.len();
+        match (&entry.tag.0, entry.transform_version) {
+            _ if !entry.is_transformed() => tables[index] = Some(stored.to_vec()),
+            (b"glyf", 0) => {
+                let is_loca =
+                    |entry: &Woff2TableEntry| entry.tag == Tag(*b"loca") && entry.is_transformed();
+                if !entries.get(index + 1).is_some_and(is_loca) {
+                    return Err(ParserError::from_string(
+                        "Transformed glyf table is not followed by its loca table.".to_string(),
+                    ));
+                }
+                let glyf = reconstruct_glyf(stored)?;
+                if glyf.loca.len() != entries[index + 1].orig_length as usize {
+                    return Err(ParserError::from_string(
+                        "Reconstructed loca table has the wrong length.".to_string(),
+                    ));
+                }
+                tables[index] = Some(glyf.glyf);
+                tables[index + 1] = Some(glyf.loca);
+                x_mins.insert(index, glyf.x_mins);
+            }
+            (b"loca", 0) => {
+                if tables[index].is_none() {
+                    return Err(ParserError::from_string(
+                        "Transformed loca table without glyf table.".to_string(),
+                    ));
+                }
+            }
+            (b"hmtx", 1) => {}
+            _ => {
+                return Err(ParserError::from_string(format!(
+                    "Unknown transformation {} of table {}.",
+                    entry.transform_version, entry.tag
+                )))
+            }
+        }
+    }
+
+    for font in fonts {
+        let index_of = |tag: &[u8; 4]| {
+            font.table_indices
+                .iter()
+                .map(|&index| usize::from(index))
+                .find(|&index| &entries[index].tag.0 == tag)
+        };
+        let hmtx = match index_of(b"hmtx") {
+            Some(hmtx) if tables[hmtx].is_none() => hmtx,
+            _ => continue,
+        };
+        let table = |tag: &[u8; 4]| -> Result<&[u8], ParserError> {
+            index_of(tag)
+                .and_then(|index| tables[index].as_ref())
+                .map(Vec::as_slice)
+                .ok_or_else(|| ParserError::expected_table(Tag(*tag)))
+        };
+        let hhea = Hhea::from_data(table(b"hhea")?, ())?;
+        let num_glyphs = Maxp::from_data(table(b"maxp")?, ())?.num_glyphs;
+        let x_mins = index_of(b"glyf")
+            .and_then(|glyf| x_mins.get(&glyf))
+            .ok_or_else(|| {
+                ParserError::from_string(
+                    "Transformed hmtx table needs a transformed glyf table.".to_string(),
+                )
+            })?;
+        if x_mins.len() != usize::from(num_glyphs) {
+            return Err(ParserError::from_string(
+                "The glyf and maxp tables have different numbers of glyphs.".to_string(),
+            ));
+        }
+        let start = entries[..hmtx]
+            .iter()
+            .map(|entry| entry.stored_length() as usize)
+            .sum::<usize>();
+        let stored = &data[start..start + entries[hmtx].stored_length() as usize];
+        tables[hmtx] = Some(reconstruct_hmtx(stored, hhea.number_of_h_metrics, x_mins)?);
+    }
+    Ok(tables.into_iter().map(Option::unwrap_or_default).collect())
+}
+
+/// A font or font collection in the WOFF2 format.
+///
+/// The tables are decompressed and reconstructed when the file is read. The
+/// resulting font file is available with [`sfnt_data`](#method.sfnt_data),
+/// and the table access of this type reads the first font. Fonts of
+/// collections can be read with [`font`](#method.font).
+#[derive(Debug, Clone)]
+pub struct Woff2<'a> {
+    pub header: Woff2Header,
+    pub tables: Vec<Woff2TableEntry>,
+    /// The fonts of the file, which is a single font containing all tables
+    /// unless the file is a collection.
+    pub fonts: Vec<Woff2FontEntry>,
+    sfnt: Vec<u8>,
+    record: FontRecord,
+    collection: Option<FontCollection>,
+    data: &'a [u8],
+}
+
+impl<'a> Woff2<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParserError> {
+        let (rest, header) = parse_woff2_header(data)?;
+        if header.length as usize > data.len() {
+            return Err(ParserError::from_string(
+                "The woff2 file is truncated.".to_string(),
+            ));
+        }
+        let (mut rest, tables) = count!(rest, parse_table_entry, header.num_tables as usize)?;
+        let fonts = if header.flavor == TTC_TAG {
+            let (remaining, fonts) = parse_collection_directory(rest)?;
+            rest = remaining;
+            fonts
+        } else {
+            vec![Woff2FontEntry {
+                flavor: header.flavor,
+                table_indices: (0..header.num_tables).collect(),
+            }]
+        };
+        if fonts.is_empty()
+            || fonts
+                .iter()
+                .flat_map(|font| &font.table_indices)
+                .any(|&index| index >= header.num_tables)
+        {
+            return Err(ParserError::from_string(
+                "Invalid collection directory.".to_string(),
+            ));
+        }
+
+        let compressed = rest
+            .get(..header.total_compressed_size as usize)
+            .ok_or_else(|| ParserError::from_string("The woff2 file is truncated.".to_string()))?;
+        let total_length = tables
+            .iter()
+            .map(|entry| u64::from(entry.stored_length()))
+            .sum();
+        let table_data = decompress(compressed, total_length)?;
+        let table_data = reconstruct_tables(&tables, &fonts, &table_data)?;
+
+        let mut sfnt = vec![];
+        if header.flavor == TTC_TAG {
+            let font_tables: Vec<BTreeMap<Tag, Vec<u8>>> = fonts
+                .iter()
+                .map(|font| {
+                    font.table_indices
+                        .iter()
+                        .map(|&index| {
+                            let index = usize::from(index);
+                            (tables[index].tag, table_data[index].clone())
+                        })
+                        .collect()
+                })
+                .collect();
+            let font_tables: Vec<&BTreeMap<Tag, Vec<u8>>> = font_tables.iter().collect();
+            let collection_fonts: Vec<_> = fonts
+                .iter()
+                .zip(&font_tables)
+                .map(|(font, tables)| CollectionFont {
+                    font: tables,
+                    version_tag: Tag(font.flavor.to_be_bytes()),
+                    tables: tables.keys().cloned().collect(),
+                })
+                .collect();
+            write_font_collection(&collection_fonts, None, &mut sfnt)
+                .map_err(ParserError::from_err)?;
+        } else {
+            let mut builder = FontBuilder::new(Tag(header.flavor.to_be_bytes()));
+            for (entry, data) in tables.iter().zip(&table_data) {
+                builder.add_table(entry.tag, data.as_slice());
+            }
+            builder.write_to(&mut sfnt).map_err(ParserError::from_err)?;
+        }
+
+        let (record, collection) = match parse(&sfnt)? {
+            FontFile::Single(record) => (record, None),
+            FontFile::Collection(collection) => (collection.fonts[0].clone(), Some(collection)),
+        };
+        Ok(Woff2 {
+            header,
+            tables,
+            fonts,
+            sfnt,
+            record,
+            collection,
+            data: &data[..header.length as usize],
+        })
+    }
+
+    /// Returns the reconstructed font file, a font collection if the WOFF2
+    /// file contains a collection.
+    pub fn sfnt_data(&self) -> &[u8] {
+        &self.sfnt
+    }
+
+    /// Returns the header of the reconstructed collection.
+    pub fn collection(&self) -> Option<&FontCollection> {
+        self.collection.as_ref()
+    }
+
+    /// Returns the font with the given index of a collection.
+    pub fn font(&self, index: u32) -> Result<Font<'_>, ParserError> {
+        Font::from_bytes(&self.sfnt, index)
+    }
+
+    fn block(&self, offset: u32, length: u32) -> Result<&'a [u8], ParserError> {
+        let start = offset as usize;
+        self.data
+            .get(start..start + length as usize)
+            .ok_or_else(|| ParserError::from_string("Woff2 data out of bounds.".to_string()))
+    }
+
+    /// Returns the extended metadata, an XML document, if present.
+    pub fn metadata(&self) -> Result<Option<String>, ParserError> {
+        if self.header.meta_length == 0 {
+            return Ok(None);
+        }
+        let data = self.block(self.header.meta_offset, self.header.meta_length)?;
+        let metadata = decompress(data, u64::from(self.header.meta_orig_length))?;
+        String::from_utf8(metadata)
+            .map(Some)
+            .map_err(ParserError::from_err)
+    }
+
+    /// Returns the private data block if present.
+    pub fn private_data(&self) -> Result<Option<&'a [u8]>, ParserError> {
+        if self.header.priv_length == 0 {
+            return Ok(None);
+        }
+        self.block(self.header.priv_offset, self.header.priv_length)
+            .map(Some)
+    }
+}
+
+impl<'a, 'w> OpentypeTableAccess<'w> for &'w Woff2<'a> {
+    type TableData = &'w [u8];
+
+    fn table_data(&self, tag: Tag) -> Option<Self::TableData> {
+        let record = self.record.tables.get(&tag)?;
+        let start = record.offset as usize;
+        self.sfnt.get(start..start + record.length as usize)
+    }
+}
+
+fn push_uint_base128(value: u32, out: &mut Vec<u8>) {
+    let mut shift = 28;
+    while shift > 0 && value >> shift == 0 {
+        shift -= 7;
+    }
+    while shift > 0 {
+        out.push((value >> shift) as u8 & 0x7F | 0x80);
+        shift -= 7;
+    }
+    out.push(value as u8 & 0x7F);
+}
+
+fn push_255_uint16(value: u16, out: &mut Vec<u8>) {
+    match value {
+        0..=252 => out.push(value as u8),
+        253..=505 => out.extend_from_slice(&[255, (value - 253) as u8]),
+        506..=761 => out.extend_from_slice(&[254, (value - 506) as u8]),
+        _ => {
+            out.push(253);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Encodes the coordinate deltas of a point in the smallest triplet.
+fn push_triplet(dx: i32, dy: i32, on_curve: bool, flags: &mut Vec<u8>, out: &mut Vec<u8>) {
+    let (abs_x, abs_y) = (dx.unsigned_abs(), dy.unsigned_abs());
+    let on_curve = if on_curve { 0 } else { 0x80 };
+    let x_sign = if dx < 0 { 0 } else { 1 };
+    let y_sign = if dy < 0 { 0 } else { 1 };
+    let xy_sign = x_sign + 2 * y_sign;
+    if dx == 0 && abs_y < 1280 {
+        flags.push(on_curve + ((abs_y & 0xF00) >> 7) as u8 + y_sign);
+        out.push(abs_y as u8);
+    } else if dy == 0 && abs_x < 1280 {
+        flags.push(on_curve + 10 + ((abs_x & 0xF00) >> 7) as u8 + x_sign);
+        out.push(abs_x as u8);
+    } else if abs_x < 65 && abs_y < 65 {
+        let (x, y) = (abs_x - 1, abs_y - 1);
+        flags.push(on_curve + 20 + (x & 0x30) as u8 + ((y & 0x30) >> 2) as u8 + xy_sign);
+        out.push(((x & 0x0F) << 4 | (y & 0x0F)) as u8);
+    } else if abs_x < 769 && abs_y < 769 {
+        let (x, y) = (abs_x - 1, abs_y - 1);
+        flags.push(
+            on_curve + 84 + 12 * ((x & 0x300) >> 8) as u8 + ((y & 0x300) >> 6) as u8 + xy_sign,
+        );
+        out.extend_from_slice(&[x as u8, y as u8]);
+    } else if abs_x < 4096 && abs_y < 4096 {
+        flags.push(on_curve + 120 + xy_sign);
+        out.extend_from_slice(&[
+            (abs_x >> 4) as u8,
+            ((abs_x & 0x0F) << 4 | abs_y >> 8) as u8,
+            abs_y as u8,
+        ]);
+    } else {
+        flags.push(on_curve + 124 + xy_sign);
+        out.extend_from_slice(&(abs_x as u16).to_be_bytes());
+        out.extend_from_slice(&(abs_y as u16).to_be_bytes());
+    }
+}
+
+fn set_bit(bitmap: &mut [u8], index: usize) {
+    bitmap[index >> 3] |= 0x80 >> (index & 7);
+}
+
+fn push_bbox(header: &Header, out: &mut Vec<u8>) {
+    for &value in &[header.xmin, header.ymin, header.xmax, header.ymax] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Applies the WOFF2 transformation to the `glyf` table.
+///
+/// Bounding boxes of simple glyphs are only stored if they differ from the
+/// bounds of their points.
+fn transform_glyf(
+    glyf: &[u8],
+    loca: &Loca,
+    num_glyphs: u16,
+    index_format: LocFormat,
+) -> Result<Vec<u8>, ParserError> {
+    if loca.num_entries() <= usize::from(num_glyphs) {
+        return Err(ParserError::from_string(
+            "The loca table has too few entries.".to_string(),
+        ));
+    }
+    let num_glyphs = usize::from(num_glyphs);
+    let mut streams: [Vec<u8>; 7] = Default::default();
+    let [n_contour, n_points, flag, glyph, composite, bbox, instruction] = &mut streams;
+    let mut bbox_bitmap = vec![0; num_glyphs.div_ceil(32) * 4];
+    let mut overlap_bitmap = vec![0; num_glyphs.div_ceil(8)];
+    let mut has_overlap = false;
+    for index in 0..num_glyphs {
+        let start = loca.offset(index as u16)? as usize;
+        let end = loca.offset(index as u16 + 1)? as usize;
+        let data = glyf.get(start..end).ok_or_else(|| {
+            ParserError::glyph_parse(
+                index as u32,
+                ParserError::from_string("Glyph out of bounds.".to_string()),
+            )
+        })?;
+        if data.is_empty() {
+            n_contour.extend_from_slice(&0i16.to_be_bytes());
+            continue;
+        }
+        let (_, header) = parse_header(data)?;
+        n_contour.extend_from_slice(&header.number_of_contours.to_be_bytes());
+        if header.number_of_contours > 0 {
+            let (_, simple) = parse_simple_glyph(data)?;
+            let contours = simple.contours();
+            let (mut x, mut y) = (0, 0);
+            for contour in &contours {
+                push_255_uint16(contour.len() as u16, n_points);
+                for point in contour {
+                    push_triplet(point.x - x, point.y - y, point.on_curve, flag, glyph);
+                    x = point.x;
+                    y = point.y;
+                }
+            }
+            push_255_uint16(simple.instructions().len() as u16, glyph);
+            instruction.extend_from_slice(simple.instructions());
+            if contour_bounds(&contours)? != header {
+                set_bit(&mut bbox_bitmap, index);
+                push_bbox(&header, bbox);
+            }
+            if simple.overlap_simple() {
+                set_bit(&mut overlap_bitmap, index);
+                has_overlap = true;
+            }
+        } else if header.number_of_contours < 0 {
+            set_bit(&mut bbox_bitmap, index);
+            push_bbox(&header, bbox);
+            let mut components = &data[10..];
+            let mut have_instructions = false;
+            loop {
+                let (_, flags) = be_u16(components)?;
+                composite.extend_from_slice(take_stream(&mut components, component_len(flags))?);
+                have_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+                if flags & MORE_COMPONENTS == 0 {
+                    break;
+                }
+            }
+            if have_instructions {
+                let (_, instructions) = length_data!(components, be_u16)?;
+                push_255_uint16(instructions.len() as u16, glyph);
+                instruction.extend_from_slice(instructions);
+            }
+        }
+    }
+
+    let mut out = vec![0, 0];
+    out.extend_from_slice(&u16::from(has_overlap).to_be_bytes());
+    out.extend_from_slice(&(num_glyphs as u16).to_be_bytes());
+    let index_format: u16 = match index_format {
+        LocFormat::Short => 0,
+        LocFormat::Long => 1,
+    };
+    out.extend_from_slice(&index_format.to_be_bytes());
+    for (i, stream) in streams.iter().enumerate() {
+        // the bounding box stream starts with its bitmap
+        let len = if i == 5 {
+            stream.len() + bbox_bitmap.len()
+        } else {
+            stream.len()
+        };
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    for (i, stream) in streams.iter().enumerate() {
+        if i == 5 {
+            out.extend_from_slice(&bbox_bitmap);
+        }
+        out.extend_from_slice(stream);
+    }
+    if has_overlap {
+        out.extend_from_slice(&overlap_bitmap);
+    }
+    Ok(out)
+}
+
+/// Applies the WOFF2 transformation to the `hmtx` table if one of the arrays
+/// of left side bearings equals the minimal x coordinates of the glyphs.
+fn transform_hmtx(hmtx: &Hmtx, x_mins: &[i16]) -> Option<Vec<u8>> {
+    let num_long = hmtx.h_metrics.len();
+    if hmtx.num_glyphs() != x_mins.len() {
+        return None;
+    }
+    let proportional = hmtx
+        .h_metrics
+        .iter()
+        .zip(x_mins)
+        .all(|(metric, &x_min)| metric.lsb == x_min);
+    let monospaced = hmtx.left_side_bearings[..] == x_mins[num_long..];
+    if !proportional && !monospaced {
+        return None;
+    }
+    let mut out = vec![u8::from(proportional) | u8::from(monospaced) << 1];
+    for metric in &hmtx.h_metrics {
+        out.extend_from_slice(&metric.advance_width.to_be_bytes());
+    }
+    if !proportional {
+        for metric in &hmtx.h_metrics {
+            out.extend_from_slice(&metric.lsb.to_be_bytes());
+        }
+    }
+    if !monospaced {
+        for lsb in &hmtx.left_side_bearings {
+            out.extend_from_slice(&lsb.to_be_bytes());
+        }
+    }
+    Some(out)
+}
+
+/// The transformed `glyf`, `loca` and `hmtx` tables of a font.
+struct TransformedTables {
+    /// The transformed `glyf` table.
+    glyf: Vec<u8>,
+    /// The reconstructed tables which replace the original `glyf` and `loca`
+    /// tables, so that the decoded font equals the encoded one.
+    reconstructed: ReconstructedGlyf,
+    hmtx: Option<Vec<u8>>,
+}
+
+fn transform_tables<'a>(
+    font: &impl OpentypeTableAccess<'a>,
+    with_hmtx: bool,
+) -> Result<TransformedTables, ParserError> {
+    let table = |tag: &[u8; 4]| {
+        font.table_data(Tag(*tag))
+            .ok_or_else(|| ParserError::expected_table(Tag(*tag)))
+    };
+    let head = Head::from_data(&table(b"head")?, ())?;
+    let num_glyphs = Maxp::from_data(&table(b"maxp")?, ())?.num_glyphs;
+    let loca = Loca::from_data(&table(b"loca")?, head.index_to_loc_format)?;
+    let glyf = transform_glyf(
+        &table(b"glyf")?,
+        &loca,
+        num_glyphs,
+        head.index_to_loc_format,
+    )?;
+    let reconstructed = reconstruct_glyf(&glyf)?;
+
+    let mut hmtx = None;
+    if with_hmtx {
+        if let (Some(hhea), Some(hmtx_data)) = (
+            font.table_data(Tag(*b"hhea")),
+            font.table_data(Tag(*b"hmtx")),
+        ) {
+            let context = HmtxContext {
+                number_of_h_metrics: Hhea::from_data(&hhea, ())?.number_of_h_metrics,
+                num_glyphs,
+            };
+            let metrics = Hmtx::from_data(&hmtx_data, context)?;
+            // tables with trailing data can't be reconstructed
+            let mut data = vec![];
+            metrics.write_to(&mut data).map_err(ParserError::from_err)?;
+            if data[..] == hmtx_data[..] {
+                hmtx = transform_hmtx(&metrics, &reconstructed.x_mins);
+            }
+        }
+    }
+    Ok(TransformedTables {
+        glyf,
+        reconstructed,
+        hmtx,
+    })
+}
+
+/// Options for writing WOFF2 files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Woff2Options<'m> {
+    /// The version of the font.
+    pub major_version: u16,
+    pub minor_version: u16,
+    /// The extended metadata, an XML document.
+    pub metadata: Option<&'m str>,
+    pub private_data: Option<&'m [u8]>,
+    /// Transforms the `glyf` and `loca` tables. The glyphs are stored in the
+    /// encoding produced by the decoder, which can differ from the original
+    /// encoding.
+    pub transform_glyf: bool,
+    /// Transforms the `hmtx` table if possible, which requires the `glyf`
+    /// transformation.
+    pub transform_hmtx: bool,
+}
+
+impl<'m> Default for Woff2Options<'m> {
+    fn default() -> Self {
+        Woff2Options {
+            major_version: 0,
+            minor_version: 0,
+            metadata: None,
+            private_data: None,
+            transform_glyf: true,
+            transform_hmtx: true,
+        }
+    }
+}
+
+fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = CompressorWriter::new(Vec::new(), 4096, 11, 22);
+    encoder.write_all(data)?;
+    encoder.flush()?;
+    Ok(encoder.into_inner())
+}
+
+/// Writes the given tables of `font` as a WOFF2 file.
+///
+/// The tables are those of the font written by
+/// [`FontBuilder`](../struct.FontBuilder.html), so decoding the file results
+/// in exactly this font. If the `glyf` table can't be transformed, it is
+/// stored unchanged.
+pub fn write_woff2<'a>(
+    font: &impl OpentypeTableAccess<'a>,
+    version_tag: Tag,
+    tables: &[Tag],
+    options: &Woff2Options<'_>,
+    sink: &mut dyn Write,
+) -> io::Result<()> {
+    let invalid_data =
+        |err: ParserError| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+    let mut transformed = None;
+    if options.transform_glyf && tables.contains(&Tag(*b"glyf")) && tables.contains(&Tag(*b"loca"))
+    {
+        match transform_tables(font, options.transform_hmtx) {
+            Ok(tables) => transformed = Some(tables),
+            Err(err) => warn!("Could not transform the glyf table: {}", err),
+        }
+    }
+
+    let mut builder = FontBuilder::new(version_tag);
+    builder.copy_tables(font, tables).map_err(invalid_data)?;
+    if let Some(ref transformed) = transformed {
+        builder.add_table(Tag(*b"glyf"), transformed.reconstructed.glyf.as_slice());
+        builder.add_table(Tag(*b"loca"), transformed.reconstructed.loca.as_slice());
+    }
+    let mut sfnt = vec![];
+    builder.write_to(&mut sfnt)?;
+    let sfnt_font = Font::from_bytes(&sfnt, 0).map_err(invalid_data)?;
+
+    // a transformed loca table has to follow the glyf table
+    let mut order: Vec<Tag> = sfnt_font
+        .record()
+        .tables
+        .keys()
+        .cloned()
+        .filter(|&tag| tag != Tag(*b"loca"))
+        .collect();
+    if sfnt_font.has_table(Tag(*b"loca")) {
+        let position = order
+            .iter()
+            .position(|&tag| tag == Tag(*b"glyf"))
+            .map_or(order.len(), |glyf| glyf + 1);
+        order.insert(position, Tag(*b"loca"));
+    }
+
+    let mut directory = vec![];
+    let mut table_data = vec![];
+    for tag in order {
+        let data = sfnt_font.table_data(tag).unwrap_or(&[]);
+        let transform: Option<(u8, &[u8])> = match (&tag.0, &transformed) {
+            (b"glyf", Some(transformed)) => Some((0, &transformed.glyf)),
+            (b"loca", Some(_)) => Some((0, &[])),
+            (b"glyf", None) | (b"loca", None) => None,
+            (
+                b"hmtx",
+                Some(TransformedTables {
+                    hmtx: Some(hmtx), ..
+                }),
+            ) => Some((1, hmtx)),
+            _ => None,
+        };
+        let version = match transform {
+            Some((version, _)) => version,
+            None if tag == Tag(*b"glyf") || tag == Tag(*b"loca") => 3,
+            None => 0,
+        };
+        match KNOWN_TAGS.iter().position(|&known| known == &tag.0) {
+            Some(index) => directory.push(version << 6 | index as u8),
+            None => {
+                directory.push(version << 6 | 0x3F);
+                directory.extend_from_slice(&tag.0);
+            }
+        }
+        push_uint_base128(data.len() as u32, &mut directory);
+        match transform {
+            Some((_, transformed)) => {
+                push_uint_base128(transformed.len() as u32, &mut directory);
+                table_data.extend_from_slice(transformed);
+            }
+            None => table_data.extend_from_slice(data),
+        }
+    }
+    let compressed = compress(&table_data)?;
+
+    let mut offset = HEADER_LEN + directory.len() + compressed.len();
+    let metadata = match options.metadata {
+        Some(metadata) => compress(metadata.as_bytes())?,
+        None => vec![],
+    };
+    if !metadata.is_empty() {
+        offset = padded_len(offset);
+    }
+    let meta_offset = if metadata.is_empty() { 0 } else { offset };
+    offset += metadata.len();
+    let private_data = options.private_data.unwrap_or(&[]);
+    if !private_data.is_empty() {
+        offset = padded_len(offset);
+    }
+    let priv_offset = if private_data.is_empty() { 0 } else { offset };
+    offset += private_data.len();
+    if offset > u32::MAX as usize || table_data.len() > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too much data for a woff2 file.",
+        ));
+    }
+
+    let header = [
+        WOFF2_SIGNATURE,
+        u32::from_be_bytes(version_tag.0),
+        offset as u32,
+        (sfnt_font.record().tables.len() as u32) << 16,
+        sfnt.len() as u32,
+        compressed.len() as u32,
+        u32::from(options.major_version) << 16 | u32::from(options.minor_version),
+        meta_offset as u32,
+        metadata.len() as u32,
+        options.metadata.map_or(0, str::len) as u32,
+        priv_offset as u32,
+        private_data.len() as u32,
+    ];
+    let mut written = HEADER_LEN + directory.len() + compressed.len();
+    for value in &header {
+        sink.write_all(&value.to_be_bytes())?;
+    }
+    sink.write_all(&directory)?;
+    sink.write_all(&compressed)?;
+    if !metadata.is_empty() {
+        sink.write_all(&[0; 4][..meta_offset - written])?;
+        sink.write_all(&metadata)?;
+        written = meta_offset + metadata.len();
+    }
+    if !private_data.is_empty() {
+        sink.write_all(&[0; 4][..priv_offset - written])?;
+        sink.write_all(private_data)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tables::glyf::parse_simple_glyph;
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_uint_base128(&[0x3F]).unwrap().1, 63);
+        assert_eq!(parse_uint_base128(&[0x81, 0x00]).unwrap().1, 128);
+        assert_eq!(
+            parse_uint_base128(&[0x8F, 0xFF, 0xFF, 0xFF, 0x7F])
+                .unwrap()
+                .1,
+            u32::MAX
+        );
+        // leading zeros, overflow and too many bytes
+        assert!(parse_uint_base128(&[0x80, 0x01]).is_err());
+        assert!(parse_uint_base128(&[0x9F, 0xFF, 0xFF, 0xFF, 0x7F]).is_err());
+        assert!(parse_uint_base128(&[0x81, 0x80, 0x80, 0x80, 0x80, 0x00]).is_err());
+
+        assert_eq!(parse_255_uint16(&[252]).unwrap().1, 252);
+        assert_eq!(parse_255_uint16(&[255, 0]).unwrap().1, 253);
+        assert_eq!(parse_255_uint16(&[254, 0]).unwrap().1, 506);
+        assert_eq!(parse_255_uint16(&[253, 0x12, 0x34]).unwrap().1, 0x1234);
+    }
+
+    #[test]
+    fn test_number_round_trip() {
+        for &value in &[0, 1, 127, 128, 0x3FFF, 0x4000, 0x0FFF_FFFF, u32::MAX] {
+            let mut data = vec![];
+            push_uint_base128(value, &mut data);
+            assert_eq!(parse_uint_base128(&data), Ok((&[][..], value)));
+        }
+        for value in 0..=u16::MAX {
+            let mut data = vec![];
+            push_255_uint16(value, &mut data);
+            assert_eq!(parse_255_uint16(&data), Ok((&[][..], value)));
+        }
+    }
+
+    #[test]
+    fn test_triplet_round_trip() {
+        let values = [0, 1, 15, 64, 65, 255, 768, 769, 1279, 1280, 4095, 4096, 65535];
+        for &x in &values {
+            for &y in &values {
+                for &(dx, dy) in &[(x, y), (-x, y), (x, -y), (-x, -y)] {
+                    for &on_curve in &[true, false] {
+                        let (mut flags, mut data) = (vec![], vec![]);
+                        push_triplet(dx, dy, on_curve, &mut flags, &mut data);
+                        assert_eq!(flags.len(), 1);
+                        assert_eq!(flags[0] & 0x80 == 0, on_curve);
+                        let (rest, delta) = parse_triplet(&data, flags[0]).unwrap();
+                        assert!(rest.is_empty());
+                        assert_eq!(delta, (dx, dy));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_triplet() {
+        assert_eq!(parse_triplet(&[5], 1).unwrap().1, (0, 5));
+        assert_eq!(parse_triplet(&[5], 0x80 | 10).unwrap().1, (-5, 0));
+        assert_eq!(parse_triplet(&[0x12], 23).unwrap().1, (2, 3));
+        assert_eq!(parse_triplet(&[1, 2], 84).unwrap().1, (-2, -3));
+        assert_eq!(
+            parse_triplet(&[0x12, 0x34, 0x56], 123).unwrap().1,
+            (0x123, 0x456)
+        );
+        assert_eq!(
+            parse_triplet(&[0x01, 0x00, 0x02, 0x00], 124).unwrap().1,
+            (-256, -512)
+        );
+        assert!(parse_triplet(&[0x01, 0x00], 124).is_err());
+    }
+
+    #[test]
+    fn test_coordinate_overflow() {
+        // 40000 points that each move by 65535 units in both directions
+        let glyph = vec![0xFF; 4 * 40000];
+        let flag = vec![127; 40000];
+        let mut streams = GlyfStreams {
+            n_contour: &[],
+            n_points: &[253, 0x9C, 0x40],
+            flag: &flag,
+            glyph: &glyph,
+            composite: &[],
+            bbox_bitmap: &[],
+            bbox: &[],
+            instruction: &[],
+            overlap_bitmap: &[],
+        };
+        let err = streams.simple_glyph(0, 1, &mut vec![]).unwrap_err();
+        assert!(err.to_string().contains("overflows"));
+    }
+
+    #[test]
+    fn test_reconstruct_glyf() {
+        // an empty glyph and a triangle with an explicit bounding box
+        let streams: [&[u8]; 7] = [
+            &[0, 0, 0, 1],
+            &[3],
+            &[11, 0x80 | 1, 84],
+            &[100, 200, 99, 199, 1],
+            &[],
+            &[0x40, 0, 0, 0, 0, 0, 0, 0, 0, 200, 0, 200],
+            &[0x2A],
+        ];
+        let mut data = vec![0, 0, 0, 0, 0, 2, 0, 0];
+        for stream in &streams {
+            data.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+        }
+        for stream in &streams {
+            data.extend_from_slice(stream);
+        }
+        let glyf = reconstruct_glyf(&data).unwrap();
+        assert_eq!(glyf.x_mins, vec![0, 0]);
+        let loca = Loca::from_data(&glyf.loca, LocFormat::Short).unwrap();
+        assert_eq!(loca.num_entries(), 3);
+        assert_eq!(loca.offset(0).unwrap(), loca.offset(1).unwrap());
+        let glyph_data =
+            &glyf.glyf[loca.offset(1).unwrap() as usize..loca.offset(2).unwrap() as usize];
+        let glyph = parse_simple_glyph(glyph_data).unwrap().1;
+        assert_eq!(glyph.header.xmax, 200);
+        assert_eq!(glyph.instructions(), &[0x2A]);
+        assert_eq!(
+            glyph.contours(),
+            vec![vec![
+                GlyphPoint::new(100, 0, true),
+                GlyphPoint::new(100, 200, false),
+                GlyphPoint::new(0, 0, true),
+            ]]
+        );
+
+        // an empty glyph must not have a bounding box
+        let mut invalid = data.clone();
+        invalid[39] = 0;
+        assert!(reconstruct_glyf(&invalid).is_err());
+        assert!(reconstruct_glyf(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -15,8 +15,14 @@
 extern crate font_parse;
 extern crate simple_logger;
 
-use font_parse::tables::cff;
-use font_parse::{Font, Glyph, OpentypeTableAccess, ParseTable, Tag};
+use font_parse::tables::cff::{self, Cff, CffBuilder};
+use font_parse::tables::hhea::Hhea;
+use font_parse::tables::hmtx::{Hmtx, HmtxContext};
+use font_parse::tables::maxp::Maxp;
+use font_parse::tables::SfntTable;
+use font_parse::{Font, Glyph, OpentypeTableAccess, ParseTable, PathInstruction, Tag};
+
+use std::collections::BTreeMap;
 
 fn get_cff_font() -> Font<'static> {
     let data = include_bytes!("font_files/LinBiolinum_R.otf");
@@ -54,3 +60,86 @@ fn test_parse_glyphs_cff() {
         }
     }
 }
+
+const NUM_GLYPHS: u32 = 120;
+
+fn path(glyph: Glyph) -> Vec<PathInstruction> {
+    match glyph {
+        Glyph::Cff(mut glyph) => glyph
+            .contour_iter()
+            .filter(|&instr| instr != PathInstruction::Close)
+            .collect(),
+        _ => panic!("Expected cff glyph"),
+    }
+}
+
+fn build_cff(subroutinize: bool) -> (Vec<u8>, Vec<Vec<PathInstruction>>) {
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let cff = font.parse_table::<Cff>().unwrap();
+    let cff = cff.get().unwrap();
+    let hhea: Hhea = font.parse_table().unwrap().get().unwrap();
+    let maxp = font.parse_table::<Maxp>().unwrap();
+    let maxp = maxp.get().unwrap();
+    let context = HmtxContext {
+        number_of_h_metrics: hhea.number_of_h_metrics,
+        num_glyphs: maxp.num_glyphs,
+    };
+    let hmtx: Hmtx = font.parse_table_context(context).unwrap().get().unwrap();
+    let mut glyphs = font.glyphs().unwrap();
+
+    let mut builder = CffBuilder::new(&cff.name);
+    builder.full_name = cff.top_dict_data.full_name.clone();
+    builder.family_name = cff.top_dict_data.family_name.clone();
+    builder.copyright = "Copyright (c) Libertine Open Fonts Project".to_string();
+    builder.subroutinize = subroutinize;
+
+    let mut paths = vec![];
+    for index in 0..NUM_GLYPHS {
+        let path = path(glyphs.index(index).unwrap().unwrap());
+        let width = hmtx.advance_width(index as u16).unwrap();
+        builder.add_glyph(&cff.glyph_name(index).unwrap(), width.into(), &path);
+        paths.push(path);
+    }
+
+    let mut out = vec![];
+    builder.write_to(&mut out).unwrap();
+    (out, paths)
+}
+
+#[test]
+fn test_write_cff() {
+    for &subroutinize in &[false, true] {
+        let (data, paths) = build_cff(subroutinize);
+        let cff = Cff::from_data(&data, ()).unwrap();
+        assert_eq!(cff.name, "LinBiolinumO");
+        assert_eq!(cff.top_dict_data.family_name, "Linux Biolinum O");
+        assert_eq!(
+            cff.top_dict_data.copyright,
+            "Copyright (c) Libertine Open Fonts Project"
+        );
+        assert_eq!(cff.num_glyphs(), NUM_GLYPHS);
+        assert_eq!(cff.glyph_name(0).unwrap(), ".notdef");
+        assert_eq!(cff.glyph_name(1).unwrap(), "space");
+
+        let mut tables = BTreeMap::new();
+        tables.insert(Tag(*b"CFF "), data);
+        let mut glyphs = (&tables).glyphs().unwrap();
+        for (index, expected) in paths.iter().enumerate() {
+            let glyph = glyphs.index(index as u32).unwrap().unwrap();
+            assert_eq!(&path(glyph), expected, "glyph {}", index);
+        }
+    }
+}
+
+#[test]
+fn test_subroutinize_cff() {
+    let (plain, _) = build_cff(false);
+    let (subroutinized, _) = build_cff(true);
+    assert!(
+        subroutinized.len() < plain.len(),
+        "{} >= {}",
+        subroutinized.len(),
+        plain.len()
+    );
+}
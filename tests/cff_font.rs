@@ -0,0 +1,164 @@
+extern crate font_parse;
+
+use font_parse::cff_font::CffFont;
+use font_parse::tables::cff::CffBuilder;
+use font_parse::tables::cmap::Cmap;
+use font_parse::tables::head::Head;
+use font_parse::tables::hhea::Hhea;
+use font_parse::tables::hmtx::{Hmtx, HmtxContext};
+use font_parse::tables::maxp::Maxp;
+use font_parse::tables::name::{self, Name};
+use font_parse::tables::os2::Os2;
+use font_parse::tables::post::Post;
+use font_parse::{Font, OpentypeTableAccess, ParseTable, PathInstruction, Tag};
+
+fn read_hmtx<'a>(font: &impl OpentypeTableAccess<'a>) -> Hmtx {
+    let hhea: Hhea = font.parse_table().unwrap().get().unwrap();
+    let maxp = font.parse_table::<Maxp>().unwrap();
+    let context = HmtxContext {
+        number_of_h_metrics: hhea.number_of_h_metrics,
+        num_glyphs: maxp.get().unwrap().num_glyphs,
+    };
+    font.parse_table_context(context).unwrap().get().unwrap()
+}
+
+#[test]
+fn test_cff_font() {
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let otf = Font::from_bytes(data, 0).unwrap();
+    let cff_data = otf.table_data(Tag(*b"CFF ")).unwrap();
+    let font = CffFont::from_bytes(cff_data).unwrap();
+    let font = &font;
+    assert_eq!(font.table_data(Tag(*b"CFF ")), Some(cff_data));
+    assert!(font.table_data(Tag(*b"glyf")).is_none());
+
+    let head: Head = font.parse_table().unwrap().get().unwrap();
+    let otf_head: Head = otf.parse_table().unwrap().get().unwrap();
+    assert_eq!(head.units_per_em, 1000);
+    assert!(head.x_min <= otf_head.x_min && head.y_min <= otf_head.y_min);
+    assert!(head.x_max >= otf_head.x_max && head.y_max >= otf_head.y_max);
+
+    let maxp = font.parse_table::<Maxp>().unwrap();
+    let maxp = maxp.get().unwrap();
+    let otf_maxp = otf.parse_table::<Maxp>().unwrap();
+    assert_eq!(maxp.version, 0x5000);
+    assert_eq!(maxp.num_glyphs, otf_maxp.get().unwrap().num_glyphs);
+
+    // the widths of the charstrings match the metrics of the OpenType font
+    let hmtx = read_hmtx(&font);
+    let otf_hmtx = read_hmtx(&otf);
+    let mut glyphs = font.glyphs().unwrap();
+    let mut otf_glyphs = otf.glyphs().unwrap();
+    for index in 0..maxp.num_glyphs {
+        assert_eq!(
+            hmtx.advance_width(index),
+            otf_hmtx.advance_width(index),
+            "glyph {}",
+            index
+        );
+        assert_eq!(
+            glyphs.outline(u32::from(index)).unwrap(),
+            otf_glyphs.outline(u32::from(index)).unwrap()
+        );
+    }
+
+    // the cmap maps the codes of the standard encoding
+    let cff = font.cff();
+    let cmap = font.parse_table::<Cmap>().unwrap();
+    let mappings = cmap.get().unwrap().mappings();
+    assert_eq!(mappings.len(), cff.encoding().len());
+    let glyph = mappings[&u32::from(b'A')];
+    assert_eq!(cff.glyph_name(u32::from(glyph)).unwrap(), "A");
+    assert_eq!(Some(&glyph), cff.encoding().get(&b'A'));
+}
+
+#[test]
+fn test_cff_font_widths() {
+    let square = [
+        PathInstruction::MoveTo(10.into(), 0.into()),
+        PathInstruction::LineTo(100.into(), 0.into()),
+        PathInstruction::LineTo(0.into(), 200.into()),
+        PathInstruction::LineTo((-100).into(), 0.into()),
+    ];
+    let mut builder = CffBuilder::new("Test");
+    builder
+        .add_glyph(".notdef", 500, &[])
+        .add_glyph("space", 250, &[])
+        .add_glyph("A", 500, &square)
+        .add_glyph("B", 700, &square);
+    let mut data = vec![];
+    builder.write_to(&mut data).unwrap();
+
+    let font = CffFont::from_bytes(&data).unwrap();
+    let hmtx = read_hmtx(&&font);
+    let widths: Vec<_> = (0..4).map(|index| hmtx.advance_width(index)).collect();
+    assert_eq!(widths, vec![Some(500), Some(250), Some(500), Some(700)]);
+    assert_eq!(hmtx.metric(2).unwrap().lsb, 10);
+
+    let hhea: Hhea = (&font).parse_table().unwrap().get().unwrap();
+    assert_eq!(hhea.advance_width_max, 700);
+    assert_eq!(hhea.ascender, 200);
+    assert_eq!(hhea.min_right_side_bearing, 390);
+
+    let cmap = (&font).parse_table::<Cmap>().unwrap();
+    let mappings: Vec<_> = cmap.get().unwrap().mappings().into_iter().collect();
+    assert_eq!(mappings, vec![(32, 1), (65, 2), (66, 3)]);
+
+    assert!(CffFont::from_bytes(&data[..20]).is_err());
+}
+
+#[test]
+fn test_write_otf() {
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let original = Font::from_bytes(data, 0).unwrap();
+    let cff_data = original.table_data(Tag(*b"CFF ")).unwrap();
+    let font = CffFont::from_bytes(cff_data).unwrap();
+    let mut out = vec![];
+    font.write_otf(&mut out).unwrap();
+
+    let otf = Font::from_bytes(&out, 0).unwrap();
+    assert_eq!(otf.table_data(Tag(*b"CFF ")), Some(cff_data));
+    for tag in &[
+        b"head", b"hhea", b"hmtx", b"maxp", b"OS/2", b"name", b"post", b"cmap",
+    ] {
+        assert!(otf.table_data(Tag(**tag)).is_some());
+    }
+
+    // the glyph names map to the same code points as the original cmap
+    let cmap = otf.parse_table::<Cmap>().unwrap();
+    let mappings = cmap.get().unwrap().mappings();
+    let original_cmap = original.parse_table::<Cmap>().unwrap();
+    let original_mappings = original_cmap.get().unwrap().mappings();
+    for code in "Aa0&\u{E9}\u{20AC}".chars().map(|c| c as u32) {
+        assert_eq!(
+            mappings.get(&code),
+            original_mappings.get(&code),
+            "{:x}",
+            code
+        );
+    }
+
+    let name = otf.parse_table::<Name>().unwrap();
+    let name = name.get().unwrap();
+    let cff = font.cff();
+    assert_eq!(name.string(name::POSTSCRIPT_NAME).unwrap(), cff.name);
+    assert_eq!(
+        name.string(name::FAMILY_NAME).unwrap(),
+        cff.top_dict_data.family_name
+    );
+    assert_eq!(name.string(name::SUBFAMILY_NAME).unwrap(), "Regular");
+
+    let os2: Os2 = otf.parse_table().unwrap().get().unwrap();
+    let original_os2: Os2 = original.parse_table().unwrap().get().unwrap();
+    assert_eq!(os2.weight_class, 400);
+    assert_eq!(os2.fs_selection, 1 << 6);
+    assert_eq!(os2.unicode_range[0] & 0b111, 0b111);
+    assert_eq!(os2.x_avg_char_width, original_os2.x_avg_char_width);
+    assert!(os2.x_height > 400 && os2.x_height < os2.cap_height);
+    assert!(os2.cap_height > 600 && os2.cap_height <= original_os2.cap_height);
+
+    let post = otf.parse_table::<Post>().unwrap();
+    let post = post.get().unwrap();
+    assert_eq!(post.version, 0x0003_0000);
+    assert_eq!(post.italic_angle, 0);
+}
@@ -0,0 +1,131 @@
+extern crate font_parse;
+
+use font_parse::collection::{extract_font, write_font_collection, CollectionFont};
+use font_parse::{parse, Font, FontFile, OpentypeTableAccess, Tag};
+
+/// Returns the sum of the data as big-endian 32 bit integers.
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut bytes = [0; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(bytes))
+    })
+}
+
+/// Returns the checksum of the table directory and the tables of a font.
+fn font_checksum(data: &[u8], font: &Font) -> u32 {
+    let record = font.record();
+    let num_tables = record.tables.len();
+    let mut directory = vec![];
+    record.write_to(&mut directory).unwrap();
+    assert_eq!(directory.len(), 12 + 16 * num_tables);
+    assert!(data
+        .windows(directory.len())
+        .any(|window| window == &directory[..]));
+    record
+        .tables
+        .keys()
+        .map(|&tag| checksum(font.table_data(tag).unwrap()))
+        .fold(checksum(&directory), u32::wrapping_add)
+}
+
+fn build_collection(dsig: Option<&[u8]>) -> Vec<u8> {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let all_tables: Vec<Tag> = font.record().tables.keys().cloned().collect();
+    let some_tables: Vec<Tag> = all_tables
+        .iter()
+        .cloned()
+        .filter(|tag| tag != &Tag(*b"GSUB") && tag != &Tag(*b"GPOS"))
+        .collect();
+    let fonts = [
+        CollectionFont {
+            font: &font,
+            version_tag: Tag([0, 1, 0, 0]),
+            tables: all_tables,
+        },
+        CollectionFont {
+            font: &font,
+            version_tag: Tag([0, 1, 0, 0]),
+            tables: some_tables,
+        },
+    ];
+    let mut out = vec![];
+    write_font_collection(&fonts, dsig, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn test_write_font_collection() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let original = Font::from_bytes(data, 0).unwrap();
+    let out = build_collection(None);
+    // shared tables are only stored once
+    assert!(out.len() < data.len() + 1000);
+
+    match parse(&out).unwrap() {
+        FontFile::Collection(collection) => {
+            assert_eq!(collection.major_version, 1);
+            assert_eq!(collection.fonts.len(), 2);
+        }
+        FontFile::Single(_) => panic!("Expected a font collection"),
+    }
+
+    for index in 0..2 {
+        let font = Font::from_bytes(&out, index).unwrap();
+        for &tag in font.record().tables.keys() {
+            if tag != Tag(*b"head") {
+                assert_eq!(font.table_data(tag), original.table_data(tag), "{}", tag);
+            }
+        }
+        assert_eq!(font_checksum(&out, &font), 0xB1B0_AFBA);
+        let mut glyphs = font.glyphs().unwrap();
+        assert!(glyphs.outline(36).unwrap().is_some());
+    }
+    let second = Font::from_bytes(&out, 1).unwrap();
+    assert!(!second.has_table(Tag(*b"GSUB")));
+    assert_eq!(
+        second.record().tables[&Tag(*b"glyf")].offset,
+        Font::from_bytes(&out, 0).unwrap().record().tables[&Tag(*b"glyf")].offset
+    );
+}
+
+#[test]
+fn test_font_collection_dsig() {
+    let dsig = [0, 0, 0, 1, 0, 0, 0, 0];
+    let out = build_collection(Some(&dsig));
+    match parse(&out).unwrap() {
+        FontFile::Collection(collection) => {
+            assert_eq!(collection.major_version, 2);
+            assert_eq!(collection.dsig_tag, u32::from_be_bytes(*b"DSIG"));
+            assert_eq!(collection.dsig_length, 8);
+            let offset = collection.dsig_offset as usize;
+            assert_eq!(out[offset..offset + 8], dsig);
+        }
+        FontFile::Single(_) => panic!("Expected a font collection"),
+    }
+}
+
+#[test]
+fn test_extract_font() {
+    let out = build_collection(None);
+    let collection_font = Font::from_bytes(&out, 1).unwrap();
+    let mut extracted = vec![];
+    extract_font(&out, 1, &mut extracted).unwrap();
+    match parse(&extracted).unwrap() {
+        FontFile::Single(_) => {}
+        FontFile::Collection(_) => panic!("Expected a single font"),
+    }
+    let font = Font::from_bytes(&extracted, 0).unwrap();
+    assert_eq!(
+        font.record().tables.keys().collect::<Vec<_>>(),
+        collection_font.record().tables.keys().collect::<Vec<_>>()
+    );
+    for &tag in font.record().tables.keys() {
+        if tag != Tag(*b"head") {
+            assert_eq!(font.table_data(tag), collection_font.table_data(tag));
+        }
+    }
+    assert_eq!(checksum(&extracted), 0xB1B0_AFBA);
+    assert!(extract_font(&out, 2, &mut vec![]).is_err());
+}
@@ -0,0 +1,125 @@
+extern crate font_parse;
+
+use font_parse::dfont::ResourceFork;
+use font_parse::{Font, OpentypeTableAccess, Tag};
+
+/// A resource with type, id, name and data.
+type TestResource<'a> = (&'a [u8; 4], i16, Option<&'a str>, &'a [u8]);
+
+/// Writes a resource fork with the data at offset 256 followed by the map.
+fn write_resource_fork(resources: &[TestResource]) -> Vec<u8> {
+    let mut types: Vec<&[u8; 4]> = vec![];
+    for &(resource_type, ..) in resources {
+        if !types.contains(&resource_type) {
+            types.push(resource_type);
+        }
+    }
+
+    let mut data = vec![];
+    let mut data_offsets = vec![];
+    for &(_, _, _, resource_data) in resources {
+        data_offsets.push(data.len() as u32);
+        data.extend_from_slice(&(resource_data.len() as u32).to_be_bytes());
+        data.extend_from_slice(resource_data);
+    }
+
+    let type_list_len = 2 + 8 * types.len();
+    let mut type_list = vec![];
+    type_list.extend_from_slice(&(types.len() as u16).wrapping_sub(1).to_be_bytes());
+    let mut reference_lists = vec![];
+    let mut names = vec![];
+    for &resource_type in &types {
+        let of_type: Vec<usize> = (0..resources.len())
+            .filter(|&index| resources[index].0 == resource_type)
+            .collect();
+        type_list.extend_from_slice(resource_type);
+        type_list.extend_from_slice(&(of_type.len() as u16 - 1).to_be_bytes());
+        type_list
+            .extend_from_slice(&((type_list_len + reference_lists.len()) as u16).to_be_bytes());
+        for index in of_type {
+            let (_, id, name, _) = resources[index];
+            reference_lists.extend_from_slice(&id.to_be_bytes());
+            match name {
+                Some(name) => {
+                    reference_lists.extend_from_slice(&(names.len() as u16).to_be_bytes());
+                    names.push(name.len() as u8);
+                    names.extend_from_slice(name.as_bytes());
+                }
+                None => reference_lists.extend_from_slice(&[0xFF, 0xFF]),
+            }
+            reference_lists.push(0);
+            reference_lists.extend_from_slice(&data_offsets[index].to_be_bytes()[1..]);
+            reference_lists.extend_from_slice(&[0; 4]);
+        }
+    }
+    type_list.extend_from_slice(&reference_lists);
+
+    let map_offset = 256 + data.len() as u32;
+    let map_len = 28 + type_list.len() + names.len();
+    let mut header = vec![];
+    for &value in &[256, map_offset, data.len() as u32, map_len as u32] {
+        header.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut out = header.clone();
+    out.resize(256, 0);
+    out.extend_from_slice(&data);
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&[0; 8]);
+    out.extend_from_slice(&28u16.to_be_bytes());
+    out.extend_from_slice(&((28 + type_list.len()) as u16).to_be_bytes());
+    out.extend_from_slice(&type_list);
+    out.extend_from_slice(&names);
+    out
+}
+
+#[test]
+fn test_dfont() {
+    let inconsolata = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let biolinum = include_bytes!("font_files/LinBiolinum_R.otf");
+    let data = write_resource_fork(&[
+        (b"sfnt", 256, Some("Inconsolata"), &inconsolata[..]),
+        (b"FOND", 256, Some("Inconsolata"), &[1, 2, 3][..]),
+        (b"sfnt", 257, None, &biolinum[..]),
+    ]);
+
+    let fork = ResourceFork::from_bytes(&data).unwrap();
+    assert_eq!(fork.resources.len(), 3);
+    assert_eq!(fork.num_fonts(), 2);
+    let fond: Vec<_> = fork.resources_of_type(Tag(*b"FOND")).collect();
+    assert_eq!(fond.len(), 1);
+    assert_eq!(fork.resource_data(fond[0]).unwrap(), &[1, 2, 3]);
+
+    let sfnt: Vec<_> = fork.resources_of_type(Tag(*b"sfnt")).collect();
+    assert_eq!(sfnt[0].id, 256);
+    assert_eq!(sfnt[0].name.as_ref().unwrap(), b"Inconsolata");
+    assert_eq!(sfnt[1].id, 257);
+    assert_eq!(sfnt[1].name, None);
+
+    for (index, &expected) in [&inconsolata[..], &biolinum[..]].iter().enumerate() {
+        let font = fork.font(index as u32).unwrap();
+        let expected = Font::from_bytes(expected, 0).unwrap();
+        assert_eq!(font.record(), expected.record());
+        assert_eq!(
+            font.table_data(Tag(*b"head")),
+            expected.table_data(Tag(*b"head"))
+        );
+    }
+    assert!(fork.font(2).is_err());
+
+    // resources extending past the end of the data are rejected
+    let mut broken = data.clone();
+    broken[256..260].copy_from_slice(&u32::MAX.to_be_bytes());
+    let fork = ResourceFork::from_bytes(&broken).unwrap();
+    assert!(fork.font(0).is_err());
+    assert!(ResourceFork::from_bytes(&data[..data.len() - 1]).is_err());
+}
+
+#[test]
+fn test_empty_resource_fork() {
+    let data = write_resource_fork(&[]);
+    let fork = ResourceFork::from_bytes(&data).unwrap();
+    assert!(fork.resources.is_empty());
+    assert_eq!(fork.num_fonts(), 0);
+    assert!(fork.font(0).is_err());
+}
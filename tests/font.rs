@@ -1,8 +1,243 @@
 extern crate font_parse;
-use font_parse::Font;
+
+use std::collections::BTreeMap;
+
+use font_parse::checksum::{table_checksum, FONT_CHECKSUM};
+use font_parse::collection::{write_font_collection, CollectionFont};
+use font_parse::directory::{DirectoryDiagnostic, InvalidDirectory};
+use font_parse::tables::head::Head;
+use font_parse::{ErrorKind, Font, OpentypeTableAccess, ParseTable, Tag};
 
 #[test]
 fn test_font_collection_02() {
     let data = include_bytes!("font_files/02font-collection-broken.otc");
     let _ = Font::from_bytes(data, 0);
 }
+
+/// Returns the offset of the table record with the given index.
+fn record_offset(index: usize) -> usize {
+    12 + 16 * index
+}
+
+#[test]
+fn test_directory_diagnostics() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    assert_eq!(font.directory_diagnostics().unwrap(), vec![]);
+    assert!(Font::from_bytes_strict(data, 0).is_ok());
+    let tags: Vec<Tag> = font.record().tables.keys().cloned().collect();
+    let offset = |index: usize| font.record().tables[&tags[index]].offset;
+
+    // the first table extends past the end of the file
+    let mut broken = data.to_vec();
+    let start = record_offset(0) + 12;
+    broken[start..start + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    let font = Font::from_bytes(&broken, 0).unwrap();
+    assert!(font.table_data(tags[0]).is_none());
+    let diagnostics = font.directory_diagnostics().unwrap();
+    assert!(diagnostics.iter().any(|diagnostic| matches!(
+        diagnostic,
+        DirectoryDiagnostic::OutOfBounds { tag, .. } if *tag == tags[0]
+    )));
+    assert!(diagnostics.iter().all(DirectoryDiagnostic::is_error));
+    assert!(Font::from_bytes_strict(&broken, 0).is_err());
+
+    // the second table starts at the offset of the first
+    let mut broken = data.to_vec();
+    let start = record_offset(1) + 8;
+    broken[start..start + 4].copy_from_slice(&offset(0).to_be_bytes());
+    let diagnostics = Font::from_bytes(&broken, 0)
+        .unwrap()
+        .directory_diagnostics()
+        .unwrap();
+    assert!(diagnostics.contains(&DirectoryDiagnostic::Overlap(tags[0], tags[1])));
+
+    // the first two records are swapped
+    let mut broken = data.to_vec();
+    let (first, second) = (record_offset(0), record_offset(1));
+    let record: Vec<u8> = broken[first..second].to_vec();
+    broken.copy_within(second..second + 16, first);
+    broken[second..second + 16].copy_from_slice(&record);
+    let diagnostics = Font::from_bytes_strict(&broken, 0)
+        .unwrap()
+        .directory_diagnostics()
+        .unwrap();
+    assert_eq!(
+        diagnostics,
+        vec![DirectoryDiagnostic::Unsorted {
+            previous: tags[1],
+            tag: tags[0]
+        }]
+    );
+
+    // the first tag is repeated and the search parameters are wrong
+    let mut broken = data.to_vec();
+    broken[record_offset(1)..record_offset(1) + 4].copy_from_slice(&tags[0].0);
+    broken[6..8].copy_from_slice(&0u16.to_be_bytes());
+    let diagnostics = Font::from_bytes(&broken, 0)
+        .unwrap()
+        .directory_diagnostics()
+        .unwrap();
+    assert!(diagnostics.contains(&DirectoryDiagnostic::DuplicateTag(tags[0])));
+    assert!(diagnostics.iter().any(|diagnostic| matches!(
+        diagnostic,
+        DirectoryDiagnostic::SearchParameters {
+            found: (0, _, _),
+            ..
+        }
+    )));
+    let err = Font::from_bytes_strict(&broken, 0).unwrap_err();
+    let invalid = match err.kind() {
+        ErrorKind::ForeignError(err) => err.downcast_ref::<InvalidDirectory>().unwrap(),
+        kind => panic!("Unexpected error {:?}", kind),
+    };
+    assert_eq!(invalid.0, vec![DirectoryDiagnostic::DuplicateTag(tags[0])]);
+
+    // empty and misaligned tables are only warnings
+    let mut broken = data.to_vec();
+    let start = record_offset(0) + 8;
+    broken[start..start + 8].copy_from_slice(&[0, 0, 0, 13, 0, 0, 0, 0]);
+    let diagnostics = Font::from_bytes_strict(&broken, 0)
+        .unwrap()
+        .directory_diagnostics()
+        .unwrap();
+    assert_eq!(
+        diagnostics,
+        vec![
+            DirectoryDiagnostic::ZeroLength(tags[0]),
+            DirectoryDiagnostic::Misaligned {
+                tag: tags[0],
+                offset: 13
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_collection_directory_diagnostics() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let tables: Vec<Tag> = font.record().tables.keys().cloned().collect();
+    let fonts: Vec<_> = (0..2)
+        .map(|_| CollectionFont {
+            font: &font,
+            version_tag: Tag([0, 1, 0, 0]),
+            tables: tables.clone(),
+        })
+        .collect();
+    let mut out = vec![];
+    write_font_collection(&fonts, None, &mut out).unwrap();
+    for index in 0..2 {
+        let font = Font::from_bytes(&out, index).unwrap();
+        assert_eq!(font.directory_diagnostics().unwrap(), vec![]);
+    }
+}
+
+#[test]
+fn test_collection_duplicate_tables() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let mut tables: Vec<Tag> = font.record().tables.keys().cloned().collect();
+    tables.push(Tag(*b"glyf"));
+    let fonts = [CollectionFont {
+        font: &font,
+        version_tag: Tag([0, 1, 0, 0]),
+        tables,
+    }];
+    let mut out = vec![];
+    let err = write_font_collection(&fonts, None, &mut out).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(out.is_empty());
+}
+
+#[test]
+fn test_verify_checksums() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let report = font.verify_checksums();
+    assert!(report.is_valid(), "{:?}", report);
+    assert_eq!(report.tables.len(), font.record().tables.len());
+    assert_eq!(table_checksum(data), FONT_CHECKSUM);
+
+    // a corrupted byte invalidates the table and the adjustment
+    let glyf = font.record().tables[&Tag(*b"glyf")];
+    let mut broken = data.to_vec();
+    broken[glyf.offset as usize + 100] ^= 1;
+    let report = Font::from_bytes(&broken, 0).unwrap().verify_checksums();
+    assert!(!report.is_valid());
+    let invalid: Vec<_> = report.invalid_tables().map(|table| table.tag).collect();
+    assert_eq!(invalid, vec![Tag(*b"glyf")]);
+    let (stored, computed) = report.adjustment.unwrap();
+    assert_ne!(stored, computed);
+
+    // the adjustment does not change the checksum of head
+    let head = font.record().tables[&Tag(*b"head")];
+    let mut broken = data.to_vec();
+    broken[head.offset as usize + 8] ^= 1;
+    let report = Font::from_bytes(&broken, 0).unwrap().verify_checksums();
+    assert_eq!(report.invalid_tables().count(), 0);
+    assert!(!report.is_valid());
+
+    // truncated tables have no computed checksum
+    let report = Font::from_bytes(&data[..glyf.offset as usize + 10], 0)
+        .unwrap()
+        .verify_checksums();
+    let glyf_checksum = report
+        .tables
+        .iter()
+        .find(|table| table.tag == Tag(*b"glyf"))
+        .unwrap();
+    assert_eq!(glyf_checksum.computed, None);
+    assert!(!glyf_checksum.is_valid());
+}
+
+#[test]
+fn test_verify_collection_checksums() {
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let tables: Vec<Tag> = font.record().tables.keys().cloned().collect();
+    let fonts: Vec<_> = [&tables[..], &tables[1..]]
+        .iter()
+        .map(|tables| CollectionFont {
+            font: &font,
+            version_tag: Tag(*b"OTTO"),
+            tables: tables.to_vec(),
+        })
+        .collect();
+    let mut out = vec![];
+    write_font_collection(&fonts, None, &mut out).unwrap();
+    for index in 0..2 {
+        let report = Font::from_bytes(&out, index).unwrap().verify_checksums();
+        assert!(report.is_valid(), "{:?}", report);
+    }
+}
+
+#[test]
+fn test_directory_error_location() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    // the second table record is cut off
+    let err = Font::from_bytes(&data[..30], 0).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEndOfData));
+    assert_eq!(err.structure(), Some("table record"));
+    assert_eq!(err.offset(), Some(record_offset(1)));
+    assert_eq!(err.table(), None);
+    assert_eq!(
+        err.to_string(),
+        "Unexpected end of data (while parsing table record at offset 28)"
+    );
+}
+
+#[test]
+fn test_table_error_location() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let mut tables = BTreeMap::new();
+    for &tag in font.record().tables.keys() {
+        tables.insert(tag, font.table_data(tag).unwrap().to_vec());
+    }
+    tables.get_mut(&Tag(*b"head")).unwrap().truncate(20);
+    let err = (&tables).parse_table::<Head>().unwrap().get().unwrap_err();
+    assert_eq!(err.table(), Some(Tag(*b"head")));
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEndOfData));
+    assert!(err.to_string().contains("in table head"));
+}
@@ -0,0 +1,302 @@
+extern crate font_parse;
+
+use std::collections::BTreeMap;
+
+use font_parse::tables::glyf::{
+    parse_composite_glyph, parse_header, parse_simple_glyph, phantom_points, remap_components,
+    VerticalMetric,
+};
+use font_parse::tables::head::Head;
+use font_parse::tables::hhea::Hhea;
+use font_parse::tables::hmtx::{Hmtx, HmtxContext};
+use font_parse::tables::loca::{LocFormat, Loca};
+use font_parse::tables::maxp::Maxp;
+use font_parse::{ErrorKind, Font, Glyph, OpentypeTableAccess, ParseTable, Tag, TtfGlyph};
+
+#[test]
+fn test_glyph_points() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let hhea: Hhea = font.parse_table().unwrap().get().unwrap();
+    let maxp = font.parse_table::<Maxp>().unwrap();
+    let maxp = maxp.get().unwrap();
+    let context = HmtxContext {
+        number_of_h_metrics: hhea.number_of_h_metrics,
+        num_glyphs: maxp.num_glyphs,
+    };
+    let hmtx: Hmtx = font.parse_table_context(context).unwrap().get().unwrap();
+    let mut glyphs = font.glyphs().unwrap();
+
+    let mut simple_glyphs = 0;
+    for index in 0..glyphs.num_glyphs() {
+        let glyph = match glyphs.index(index).unwrap() {
+            Some(Glyph::Ttf(TtfGlyph::Simple(glyph))) => glyph,
+            _ => continue,
+        };
+        simple_glyphs += 1;
+        let contours = glyph.contours();
+        assert_eq!(contours.len(), glyph.header.number_of_contours as usize);
+        let points: Vec<_> = contours.iter().flatten().collect();
+        assert_eq!(points.len(), glyph.num_points() as usize);
+        assert_eq!(glyph.flags().len(), points.len());
+        assert!(points.iter().all(|p| {
+            i32::from(glyph.header.xmin) <= p.x
+                && p.x <= i32::from(glyph.header.xmax)
+                && i32::from(glyph.header.ymin) <= p.y
+                && p.y <= i32::from(glyph.header.ymax)
+        }));
+
+        // the horizontal phantom points span the advance width
+        let metric = hmtx.metric(index as u16).unwrap();
+        let vertical = VerticalMetric::from_ascender(&glyph.header, hhea.ascender, hhea.descender);
+        let phantom = phantom_points(&glyph.header, metric, vertical);
+        assert_eq!(phantom[1].x - phantom[0].x, i32::from(metric.advance_width));
+        assert_eq!(phantom[2].y, i32::from(hhea.ascender));
+    }
+    assert!(simple_glyphs > 100);
+}
+
+#[test]
+fn test_composite_contours() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let mut glyphs = font.glyphs().unwrap();
+    let index = (0..glyphs.num_glyphs())
+        .find(|&index| {
+            matches!(
+                glyphs.index(index).unwrap(),
+                Some(Glyph::Ttf(TtfGlyph::Composite(_)))
+            )
+        })
+        .unwrap();
+
+    // the components can only be resolved through the accessor
+    let mut glyph = glyphs.index(index).unwrap().unwrap();
+    if let Glyph::Ttf(ref glyph) = glyph {
+        assert!(glyph.contour_iter().is_err());
+    }
+    assert!(glyph.outline().is_err());
+    let outline = glyphs.outline(index).unwrap().unwrap();
+    assert!(outline.control_bounds().is_some());
+}
+
+#[test]
+fn test_write_modified_glyphs() {
+    use font_parse::tables::glyf::{encode_glyf, GlyphBuf};
+    use font_parse::tables::head::Head;
+    use font_parse::{write_font, Tag};
+    use std::collections::BTreeMap;
+
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let mut accessor = font.glyphs().unwrap();
+    let mut glyphs = vec![];
+    let mut headers = vec![];
+    for index in 0..accessor.num_glyphs() {
+        match accessor.index(index).unwrap() {
+            Some(Glyph::Ttf(glyph)) => {
+                headers.push(Some(glyph.header()));
+                glyphs.push(GlyphBuf::from(glyph));
+            }
+            _ => {
+                headers.push(None);
+                glyphs.push(GlyphBuf::Empty);
+            }
+        }
+    }
+
+    // re-encoding reproduces the bounding boxes and is not larger
+    let encoded = encode_glyf(&glyphs).unwrap();
+    assert!(encoded.glyf.len() <= font.table_data(Tag(*b"glyf")).unwrap().len());
+    for (index, header) in headers.iter().enumerate() {
+        if let Some(header) = header {
+            let start = encoded.loca.offset(index as u16).unwrap() as usize;
+            let (_, parsed) =
+                font_parse::tables::glyf::parse_header(&encoded.glyf[start..]).unwrap();
+            if header.number_of_contours >= 0 {
+                assert_eq!(&parsed, header, "glyph {}", index);
+            } else {
+                // Some composite glyphs in the font have outdated bounds, so
+                // compare them with the bounds of the resolved outline.
+                let (min, max) = accessor
+                    .outline(index as u32)
+                    .unwrap()
+                    .unwrap()
+                    .control_bounds()
+                    .unwrap();
+                assert_eq!(
+                    (parsed.xmin, parsed.ymin, parsed.xmax, parsed.ymax),
+                    (min.x as i16, min.y as i16, max.x as i16, max.y as i16),
+                    "glyph {}",
+                    index
+                );
+            }
+        }
+    }
+
+    // move the contours of one glyph far to the right
+    if let GlyphBuf::Simple { contours, .. } = &mut glyphs[36] {
+        for point in contours.iter_mut().flatten() {
+            point.x += 20_000;
+        }
+    }
+    let encoded = encode_glyf(&glyphs).unwrap();
+    let mut head: Head = font.parse_table().unwrap().get().unwrap();
+    encoded.update_head(&mut head);
+    assert!(head.x_max > 20_000);
+
+    let mut tables = BTreeMap::new();
+    for &tag in font.record().tables.keys() {
+        tables.insert(tag, font.table_data(tag).unwrap().to_vec());
+    }
+    let mut head_data = vec![];
+    head.write_to(&mut head_data).unwrap();
+    tables.insert(Tag(*b"head"), head_data);
+    tables.insert(Tag(*b"glyf"), encoded.glyf.clone());
+    tables.insert(Tag(*b"loca"), encoded.loca_data());
+    let tags: Vec<Tag> = tables.keys().cloned().collect();
+    let mut output = vec![];
+    write_font(&&tables, Tag([0, 1, 0, 0]), &tags, &mut output).unwrap();
+
+    let written = Font::from_bytes(&output, 0).expect("Could not read written font.");
+    let mut written_glyphs = written.glyphs().unwrap();
+    for index in 0..written_glyphs.num_glyphs() {
+        let glyph = match written_glyphs.index(index).unwrap() {
+            Some(Glyph::Ttf(glyph)) => GlyphBuf::from(glyph),
+            _ => GlyphBuf::Empty,
+        };
+        assert_eq!(glyph, glyphs[index as usize], "glyph {}", index);
+    }
+}
+
+#[test]
+fn test_glyph_error_location() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let head: Head = font.parse_table().unwrap().get().unwrap();
+    let loca: Loca = font
+        .parse_table_context(head.index_to_loc_format)
+        .unwrap()
+        .get()
+        .unwrap();
+    let mut tables = BTreeMap::new();
+    for &tag in font.record().tables.keys() {
+        tables.insert(tag, font.table_data(tag).unwrap().to_vec());
+    }
+
+    // claim more contours than the glyph has data for
+    let index = 36;
+    let start = loca.offset(index).unwrap() as usize;
+    tables.get_mut(&Tag(*b"glyf")).unwrap()[start..start + 2]
+        .copy_from_slice(&i16::MAX.to_be_bytes());
+    let mut glyphs = (&tables).glyphs().unwrap();
+    let err = glyphs.index(u32::from(index)).unwrap_err();
+    match err.kind() {
+        ErrorKind::GlyphParse { index: glyph, .. } => assert_eq!(*glyph, u32::from(index)),
+        kind => panic!("Unexpected error {:?}", kind),
+    }
+    assert_eq!(err.table(), Some(Tag(*b"glyf")));
+    assert_eq!(err.structure(), Some("endPtsOfContours"));
+    assert_eq!(err.offset(), Some(start + 10));
+    let cause = std::error::Error::source(&err).unwrap();
+    assert_eq!(
+        cause.to_string(),
+        format!(
+            "Unexpected end of data (while parsing endPtsOfContours in table glyf at offset {})",
+            start + 10
+        )
+    );
+}
+
+/// Runs the parsers of the `glyf` table on the data of a glyph, which must not
+/// panic however the data is malformed.
+fn parse_glyph_data(data: &[u8]) {
+    let header = match parse_header(data) {
+        Ok((_, header)) => header,
+        Err(_) => return,
+    };
+    if header.number_of_contours >= 0 {
+        if let Ok((_, glyph)) = parse_simple_glyph(data) {
+            assert!(glyph.point_iter().count() <= usize::from(glyph.num_points()));
+            let _ = glyph.contours();
+            let _ = glyph.flags();
+        }
+    } else {
+        if let Ok((_, glyph)) = parse_composite_glyph(data) {
+            let _: Vec<_> = glyph.components().collect();
+            let _ = glyph.instructions();
+        }
+        let _ = remap_components(&mut data.to_vec(), Ok);
+    }
+}
+
+#[test]
+fn test_malformed_glyphs() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let head: Head = font.parse_table().unwrap().get().unwrap();
+    let loca: Loca = font
+        .parse_table_context(head.index_to_loc_format)
+        .unwrap()
+        .get()
+        .unwrap();
+    let glyf = font.table_data(Tag(*b"glyf")).unwrap();
+
+    for index in (0..loca.num_entries() as u16 - 1).step_by(5) {
+        let start = loca.offset(index).unwrap() as usize;
+        let end = loca.offset(index + 1).unwrap() as usize;
+        let glyph = &glyf[start..end];
+        for position in 0..glyph.len() {
+            parse_glyph_data(&glyph[..position]);
+            for &value in &[0x00, 0x7f, 0xff] {
+                let mut mutated = glyph.to_vec();
+                mutated[position] = value;
+                parse_glyph_data(&mutated);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_malformed_loca() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let head: Head = font.parse_table().unwrap().get().unwrap();
+    let loca: Loca = font
+        .parse_table_context(head.index_to_loc_format)
+        .unwrap()
+        .get()
+        .unwrap();
+    let mut tables = BTreeMap::new();
+    for &tag in font.record().tables.keys() {
+        tables.insert(tag, font.table_data(tag).unwrap().to_vec());
+    }
+    let offsets: Vec<u32> = (0..loca.num_entries() as u16)
+        .map(|index| loca.offset(index).unwrap())
+        .collect();
+    let glyf_len = tables[&Tag(*b"glyf")].len() as u32;
+
+    // decreasing, out of bounds, unaligned and missing offsets
+    let mutations: [fn(&mut Vec<u32>, u32); 4] = [
+        |offsets, _| offsets.swap(10, 11),
+        |offsets, glyf_len| offsets[20] = glyf_len + 100,
+        |offsets, _| offsets[30] += 1,
+        |offsets, _| offsets.truncate(40),
+    ];
+    for mutate in &mutations {
+        let mut offsets = offsets.clone();
+        mutate(&mut offsets, glyf_len);
+        let mut data = Vec::new();
+        Loca::from_offsets(offsets)
+            .write_to(LocFormat::Long, &mut data)
+            .unwrap();
+        tables.insert(Tag(*b"loca"), data);
+        tables.get_mut(&Tag(*b"head")).unwrap()[51] = 1;
+
+        let mut glyphs = (&tables).glyphs().unwrap();
+        for index in 0..glyphs.num_glyphs() {
+            let _ = glyphs.outline(index);
+        }
+        assert!(font_parse::validate::validate(&&tables).has_errors());
+    }
+}
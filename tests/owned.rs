@@ -0,0 +1,77 @@
+extern crate font_parse;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use font_parse::owned::OwnedFont;
+use font_parse::tables::head::Head;
+use font_parse::{Font, OpentypeTableAccess, ParseTable, Tag};
+
+#[test]
+fn test_owned_font() {
+    let path = "tests/font_files/LinBiolinum_R.otf";
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let expected = Font::from_bytes(data, 0).unwrap();
+
+    let fonts = vec![
+        OwnedFont::from_vec(data.to_vec(), 0).unwrap(),
+        OwnedFont::from_file(path, 0).unwrap(),
+        unsafe { OwnedFont::map_file(path, 0).unwrap() },
+    ];
+    for font in fonts {
+        assert_eq!(font.record(), expected.record());
+        assert_eq!(&font.data()[..], &data[..]);
+        for &tag in expected.record().tables.keys() {
+            assert_eq!(
+                font.table_data(tag).as_deref(),
+                expected.table_data(tag),
+                "{}",
+                tag
+            );
+        }
+        assert!(font.table_data(Tag(*b"glyf")).is_none());
+
+        let head: Head = font.parse_table().unwrap().get().unwrap();
+        assert_eq!(head.units_per_em, 1000);
+        // the borrowed font shares the table directory
+        assert!(std::ptr::eq(font.as_font().record(), font.record()));
+        let mut glyphs = font.as_font().glyphs().unwrap();
+        assert_eq!(
+            glyphs.outline(10).unwrap(),
+            expected.glyphs().unwrap().outline(10).unwrap()
+        );
+
+        // the table data outlives the font
+        let cff = font.table_data(Tag(*b"CFF ")).unwrap();
+        drop(font);
+        assert_eq!(Some(&cff[..]), expected.table_data(Tag(*b"CFF ")));
+        assert_eq!(cff.slice(1..3).as_deref(), Some(&cff[1..3]));
+        assert!(cff.slice(0..cff.len() + 1).is_none());
+    }
+}
+
+#[test]
+fn test_owned_font_threads() {
+    let data: Arc<[u8]> = include_bytes!("font_files/Inconsolata-Regular.ttf")[..].into();
+    let mut cache = HashMap::new();
+    for key in 0..4 {
+        cache.insert(key, OwnedFont::from_arc(data.clone(), 0).unwrap());
+    }
+    assert_eq!(Arc::strong_count(&data), 5);
+
+    let handles: Vec<_> = cache
+        .values()
+        .cloned()
+        .map(|font| std::thread::spawn(move || font.table_data(Tag(*b"name")).unwrap()))
+        .collect();
+    for handle in handles {
+        assert!(!handle.join().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn test_owned_font_errors() {
+    assert!(OwnedFont::from_file("tests/font_files/missing.otf", 0).is_err());
+    assert!(unsafe { OwnedFont::map_file("tests/font_files/missing.otf", 0) }.is_err());
+    assert!(OwnedFont::from_vec(vec![], 0).is_err());
+}
@@ -0,0 +1,67 @@
+extern crate font_parse;
+
+use font_parse::curve_conversion::cubic_to_quadratic;
+use font_parse::outline::{Outline, Point};
+use font_parse::path_ops::{correct_directions, embolden, remove_overlaps, Direction, LineJoin};
+use font_parse::{Font, OpentypeTableAccess};
+
+/// Checks that both outlines fill the same points on a grid over the glyph.
+fn assert_same_fill(original: &Outline, result: &Outline) {
+    let (min, max) = original.control_bounds().unwrap();
+    let mut mismatches = 0;
+    for i in 0..40 {
+        for j in 0..40 {
+            let p = Point::new(
+                min.x + (max.x - min.x) * (i as f32 + 0.37) / 40.0,
+                min.y + (max.y - min.y) * (j as f32 + 0.41) / 40.0,
+            );
+            let expected = original.winding_number(p) != 0;
+            let winding = result.winding_number(p);
+            assert!(winding == 0 || winding == 1, "overlap left at {:?}", p);
+            if expected != (winding != 0) {
+                mismatches += 1;
+            }
+        }
+    }
+    // sample points lying almost exactly on an edge may be classified differently
+    assert!(mismatches <= 2, "{} mismatches", mismatches);
+}
+
+#[test]
+fn test_remove_overlaps_glyphs() {
+    for data in &[
+        &include_bytes!("font_files/Inconsolata-Regular.ttf")[..],
+        &include_bytes!("font_files/LinBiolinum_R.otf")[..],
+    ] {
+        let font = Font::from_bytes(data, 0).expect("Could not read font.");
+        let mut glyphs = font.glyphs().unwrap();
+        for index in 30..70 {
+            let outline = match glyphs.outline(index).unwrap() {
+                Some(ref outline) if !outline.contours.is_empty() => outline.clone(),
+                _ => continue,
+            };
+            let result = remove_overlaps(&outline);
+            assert_same_fill(&outline, &result);
+        }
+    }
+}
+
+#[test]
+fn test_embolden_glyph() {
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let mut glyphs = font.glyphs().unwrap();
+    let outline = glyphs.outline(36).unwrap().unwrap();
+    let bold = embolden(&outline, 20.0, LineJoin::Round);
+
+    let (min, max) = outline.control_bounds().unwrap();
+    let (bold_min, bold_max) = bold.control_bounds().unwrap();
+    assert!((bold_min.x - (min.x - 20.0)).abs() < 1.0);
+    assert!((bold_max.y - (max.y + 20.0)).abs() < 1.0);
+
+    // the result can be written as TrueType outline with clockwise contours
+    let truetype = correct_directions(&bold, Direction::TRUETYPE);
+    let quadratic = cubic_to_quadratic(truetype.to_path_instructions(), 1.0).unwrap();
+    let round_trip = Outline::from_quadratic_path(quadratic);
+    assert_eq!(round_trip.contours.len(), bold.contours.len());
+}
@@ -0,0 +1,48 @@
+extern crate font_parse;
+
+use font_parse::sdf::{generate_msdf, generate_sdf, SdfParams};
+use font_parse::{Font, OpentypeTableAccess};
+
+fn check_glyph(data: &[u8], index: u32) {
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let mut glyph_accessor = font.glyphs().unwrap();
+    let outline = glyph_accessor
+        .index(index)
+        .unwrap()
+        .expect("Glyph not found")
+        .outline()
+        .unwrap();
+    assert!(!outline.contours.is_empty());
+
+    let params = SdfParams::fit(&outline, 32, 32, 4.0);
+    let sdf = generate_sdf(&outline, &params);
+    let msdf = generate_msdf(&outline, &params);
+
+    let mut inside = 0;
+    let mut mismatches = 0;
+    for row in 0..32 {
+        for column in 0..32 {
+            let sdf_value = sdf.pixel(column, row)[0];
+            let rgb = msdf.pixel(column, row);
+            let median = rgb[0].min(rgb[1]).max(rgb[0].max(rgb[1]).min(rgb[2]));
+            if sdf_value > 0.5 {
+                inside += 1;
+            }
+            if (sdf_value > 0.5) != (median > 0.5) {
+                mismatches += 1;
+            }
+        }
+    }
+    assert!(inside > 0);
+    assert!(mismatches < 32, "{} mismatching pixels", mismatches);
+}
+
+#[test]
+fn test_sdf_ttf_glyph() {
+    check_glyph(include_bytes!("font_files/Inconsolata-Regular.ttf"), 36);
+}
+
+#[test]
+fn test_sdf_cff_glyph() {
+    check_glyph(include_bytes!("font_files/LinBiolinum_R.otf"), 36);
+}
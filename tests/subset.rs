@@ -0,0 +1,165 @@
+extern crate font_parse;
+
+use font_parse::subset::{glyph_closure, glyphs_for_codepoints, subset_font, SubsetOptions};
+use font_parse::tables::cff::Cff;
+use font_parse::tables::cmap::Cmap;
+use font_parse::tables::head::Head;
+use font_parse::tables::maxp::Maxp;
+use font_parse::tables::os2::Os2;
+use font_parse::tables::post::Post;
+use font_parse::{Font, OpentypeTableAccess, ParseTable, Tag};
+
+use std::collections::BTreeSet;
+
+fn subset(data: &[u8], glyphs: &BTreeSet<u16>, options: &SubsetOptions) -> Vec<u8> {
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let mut out = vec![];
+    subset_font(&font, glyphs, options, &mut out).unwrap();
+    out
+}
+
+/// Asserts that the glyphs of the subset have the outlines of the original
+/// glyphs.
+fn assert_same_outlines(original: &Font, subset: &Font, glyphs: &[(u32, u32)]) {
+    let mut original = original.glyphs().unwrap();
+    let mut subset = subset.glyphs().unwrap();
+    for &(old, new) in glyphs {
+        assert_eq!(
+            subset.outline(new).unwrap(),
+            original.outline(old).unwrap(),
+            "glyph {}",
+            old
+        );
+    }
+}
+
+#[test]
+fn test_subset_truetype() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    // glyph 5 is composed of glyphs 4 and 744
+    let glyphs: BTreeSet<u16> = [5, 36].iter().cloned().collect();
+    let closure: Vec<u16> = glyph_closure(&font, &glyphs).unwrap().into_iter().collect();
+    assert_eq!(closure, vec![0, 4, 5, 36, 744]);
+
+    let out = subset(data, &glyphs, &SubsetOptions::default());
+    let subset_font = Font::from_bytes(&out, 0).unwrap();
+    assert!(!subset_font.has_table(Tag(*b"GSUB")));
+    let maxp = subset_font.parse_table::<Maxp>().unwrap();
+    assert_eq!(maxp.get().unwrap().num_glyphs, 5);
+    assert_same_outlines(
+        &font,
+        &subset_font,
+        &[(0, 0), (4, 1), (5, 2), (36, 3), (744, 4)],
+    );
+
+    let post = font.parse_table::<Post>().unwrap();
+    let post = post.get().unwrap();
+    let subset_post = subset_font.parse_table::<Post>().unwrap();
+    let subset_post = subset_post.get().unwrap();
+    assert_eq!(subset_post.glyph_name(3), post.glyph_name(36));
+    assert_eq!(subset_post.glyph_name(4), post.glyph_name(744));
+
+    // the bounding box only covers the glyphs of the subset
+    let head = font.parse_table::<Head>().unwrap();
+    let head = head.get().unwrap();
+    let subset_head = subset_font.parse_table::<Head>().unwrap();
+    let subset_head = subset_head.get().unwrap();
+    assert!(subset_head.x_min >= head.x_min && subset_head.y_min >= head.y_min);
+    assert!(subset_head.x_max <= head.x_max && subset_head.y_max <= head.y_max);
+    assert!(subset_head.y_max < head.y_max || subset_head.y_min > head.y_min);
+
+    let options = SubsetOptions {
+        retain_gids: true,
+        drop_glyph_names: true,
+    };
+    let out = subset(data, &glyphs, &options);
+    let retained = Font::from_bytes(&out, 0).unwrap();
+    let maxp = retained.parse_table::<Maxp>().unwrap();
+    assert_eq!(maxp.get().unwrap().num_glyphs, 745);
+    assert_same_outlines(&font, &retained, &[(5, 5), (36, 36), (744, 744)]);
+    assert_eq!(retained.glyphs().unwrap().outline(6).unwrap(), None);
+    let post = retained.parse_table::<Post>().unwrap();
+    assert_eq!(post.get().unwrap().version, 0x0003_0000);
+    assert!(out.len() < data.len() / 2);
+}
+
+#[test]
+fn test_subset_codepoints() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let text = "Hé!";
+    let glyphs = glyphs_for_codepoints(&font, text.chars().map(u32::from)).unwrap();
+    assert_eq!(glyphs.len(), 3);
+
+    let out = subset(data, &glyphs, &SubsetOptions::default());
+    let subset_font = Font::from_bytes(&out, 0).unwrap();
+    let cmap = font.parse_table::<Cmap>().unwrap();
+    let cmap = cmap.get().unwrap().mappings();
+    let subset_cmap = subset_font.parse_table::<Cmap>().unwrap();
+    let subset_cmap = subset_cmap.get().unwrap().mappings();
+    // é is a composite glyph, so its components are kept as well
+    let maxp = subset_font.parse_table::<Maxp>().unwrap();
+    assert!(maxp.get().unwrap().num_glyphs > 4);
+
+    let mut pairs = vec![];
+    for c in text.chars() {
+        let old = cmap[&u32::from(c)];
+        let new = subset_cmap[&u32::from(c)];
+        pairs.push((u32::from(old), u32::from(new)));
+    }
+    assert_same_outlines(&font, &subset_font, &pairs);
+    // mappings of the kept glyphs are preserved, for example of the
+    // components of é
+    assert!(subset_cmap.len() >= 3);
+    assert!(subset_cmap.keys().all(|c| cmap.contains_key(c)));
+
+    let os2 = subset_font.parse_table::<Os2>().unwrap();
+    let os2 = os2.get().unwrap();
+    let first = *subset_cmap.keys().next().unwrap();
+    let last = *subset_cmap.keys().next_back().unwrap();
+    assert_eq!(u32::from(os2.first_char_index), first);
+    assert_eq!(u32::from(os2.last_char_index), last);
+    assert_eq!(
+        subset_font.table_data(Tag(*b"OS/2")).unwrap().len(),
+        font.table_data(Tag(*b"OS/2")).unwrap().len()
+    );
+}
+
+#[test]
+fn test_subset_cff() {
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let glyphs = glyphs_for_codepoints(&font, "Subset".chars().map(u32::from)).unwrap();
+
+    let out = subset(data, &glyphs, &SubsetOptions::default());
+    let subset_font = Font::from_bytes(&out, 0).unwrap();
+    let mut pairs: Vec<(u32, u32)> = vec![(0, 0)];
+    pairs.extend(
+        glyphs
+            .iter()
+            .enumerate()
+            .map(|(new, &old)| (u32::from(old), new as u32 + 1)),
+    );
+    assert_same_outlines(&font, &subset_font, &pairs);
+
+    let cff = font.parse_table::<Cff>().unwrap();
+    let cff = cff.get().unwrap();
+    let subset_cff = subset_font.parse_table::<Cff>().unwrap();
+    let subset_cff = subset_cff.get().unwrap();
+    assert_eq!(subset_cff.num_glyphs(), glyphs.len() as u32 + 1);
+    assert_eq!(subset_cff.name, cff.name);
+    for &(old, new) in &pairs {
+        assert_eq!(subset_cff.glyph_name(new), cff.glyph_name(old));
+    }
+    let mut names: Vec<_> = (1..subset_cff.num_glyphs())
+        .map(|index| subset_cff.glyph_name(index).unwrap().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["S", "b", "e", "s", "t", "u"]);
+
+    // unused subroutines are pruned
+    let original_len = font.table_data(Tag(*b"CFF ")).unwrap().len();
+    let subset_len = subset_font.table_data(Tag(*b"CFF ")).unwrap().len();
+    assert!(subset_len * 4 < original_len, "{}", subset_len);
+}
@@ -0,0 +1,83 @@
+extern crate font_parse;
+
+use font_parse::svg::{specimen_sheet, SpecimenOptions};
+use font_parse::tables::cff::Cff;
+use font_parse::tables::hhea::Hhea;
+use font_parse::tables::post::Post;
+use font_parse::{Font, OpentypeTableAccess, ParseTable, Tag};
+
+#[test]
+fn test_glyph_to_svg_path() {
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let mut glyph_accessor = font.glyphs().unwrap();
+
+    let path = glyph_accessor.to_svg_path(36).unwrap().unwrap();
+    assert!(path.starts_with('M'));
+    assert!(path.ends_with('Z'));
+    assert!(glyph_accessor.to_svg_path(100_000).unwrap().is_none());
+}
+
+#[test]
+fn test_glyph_names() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let post = font.parse_table::<Post>().unwrap();
+    let post = post.get().unwrap();
+    assert_eq!(post.glyph_name(0).unwrap(), ".notdef");
+
+    let data = include_bytes!("font_files/LinBiolinum_R.otf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let cff = font.parse_table::<Cff>().unwrap();
+    let cff = cff.get().unwrap();
+    assert_eq!(cff.glyph_name(0).unwrap(), ".notdef");
+    assert!(cff.glyph_name(1).is_some());
+}
+
+#[test]
+fn test_hhea() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let hhea = font.parse_table::<Hhea>().unwrap().get().unwrap();
+    assert!(hhea.ascender > 0);
+    assert!(hhea.descender < 0);
+    assert!(hhea.number_of_h_metrics > 0);
+}
+
+#[test]
+fn test_specimen_sheet() {
+    for data in &[
+        &include_bytes!("font_files/Inconsolata-Regular.ttf")[..],
+        &include_bytes!("font_files/LinBiolinum_R.otf")[..],
+    ] {
+        let font = Font::from_bytes(data, 0).expect("Could not read font.");
+        let options = SpecimenOptions {
+            glyphs: Some(30..60),
+            columns: 10,
+            ..Default::default()
+        };
+        let svg = specimen_sheet(&font, &options).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<text").count(), 30);
+        assert!(svg.matches("<path").count() > 20);
+    }
+}
+
+#[test]
+fn test_specimen_sheet_extreme_metrics() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).expect("Could not read font.");
+    let hhea = font.record().tables[&Tag(*b"hhea")].offset as usize;
+    for &(ascender, descender) in &[(30000i16, -30000i16), (i16::MIN, i16::MIN)] {
+        let mut data = data.to_vec();
+        data[hhea + 4..hhea + 6].copy_from_slice(&ascender.to_be_bytes());
+        data[hhea + 6..hhea + 8].copy_from_slice(&descender.to_be_bytes());
+        let font = Font::from_bytes(&data, 0).expect("Could not read font.");
+        let options = SpecimenOptions {
+            glyphs: Some(30..31),
+            ..Default::default()
+        };
+        let svg = specimen_sheet(&font, &options).unwrap();
+        assert!(svg.contains(&format!(r#"y1="{}""#, -i32::from(ascender))));
+    }
+}
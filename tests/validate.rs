@@ -0,0 +1,190 @@
+extern crate font_parse;
+
+use std::collections::BTreeMap;
+
+use font_parse::tables::loca::{LocFormat, Loca};
+use font_parse::tables::SfntTable;
+use font_parse::validate::{sanitize, validate, Severity};
+use font_parse::{Font, OpentypeTableAccess, Tag};
+
+/// Copies all tables of a font into memory.
+fn tables(data: &[u8]) -> BTreeMap<Tag, Vec<u8>> {
+    let font = Font::from_bytes(data, 0).unwrap();
+    font.record()
+        .tables
+        .keys()
+        .map(|&tag| (tag, font.table_data(tag).unwrap().to_vec()))
+        .collect()
+}
+
+#[test]
+fn test_validate_fonts() {
+    for data in [
+        &include_bytes!("font_files/Inconsolata-Regular.ttf")[..],
+        &include_bytes!("font_files/LinBiolinum_R.otf")[..],
+    ] {
+        let font = Font::from_bytes(data, 0).unwrap();
+        let report = validate(&font);
+        let errors: Vec<_> = report.errors().map(ToString::to_string).collect();
+        assert_eq!(errors, Vec::<String>::new());
+    }
+}
+
+#[test]
+fn test_validate_loca() {
+    let mut tables = tables(include_bytes!("font_files/Inconsolata-Regular.ttf"));
+    let format = match tables[&Tag(*b"head")][51] {
+        0 => LocFormat::Short,
+        _ => LocFormat::Long,
+    };
+    let loca = Loca::from_data(&tables[&Tag(*b"loca")], format).unwrap();
+    let mut offsets: Vec<u32> = (0..loca.num_entries() as u16)
+        .map(|index| loca.offset(index).unwrap())
+        .collect();
+    offsets.swap(3, 5);
+    let mut data = Vec::new();
+    Loca::from_offsets(offsets)
+        .write_to(format, &mut data)
+        .unwrap();
+    tables.insert(Tag(*b"loca"), data);
+
+    let report = validate(&&tables);
+    let issue = report.errors().next().unwrap();
+    assert_eq!(issue.table, Tag(*b"loca"));
+    assert!(issue.glyph == Some(2) || issue.glyph == Some(3));
+    let bad_tables = report.bad_tables();
+    assert!(bad_tables.contains(&Tag(*b"loca")));
+    assert!(bad_tables.contains(&Tag(*b"glyf")));
+
+    // the glyphs can not be read without loca
+    let tags: Vec<Tag> = tables.keys().cloned().collect();
+    let err = sanitize(&&tables, Tag([0, 1, 0, 0]), &tags, &mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_validate_glyph() {
+    let mut tables = tables(include_bytes!("font_files/Inconsolata-Regular.ttf"));
+    let format = match tables[&Tag(*b"head")][51] {
+        0 => LocFormat::Short,
+        _ => LocFormat::Long,
+    };
+    let loca = Loca::from_data(&tables[&Tag(*b"loca")], format).unwrap();
+    let glyph = (1..loca.num_entries() as u16 - 1)
+        .find(|&index| loca.offset(index + 1).unwrap() > loca.offset(index).unwrap())
+        .unwrap();
+    // more contours than the glyph has data for
+    let start = loca.offset(glyph).unwrap() as usize;
+    tables.get_mut(&Tag(*b"glyf")).unwrap()[start..start + 2]
+        .copy_from_slice(&0x7FFFu16.to_be_bytes());
+
+    let report = validate(&&tables);
+    let issue = report
+        .errors()
+        .find(|issue| issue.glyph == Some(u32::from(glyph)))
+        .unwrap();
+    assert!(issue.message.starts_with("The glyph can not be parsed: "));
+    assert!(!issue.message.contains("ParserError"), "{}", issue.message);
+}
+
+#[test]
+fn test_validate_cmap() {
+    let mut tables = tables(include_bytes!("font_files/Inconsolata-Regular.ttf"));
+    // remove the last glyph, which is mapped by cmap
+    let maxp = tables.get_mut(&Tag(*b"maxp")).unwrap();
+    let num_glyphs = u16::from_be_bytes([maxp[4], maxp[5]]);
+    maxp[4..6].copy_from_slice(&(num_glyphs - 1).to_be_bytes());
+
+    let report = validate(&&tables);
+    assert!(report
+        .issues
+        .iter()
+        .any(|issue| issue.table == Tag(*b"loca") && issue.severity == Severity::Warning));
+    let cmap_errors: Vec<_> = report
+        .errors()
+        .filter(|issue| issue.table == Tag(*b"cmap"))
+        .collect();
+    assert!(!cmap_errors.is_empty());
+    assert!(cmap_errors
+        .iter()
+        .all(|issue| issue.glyph == Some(u32::from(num_glyphs - 1))));
+}
+
+#[test]
+fn test_validate_cmap_groups() {
+    let mut tables = tables(include_bytes!("font_files/Inconsolata-Regular.ttf"));
+    let maxp = &tables[&Tag(*b"maxp")];
+    let num_glyphs = u32::from(u16::from_be_bytes([maxp[4], maxp[5]]));
+
+    // many groups covering all code points, which must not be expanded
+    let num_groups = 5000u32;
+    let mut cmap = vec![0, 0, 0, 1, 0, 3, 0, 10, 0, 0, 0, 12];
+    cmap.extend_from_slice(&[0, 12, 0, 0]);
+    cmap.extend_from_slice(&(16 + 12 * num_groups).to_be_bytes());
+    cmap.extend_from_slice(&[0, 0, 0, 0]);
+    cmap.extend_from_slice(&num_groups.to_be_bytes());
+    for _ in 0..num_groups {
+        cmap.extend_from_slice(&0u32.to_be_bytes());
+        cmap.extend_from_slice(&0x10_ffffu32.to_be_bytes());
+        cmap.extend_from_slice(&1u32.to_be_bytes());
+    }
+    tables.insert(Tag(*b"cmap"), cmap.clone());
+
+    let report = validate(&&tables);
+    let cmap_errors: Vec<_> = report
+        .errors()
+        .filter(|issue| issue.table == Tag(*b"cmap"))
+        .collect();
+    assert_eq!(cmap_errors.len(), 1);
+    assert_eq!(cmap_errors[0].glyph, Some(num_glyphs));
+    assert!(cmap_errors[0]
+        .message
+        .contains(&format!("{:#x}", num_glyphs - 1)));
+
+    // a format 13 group maps every code to the same glyph
+    cmap[13] = 13;
+    tables.insert(Tag(*b"cmap"), cmap);
+    let report = validate(&&tables);
+    assert!(!report.errors().any(|issue| issue.table == Tag(*b"cmap")));
+}
+
+#[test]
+fn test_validate_cff() {
+    let mut tables = tables(include_bytes!("font_files/LinBiolinum_R.otf"));
+    let maxp = tables.get_mut(&Tag(*b"maxp")).unwrap();
+    let num_glyphs = u16::from_be_bytes([maxp[4], maxp[5]]);
+    maxp[4..6].copy_from_slice(&(num_glyphs + 1).to_be_bytes());
+
+    let report = validate(&&tables);
+    assert!(report.errors().any(|issue| issue.table == Tag(*b"CFF ")));
+}
+
+#[test]
+fn test_sanitize() {
+    let mut tables = tables(include_bytes!("font_files/Inconsolata-Regular.ttf"));
+    tables.get_mut(&Tag(*b"OS/2")).unwrap().truncate(10);
+    let head = tables.get_mut(&Tag(*b"head")).unwrap();
+    // shrink the font bounding box
+    head[42..44].copy_from_slice(&0i16.to_be_bytes());
+
+    let report = validate(&&tables);
+    assert_eq!(
+        report.bad_tables().into_iter().collect::<Vec<_>>(),
+        vec![Tag(*b"OS/2")]
+    );
+    assert!(report
+        .issues
+        .iter()
+        .any(|issue| issue.table == Tag(*b"glyf")
+            && issue.severity == Severity::Warning
+            && issue.glyph.is_some()));
+
+    let tags: Vec<Tag> = tables.keys().cloned().collect();
+    let mut sanitized = Vec::new();
+    let version = Tag([0, 1, 0, 0]);
+    sanitize(&&tables, version, &tags, &mut sanitized).unwrap();
+    let font = Font::from_bytes(&sanitized, 0).unwrap();
+    assert!(!font.has_table(Tag(*b"OS/2")));
+    assert!(font.has_table(Tag(*b"glyf")));
+    assert!(!validate(&font).has_errors());
+}
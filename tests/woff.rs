@@ -0,0 +1,87 @@
+extern crate font_parse;
+
+use font_parse::woff::{write_woff, Woff, WoffOptions};
+use font_parse::{Font, OpentypeTableAccess, Tag};
+
+use std::collections::BTreeMap;
+
+fn assert_same_tables(woff: &Woff, font: &Font) {
+    assert_eq!(
+        woff.tables.keys().collect::<Vec<_>>(),
+        font.record().tables.keys().collect::<Vec<_>>()
+    );
+    for &tag in font.record().tables.keys() {
+        let data = woff.table_data(tag).unwrap();
+        let expected = font.table_data(tag).unwrap();
+        if tag == Tag(*b"head") {
+            // the checksum adjustment is recomputed
+            assert_eq!(data[..8], expected[..8]);
+            assert_eq!(data[12..], expected[12..]);
+        } else {
+            assert_eq!(&*data, expected, "{}", tag);
+        }
+    }
+}
+
+#[test]
+fn test_write_woff() {
+    for &data in &[
+        &include_bytes!("font_files/Inconsolata-Regular.ttf")[..],
+        &include_bytes!("font_files/LinBiolinum_R.otf")[..],
+    ] {
+        let font = Font::from_bytes(data, 0).unwrap();
+        let tags: Vec<Tag> = font.record().tables.keys().cloned().collect();
+        let version_tag = Tag(font.record().version.to_be_bytes());
+        let mut out = vec![];
+        write_woff(&font, version_tag, &tags, &WoffOptions::default(), &mut out).unwrap();
+        assert!(out.len() < data.len() * 3 / 4);
+
+        let woff = Woff::from_bytes(&out).unwrap();
+        assert_eq!(woff.header.flavor, font.record().version);
+        assert_eq!(woff.header.length as usize, out.len());
+        assert_eq!(woff.metadata().unwrap(), None);
+        assert_eq!(woff.private_data().unwrap(), None);
+        assert_same_tables(&woff, &font);
+
+        // the decompressed tables form a complete font
+        let tables = woff.decompress_tables().unwrap();
+        let mut glyphs = (&tables).glyphs().unwrap();
+        assert!(glyphs.outline(36).unwrap().is_some());
+    }
+}
+
+#[test]
+fn test_woff_metadata() {
+    let mut tables = BTreeMap::new();
+    tables.insert(Tag(*b"head"), (0..54).collect::<Vec<u8>>());
+    tables.insert(Tag(*b"zzzz"), vec![1, 2, 3]);
+    tables.insert(Tag(*b"long"), vec![7; 1000]);
+    let tags: Vec<Tag> = tables.keys().cloned().collect();
+    let metadata = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                    <metadata version=\"1.0\"><uniqueid id=\"font_parse\"/></metadata>";
+    let options = WoffOptions {
+        major_version: 2,
+        minor_version: 5,
+        metadata: Some(metadata),
+        private_data: Some(&[0xde, 0xad, 0xbe, 0xef, 0x01]),
+    };
+    let mut out = vec![];
+    write_woff(&&tables, Tag([0, 1, 0, 0]), &tags, &options, &mut out).unwrap();
+
+    let woff = Woff::from_bytes(&out).unwrap();
+    assert_eq!(woff.header.major_version, 2);
+    assert_eq!(woff.header.minor_version, 5);
+    assert_eq!(woff.header.priv_offset % 4, 0);
+    assert_eq!(woff.metadata().unwrap().as_deref(), Some(metadata));
+    assert_eq!(
+        woff.private_data().unwrap(),
+        Some(&[0xde, 0xad, 0xbe, 0xef, 0x01][..])
+    );
+    // incompressible tables are stored as they are
+    let entry = woff.tables[&Tag(*b"zzzz")];
+    assert_eq!(entry.comp_length, entry.orig_length);
+    let entry = woff.tables[&Tag(*b"long")];
+    assert!(entry.comp_length < entry.orig_length);
+    assert_eq!(&*woff.table_data(Tag(*b"long")).unwrap(), &[7; 1000][..]);
+    assert_eq!(&*woff.table_data(Tag(*b"zzzz")).unwrap(), &[1, 2, 3]);
+}
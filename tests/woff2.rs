@@ -0,0 +1,283 @@
+extern crate font_parse;
+
+use font_parse::tables::maxp::Maxp;
+use font_parse::woff::{write_woff, WoffOptions};
+use font_parse::woff2::{write_woff2, Woff2, Woff2Options};
+use font_parse::{Font, OpentypeTableAccess, ParseTable, Tag};
+
+use std::io::Write;
+
+const KNOWN_TAGS: [&[u8; 4]; 3] = [b"cmap", b"head", b"hhea"];
+
+fn push_uint_base128(mut value: u32, out: &mut Vec<u8>) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.extend(bytes.into_iter().rev());
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = brotli::CompressorWriter::new(vec![], 4096, 9, 22);
+    writer.write_all(data).unwrap();
+    writer.into_inner()
+}
+
+/// Writes the fonts as a WOFF2 file without transforming any table.
+fn write_untransformed(fonts: &[&Font], metadata: Option<&str>) -> Vec<u8> {
+    let mut directory = vec![];
+    let mut table_data = vec![];
+    let mut font_tables = vec![];
+    for font in fonts {
+        for &tag in font.record().tables.keys() {
+            let data = font.table_data(tag).unwrap();
+            let index = KNOWN_TAGS.iter().position(|&&known| known == tag.0);
+            // glyf and loca with transformation version 3 are not transformed
+            let version = if tag == Tag(*b"glyf") || tag == Tag(*b"loca") {
+                0xC0
+            } else {
+                0
+            };
+            match index {
+                Some(index) => directory.push(version | index as u8),
+                None => {
+                    directory.push(version | 0x3F);
+                    directory.extend_from_slice(&tag.0);
+                }
+            }
+            push_uint_base128(data.len() as u32, &mut directory);
+            table_data.extend_from_slice(data);
+        }
+        font_tables.push((font.record().version, font.record().tables.len()));
+    }
+    let num_tables: usize = font_tables.iter().map(|&(_, num_tables)| num_tables).sum();
+    if fonts.len() > 1 {
+        directory.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        directory.push(fonts.len() as u8);
+        let mut first = 0;
+        for &(flavor, num_tables) in &font_tables {
+            directory.push(num_tables as u8);
+            directory.extend_from_slice(&flavor.to_be_bytes());
+            directory.extend((first..first + num_tables).map(|index| index as u8));
+            first += num_tables;
+        }
+    }
+
+    let compressed = compress(&table_data);
+    let mut offset = 48 + directory.len() + compressed.len();
+    let metadata = metadata.map(|metadata| (metadata.len(), compress(metadata.as_bytes())));
+    let meta_offset = offset.div_ceil(4) * 4;
+    if let Some((_, ref data)) = metadata {
+        offset = meta_offset + data.len();
+    }
+
+    let flavor = if fonts.len() > 1 {
+        u32::from_be_bytes(*b"ttcf")
+    } else {
+        fonts[0].record().version
+    };
+    let mut out = vec![];
+    out.extend_from_slice(b"wOF2");
+    out.extend_from_slice(&flavor.to_be_bytes());
+    out.extend_from_slice(&(offset as u32).to_be_bytes());
+    out.extend_from_slice(&(num_tables as u16).to_be_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+    out.extend_from_slice(&[0, 1, 0, 0]);
+    match metadata {
+        Some((orig_len, ref data)) => {
+            for &value in &[meta_offset, data.len(), orig_len] {
+                out.extend_from_slice(&(value as u32).to_be_bytes());
+            }
+        }
+        None => out.extend_from_slice(&[0; 12]),
+    }
+    out.extend_from_slice(&[0; 8]);
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&compressed);
+    if let Some((_, ref data)) = metadata {
+        out.resize(meta_offset, 0);
+        out.extend_from_slice(data);
+    }
+    out
+}
+
+fn assert_same_tables(woff2: &Font, font: &Font) {
+    assert_eq!(woff2.record().tables.len(), font.record().tables.len());
+    for &tag in font.record().tables.keys() {
+        let data = woff2.table_data(tag).unwrap();
+        let expected = font.table_data(tag).unwrap();
+        if tag == Tag(*b"head") {
+            // the checksum adjustment is recomputed
+            assert_eq!(data[..8], expected[..8]);
+            assert_eq!(data[12..], expected[12..]);
+        } else {
+            assert_eq!(data, expected, "{}", tag);
+        }
+    }
+}
+
+#[test]
+fn test_read_woff2() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let out = write_untransformed(&[&font], None);
+    assert!(out.len() < data.len() / 2);
+
+    let woff2 = Woff2::from_bytes(&out).unwrap();
+    assert_eq!(woff2.header.flavor, font.record().version);
+    assert_eq!(woff2.tables.len(), font.record().tables.len());
+    assert!(woff2.collection().is_none());
+    assert_eq!(woff2.metadata().unwrap(), None);
+    assert_same_tables(&woff2.font(0).unwrap(), &font);
+    assert_eq!(
+        (&woff2).table_data(Tag(*b"cmap")),
+        font.table_data(Tag(*b"cmap"))
+    );
+    assert!((&woff2).glyphs().unwrap().outline(36).unwrap().is_some());
+
+    // truncated or corrupted files are rejected
+    assert!(Woff2::from_bytes(&out[..out.len() - 1]).is_err());
+    let mut corrupted = out.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0xFF;
+    assert!(Woff2::from_bytes(&corrupted).is_err());
+}
+
+#[test]
+fn test_read_woff2_collection() {
+    let inconsolata = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let biolinum = include_bytes!("font_files/LinBiolinum_R.otf");
+    let fonts = [
+        Font::from_bytes(inconsolata, 0).unwrap(),
+        Font::from_bytes(biolinum, 0).unwrap(),
+    ];
+    let metadata = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<metadata version=\"1.0\"/>";
+    let out = write_untransformed(&[&fonts[0], &fonts[1]], Some(metadata));
+
+    let woff2 = Woff2::from_bytes(&out).unwrap();
+    assert_eq!(woff2.fonts.len(), 2);
+    assert_eq!(woff2.collection().unwrap().fonts.len(), 2);
+    assert_eq!(woff2.metadata().unwrap().as_deref(), Some(metadata));
+    for (index, font) in fonts.iter().enumerate() {
+        assert_same_tables(&woff2.font(index as u32).unwrap(), font);
+    }
+    assert!(woff2.font(2).is_err());
+}
+
+fn encode(font: &Font, options: &Woff2Options) -> Vec<u8> {
+    let tags: Vec<Tag> = font.record().tables.keys().cloned().collect();
+    let version_tag = Tag(font.record().version.to_be_bytes());
+    let mut out = vec![];
+    write_woff2(font, version_tag, &tags, options, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn test_write_woff2() {
+    for &data in &[
+        &include_bytes!("font_files/Inconsolata-Regular.ttf")[..],
+        &include_bytes!("font_files/LinBiolinum_R.otf")[..],
+    ] {
+        let font = Font::from_bytes(data, 0).unwrap();
+        let out = encode(&font, &Woff2Options::default());
+        let tags: Vec<Tag> = font.record().tables.keys().cloned().collect();
+        let mut woff = vec![];
+        let version_tag = Tag(font.record().version.to_be_bytes());
+        write_woff(
+            &font,
+            version_tag,
+            &tags,
+            &WoffOptions::default(),
+            &mut woff,
+        )
+        .unwrap();
+        assert!(out.len() < woff.len());
+
+        let woff2 = Woff2::from_bytes(&out).unwrap();
+        assert_eq!(woff2.header.length as usize, out.len());
+        assert_eq!(
+            woff2.header.total_sfnt_size as usize,
+            woff2.sfnt_data().len()
+        );
+        let decoded = woff2.font(0).unwrap();
+        let is_truetype = font.has_table(Tag(*b"glyf"));
+        for entry in &woff2.tables {
+            let transformed = match &entry.tag.0 {
+                b"glyf" | b"loca" | b"hmtx" => is_truetype,
+                _ => false,
+            };
+            assert_eq!(entry.is_transformed(), transformed, "{}", entry.tag);
+        }
+
+        // glyf and loca are re-encoded, all other tables are unchanged
+        for &tag in font.record().tables.keys() {
+            let data = decoded.table_data(tag).unwrap();
+            let expected = font.table_data(tag).unwrap();
+            match &tag.0 {
+                b"glyf" | b"loca" => {}
+                b"head" => {
+                    assert_eq!(data[..8], expected[..8]);
+                    assert_eq!(data[12..], expected[12..]);
+                }
+                _ => assert_eq!(data, expected, "{}", tag),
+            }
+        }
+        if !is_truetype {
+            continue;
+        }
+        let maxp = font.parse_table::<Maxp>().unwrap();
+        let num_glyphs = u32::from(maxp.get().unwrap().num_glyphs);
+        let mut original = font.glyphs().unwrap();
+        let mut glyphs = decoded.glyphs().unwrap();
+        for index in 0..num_glyphs {
+            assert_eq!(
+                glyphs.outline(index).unwrap(),
+                original.outline(index).unwrap(),
+                "glyph {}",
+                index
+            );
+        }
+
+        // encoding the decoded font reproduces every table
+        let again = encode(&decoded, &Woff2Options::default());
+        assert_eq!(again, out);
+        let woff2_again = Woff2::from_bytes(&again).unwrap();
+        assert_eq!(woff2_again.sfnt_data(), woff2.sfnt_data());
+
+        // without transformations all tables are unchanged
+        let options = Woff2Options {
+            transform_glyf: false,
+            ..Woff2Options::default()
+        };
+        let out = encode(&font, &options);
+        let woff2 = Woff2::from_bytes(&out).unwrap();
+        assert!(woff2.tables.iter().all(|entry| !entry.is_transformed()));
+        assert_same_tables(&woff2.font(0).unwrap(), &font);
+    }
+}
+
+#[test]
+fn test_woff2_metadata() {
+    let data = include_bytes!("font_files/Inconsolata-Regular.ttf");
+    let font = Font::from_bytes(data, 0).unwrap();
+    let metadata = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<metadata version=\"1.0\"/>";
+    let options = Woff2Options {
+        major_version: 1,
+        minor_version: 2,
+        metadata: Some(metadata),
+        private_data: Some(&[1, 2, 3]),
+        ..Woff2Options::default()
+    };
+    let out = encode(&font, &options);
+    let woff2 = Woff2::from_bytes(&out).unwrap();
+    assert_eq!(woff2.header.major_version, 1);
+    assert_eq!(woff2.header.minor_version, 2);
+    assert_eq!(woff2.header.meta_offset % 4, 0);
+    assert_eq!(woff2.header.priv_offset % 4, 0);
+    assert_eq!(woff2.metadata().unwrap().as_deref(), Some(metadata));
+    assert_eq!(woff2.private_data().unwrap(), Some(&[1, 2, 3][..]));
+}